@@ -0,0 +1,183 @@
+//! On-disk backing of persistent caches.
+//!
+//! A cache keeps one JSONL file and appends a line per change: an entry with
+//! its `CachedItem` and tags, or a key with no item for a removal. Opening the
+//! file replays it into memory, expired items left out, and rewrites it with
+//! only the live entries. It is rewritten the same way whenever dead lines
+//! outnumber live ones by more than `SLACK`.
+//!
+//! The file is written by a thread of its own, so cache calls never wait on the
+//! disk. Lines sent while it writes are gathered and appended together.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tokio::sync::oneshot;
+use crate::error::RdfEditorError;
+use super::{CacheTag, CachedItem};
+
+/// Dead lines tolerated on top of one per live entry before a rewrite
+const SLACK: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+pub struct Line<K, I> {
+    pub key: K,
+    /// None removes the key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<I>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<CacheTag>,
+}
+
+/// A line as read back
+pub type Stored<K, V> = Line<K, CachedItem<V>>;
+
+/// Work for the writer thread
+enum Job {
+    Append(String),
+    Rewrite(Vec<String>),
+    /// Answered once everything sent before it is written
+    Sync(oneshot::Sender<()>),
+}
+
+pub struct CacheFile {
+    writer: mpsc::Sender<Job>,
+    /// Lines in the file once the writer has caught up
+    lines: usize,
+}
+
+impl CacheFile {
+    /// Open the file at `path`, creating it when missing, with the lines it
+    /// holds, oldest first, and start its writer. Unreadable lines are skipped.
+    pub fn open<K: DeserializeOwned, V: DeserializeOwned>(
+        path: &Path,
+    ) -> Result<(Self, Vec<Stored<K, V>>), RdfEditorError> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| io_error(path, e))?;
+        }
+        let file = OpenOptions::new().create(true).read(true).append(true).open(path)
+            .map_err(|e| io_error(path, e))?;
+        
+        let mut lines = Vec::new();
+        for line in BufReader::new(&file).lines() {
+            let line = line.map_err(|e| io_error(path, e))?;
+            match serde_json::from_str(&line) {
+                Ok(line) => lines.push(line),
+                Err(e) => log::warn!("Skipping unreadable line of cache file {}: {}", path.display(), e),
+            }
+        }
+        
+        let (writer, work) = mpsc::channel();
+        let mut worker = Writer { path: path.to_path_buf(), file, pending: Vec::new() };
+        std::thread::Builder::new()
+            .name("cache-writer".to_string())
+            .spawn(move || worker.run(work))
+            .map_err(|e| io_error(path, e))?;
+        
+        let count = lines.len();
+        Ok((CacheFile { writer, lines: count }, lines))
+    }
+    
+    /// Queue one line to append
+    pub fn append(&mut self, line: String) {
+        if self.writer.send(Job::Append(line)).is_ok() {
+            self.lines += 1;
+        }
+    }
+    
+    /// Whether the file holds enough dead lines for `live` entries to be worth a rewrite
+    pub fn needs_rewrite(&self, live: usize) -> bool {
+        self.lines > 2 * live + SLACK
+    }
+    
+    /// Queue a replacement of the content with `lines`, dropping appends still queued
+    pub fn rewrite<L: Serialize>(&mut self, lines: impl Iterator<Item = L>) {
+        let lines: Vec<String> = lines.filter_map(|line| match serde_json::to_string(&line) {
+            Ok(line) => Some(line),
+            Err(e) => {
+                log::warn!("Cache entry not written to disk: {}", e);
+                None
+            },
+        }).collect();
+        let count = lines.len();
+        if self.writer.send(Job::Rewrite(lines)).is_ok() {
+            self.lines = count;
+        }
+    }
+    
+    /// Resolves once the writes queued so far are on disk
+    pub fn sync(&self) -> oneshot::Receiver<()> {
+        let (done, synced) = oneshot::channel();
+        let _ = self.writer.send(Job::Sync(done));
+        synced
+    }
+}
+
+/// The writer thread's end; it stops once the `CacheFile` is dropped
+struct Writer {
+    path: PathBuf,
+    file: File,
+    /// Appends gathered from the queue, written together
+    pending: Vec<u8>,
+}
+
+impl Writer {
+    fn run(&mut self, work: mpsc::Receiver<Job>) {
+        while let Ok(first) = work.recv() {
+            for job in std::iter::once(first).chain(work.try_iter()) {
+                match job {
+                    Job::Append(line) => {
+                        self.pending.extend_from_slice(line.as_bytes());
+                        self.pending.push(b'\n');
+                    },
+                    Job::Rewrite(lines) => {
+                        // The new content already holds what the queued appends recorded
+                        self.pending.clear();
+                        if let Err(e) = self.rewrite(&lines) {
+                            log::warn!("Failed to rewrite cache file {}: {}", self.path.display(), e);
+                        }
+                    },
+                    Job::Sync(done) => {
+                        self.flush();
+                        let _ = done.send(());
+                    },
+                }
+            }
+            self.flush();
+        }
+    }
+    
+    /// Write the gathered appends. A cache is only a shortcut, so failures are
+    /// logged and otherwise ignored.
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        if let Err(e) = self.file.write_all(&self.pending) {
+            log::warn!("Failed to write cache file {}: {}", self.path.display(), e);
+        }
+        self.pending.clear();
+    }
+    
+    /// Replace the content with `lines`. The new file is written under a
+    /// temporary name first, so a crash leaves either the old or the new one.
+    fn rewrite(&mut self, lines: &[String]) -> std::io::Result<()> {
+        let partial = self.path.with_extension("jsonl.partial");
+        let mut out = std::io::BufWriter::new(File::create(&partial)?);
+        for line in lines {
+            out.write_all(line.as_bytes())?;
+            out.write_all(b"\n")?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&partial, &self.path)?;
+        
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> RdfEditorError {
+    RdfEditorError::Cache(format!("Cache file {}: {}", path.display(), e))
+}
@@ -0,0 +1,665 @@
+mod file;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+use chrono::{DateTime, Utc, Duration};
+use serde::{de::DeserializeOwned, Serialize, Deserialize};
+use crate::config::Config;
+use crate::error::RdfEditorError;
+use crate::models::{GraphNode, GraphEdge, GraphPath, NodeLabel, RdfNode, Triple};
+use file::{CacheFile, Line};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedItem<T> {
+    pub data: T,
+    pub cached_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl<T> CachedItem<T> {
+    fn new(data: T, ttl_seconds: i64) -> Self {
+        let now = Utc::now();
+        CachedItem {
+            data,
+            cached_at: now,
+            expires_at: now + Duration::seconds(ttl_seconds),
+        }
+    }
+    
+    fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+}
+
+/// What a cached entry was computed from. Writes drop the entries tagged with
+/// what they touched, see `write_tags`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CacheTag {
+    /// Anything in the store, for results whose inputs are unknown, such as those
+    /// of arbitrary queries
+    Store,
+    /// A graph, "default" for the default graph
+    Graph(String),
+    /// Triples with this IRI or blank node as subject or object
+    Resource(String),
+}
+
+/// Tags of the entries a write of `triples` may have changed. Triples naming no
+/// graph were written to `graph`. Many stores answer default graph queries from
+/// the union of all graphs, so every write touches the default graph.
+pub fn write_tags(triples: &[Triple], graph: &str) -> Vec<CacheTag> {
+    let mut tags = vec![CacheTag::Store, CacheTag::Graph("default".to_string())];
+    let mut add = |tag: CacheTag| {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    };
+    add(CacheTag::Graph(graph.to_string()));
+    for triple in triples {
+        if let Some(g) = &triple.graph {
+            add(CacheTag::Graph(g.clone()));
+        }
+        add(CacheTag::Resource(triple.subject.clone()));
+        match &triple.object {
+            RdfNode::Uri { value } => add(CacheTag::Resource(value.clone())),
+            RdfNode::Blank { value } => add(CacheTag::Resource(format!("_:{}", value))),
+            RdfNode::Literal { .. } => {},
+        }
+    }
+    tags
+}
+
+/// Limits shared by every cache; a zero bound is no bound
+#[derive(Debug, Clone, Copy)]
+pub struct CacheSettings {
+    /// When false, caches store nothing and every lookup misses
+    pub enabled: bool,
+    pub ttl_seconds: i64,
+    pub max_entries: usize,
+    /// Bound on the estimated size of the cached values, measured as their JSON length
+    pub max_bytes: usize,
+}
+
+/// File of cache `name` of `profile` in CACHE_DIR, or None when caches are kept
+/// in memory only
+pub fn file_path(config: &Config, profile: &str, name: &str) -> Option<PathBuf> {
+    config.cache_dir.as_ref().map(|dir| Path::new(dir).join(format!("{}.{}.jsonl", profile, name)))
+}
+
+impl CacheSettings {
+    pub fn from_config(config: &Config) -> Self {
+        CacheSettings {
+            enabled: config.cache_enabled,
+            ttl_seconds: config.cache_ttl_seconds as i64,
+            max_entries: config.cache_max_entries,
+            max_bytes: config.cache_max_bytes,
+        }
+    }
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            enabled: true,
+            ttl_seconds: 300,
+            max_entries: 1000,
+            max_bytes: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// Estimated size of the cached values; only measured when `max_bytes` is set
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within the bounds
+    pub evictions: u64,
+}
+
+struct Entry<V> {
+    item: CachedItem<V>,
+    /// Recency stamp, the key of the entry in `Entries::recency`
+    used: u64,
+    bytes: usize,
+    tags: Vec<CacheTag>,
+}
+
+/// Cached items with their order of use, least recently used first, and the
+/// keys carrying each tag. Changes are mirrored to `file` when there is one.
+struct Entries<K, V> {
+    items: HashMap<K, Entry<V>>,
+    recency: BTreeMap<u64, K>,
+    tagged: HashMap<CacheTag, HashSet<K>>,
+    clock: u64,
+    bytes: usize,
+    file: Option<CacheFile>,
+}
+
+impl<K: Eq + std::hash::Hash + Clone + Serialize, V: Serialize> Entries<K, V> {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+    
+    fn insert(&mut self, key: K, item: CachedItem<V>, bytes: usize, tags: Vec<CacheTag>) {
+        let line = self.file.is_some().then(|| {
+            serde_json::to_string(&Line { key: &key, item: Some(&item), tags: tags.clone() })
+        });
+        self.unlink(&key);
+        self.link(key, item, bytes, tags);
+        match line {
+            Some(Ok(line)) => self.write(line),
+            Some(Err(e)) => log::warn!("Cache entry not written to disk: {}", e),
+            None => {},
+        }
+    }
+    
+    fn remove(&mut self, key: &K) -> Option<Entry<V>> {
+        let entry = self.unlink(key)?;
+        if self.file.is_some() {
+            if let Ok(line) = serde_json::to_string(&Line::<&K, ()> { key, item: None, tags: Vec::new() }) {
+                self.write(line);
+            }
+        }
+        Some(entry)
+    }
+    
+    /// Add an entry in memory only
+    fn link(&mut self, key: K, item: CachedItem<V>, bytes: usize, tags: Vec<CacheTag>) {
+        let used = self.tick();
+        self.recency.insert(used, key.clone());
+        self.bytes += bytes;
+        for tag in &tags {
+            self.tagged.entry(tag.clone()).or_default().insert(key.clone());
+        }
+        self.items.insert(key, Entry { item, used, bytes, tags });
+    }
+    
+    /// Drop an entry from memory only
+    fn unlink(&mut self, key: &K) -> Option<Entry<V>> {
+        let entry = self.items.remove(key)?;
+        self.recency.remove(&entry.used);
+        self.bytes -= entry.bytes;
+        for tag in &entry.tags {
+            if let Some(keys) = self.tagged.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tagged.remove(tag);
+                }
+            }
+        }
+        Some(entry)
+    }
+    
+    fn write(&mut self, line: String) {
+        let live = self.items.len();
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        file.append(line);
+        if file.needs_rewrite(live) {
+            self.rewrite();
+        }
+    }
+    
+    /// Rewrite the file with the live entries, least recently used first
+    fn rewrite(&mut self) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        file.rewrite(self.recency.values().map(|key| {
+            let entry = &self.items[key];
+            Line { key, item: Some(&entry.item), tags: entry.tags.clone() }
+        }));
+    }
+    
+    /// Mark `key` as just used
+    fn touch(&mut self, key: &K) {
+        let used = self.tick();
+        if let Some(entry) = self.items.get_mut(key) {
+            self.recency.remove(&entry.used);
+            entry.used = used;
+            self.recency.insert(used, key.clone());
+        }
+    }
+    
+    fn pop_least_recent(&mut self) -> Option<Entry<V>> {
+        let key = self.recency.values().next()?.clone();
+        self.remove(&key)
+    }
+    
+    fn clear(&mut self) {
+        self.items.clear();
+        self.recency.clear();
+        self.tagged.clear();
+        self.bytes = 0;
+        self.rewrite();
+    }
+}
+
+/// TTL cache evicting the least recently used entries past its bounds, kept in
+/// memory and optionally in a file that outlives restarts
+pub struct Cache<K, V> 
+where 
+    K: Eq + std::hash::Hash + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    data: Arc<Mutex<Entries<K, V>>>,
+    settings: CacheSettings,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<K, V> Cache<K, V> 
+where 
+    K: Eq + std::hash::Hash + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    pub fn new(settings: CacheSettings) -> Self {
+        Cache {
+            data: Arc::new(Mutex::new(Entries {
+                items: HashMap::new(),
+                recency: BTreeMap::new(),
+                tagged: HashMap::new(),
+                clock: 0,
+                bytes: 0,
+                file: None,
+            })),
+            settings,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+    
+    /// Cache backed by the file at `path`, loaded with the entries stored there
+    /// that have not expired; without a path, or when caching is disabled, a
+    /// memory-only cache
+    pub fn open(settings: CacheSettings, path: Option<PathBuf>) -> Result<Self, RdfEditorError> {
+        let cache = Cache::new(settings);
+        let Some(path) = path.filter(|_| settings.enabled) else {
+            return Ok(cache);
+        };
+        
+        let (file, lines) = CacheFile::open::<K, V>(&path)?;
+        let mut entries = cache.data.try_lock().expect("a new cache is not shared");
+        for line in lines {
+            entries.unlink(&line.key);
+            if let Some(item) = line.item.filter(|item| !item.is_expired()) {
+                let bytes = cache.measure(&item.data);
+                entries.link(line.key, item, bytes, line.tags);
+            }
+        }
+        while cache.over_bounds(&entries) {
+            let Some(key) = entries.recency.values().next().cloned() else {
+                break;
+            };
+            entries.unlink(&key);
+        }
+        entries.file = Some(file);
+        entries.rewrite();
+        drop(entries);
+        Ok(cache)
+    }
+    
+    /// Estimated size of `value`, only measured when the bytes are bounded
+    fn measure(&self, value: &V) -> usize {
+        if self.settings.max_bytes > 0 {
+            serde_json::to_vec(value).map_or(0, |json| json.len())
+        } else {
+            0
+        }
+    }
+    
+    fn over_bounds(&self, entries: &Entries<K, V>) -> bool {
+        (self.settings.max_entries > 0 && entries.items.len() > self.settings.max_entries)
+            || (self.settings.max_bytes > 0 && entries.bytes > self.settings.max_bytes)
+    }
+    
+    pub async fn get(&self, key: &K) -> Option<V> {
+        if !self.settings.enabled {
+            return None;
+        }
+        
+        let mut cache = self.data.lock().await;
+        let found = match cache.items.get(key) {
+            Some(entry) if !entry.item.is_expired() => Some(entry.item.data.clone()),
+            Some(_) => {
+                cache.remove(key);
+                None
+            },
+            None => None,
+        };
+        match found {
+            Some(value) => {
+                cache.touch(key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            },
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            },
+        }
+    }
+    
+    /// Cache `value` with no tags; only the TTL, eviction and explicit
+    /// invalidation remove it
+    pub async fn set(&self, key: K, value: V) {
+        self.set_tagged(key, value, Vec::new()).await;
+    }
+    
+    /// Cache `value`, to be dropped when any of `tags` is invalidated
+    pub async fn set_tagged(&self, key: K, value: V, tags: Vec<CacheTag>) {
+        if !self.settings.enabled {
+            return;
+        }
+        
+        let bytes = self.measure(&value);
+        let mut cache = self.data.lock().await;
+        if self.settings.max_bytes > 0 && bytes > self.settings.max_bytes {
+            // Caching it would only empty the cache
+            cache.remove(&key);
+            return;
+        }
+        cache.insert(key, CachedItem::new(value, self.settings.ttl_seconds), bytes, tags);
+        
+        while self.over_bounds(&cache) {
+            if cache.pop_least_recent().is_none() {
+                break;
+            }
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    pub async fn invalidate(&self, key: &K) {
+        let mut cache = self.data.lock().await;
+        cache.remove(key);
+    }
+    
+    /// Drop every entry carrying any of `tags`
+    pub async fn invalidate_tags(&self, tags: &[CacheTag]) {
+        let mut cache = self.data.lock().await;
+        let keys: Vec<K> = tags.iter()
+            .filter_map(|tag| cache.tagged.get(tag))
+            .flatten()
+            .cloned()
+            .collect();
+        for key in keys {
+            cache.remove(&key);
+        }
+    }
+    
+    /// Drop every entry, returning once the file is emptied too
+    pub async fn clear(&self) {
+        self.data.lock().await.clear();
+        self.persisted().await;
+    }
+    
+    /// Wait until the changes made so far are in the file, if there is one
+    pub async fn persisted(&self) {
+        let synced = self.data.lock().await.file.as_ref().map(CacheFile::sync);
+        if let Some(synced) = synced {
+            let _ = synced.await;
+        }
+    }
+    
+    pub async fn cleanup_expired(&self) {
+        let mut cache = self.data.lock().await;
+        let expired: Vec<K> = cache.items.iter()
+            .filter(|(_, entry)| entry.item.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            cache.remove(&key);
+        }
+    }
+    
+    pub async fn size(&self) -> usize {
+        let cache = self.data.lock().await;
+        cache.items.len()
+    }
+    
+    pub async fn stats(&self) -> CacheStats {
+        let cache = self.data.lock().await;
+        CacheStats {
+            entries: cache.items.len(),
+            bytes: cache.bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Specific cache types for our use cases
+pub type LabelCache = Cache<String, Vec<NodeLabel>>; // URI -> [(label, lang, property)]
+pub type TypeCache = Cache<String, Vec<String>>; // URI -> [types]
+pub type QueryCache = Cache<String, serde_json::Value>; // Query hash -> Results
+
+// Graph-specific caches
+pub type NodeCache = Cache<String, GraphNode>; // URI -> GraphNode
+pub type ConnectionCache = Cache<String, (Vec<GraphNode>, Vec<GraphEdge>)>; // limit|URI -> (nodes, edges)
+pub type PathCache = Cache<(String, String, usize), Option<GraphPath>>; // (from, to, max depth) -> path
+
+// Graph cache manager
+pub struct GraphCacheManager {
+    pub nodes: Arc<NodeCache>,
+    pub connections: Arc<ConnectionCache>,
+    pub paths: Arc<PathCache>,
+    pub search_results: Arc<Cache<String, Vec<GraphNode>>>, // limit|search term -> results
+}
+
+impl GraphCacheManager {
+    pub fn new(settings: CacheSettings) -> Self {
+        GraphCacheManager {
+            nodes: Arc::new(NodeCache::new(settings)),
+            connections: Arc::new(ConnectionCache::new(settings)),
+            paths: Arc::new(PathCache::new(settings)),
+            search_results: Arc::new(Cache::new(settings)),
+        }
+    }
+    
+    /// Caches backed by the files `path` gives for their names, see `Cache::open`
+    pub fn open(settings: CacheSettings, path: impl Fn(&str) -> Option<PathBuf>) -> Result<Self, RdfEditorError> {
+        Ok(GraphCacheManager {
+            nodes: Arc::new(NodeCache::open(settings, path("nodes"))?),
+            connections: Arc::new(ConnectionCache::open(settings, path("connections"))?),
+            paths: Arc::new(PathCache::open(settings, path("paths"))?),
+            search_results: Arc::new(Cache::open(settings, path("search"))?),
+        })
+    }
+    
+    pub async fn clear(&self) {
+        self.nodes.clear().await;
+        self.connections.clear().await;
+        self.paths.clear().await;
+        self.search_results.clear().await;
+    }
+    
+    /// Drop the node, the connections and paths it appears in, and the searches that found it
+    pub async fn invalidate_node(&self, uri: &str) {
+        self.invalidate_tags(&[CacheTag::Resource(uri.to_string())]).await;
+    }
+    
+    pub async fn invalidate_tags(&self, tags: &[CacheTag]) {
+        self.nodes.invalidate_tags(tags).await;
+        self.connections.invalidate_tags(tags).await;
+        self.paths.invalidate_tags(tags).await;
+        self.search_results.invalidate_tags(tags).await;
+    }
+    
+    pub async fn cleanup_all(&self) {
+        self.nodes.cleanup_expired().await;
+        self.connections.cleanup_expired().await;
+        self.paths.cleanup_expired().await;
+        self.search_results.cleanup_expired().await;
+    }
+    
+    pub async fn get_stats(&self) -> GraphCacheStats {
+        GraphCacheStats {
+            nodes: self.nodes.stats().await,
+            connections: self.connections.stats().await,
+            paths: self.paths.stats().await,
+            search_results: self.search_results.stats().await,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphCacheStats {
+    pub nodes: CacheStats,
+    pub connections: CacheStats,
+    pub paths: CacheStats,
+    pub search_results: CacheStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ttl(ttl_seconds: i64) -> CacheSettings {
+        CacheSettings { ttl_seconds, ..CacheSettings::default() }
+    }
+    
+    #[tokio::test]
+    async fn test_cache_expiration() {
+        let cache: Cache<String, String> = Cache::new(ttl(1)); // 1 second TTL
+        
+        cache.set("key".to_string(), "value".to_string()).await;
+        
+        // Should be available immediately
+        assert_eq!(cache.get(&"key".to_string()).await, Some("value".to_string()));
+        
+        // Wait for expiration
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        
+        // Should be expired
+        assert_eq!(cache.get(&"key".to_string()).await, None);
+    }
+    
+    #[tokio::test]
+    async fn test_cache_cleanup() {
+        let cache: Cache<String, String> = Cache::new(ttl(1));
+        
+        cache.set("key1".to_string(), "value1".to_string()).await;
+        cache.set("key2".to_string(), "value2".to_string()).await;
+        
+        assert_eq!(cache.size().await, 2);
+        
+        // Wait for expiration
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        
+        // Cleanup expired items
+        cache.cleanup_expired().await;
+        
+        assert_eq!(cache.size().await, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_cache_evicts_least_recently_used() {
+        let cache: Cache<String, String> = Cache::new(CacheSettings { max_entries: 2, ..CacheSettings::default() });
+        
+        cache.set("a".to_string(), "1".to_string()).await;
+        cache.set("b".to_string(), "2".to_string()).await;
+        assert_eq!(cache.get(&"a".to_string()).await, Some("1".to_string()));
+        cache.set("c".to_string(), "3".to_string()).await;
+        
+        assert_eq!(cache.get(&"b".to_string()).await, None);
+        assert_eq!(cache.get(&"a".to_string()).await, Some("1".to_string()));
+        assert_eq!(cache.stats().await, CacheStats { entries: 2, bytes: 0, hits: 2, misses: 1, evictions: 1 });
+        
+        // Values are measured as JSON: "1234" takes 6 bytes
+        let cache: Cache<String, String> = Cache::new(CacheSettings { max_bytes: 10, ..CacheSettings::default() });
+        cache.set("a".to_string(), "1234".to_string()).await;
+        cache.set("b".to_string(), "5678".to_string()).await;
+        cache.set("huge".to_string(), "x".repeat(20)).await;
+        let stats = cache.stats().await;
+        assert_eq!((stats.entries, stats.bytes, stats.evictions), (1, 6, 1));
+        assert_eq!(cache.get(&"b".to_string()).await, Some("5678".to_string()));
+    }
+    
+    #[tokio::test]
+    async fn test_disabled_cache_stores_nothing() {
+        let cache: Cache<String, String> = Cache::new(CacheSettings { enabled: false, ..CacheSettings::default() });
+        cache.set("key".to_string(), "value".to_string()).await;
+        
+        assert_eq!(cache.get(&"key".to_string()).await, None);
+        assert_eq!(cache.stats().await, CacheStats::default());
+    }
+    
+    #[tokio::test]
+    async fn test_invalidating_a_node_keeps_unrelated_paths() {
+        let manager = GraphCacheManager::new(CacheSettings::default());
+        let path = |nodes: &[&str]| -> Vec<CacheTag> {
+            nodes.iter().map(|node| CacheTag::Resource(node.to_string())).collect()
+        };
+        let found = |nodes: &[&str]| Some(GraphPath {
+            nodes: nodes.iter().map(|node| node.to_string()).collect(),
+            edges: vec![],
+            length: nodes.len() - 1,
+        });
+        let key = |from: &str, to: &str| (from.to_string(), to.to_string(), 3);
+        manager.paths.set_tagged(key("a", "c"), found(&["a", "b", "c"]), path(&["a", "b", "c"])).await;
+        manager.paths.set_tagged(key("d", "e"), found(&["d", "e"]), path(&["d", "e"])).await;
+        
+        manager.invalidate_node("b").await;
+        assert!(manager.paths.get(&key("a", "c")).await.is_none());
+        assert!(manager.paths.get(&key("d", "e")).await.is_some());
+        
+        let triple = Triple {
+            subject: "e".to_string(),
+            predicate: "p".to_string(),
+            object: RdfNode::Literal { value: "x".to_string(), datatype: None, language: None },
+            graph: Some("g".to_string()),
+        };
+        let tags = write_tags(&[triple], "default");
+        assert_eq!(tags, vec![
+            CacheTag::Store,
+            CacheTag::Graph("default".to_string()),
+            CacheTag::Graph("g".to_string()),
+            CacheTag::Resource("e".to_string()),
+        ]);
+        manager.invalidate_tags(&tags).await;
+        assert_eq!(manager.get_stats().await.paths.entries, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_persistent_cache_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("rdf-editor-cache-{}.jsonl", uuid::Uuid::new_v4()));
+        let open = |settings| Cache::<String, String>::open(settings, Some(path.clone())).unwrap();
+        let key = |k: &str| k.to_string();
+        
+        let cache = open(CacheSettings::default());
+        cache.set_tagged(key("a"), "1".to_string(), vec![CacheTag::Resource("x".to_string())]).await;
+        cache.set_tagged(key("b"), "2".to_string(), vec![CacheTag::Resource("y".to_string())]).await;
+        cache.set(key("c"), "3".to_string()).await;
+        cache.invalidate(&key("c")).await;
+        cache.persisted().await;
+        let expired = open(ttl(-1));
+        expired.set(key("d"), "expired".to_string()).await;
+        expired.persisted().await;
+        
+        let cache = open(CacheSettings::default());
+        assert_eq!(cache.size().await, 2);
+        assert_eq!(cache.get(&key("a")).await, Some("1".to_string()));
+        // Tags are kept, so invalidation still reaches reloaded entries
+        cache.invalidate_tags(&[CacheTag::Resource("x".to_string())]).await;
+        cache.persisted().await;
+        
+        let cache = open(CacheSettings::default());
+        assert_eq!(cache.get(&key("a")).await, None);
+        assert_eq!(cache.get(&key("b")).await, Some("2".to_string()));
+        cache.clear().await;
+        
+        assert_eq!(open(CacheSettings::default()).size().await, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
\ No newline at end of file
@@ -1,6 +1,7 @@
 use serde::Deserialize;
 use std::env;
 use thiserror::Error;
+use crate::dialect::StoreDialect;
 
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -15,6 +16,12 @@ pub enum ConfigError {
     
     #[error("Invalid number value for {0}: {1}")]
     InvalidNumber(String, String),
+    
+    #[error("Invalid endpoint profile: {0}")]
+    InvalidProfile(String),
+    
+    #[error("Invalid store dialect for {0}: {1}")]
+    InvalidDialect(String, String),
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -25,6 +32,14 @@ pub struct Config {
     pub sparql_username: Option<String>,
     pub sparql_password: Option<String>,
     pub sparql_password_hashed: Option<String>,
+    pub sparql_ca_cert: Option<String>,
+    pub graph_store_endpoint: Option<String>,
+    pub sparql_dialect: StoreDialect,
+    pub full_text_search: bool,
+    
+    // Endpoint profiles; the settings above form the "default" profile
+    pub profiles: Vec<EndpointProfile>,
+    pub default_profile: String,
     
     // Server configuration
     pub host: String,
@@ -41,6 +56,9 @@ pub struct Config {
     pub cache_enabled: bool,
     pub cache_ttl_seconds: u64,
     pub cache_max_entries: usize,
+    pub cache_max_bytes: usize,
+    /// Directory of the persistent cache files; None keeps caches in memory only
+    pub cache_dir: Option<String>,
     
     // Security configuration
     pub enable_auth: bool,
@@ -52,6 +70,20 @@ pub struct Config {
     pub graph_max_edges: usize,
     pub graph_expansion_limit: usize,
     pub graph_search_limit: usize,
+    
+    // Import/export settings
+    pub export_page_size: usize,
+    pub import_chunk_size: usize,
+    /// Largest background import read into memory whole, in bytes: anything but N-Triples and N-Quads
+    pub import_max_document_size: u64,
+    pub import_dir: String,
+    
+    // Transaction log settings
+    pub transaction_log_dir: String,
+    pub transaction_log_max_bytes: u64,
+    pub transaction_log_max_age_hours: u64,
+    pub transaction_log_retain_segments: usize,
+    pub transaction_log_retain_days: u64,
 }
 
 impl Config {
@@ -63,20 +95,13 @@ impl Config {
         let sparql_endpoint = env::var("SPARQL_ENDPOINT")
             .map_err(|_| ConfigError::MissingEnvVar("SPARQL_ENDPOINT".to_string()))?;
         
+        // Store product, named or detected from the endpoint layout
+        let sparql_dialect = resolve_dialect("SPARQL_DIALECT", env::var("SPARQL_DIALECT").ok(), &sparql_endpoint)?;
+        
         // Try to get update endpoint, otherwise derive it from the query endpoint
         let sparql_update_endpoint = env::var("SPARQL_UPDATE_ENDPOINT")
             .ok()
-            .or_else(|| {
-                // If not specified, try to derive it from the query endpoint
-                // For Stardog URLs like http://localhost:5820/myDB/query
-                // Convert to http://localhost:5820/myDB/update
-                if sparql_endpoint.ends_with("/query") {
-                    let base = sparql_endpoint.trim_end_matches("/query");
-                    Some(format!("{}/update", base))
-                } else {
-                    None
-                }
-            });
+            .or_else(|| sparql_dialect.update_endpoint(&sparql_endpoint));
         
         // Authentication
         let sparql_username = env::var("SPARQL_USERNAME").ok();
@@ -90,6 +115,18 @@ impl Config {
             ));
         }
         
+        // Extra CA certificate (PEM) for stores behind a private CA
+        let sparql_ca_cert = env::var("SPARQL_CA_CERT").ok();
+        
+        // SPARQL 1.1 Graph Store Protocol endpoint, if the store offers one
+        let graph_store_endpoint = resolve_graph_store(env::var("GRAPH_STORE_ENDPOINT").ok(), sparql_dialect, &sparql_endpoint);
+        
+        // Full-text search needs an index set up in the store
+        let full_text_search = env::var("FULL_TEXT_SEARCH")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+        
         // Server settings
         let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
         let port = env::var("PORT")
@@ -151,6 +188,13 @@ impl Config {
             .parse::<usize>()
             .unwrap_or(1000);
         
+        let cache_max_bytes = env::var("CACHE_MAX_BYTES")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0);
+        
+        let cache_dir = env::var("CACHE_DIR").ok().filter(|dir| !dir.is_empty());
+        
         // Security settings
         let enable_auth = env::var("ENABLE_AUTH")
             .unwrap_or_else(|_| "false".to_string())
@@ -191,12 +235,92 @@ impl Config {
             .parse::<usize>()
             .unwrap_or(10);
         
+        // Import/export settings
+        let export_page_size = env::var("EXPORT_PAGE_SIZE")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<usize>()
+            .unwrap_or(10000)
+            .max(1);
+        
+        let import_chunk_size = env::var("IMPORT_CHUNK_SIZE")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<usize>()
+            .unwrap_or(1000)
+            .max(1);
+        
+        let import_max_document_size = env::var("IMPORT_MAX_DOCUMENT_SIZE")
+            .unwrap_or_else(|_| "268435456".to_string()) // 256MB default
+            .parse::<u64>()
+            .unwrap_or(268_435_456);
+        
+        let import_dir = env::var("IMPORT_DIR").unwrap_or_else(|_| "imports".to_string());
+        
+        let transaction_log_dir = env::var("TRANSACTION_LOG_DIR").unwrap_or_else(|_| ".".to_string());
+        
+        // Rotation and retention; 0 turns each off
+        let transaction_log_max_bytes = env::var("TRANSACTION_LOG_MAX_BYTES")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0);
+        
+        let transaction_log_max_age_hours = env::var("TRANSACTION_LOG_MAX_AGE_HOURS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0);
+        
+        let transaction_log_retain_segments = env::var("TRANSACTION_LOG_RETAIN_SEGMENTS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0);
+        
+        let transaction_log_retain_days = env::var("TRANSACTION_LOG_RETAIN_DAYS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0);
+        
+        // Endpoint profiles: the top-level settings are "default", SPARQL_PROFILES names the rest
+        let default = EndpointProfile {
+            name: DEFAULT_PROFILE.to_string(),
+            sparql_endpoint: sparql_endpoint.clone(),
+            sparql_update_endpoint: sparql_update_endpoint.clone(),
+            sparql_username: sparql_username.clone(),
+            sparql_password: sparql_password.clone(),
+            sparql_password_hashed: sparql_password_hashed.clone(),
+            verify_ssl,
+            ca_cert: sparql_ca_cert.clone(),
+            graph_store_endpoint: graph_store_endpoint.clone(),
+            dialect: sparql_dialect,
+            full_text_search,
+            graph_max_nodes,
+            graph_max_edges,
+            graph_expansion_limit,
+            graph_search_limit,
+        };
+        let profiles = parse_profiles(
+            &env::var("SPARQL_PROFILES").unwrap_or_default(),
+            default,
+            &|key| env::var(key).ok(),
+        )?;
+        
+        let default_profile = env::var("DEFAULT_PROFILE").unwrap_or_else(|_| DEFAULT_PROFILE.to_string());
+        if !profiles.iter().any(|p| p.name == default_profile) {
+            return Err(ConfigError::InvalidProfile(format!(
+                "DEFAULT_PROFILE {} is not in SPARQL_PROFILES", default_profile
+            )));
+        }
+        
         Ok(Config {
             sparql_endpoint,
             sparql_update_endpoint,
             sparql_username,
             sparql_password,
             sparql_password_hashed,
+            sparql_ca_cert,
+            graph_store_endpoint,
+            sparql_dialect,
+            full_text_search,
+            profiles,
+            default_profile,
             host,
             port,
             cors_enabled,
@@ -207,6 +331,8 @@ impl Config {
             cache_enabled,
             cache_ttl_seconds,
             cache_max_entries,
+            cache_max_bytes,
+            cache_dir,
             enable_auth,
             api_key,
             allowed_origins,
@@ -214,31 +340,28 @@ impl Config {
             graph_max_edges,
             graph_expansion_limit,
             graph_search_limit,
+            export_page_size,
+            import_chunk_size,
+            import_max_document_size,
+            import_dir,
+            transaction_log_dir,
+            transaction_log_max_bytes,
+            transaction_log_max_age_hours,
+            transaction_log_retain_segments,
+            transaction_log_retain_days,
         })
     }
     
     /// Get the update endpoint, using the configured one or the main endpoint as fallback
     pub fn get_update_endpoint(&self) -> &str {
         self.sparql_update_endpoint
-            .as_ref()
-            .map(|s| s.as_str())
+            .as_deref()
             .unwrap_or(&self.sparql_endpoint)
     }
     
     /// Get the actual password, decoding from base64 if hashed
     pub fn get_password(&self) -> Option<String> {
-        if let Some(ref password) = self.sparql_password {
-            Some(password.clone())
-        } else if let Some(ref hashed) = self.sparql_password_hashed {
-            // Decode base64 password
-            use base64::{Engine as _, engine::general_purpose};
-            match general_purpose::STANDARD.decode(hashed) {
-                Ok(decoded) => String::from_utf8(decoded).ok(),
-                Err(_) => None,
-            }
-        } else {
-            None
-        }
+        decode_password(self.sparql_password.as_deref(), self.sparql_password_hashed.as_deref())
     }
     
     /// Check if caching is enabled
@@ -257,7 +380,7 @@ impl Config {
             return true;
         }
         
-        self.api_key.as_ref().map_or(false, |k| k == key)
+        self.api_key.as_ref().is_some_and(|k| k == key)
     }
     
     /// Check if origin is allowed for CORS
@@ -270,6 +393,169 @@ impl Config {
     }
 }
 
+/// Name of the profile built from the top-level SPARQL_* settings
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// One SPARQL store the editor can work against, with its own credentials,
+/// TLS settings and graph limits
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndpointProfile {
+    pub name: String,
+    pub sparql_endpoint: String,
+    pub sparql_update_endpoint: Option<String>,
+    pub sparql_username: Option<String>,
+    pub sparql_password: Option<String>,
+    pub sparql_password_hashed: Option<String>,
+    
+    // TLS settings
+    pub verify_ssl: bool,
+    /// Path to an extra PEM root certificate
+    pub ca_cert: Option<String>,
+    
+    /// Graph Store Protocol endpoint; whole-graph reads and writes use SPARQL without it
+    pub graph_store_endpoint: Option<String>,
+    
+    // Store product and the features it is set up for
+    pub dialect: StoreDialect,
+    pub full_text_search: bool,
+    
+    // Graph visualization limits
+    pub graph_max_nodes: usize,
+    pub graph_max_edges: usize,
+    pub graph_expansion_limit: usize,
+    pub graph_search_limit: usize,
+}
+
+impl EndpointProfile {
+    /// Read profile `name` from PROFILE_<NAME>_* variables. The endpoint is required;
+    /// TLS verification and graph limits fall back to `defaults`; credentials and
+    /// store-specific settings never do.
+    fn from_vars(
+        name: &str,
+        defaults: &EndpointProfile,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let prefix = format!("PROFILE_{}_", name.to_uppercase().replace('-', "_"));
+        let key = |suffix: &str| format!("{}{}", prefix, suffix);
+        let var = |suffix: &str| lookup(&key(suffix));
+        let number = |suffix: &str, fallback: usize| match var(suffix) {
+            Some(value) => value.parse::<usize>()
+                .map_err(|_| ConfigError::InvalidNumber(key(suffix), value)),
+            None => Ok(fallback),
+        };
+        
+        let sparql_endpoint = var("SPARQL_ENDPOINT")
+            .ok_or_else(|| ConfigError::MissingEnvVar(key("SPARQL_ENDPOINT")))?;
+        let dialect = resolve_dialect(&key("SPARQL_DIALECT"), var("SPARQL_DIALECT"), &sparql_endpoint)?;
+        let sparql_update_endpoint = var("SPARQL_UPDATE_ENDPOINT")
+            .or_else(|| dialect.update_endpoint(&sparql_endpoint));
+        
+        let sparql_username = var("SPARQL_USERNAME");
+        let sparql_password = var("SPARQL_PASSWORD");
+        let sparql_password_hashed = var("SPARQL_PASSWORD_HASHED");
+        if sparql_username.is_some() && sparql_password.is_none() && sparql_password_hashed.is_none() {
+            return Err(ConfigError::MissingEnvVar(format!(
+                "{} or {} (when {} is set)",
+                key("SPARQL_PASSWORD"), key("SPARQL_PASSWORD_HASHED"), key("SPARQL_USERNAME")
+            )));
+        }
+        
+        let flag = |suffix: &str, fallback: bool| match var(suffix) {
+            Some(value) => value.parse::<bool>()
+                .map_err(|_| ConfigError::InvalidBool(key(suffix), value)),
+            None => Ok(fallback),
+        };
+        let verify_ssl = flag("VERIFY_SSL", defaults.verify_ssl)?;
+        let graph_store_endpoint = resolve_graph_store(var("GRAPH_STORE_ENDPOINT"), dialect, &sparql_endpoint);
+        
+        Ok(EndpointProfile {
+            name: name.to_string(),
+            sparql_endpoint,
+            sparql_update_endpoint,
+            sparql_username,
+            sparql_password,
+            sparql_password_hashed,
+            verify_ssl,
+            ca_cert: var("CA_CERT").or_else(|| defaults.ca_cert.clone()),
+            graph_store_endpoint,
+            dialect,
+            full_text_search: flag("FULL_TEXT_SEARCH", false)?,
+            graph_max_nodes: number("GRAPH_MAX_NODES", defaults.graph_max_nodes)?,
+            graph_max_edges: number("GRAPH_MAX_EDGES", defaults.graph_max_edges)?,
+            graph_expansion_limit: number("GRAPH_EXPANSION_LIMIT", defaults.graph_expansion_limit)?,
+            graph_search_limit: number("GRAPH_SEARCH_LIMIT", defaults.graph_search_limit)?,
+        })
+    }
+    
+    /// Get the update endpoint, using the configured one or the query endpoint as fallback
+    pub fn get_update_endpoint(&self) -> &str {
+        self.sparql_update_endpoint
+            .as_deref()
+            .unwrap_or(&self.sparql_endpoint)
+    }
+    
+    /// Get the actual password, decoding from base64 if hashed
+    pub fn get_password(&self) -> Option<String> {
+        decode_password(self.sparql_password.as_deref(), self.sparql_password_hashed.as_deref())
+    }
+}
+
+/// Build the profile list: `default` first, then each comma-separated name in `names`
+fn parse_profiles(
+    names: &str,
+    default: EndpointProfile,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<EndpointProfile>, ConfigError> {
+    let mut profiles = vec![default];
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(ConfigError::InvalidProfile(format!(
+                "{} (names may only use letters, digits, '-' and '_')", name
+            )));
+        }
+        if profiles.iter().any(|p| p.name == name) {
+            return Err(ConfigError::InvalidProfile(format!("{} is defined twice", name)));
+        }
+        
+        let profile = EndpointProfile::from_vars(name, &profiles[0], lookup)?;
+        profiles.push(profile);
+    }
+    Ok(profiles)
+}
+
+/// Dialect named by `value`, or detected from the query endpoint when unset or "auto"
+fn resolve_dialect(key: &str, value: Option<String>, query_endpoint: &str) -> Result<StoreDialect, ConfigError> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") | Some("auto") => Ok(StoreDialect::detect(query_endpoint)),
+        Some(name) => name.parse::<StoreDialect>()
+            .map_err(|_| ConfigError::InvalidDialect(key.to_string(), name.to_string())),
+    }
+}
+
+/// Configured graph store endpoint, "none" to turn it off, or the dialect's default layout
+fn resolve_graph_store(value: Option<String>, dialect: StoreDialect, query_endpoint: &str) -> Option<String> {
+    match value.as_deref().map(str::trim) {
+        Some("none") => None,
+        Some(endpoint) => Some(endpoint.to_string()),
+        None => dialect.graph_store_endpoint(query_endpoint),
+    }
+}
+
+fn decode_password(password: Option<&str>, hashed: Option<&str>) -> Option<String> {
+    if let Some(password) = password {
+        Some(password.to_string())
+    } else if let Some(hashed) = hashed {
+        // Decode base64 password
+        use base64::{Engine as _, engine::general_purpose};
+        match general_purpose::STANDARD.decode(hashed) {
+            Ok(decoded) => String::from_utf8(decoded).ok(),
+            Err(_) => None,
+        }
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -287,6 +573,9 @@ mod tests {
         assert_eq!(config.timeout_seconds, 30);
         assert!(config.cache_enabled);
         assert_eq!(config.graph_max_nodes, 500);
+        assert_eq!(config.export_page_size, 10000);
+        assert_eq!(config.import_chunk_size, 1000);
+        assert_eq!(config.import_max_document_size, 268_435_456);
     }
     
     #[test]
@@ -331,4 +620,51 @@ mod tests {
         // Clean up
         env::remove_var("ALLOWED_ORIGINS");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_profiles_fall_back_to_default_limits_but_not_credentials() {
+        let vars: std::collections::HashMap<&str, &str> = [
+            ("PROFILE_STAGING_SPARQL_ENDPOINT", "https://staging:5820/db/query"),
+            ("PROFILE_STAGING_GRAPH_SEARCH_LIMIT", "25"),
+            ("PROFILE_FUSEKI_SANDBOX_SPARQL_ENDPOINT", "http://localhost:3030/ds/sparql"),
+            ("PROFILE_FUSEKI_SANDBOX_VERIFY_SSL", "false"),
+        ].into_iter().collect();
+        let lookup = |key: &str| vars.get(key).map(|v| v.to_string());
+        let default = EndpointProfile {
+            name: DEFAULT_PROFILE.to_string(),
+            sparql_endpoint: "http://localhost:5820/dev/query".to_string(),
+            sparql_update_endpoint: None,
+            sparql_username: Some("admin".to_string()),
+            sparql_password: Some("secret".to_string()),
+            sparql_password_hashed: None,
+            verify_ssl: true,
+            ca_cert: None,
+            graph_store_endpoint: Some("http://localhost:5820/dev".to_string()),
+            dialect: StoreDialect::Stardog,
+            full_text_search: true,
+            graph_max_nodes: 500,
+            graph_max_edges: 1000,
+            graph_expansion_limit: 50,
+            graph_search_limit: 10,
+        };
+        
+        let profiles = parse_profiles("staging, fuseki-sandbox", default.clone(), &lookup).unwrap();
+        
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["default", "staging", "fuseki-sandbox"]);
+        assert_eq!(profiles[1].get_update_endpoint(), "https://staging:5820/db/update");
+        assert_eq!(profiles[1].graph_search_limit, 25);
+        assert_eq!(profiles[1].graph_max_nodes, 500);
+        assert_eq!(profiles[1].sparql_username, None);
+        assert_eq!(profiles[1].dialect, StoreDialect::Stardog);
+        assert_eq!(profiles[1].graph_store_endpoint.as_deref(), Some("https://staging:5820/db"));
+        assert!(!profiles[1].full_text_search);
+        assert!(!profiles[2].verify_ssl);
+        assert_eq!(profiles[2].dialect, StoreDialect::Fuseki);
+        assert_eq!(profiles[2].get_update_endpoint(), "http://localhost:3030/ds/update");
+        
+        let err = parse_profiles("prod", default.clone(), &lookup).unwrap_err();
+        assert_eq!(err.to_string(), "Missing required environment variable: PROFILE_PROD_SPARQL_ENDPOINT");
+        assert!(parse_profiles("default", default, &lookup).is_err());
+    }
+}
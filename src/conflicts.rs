@@ -0,0 +1,252 @@
+//! Conflict checks for undo and redo.
+//!
+//! Reversing a logged change deletes the triples it added and inserts back the
+//! triples it removed. Before doing so the store is checked: the triples to
+//! delete should still be there and the triples to insert should still be
+//! absent. Anything else means someone changed the same data since, and each
+//! such triple is reported as a conflict. The caller picks, per triple, whether
+//! to force the reversal's write anyway, skip it, or abort the whole reversal.
+//!
+//! Blank nodes cannot be looked up: a query reads them as variables, and stores
+//! label them as they please. Triples holding them are left unchecked and
+//! reported as such, to be forced or skipped like any other conflict.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use crate::error::RdfEditorError;
+use crate::models::Triple;
+use crate::sparql::{self, SparqlClient};
+
+/// Past this many triples to check in one graph, the graph is read whole and
+/// diffed instead of asking about each triple
+const ASK_LIMIT: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    /// Leave the store untouched and report the conflicts
+    #[default]
+    Abort,
+    /// Write the triple as the reversal intends, overriding the later change
+    Force,
+    /// Leave the triple as it is now; it is left out of the logged reversal
+    Skip,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// A triple to delete is no longer in the store
+    Missing,
+    /// A triple to insert back is already in the store
+    Present,
+    /// A triple to insert back whose subject and predicate lost a triple this
+    /// reversal expected to delete, i.e. the value was edited again since
+    Edited,
+    /// A triple holding blank nodes, whose state in the store cannot be checked
+    Unchecked,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Conflict {
+    pub triple: Triple,
+    pub kind: ConflictKind,
+    pub resolution: Resolution,
+}
+
+/// Body of an undo or redo request; without one, any conflict aborts
+#[derive(Debug, Deserialize, Default)]
+pub struct ReversalOptions {
+    /// Resolution for conflicts not listed in `resolutions`
+    #[serde(default)]
+    pub on_conflict: Resolution,
+    #[serde(default)]
+    pub resolutions: Vec<TripleResolution>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripleResolution {
+    pub triple: Triple,
+    pub resolution: Resolution,
+}
+
+/// Triples of a reversal whose current state differs from what it expects.
+/// Every triple names its graph, `None` being the default graph.
+pub async fn find_conflicts(
+    client: &SparqlClient,
+    removals: &[Triple],
+    restores: &[Triple],
+    page_size: usize,
+) -> Result<Vec<Conflict>, RdfEditorError> {
+    let checked: Vec<Triple> = removals.iter().chain(restores)
+        .filter(|triple| is_checkable(triple))
+        .cloned()
+        .collect();
+    let present = present(client, &checked, page_size).await?;
+    Ok(classify(removals, restores, &present))
+}
+
+/// The triples of `triples` found in the store
+async fn present(client: &SparqlClient, triples: &[Triple], page_size: usize) -> Result<HashSet<Triple>, RdfEditorError> {
+    let mut present = HashSet::new();
+    for (graph, graph_triples) in sparql::by_graph(triples) {
+        if graph_triples.len() > ASK_LIMIT {
+            let stored: HashSet<Triple> = client.snapshot_graph(&graph, page_size).await?
+                .into_iter()
+                .map(|triple| Triple { graph: (graph != "default").then(|| graph.clone()), ..triple })
+                .collect();
+            present.extend(graph_triples.into_iter().filter(|triple| stored.contains(triple)));
+        } else {
+            for triple in graph_triples {
+                if client.contains_triple(&triple).await? {
+                    present.insert(triple);
+                }
+            }
+        }
+    }
+    Ok(present)
+}
+
+fn is_checkable(triple: &Triple) -> bool {
+    triple.blank_nodes().is_empty()
+}
+
+fn classify(removals: &[Triple], restores: &[Triple], present: &HashSet<Triple>) -> Vec<Conflict> {
+    let conflict = |triple: &Triple, kind| Conflict {
+        triple: triple.clone(),
+        kind,
+        resolution: Resolution::Abort,
+    };
+    let missing: Vec<&Triple> = removals.iter()
+        .filter(|triple| is_checkable(triple) && !present.contains(*triple))
+        .collect();
+    
+    let mut conflicts = Vec::new();
+    for triple in removals {
+        if !is_checkable(triple) {
+            conflicts.push(conflict(triple, ConflictKind::Unchecked));
+        } else if !present.contains(triple) {
+            conflicts.push(conflict(triple, ConflictKind::Missing));
+        }
+    }
+    for triple in restores {
+        if !is_checkable(triple) {
+            conflicts.push(conflict(triple, ConflictKind::Unchecked));
+        } else if present.contains(triple) {
+            conflicts.push(conflict(triple, ConflictKind::Present));
+        } else if missing.iter().any(|m| {
+            m.graph == triple.graph && m.subject == triple.subject && m.predicate == triple.predicate
+        }) {
+            conflicts.push(conflict(triple, ConflictKind::Edited));
+        }
+    }
+    conflicts
+}
+
+/// A reversal with the skipped triples left out
+#[derive(Debug)]
+pub struct Resolved {
+    pub removals: Vec<Triple>,
+    pub restores: Vec<Triple>,
+    /// The conflicts, each forced or skipped
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Apply the chosen resolutions, or hand back every conflict when any of them aborts
+pub fn resolve(
+    removals: Vec<Triple>,
+    restores: Vec<Triple>,
+    mut conflicts: Vec<Conflict>,
+    options: &ReversalOptions,
+) -> Result<Resolved, Vec<Conflict>> {
+    for conflict in &mut conflicts {
+        conflict.resolution = options.resolutions.iter()
+            .find(|r| r.triple == conflict.triple)
+            .map_or(options.on_conflict, |r| r.resolution);
+    }
+    if conflicts.iter().any(|c| c.resolution == Resolution::Abort) {
+        return Err(conflicts);
+    }
+    
+    let skipped: HashSet<&Triple> = conflicts.iter()
+        .filter(|c| c.resolution == Resolution::Skip)
+        .map(|c| &c.triple)
+        .collect();
+    Ok(Resolved {
+        removals: removals.into_iter().filter(|t| !skipped.contains(t)).collect(),
+        restores: restores.into_iter().filter(|t| !skipped.contains(t)).collect(),
+        conflicts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RdfNode;
+    use crate::store::memory::MemoryStore;
+    
+    fn triple(predicate: &str, object: &str) -> Triple {
+        Triple {
+            subject: "http://example.org/s".to_string(),
+            predicate: format!("http://example.org/{}", predicate),
+            object: RdfNode::Uri { value: format!("http://example.org/{}", object) },
+            graph: Some("http://example.org/g".to_string()),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_find_conflicts_after_an_intervening_edit() {
+        // Replaced p a -> b, then someone else replaced p b -> c and added q d
+        let client = SparqlClient::with_store(Box::new(MemoryStore::new()), false);
+        client.add_triples_batch(&[triple("p", "c"), triple("q", "d")], "http://example.org/g").await.unwrap();
+        
+        let removals = vec![triple("p", "b"), triple("q", "x")];
+        let restores = vec![triple("p", "a"), triple("q", "d"), triple("r", "e")];
+        let conflicts = find_conflicts(&client, &removals, &restores, 100).await.unwrap();
+        
+        let kinds: Vec<(Triple, ConflictKind)> = conflicts.into_iter().map(|c| (c.triple, c.kind)).collect();
+        assert_eq!(kinds, vec![
+            (triple("p", "b"), ConflictKind::Missing),
+            (triple("q", "x"), ConflictKind::Missing),
+            (triple("p", "a"), ConflictKind::Edited),
+            (triple("q", "d"), ConflictKind::Present),
+        ]);
+    }
+    
+    #[tokio::test]
+    async fn test_blank_node_triples_are_reported_unchecked() {
+        // The store holds a different blank node; an ASK would still match it
+        let client = SparqlClient::with_store(Box::new(MemoryStore::new()), false);
+        let blank = |label: &str| Triple { object: RdfNode::Blank { value: label.to_string() }, ..triple("p", "a") };
+        client.add_triples_batch(&[blank("other")], "http://example.org/g").await.unwrap();
+        
+        let conflicts = find_conflicts(&client, &[blank("x")], &[blank("y")], 100).await.unwrap();
+        let kinds: Vec<(Triple, ConflictKind)> = conflicts.into_iter().map(|c| (c.triple, c.kind)).collect();
+        assert_eq!(kinds, vec![
+            (blank("x"), ConflictKind::Unchecked),
+            (blank("y"), ConflictKind::Unchecked),
+        ]);
+    }
+    
+    #[test]
+    fn test_resolve_applies_per_triple_choices() {
+        let removals = vec![triple("p", "b")];
+        let restores = vec![triple("p", "a"), triple("r", "e")];
+        let present = HashSet::new();
+        let conflicts = classify(&removals, &restores, &present);
+        assert_eq!(conflicts.len(), 2);
+        
+        let aborted = resolve(removals.clone(), restores.clone(), conflicts.clone(), &ReversalOptions::default());
+        assert_eq!(aborted.unwrap_err().len(), 2);
+        
+        let options = ReversalOptions {
+            on_conflict: Resolution::Force,
+            resolutions: vec![TripleResolution { triple: triple("p", "a"), resolution: Resolution::Skip }],
+        };
+        let resolved = resolve(removals, restores, conflicts, &options).unwrap();
+        assert_eq!(resolved.removals, vec![triple("p", "b")]);
+        assert_eq!(resolved.restores, vec![triple("r", "e")]);
+        assert_eq!(resolved.conflicts[0].resolution, Resolution::Force);
+        assert_eq!(resolved.conflicts[1].resolution, Resolution::Skip);
+    }
+}
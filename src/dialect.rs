@@ -0,0 +1,240 @@
+//! Store dialects: what each triple store product offers beyond plain SPARQL 1.1.
+//!
+//! A dialect knows how a store lays out its endpoints, whether it has a
+//! multi-request transaction API, where its namespace list lives, how to ask
+//! its full-text index and how to switch reasoning per query. `SparqlClient`
+//! asks the dialect for the best mechanism and falls back to standard SPARQL
+//! wherever it returns `None`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+use crate::formats::escape_literal;
+use crate::store::MEMORY_SCHEME;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreDialect {
+    Generic,
+    Stardog,
+    Fuseki,
+    GraphDb,
+    Virtuoso,
+    Neptune,
+    /// The embedded store selected by a `memory:` endpoint
+    Memory,
+}
+
+/// How a store groups several requests into one transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionApi {
+    /// `POST {db}/transaction/begin`, then `{db}/{tx}/update` and commit or rollback
+    Stardog,
+    /// `POST {repository}/transactions`, then `PUT {location}?action=...`
+    Rdf4j,
+}
+
+impl StoreDialect {
+    pub fn name(self) -> &'static str {
+        match self {
+            StoreDialect::Generic => "generic",
+            StoreDialect::Stardog => "stardog",
+            StoreDialect::Fuseki => "fuseki",
+            StoreDialect::GraphDb => "graphdb",
+            StoreDialect::Virtuoso => "virtuoso",
+            StoreDialect::Neptune => "neptune",
+            StoreDialect::Memory => "memory",
+        }
+    }
+    
+    /// Guess the product from the query endpoint: the default ports and the
+    /// path layouts are distinctive enough for the common setups
+    pub fn detect(query_endpoint: &str) -> Self {
+        if query_endpoint.starts_with(MEMORY_SCHEME) {
+            return StoreDialect::Memory;
+        }
+        let Ok(url) = Url::parse(query_endpoint) else {
+            return StoreDialect::Generic;
+        };
+        let host = url.host_str().unwrap_or_default();
+        let path = url.path();
+        
+        if host.ends_with(".neptune.amazonaws.com") || url.port() == Some(8182) {
+            StoreDialect::Neptune
+        } else if path.contains("/repositories/") || url.port() == Some(7200) {
+            StoreDialect::GraphDb
+        } else if host.ends_with(".stardog.cloud") || url.port() == Some(5820) {
+            StoreDialect::Stardog
+        } else if url.port() == Some(3030) {
+            StoreDialect::Fuseki
+        } else if url.port() == Some(8890) {
+            StoreDialect::Virtuoso
+        } else {
+            StoreDialect::Generic
+        }
+    }
+    
+    /// Update endpoint implied by the query endpoint; `None` when the query
+    /// endpoint accepts updates itself
+    pub fn update_endpoint(self, query_endpoint: &str) -> Option<String> {
+        match self {
+            // Generic keeps the original guess, which matches Stardog's layout
+            StoreDialect::Generic | StoreDialect::Stardog => query_endpoint
+                .strip_suffix("/query")
+                .map(|base| format!("{}/update", base)),
+            StoreDialect::Fuseki => fuseki_dataset(query_endpoint)
+                .map(|dataset| format!("{}/update", dataset)),
+            StoreDialect::GraphDb => Some(format!("{}/statements", query_endpoint.trim_end_matches('/'))),
+            StoreDialect::Virtuoso | StoreDialect::Neptune | StoreDialect::Memory => None,
+        }
+    }
+    
+    /// Graph Store Protocol endpoint the product serves out of the box. Virtuoso
+    /// and Neptune need it enabled first, so they must be configured explicitly.
+    pub fn graph_store_endpoint(self, query_endpoint: &str) -> Option<String> {
+        match self {
+            StoreDialect::Stardog => stardog_database(query_endpoint),
+            StoreDialect::Fuseki => fuseki_dataset(query_endpoint)
+                .map(|dataset| format!("{}/data", dataset)),
+            StoreDialect::GraphDb => Some(format!("{}/rdf-graphs/service", query_endpoint.trim_end_matches('/'))),
+            StoreDialect::Generic | StoreDialect::Virtuoso | StoreDialect::Neptune | StoreDialect::Memory => None,
+        }
+    }
+    
+    /// Transaction API and the database or repository URL it hangs off
+    pub fn transaction_api(self, query_endpoint: &str) -> Option<(TransactionApi, String)> {
+        match self {
+            StoreDialect::Stardog => stardog_database(query_endpoint)
+                .map(|database| (TransactionApi::Stardog, database)),
+            StoreDialect::GraphDb => Some((TransactionApi::Rdf4j, query_endpoint.trim_end_matches('/').to_string())),
+            _ => None,
+        }
+    }
+    
+    /// URL listing the prefixes stored with the database
+    pub fn namespaces_endpoint(self, query_endpoint: &str) -> Option<String> {
+        match self {
+            StoreDialect::Stardog => stardog_database(query_endpoint)
+                .map(|database| format!("{}/namespaces", database)),
+            StoreDialect::GraphDb => Some(format!("{}/namespaces", query_endpoint.trim_end_matches('/'))),
+            _ => None,
+        }
+    }
+    
+    /// Graph pattern matching `label` (or, for Fuseki, `resource`) against the
+    /// store's full-text index. The index has to exist; Neptune needs an
+    /// OpenSearch SERVICE and is left to the plain CONTAINS filter.
+    pub fn text_search(self, resource: &str, label: &str, term: &str) -> Option<String> {
+        match self {
+            StoreDialect::Stardog => Some(format!(
+                "{} <tag:stardog:api:property:textMatch> \"{}\" .", label, escape_literal(term)
+            )),
+            StoreDialect::Fuseki => Some(format!(
+                "{} <http://jena.apache.org/text#query> \"{}\" .", resource, escape_literal(term)
+            )),
+            StoreDialect::GraphDb => Some(format!(
+                "{} <http://www.ontotext.com/fts> \"{}\" .", label, escape_literal(term)
+            )),
+            // bif:contains takes a quoted phrase inside the literal
+            StoreDialect::Virtuoso => Some(format!(
+                "{} bif:contains '\"{}\"' .", label, escape_literal(&term.replace(['"', '\''], " "))
+            )),
+            StoreDialect::Generic | StoreDialect::Neptune | StoreDialect::Memory => None,
+        }
+    }
+    
+    /// Query parameter that switches reasoning per request
+    pub fn reasoning_parameter(self) -> Option<&'static str> {
+        match self {
+            StoreDialect::Stardog => Some("reasoning"),
+            StoreDialect::GraphDb => Some("infer"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StoreDialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StoreDialect {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "generic" => Ok(StoreDialect::Generic),
+            "stardog" => Ok(StoreDialect::Stardog),
+            "fuseki" | "jena" => Ok(StoreDialect::Fuseki),
+            "graphdb" | "rdf4j" => Ok(StoreDialect::GraphDb),
+            "virtuoso" => Ok(StoreDialect::Virtuoso),
+            "neptune" => Ok(StoreDialect::Neptune),
+            "memory" => Ok(StoreDialect::Memory),
+            other => Err(format!("unknown store dialect {}", other)),
+        }
+    }
+}
+
+/// `http://host:5820/db/query` -> `http://host:5820/db`
+fn stardog_database(query_endpoint: &str) -> Option<String> {
+    query_endpoint.strip_suffix("/query").map(String::from)
+}
+
+/// `http://host:3030/ds/query` or `.../ds/sparql` -> `http://host:3030/ds`
+fn fuseki_dataset(query_endpoint: &str) -> Option<String> {
+    query_endpoint
+        .strip_suffix("/query")
+        .or_else(|| query_endpoint.strip_suffix("/sparql"))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_detect_from_endpoint_layout() {
+        assert_eq!(StoreDialect::detect("http://localhost:5820/db/query"), StoreDialect::Stardog);
+        assert_eq!(StoreDialect::detect("https://sd-1234.stardog.cloud:5820/db/query"), StoreDialect::Stardog);
+        assert_eq!(StoreDialect::detect("http://localhost:3030/ds/sparql"), StoreDialect::Fuseki);
+        assert_eq!(StoreDialect::detect("http://graphdb.example.com/repositories/repo"), StoreDialect::GraphDb);
+        assert_eq!(StoreDialect::detect("http://localhost:8890/sparql"), StoreDialect::Virtuoso);
+        assert_eq!(StoreDialect::detect("https://db.cluster-x.us-east-1.neptune.amazonaws.com:8182/sparql"), StoreDialect::Neptune);
+        assert_eq!(StoreDialect::detect("https://example.com/sparql"), StoreDialect::Generic);
+        assert_eq!(StoreDialect::detect("not a url"), StoreDialect::Generic);
+        assert_eq!(StoreDialect::detect("memory:data/seed.ttl"), StoreDialect::Memory);
+    }
+    
+    #[test]
+    fn test_endpoint_layouts() {
+        let graphdb = "http://localhost:7200/repositories/repo";
+        assert_eq!(StoreDialect::GraphDb.update_endpoint(graphdb).as_deref(), Some("http://localhost:7200/repositories/repo/statements"));
+        assert_eq!(StoreDialect::GraphDb.graph_store_endpoint(graphdb).as_deref(), Some("http://localhost:7200/repositories/repo/rdf-graphs/service"));
+        
+        let fuseki = "http://localhost:3030/ds/sparql";
+        assert_eq!(StoreDialect::Fuseki.update_endpoint(fuseki).as_deref(), Some("http://localhost:3030/ds/update"));
+        assert_eq!(StoreDialect::Fuseki.graph_store_endpoint(fuseki).as_deref(), Some("http://localhost:3030/ds/data"));
+        
+        let stardog = "http://localhost:5820/db/query";
+        assert_eq!(StoreDialect::Stardog.graph_store_endpoint(stardog).as_deref(), Some("http://localhost:5820/db"));
+        assert_eq!(StoreDialect::Stardog.transaction_api(stardog), Some((TransactionApi::Stardog, "http://localhost:5820/db".to_string())));
+        
+        assert_eq!(StoreDialect::Virtuoso.update_endpoint("http://localhost:8890/sparql"), None);
+        assert_eq!(StoreDialect::Virtuoso.transaction_api("http://localhost:8890/sparql"), None);
+    }
+    
+    #[test]
+    fn test_text_search_escapes_the_term() {
+        assert_eq!(
+            StoreDialect::Stardog.text_search("?r", "?label", "say \"hi\"").unwrap(),
+            "?label <tag:stardog:api:property:textMatch> \"say \\\"hi\\\"\" ."
+        );
+        assert_eq!(
+            StoreDialect::Virtuoso.text_search("?r", "?label", "o'brien").unwrap(),
+            "?label bif:contains '\"o brien\"' ."
+        );
+        assert_eq!(StoreDialect::Neptune.text_search("?r", "?label", "x"), None);
+    }
+}
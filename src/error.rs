@@ -12,6 +12,9 @@ pub enum RdfEditorError {
     #[error("HTTP request error: {0}")]
     Http(#[from] reqwest::Error),
     
+    #[error("Timeout: {0}")]
+    Timeout(String),
+    
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
@@ -37,12 +40,34 @@ pub enum RdfEditorError {
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+impl RdfEditorError {
+    /// A copy for callers sharing one outcome. Errors wrapping another library's
+    /// error become the variant answering with the same status.
+    pub fn duplicate(&self) -> Self {
+        match self {
+            RdfEditorError::Configuration(m) => RdfEditorError::Configuration(m.clone()),
+            RdfEditorError::Sparql(m) => RdfEditorError::Sparql(m.clone()),
+            RdfEditorError::Http(e) => RdfEditorError::Sparql(e.to_string()),
+            RdfEditorError::Timeout(m) => RdfEditorError::Timeout(m.clone()),
+            RdfEditorError::InvalidInput(m) => RdfEditorError::InvalidInput(m.clone()),
+            RdfEditorError::NotFound(m) => RdfEditorError::NotFound(m.clone()),
+            RdfEditorError::Cache(m) => RdfEditorError::Cache(m.clone()),
+            RdfEditorError::GraphLimitExceeded(m) => RdfEditorError::GraphLimitExceeded(m.clone()),
+            RdfEditorError::InvalidHeader(e) => RdfEditorError::Configuration(e.to_string()),
+            RdfEditorError::Serialization(_) | RdfEditorError::Base64Decode(_) | RdfEditorError::Utf8Error(_) => {
+                RdfEditorError::InvalidInput(self.to_string())
+            },
+        }
+    }
+}
+
 impl ResponseError for RdfEditorError {
     fn error_response(&self) -> HttpResponse {
         let status = match self {
             RdfEditorError::Configuration(_) => StatusCode::INTERNAL_SERVER_ERROR,
             RdfEditorError::Sparql(_) => StatusCode::BAD_GATEWAY,
             RdfEditorError::Http(_) => StatusCode::BAD_GATEWAY,
+            RdfEditorError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
             RdfEditorError::Serialization(_) => StatusCode::BAD_REQUEST,
             RdfEditorError::InvalidInput(_) => StatusCode::BAD_REQUEST,
             RdfEditorError::NotFound(_) => StatusCode::NOT_FOUND,
@@ -59,6 +84,7 @@ impl ResponseError for RdfEditorError {
                 RdfEditorError::Configuration(_) => "configuration",
                 RdfEditorError::Sparql(_) => "sparql",
                 RdfEditorError::Http(_) => "http",
+                RdfEditorError::Timeout(_) => "timeout",
                 RdfEditorError::Serialization(_) => "serialization",
                 RdfEditorError::InvalidInput(_) => "invalid_input",
                 RdfEditorError::NotFound(_) => "not_found",
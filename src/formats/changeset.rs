@@ -0,0 +1,344 @@
+//! Changesets: runs of logged changes in a form another store can replay.
+//!
+//! RDF Patch writes each change as `TX .`, its `D` rows, its `A` rows and
+//! `TC .`, every term in N-Triples syntax with the graph as an optional fourth
+//! term. Prefixed names (`PA` rows) are not supported; `H` and `PA`/`PD` rows
+//! are skipped and `TA .` drops the change it closes.
+//!
+//! The SPARQL Update form writes each change as DELETE DATA then INSERT DATA,
+//! introduced by a `# Transaction` comment so the boundaries survive. Reading
+//! accepts PREFIX and BASE declarations but no other operations. Without the
+//! comments, a change ends where a DELETE DATA follows an INSERT DATA.
+
+use crate::error::RdfEditorError;
+use crate::models::{ChangesetFormat, Triple};
+use crate::sparql;
+use super::{ntriples, turtle};
+
+/// Comment introducing each change in the SPARQL Update form
+const TRANSACTION_COMMENT: &str = "# Transaction";
+
+/// One change of a changeset: deletions applied before insertions
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Change {
+    /// Where the change came from, such as the logged transaction's id and description
+    pub label: Option<String>,
+    pub deletions: Vec<Triple>,
+    pub insertions: Vec<Triple>,
+}
+
+impl Change {
+    pub fn is_empty(&self) -> bool {
+        self.deletions.is_empty() && self.insertions.is_empty()
+    }
+}
+
+pub fn write(format: ChangesetFormat, changes: &[Change]) -> String {
+    match format {
+        ChangesetFormat::RdfPatch => write_patch(changes),
+        ChangesetFormat::SparqlUpdate => write_sparql(changes),
+    }
+}
+
+pub fn parse(format: ChangesetFormat, input: &str) -> Result<Vec<Change>, RdfEditorError> {
+    match format {
+        ChangesetFormat::RdfPatch => parse_patch(input),
+        ChangesetFormat::SparqlUpdate => parse_sparql(input),
+    }
+}
+
+fn write_patch(changes: &[Change]) -> String {
+    let mut output = String::new();
+    for change in changes {
+        output.push_str("TX .\n");
+        for (row, triples) in [("D", &change.deletions), ("A", &change.insertions)] {
+            for line in ntriples::serialize_quads(triples).lines() {
+                output.push_str(&format!("{} {}\n", row, line));
+            }
+        }
+        output.push_str("TC .\n");
+    }
+    output
+}
+
+fn parse_patch(input: &str) -> Result<Vec<Change>, RdfEditorError> {
+    let mut changes = Vec::new();
+    // The change being read, and whether a TX opened it
+    let mut current: Option<(Change, bool)> = None;
+
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        let error = |message: &str| RdfEditorError::InvalidInput(format!("Line {}: {}", number + 1, message));
+        let (row, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match row {
+            "" => {},
+            _ if row.starts_with('#') => {},
+            "H" | "PA" | "PD" => {},
+            "TX" => {
+                if current.as_ref().is_some_and(|(_, opened)| *opened) {
+                    return Err(error("TX inside an open transaction"));
+                }
+                changes.extend(current.take().map(|(change, _)| change));
+                current = Some((Change::default(), true));
+            },
+            "TC" | "TA" => match current.take() {
+                Some((change, true)) => {
+                    if row == "TC" {
+                        changes.push(change);
+                    }
+                },
+                _ => return Err(error(&format!("{} without TX", row))),
+            },
+            "A" | "D" => {
+                let triples = turtle::parse_nquads(rest, None).map_err(|e| error(&e.to_string()))?;
+                let (change, _) = current.get_or_insert_with(|| (Change::default(), false));
+                if row == "A" {
+                    change.insertions.extend(triples);
+                } else {
+                    change.deletions.extend(triples);
+                }
+            },
+            _ => return Err(error(&format!("Unknown row {}", row))),
+        }
+    }
+
+    match current {
+        Some((_, true)) => Err(RdfEditorError::InvalidInput("Transaction not closed with TC".to_string())),
+        Some((change, false)) => {
+            changes.push(change);
+            Ok(changes)
+        },
+        None => Ok(changes),
+    }
+}
+
+fn write_sparql(changes: &[Change]) -> String {
+    let mut operations = Vec::new();
+    for change in changes {
+        let mut updates = sparql::change_updates(&change.deletions, &change.insertions);
+        if let Some(first) = updates.first_mut() {
+            let comment = match &change.label {
+                Some(label) => format!("{} {}", TRANSACTION_COMMENT, label.replace(['\n', '\r'], " ")),
+                None => TRANSACTION_COMMENT.to_string(),
+            };
+            *first = format!("{}\n{}", comment, first);
+        }
+        operations.extend(updates);
+    }
+
+    if operations.is_empty() {
+        return String::new();
+    }
+    format!("{} ;\n", operations.join(" ;\n"))
+}
+
+fn parse_sparql(input: &str) -> Result<Vec<Change>, RdfEditorError> {
+    let mut scanner = Scanner { input, pos: 0 };
+    let mut prologue = String::new();
+    let mut changes: Vec<Change> = Vec::new();
+    let mut current = Change::default();
+
+    while let Some(c) = scanner.skip_ws() {
+        match c {
+            '#' => {
+                let comment = scanner.take_line();
+                if let Some(label) = comment.strip_prefix(TRANSACTION_COMMENT) {
+                    if !current.is_empty() {
+                        changes.push(std::mem::take(&mut current));
+                    }
+                    current.label = Some(label.trim().to_string()).filter(|label| !label.is_empty());
+                }
+            },
+            ';' => scanner.pos += 1,
+            _ => {
+                let keyword = scanner.take_word();
+                match keyword.to_ascii_uppercase().as_str() {
+                    "PREFIX" | "BASE" => {
+                        let declaration = scanner.take_through('>')?;
+                        prologue.push_str(&format!("{}{}\n", keyword, declaration));
+                    },
+                    operation @ ("INSERT" | "DELETE") => {
+                        scanner.skip_ws();
+                        if !scanner.take_word().eq_ignore_ascii_case("DATA") {
+                            return Err(RdfEditorError::InvalidInput(format!(
+                                "Only INSERT DATA and DELETE DATA can be imported, found {} without DATA", operation
+                            )));
+                        }
+                        // SPARQL lets the last triple go without the dot TriG wants
+                        let block = scanner.take_block()?.trim();
+                        let dot = if block.ends_with(['.', '}']) || block.is_empty() { "" } else { " ." };
+                        let triples = turtle::parse_trig(&format!("{}{}{}", prologue, block, dot), None)?;
+                        if operation == "DELETE" {
+                            // Deletions are applied first, so a delete after an insert is a new change
+                            if !current.insertions.is_empty() {
+                                changes.push(std::mem::take(&mut current));
+                            }
+                            current.deletions.extend(triples);
+                        } else {
+                            current.insertions.extend(triples);
+                        }
+                    },
+                    _ => return Err(RdfEditorError::InvalidInput(format!(
+                        "Only INSERT DATA and DELETE DATA can be imported, found {}",
+                        if keyword.is_empty() { c.to_string() } else { keyword.to_string() }
+                    ))),
+                }
+            },
+        }
+    }
+
+    if !current.is_empty() {
+        changes.push(current);
+    }
+    Ok(changes)
+}
+
+/// Cursor over a SPARQL Update script, just enough to find its operations
+struct Scanner<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /// Skip whitespace and return the next character
+    fn skip_ws(&mut self) -> Option<char> {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+        self.rest().chars().next()
+    }
+
+    fn take_line(&mut self) -> &'a str {
+        let rest = self.rest();
+        let line = rest.split('\n').next().unwrap_or_default();
+        self.pos += line.len();
+        line.trim_end()
+    }
+
+    fn take_word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Everything up to and including `end`
+    fn take_through(&mut self, end: char) -> Result<&'a str, RdfEditorError> {
+        let rest = self.rest();
+        let len = rest.find(end)
+            .ok_or_else(|| RdfEditorError::InvalidInput(format!("Expected {}", end)))? + end.len_utf8();
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    /// The inside of the `{ ... }` block at the cursor, stepping over IRIs,
+    /// strings and comments that may hold braces
+    fn take_block(&mut self) -> Result<&'a str, RdfEditorError> {
+        if self.skip_ws() != Some('{') {
+            return Err(RdfEditorError::InvalidInput("Expected { after DATA".to_string()));
+        }
+        let rest = self.rest();
+        let mut depth = 0;
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += i + 1;
+                        return Ok(&rest[1..i]);
+                    }
+                },
+                '<' => {
+                    chars.find(|(_, c)| *c == '>');
+                },
+                '#' => {
+                    chars.find(|(_, c)| *c == '\n');
+                },
+                '"' | '\'' => {
+                    while let Some((_, next)) = chars.next() {
+                        match next {
+                            '\\' => {
+                                chars.next();
+                            },
+                            _ if next == c => break,
+                            _ => {},
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        Err(RdfEditorError::InvalidInput("Unclosed { in DATA block".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RdfNode;
+
+    fn triple(object: &str, graph: Option<&str>) -> Triple {
+        Triple {
+            subject: "http://example.com/s".to_string(),
+            predicate: "http://example.com/p".to_string(),
+            object: RdfNode::Literal { value: object.to_string(), datatype: None, language: None },
+            graph: graph.map(String::from),
+        }
+    }
+
+    fn changes() -> Vec<Change> {
+        vec![
+            Change {
+                label: Some("1: replaced {a}".to_string()),
+                deletions: vec![triple("a}", Some("http://example.com/g"))],
+                insertions: vec![triple("b", Some("http://example.com/g")), triple("c", None)],
+            },
+            Change {
+                label: Some("2: added d".to_string()),
+                deletions: Vec::new(),
+                insertions: vec![triple("d", None)],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_rdf_patch_round_trip() {
+        let patch = write(ChangesetFormat::RdfPatch, &changes());
+        assert!(patch.starts_with("TX .\nD <http://example.com/s> <http://example.com/p> \"a}\" <http://example.com/g> .\n"));
+
+        let parsed = parse(ChangesetFormat::RdfPatch, &patch).unwrap();
+        let expected: Vec<Change> = changes().into_iter().map(|change| Change { label: None, ..change }).collect();
+        assert_eq!(parsed, expected);
+
+        let aborted = format!("H id <urn:uuid:1> .\n{}TX .\nA <http://example.com/s> <http://example.com/p> \"x\" .\nTA .\n", patch);
+        assert_eq!(parse(ChangesetFormat::RdfPatch, &aborted).unwrap().len(), 2);
+        assert!(parse(ChangesetFormat::RdfPatch, "TX .\nTX .\n").is_err());
+        assert!(parse(ChangesetFormat::RdfPatch, "TX .\nA <http://example.com/s> .\nTC .\n").is_err());
+    }
+
+    #[test]
+    fn test_sparql_update_round_trip() {
+        let script = write(ChangesetFormat::SparqlUpdate, &changes());
+        assert!(script.starts_with("# Transaction 1: replaced {a}\nDELETE DATA { GRAPH <http://example.com/g> {"));
+        assert_eq!(parse(ChangesetFormat::SparqlUpdate, &script).unwrap(), changes());
+
+        // Without the comments, the boundaries come from the order of the operations
+        let script = concat!(
+            "PREFIX ex: <http://example.com/>\n",
+            "INSERT DATA { ex:s ex:p \"a\" } ;\n",
+            "DELETE DATA { ex:s ex:p \"a\" } ; INSERT DATA { GRAPH ex:g { ex:s ex:p \"b\" } }",
+        );
+        let parsed = parse(ChangesetFormat::SparqlUpdate, script).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].deletions, vec![triple("a", None)]);
+        assert_eq!(parsed[1].insertions, vec![triple("b", Some("http://example.com/g"))]);
+
+        assert!(parse(ChangesetFormat::SparqlUpdate, "DELETE WHERE { ?s ?p ?o }").is_err());
+        assert!(parse(ChangesetFormat::SparqlUpdate, "CLEAR ALL").is_err());
+    }
+}
@@ -0,0 +1,1245 @@
+//! JSON-LD reading and writing.
+//!
+//! Serialization follows the JSON-LD 1.1 "RDF to JSON-LD" algorithm and can
+//! emit expanded, compacted or flattened documents. Compaction uses a context
+//! built from the namespace list, so `ex:Thing` style names match the rest of
+//! the editor. Parsing implements the expansion rules needed to turn inline
+//! contexts, `@language`, `@type`, `@list` and `@graph` into triples; remote
+//! contexts are not fetched.
+
+use std::collections::{HashMap, HashSet};
+use serde_json::{json, Map, Value};
+use crate::error::RdfEditorError;
+use crate::models::{JsonLdForm, Namespace, RdfNode, Triple};
+use super::{
+    check_terms, is_absolute_iri, resolve_iri, PrefixMap, StreamWriter,
+    RDF_NS, RDF_TYPE, XSD_BOOLEAN, XSD_DOUBLE, XSD_INTEGER, XSD_STRING,
+};
+
+/// Serialize triples as a JSON-LD document in the requested form
+pub fn serialize(triples: &[Triple], namespaces: &[Namespace], form: JsonLdForm) -> Result<String, RdfEditorError> {
+    let document = to_json_ld(triples, namespaces, form);
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+/// Build the JSON-LD document for a set of triples
+pub fn to_json_ld(triples: &[Triple], namespaces: &[Namespace], form: JsonLdForm) -> Value {
+    let map = NodeMap::new(triples);
+
+    match form {
+        JsonLdForm::Expanded => Value::Array(
+            map.top_level()
+                .map(|node| map.expanded_node(node))
+                .collect()
+        ),
+        JsonLdForm::Compacted | JsonLdForm::Flattened => {
+            let mut compactor = Compactor::new(&map, namespaces, form == JsonLdForm::Flattened);
+            let graph = compactor.compact_graph();
+            compactor.document(graph)
+        },
+    }
+}
+
+/// Streaming JSON-LD writer. Emits one node object per subject as triples arrive;
+/// compacted output uses the full namespace context and is flat, like the flattened form.
+pub struct JsonLdStreamWriter {
+    namespaces: Vec<Namespace>,
+    form: JsonLdForm,
+    current: Vec<Triple>,
+    written: usize,
+}
+
+impl JsonLdStreamWriter {
+    pub fn new(namespaces: &[Namespace], form: JsonLdForm) -> Self {
+        JsonLdStreamWriter { namespaces: namespaces.to_vec(), form, current: Vec::new(), written: 0 }
+    }
+
+    fn flush_node(&mut self, out: &mut String) -> Result<(), RdfEditorError> {
+        if self.current.is_empty() {
+            return Ok(());
+        }
+
+        let triples = std::mem::take(&mut self.current);
+        let map = NodeMap::new(&triples);
+        let node = match self.form {
+            JsonLdForm::Expanded => map.expanded_node(&map.nodes[0]),
+            JsonLdForm::Compacted | JsonLdForm::Flattened => {
+                Compactor::new(&map, &self.namespaces, true).compact_node(&map.nodes[0], true)
+            },
+        };
+
+        if self.written > 0 {
+            out.push_str(",\n");
+        }
+        out.push_str(&serde_json::to_string(&node)?);
+        self.written += 1;
+        Ok(())
+    }
+}
+
+impl StreamWriter for JsonLdStreamWriter {
+    fn start(&mut self) -> Result<String, RdfEditorError> {
+        if self.form == JsonLdForm::Expanded {
+            return Ok("[\n".to_string());
+        }
+        let context: Map<String, Value> = self.namespaces.iter()
+            .map(|ns| (ns.prefix.clone(), json!(ns.uri)))
+            .collect();
+        Ok(format!("{{\"@context\": {},\n\"@graph\": [\n", serde_json::to_string(&context)?))
+    }
+
+    fn write(&mut self, triples: &[Triple]) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        for triple in triples {
+            if self.current.first().is_some_and(|t| t.subject != triple.subject) {
+                self.flush_node(&mut out)?;
+            }
+            self.current.push(triple.clone());
+        }
+        Ok(out)
+    }
+
+    fn finish(&mut self) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        self.flush_node(&mut out)?;
+        out.push_str(if self.form == JsonLdForm::Expanded { "\n]\n" } else { "\n]}\n" });
+        Ok(out)
+    }
+}
+
+// RDF -> JSON-LD
+
+struct NodeObject<'a> {
+    id: &'a str,
+    types: Vec<&'a str>,
+    properties: Vec<(&'a str, Vec<&'a RdfNode>)>,
+}
+
+/// Triples grouped by subject, with `rdf:first`/`rdf:rest` chains recognised as lists
+struct NodeMap<'a> {
+    nodes: Vec<NodeObject<'a>>,
+    index: HashMap<&'a str, usize>,
+    // Blank node label -> number of references from object position
+    object_refs: HashMap<&'a str, usize>,
+    // List heads (blank node labels) and their items
+    lists: HashMap<&'a str, Vec<&'a RdfNode>>,
+    // Blank nodes that only exist as list cells
+    list_cells: HashSet<&'a str>,
+}
+
+impl<'a> NodeMap<'a> {
+    fn new(triples: &'a [Triple]) -> Self {
+        let mut nodes: Vec<NodeObject> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut object_refs: HashMap<&str, usize> = HashMap::new();
+
+        for triple in triples {
+            let i = *index.entry(triple.subject.as_str()).or_insert_with(|| {
+                nodes.push(NodeObject { id: triple.subject.as_str(), types: Vec::new(), properties: Vec::new() });
+                nodes.len() - 1
+            });
+            let node = &mut nodes[i];
+
+            match &triple.object {
+                RdfNode::Uri { value } if triple.predicate == RDF_TYPE => {
+                    if !node.types.contains(&value.as_str()) {
+                        node.types.push(value.as_str());
+                    }
+                    continue;
+                },
+                RdfNode::Blank { value } => {
+                    *object_refs.entry(value.as_str()).or_insert(0) += 1;
+                },
+                _ => {},
+            }
+
+            match node.properties.iter_mut().find(|(p, _)| *p == triple.predicate) {
+                Some((_, objects)) => {
+                    if !objects.contains(&&triple.object) {
+                        objects.push(&triple.object);
+                    }
+                },
+                None => node.properties.push((triple.predicate.as_str(), vec![&triple.object])),
+            }
+        }
+
+        let mut map = NodeMap { nodes, index, object_refs, lists: HashMap::new(), list_cells: HashSet::new() };
+        map.find_lists();
+        map
+    }
+
+    fn node(&self, id: &str) -> Option<&NodeObject<'a>> {
+        self.index.get(id).map(|&i| &self.nodes[i])
+    }
+
+    /// A well-formed list cell has exactly one rdf:first, one rdf:rest, nothing else
+    fn list_cell(&self, label: &str) -> Option<(&'a RdfNode, &'a RdfNode)> {
+        if self.object_refs.get(label) != Some(&1) {
+            return None;
+        }
+        let node = self.node(&format!("_:{}", label))?;
+        if !node.types.is_empty() || node.properties.len() != 2 {
+            return None;
+        }
+
+        let mut first = None;
+        let mut rest = None;
+        for (predicate, objects) in &node.properties {
+            if objects.len() != 1 {
+                return None;
+            }
+            match predicate.strip_prefix(RDF_NS) {
+                Some("first") => first = Some(objects[0]),
+                Some("rest") => rest = Some(objects[0]),
+                _ => return None,
+            }
+        }
+        Some((first?, rest?))
+    }
+
+    fn find_lists(&mut self) {
+        let rdf_nil = format!("{}nil", RDF_NS);
+        let mut lists = HashMap::new();
+        let mut cells = HashSet::new();
+
+        for node in &self.nodes {
+            for (_, objects) in &node.properties {
+                for object in objects {
+                    let RdfNode::Blank { value: head } = object else { continue };
+
+                    let mut items = Vec::new();
+                    let mut chain = Vec::new();
+                    let mut current = head.as_str();
+                    let complete = loop {
+                        if chain.contains(&current) {
+                            break false;
+                        }
+                        let Some((first, rest)) = self.list_cell(current) else { break false };
+                        chain.push(current);
+                        items.push(first);
+                        match rest {
+                            RdfNode::Uri { value } if *value == rdf_nil => break true,
+                            RdfNode::Blank { value } => current = value.as_str(),
+                            _ => break false,
+                        }
+                    };
+
+                    if complete {
+                        cells.extend(chain);
+                        lists.insert(head.as_str(), items);
+                    }
+                }
+            }
+        }
+
+        self.lists = lists;
+        self.list_cells = cells;
+    }
+
+    /// Nodes that appear at the top level of an expanded document
+    fn top_level(&self) -> impl Iterator<Item = &NodeObject<'a>> {
+        self.nodes.iter().filter(|node| {
+            node.id.strip_prefix("_:").map(|label| !self.list_cells.contains(label)).unwrap_or(true)
+        })
+    }
+
+    fn expanded_node(&self, node: &NodeObject) -> Value {
+        let mut object = Map::new();
+        object.insert("@id".to_string(), json!(node.id));
+        if !node.types.is_empty() {
+            object.insert("@type".to_string(), json!(node.types));
+        }
+        for (predicate, objects) in &node.properties {
+            let values: Vec<Value> = objects.iter().map(|o| self.expanded_value(o)).collect();
+            object.insert(predicate.to_string(), Value::Array(values));
+        }
+        Value::Object(object)
+    }
+
+    fn expanded_value(&self, node: &RdfNode) -> Value {
+        match node {
+            RdfNode::Uri { value } if *value == format!("{}nil", RDF_NS) => json!({ "@list": [] }),
+            RdfNode::Uri { value } => json!({ "@id": value }),
+            RdfNode::Blank { value } => match self.lists.get(value.as_str()) {
+                Some(items) => json!({
+                    "@list": items.iter().map(|item| self.expanded_value(item)).collect::<Vec<_>>()
+                }),
+                None => json!({ "@id": format!("_:{}", value) }),
+            },
+            RdfNode::Literal { value, datatype, language } => {
+                let mut object = Map::new();
+                object.insert("@value".to_string(), json!(value));
+                if let Some(lang) = language {
+                    object.insert("@language".to_string(), json!(lang));
+                } else if let Some(dt) = datatype.as_deref().filter(|dt| *dt != XSD_STRING) {
+                    object.insert("@type".to_string(), json!(dt));
+                }
+                Value::Object(object)
+            },
+        }
+    }
+}
+
+struct Compactor<'a, 'm> {
+    map: &'m NodeMap<'a>,
+    prefixes: PrefixMap,
+    namespaces: &'m [Namespace],
+    used_prefixes: HashSet<String>,
+    embedded: HashSet<&'a str>,
+    flatten: bool,
+}
+
+impl<'a, 'm> Compactor<'a, 'm> {
+    fn new(map: &'m NodeMap<'a>, namespaces: &'m [Namespace], flatten: bool) -> Self {
+        Compactor {
+            map,
+            prefixes: PrefixMap::new(namespaces),
+            namespaces,
+            used_prefixes: HashSet::new(),
+            embedded: HashSet::new(),
+            flatten,
+        }
+    }
+
+    /// Blank nodes referenced once are nested where they are used instead of listed at the top
+    fn can_embed(&self, label: &str) -> bool {
+        !self.flatten && self.map.object_refs.get(label) == Some(&1)
+    }
+
+    fn compact_graph(&mut self) -> Vec<Value> {
+        let map = self.map;
+        let mut graph = Vec::new();
+
+        let mut roots: Vec<&NodeObject> = map.top_level()
+            .filter(|node| !node.id.strip_prefix("_:").is_some_and(|label| self.can_embed(label)))
+            .collect();
+        if self.flatten {
+            roots.sort_by_key(|node| node.id);
+        }
+        for node in roots {
+            if let Some(label) = node.id.strip_prefix("_:") {
+                self.embedded.insert(label);
+            }
+            graph.push(self.compact_node(node, true));
+        }
+
+        // Blank nodes that only reference each other never get embedded; list them at the top
+        for node in map.top_level() {
+            if let Some(label) = node.id.strip_prefix("_:") {
+                if !self.embedded.contains(label) {
+                    self.embedded.insert(label);
+                    graph.push(self.compact_node(node, true));
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn document(&self, graph: Vec<Value>) -> Value {
+        let mut context = Map::new();
+        for ns in self.namespaces {
+            if self.used_prefixes.contains(&ns.prefix) {
+                context.insert(ns.prefix.clone(), json!(ns.uri));
+            }
+        }
+
+        if graph.len() == 1 && !self.flatten {
+            if let Some(Value::Object(node)) = graph.into_iter().next() {
+                let mut document = Map::new();
+                document.insert("@context".to_string(), Value::Object(context));
+                document.extend(node);
+                return Value::Object(document);
+            }
+            return json!({ "@context": context });
+        }
+
+        json!({ "@context": context, "@graph": graph })
+    }
+
+    fn compact_iri(&mut self, iri: &str) -> String {
+        match self.prefixes.compact(iri) {
+            Some((ns, local)) => {
+                self.used_prefixes.insert(ns.prefix.clone());
+                format!("{}:{}", ns.prefix, local)
+            },
+            None => iri.to_string(),
+        }
+    }
+
+    fn compact_node(&mut self, node: &NodeObject<'a>, with_id: bool) -> Value {
+        let mut object = Map::new();
+        if with_id {
+            let id = if node.id.starts_with("_:") { node.id.to_string() } else { self.compact_iri(node.id) };
+            object.insert("@id".to_string(), json!(id));
+        }
+
+        match node.types.len() {
+            0 => {},
+            1 => {
+                let t = self.compact_iri(node.types[0]);
+                object.insert("@type".to_string(), json!(t));
+            },
+            _ => {
+                let types: Vec<String> = node.types.iter().map(|t| self.compact_iri(t)).collect();
+                object.insert("@type".to_string(), json!(types));
+            },
+        }
+
+        for (predicate, objects) in &node.properties {
+            let key = self.compact_iri(predicate);
+            let mut values: Vec<Value> = objects.iter().map(|o| self.compact_value(o)).collect();
+            let value = if values.len() == 1 { values.remove(0) } else { Value::Array(values) };
+            object.insert(key, value);
+        }
+
+        Value::Object(object)
+    }
+
+    fn compact_value(&mut self, node: &'a RdfNode) -> Value {
+        match node {
+            RdfNode::Uri { value } if *value == format!("{}nil", RDF_NS) => json!({ "@list": [] }),
+            RdfNode::Uri { value } => json!({ "@id": self.compact_iri(value) }),
+            RdfNode::Blank { value } => {
+                let map = self.map;
+                if let Some(items) = map.lists.get(value.as_str()) {
+                    let items: Vec<Value> = items.iter().map(|item| self.compact_value(item)).collect();
+                    return json!({ "@list": items });
+                }
+
+                let label = value.as_str();
+                if self.can_embed(label) && !self.embedded.contains(label) {
+                    if let Some(node) = map.node(&format!("_:{}", label)) {
+                        self.embedded.insert(label);
+                        return self.compact_node(node, false);
+                    }
+                }
+                json!({ "@id": format!("_:{}", label) })
+            },
+            RdfNode::Literal { value, datatype, language } => {
+                if let Some(lang) = language {
+                    return json!({ "@value": value, "@language": lang });
+                }
+                match datatype.as_deref() {
+                    None | Some(XSD_STRING) => json!(value),
+                    Some(dt) => json!({ "@value": value, "@type": self.compact_iri(dt) }),
+                }
+            },
+        }
+    }
+}
+
+// JSON-LD -> RDF
+
+/// Parse a JSON-LD document into triples. Named graphs are reported through `Triple.graph`.
+pub fn parse(input: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    let document: Value = serde_json::from_str(input).map_err(|e| RdfEditorError::InvalidInput(format!(
+        "Invalid JSON-LD document at line {}, column {}: {}", e.line(), e.column(), e
+    )))?;
+
+    let mut parser = JsonLdParser {
+        triples: Vec::new(),
+        blank_ids: HashMap::new(),
+        next_blank: 0,
+    };
+    let context = Context { base: base.map(String::from), ..Context::default() };
+
+    match &document {
+        Value::Array(items) => {
+            for item in items {
+                parser.top_level_item(item, &context)?;
+            }
+        },
+        Value::Object(_) => parser.top_level_item(&document, &context)?,
+        _ => return Err(invalid("top-level value must be an object or array")),
+    }
+
+    // Expansion lets any string through as an IRI or language tag
+    for triple in &parser.triples {
+        check_terms(triple).map_err(invalid)?;
+    }
+    Ok(parser.triples)
+}
+
+fn invalid(message: impl std::fmt::Display) -> RdfEditorError {
+    RdfEditorError::InvalidInput(format!("Invalid JSON-LD: {}", message))
+}
+
+const KEYWORDS: &[&str] = &[
+    "@base", "@container", "@context", "@direction", "@graph", "@id", "@import", "@included",
+    "@index", "@json", "@language", "@list", "@nest", "@none", "@prefix", "@propagate",
+    "@protected", "@reverse", "@set", "@type", "@value", "@version", "@vocab",
+];
+
+#[derive(Clone, Default)]
+struct TermDefinition {
+    /// `None` when the term is explicitly mapped to null
+    id: Option<String>,
+    reverse: bool,
+    type_mapping: Option<String>,
+    /// `Some(None)` when the term sets `"@language": null`
+    language: Option<Option<String>>,
+    container: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct Context {
+    base: Option<String>,
+    vocab: Option<String>,
+    language: Option<String>,
+    terms: HashMap<String, TermDefinition>,
+}
+
+impl Context {
+    fn with_local(&self, local: &Value) -> Result<Context, RdfEditorError> {
+        let mut result = self.clone();
+        let contexts = match local {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+
+        for context in contexts {
+            match context {
+                Value::Null => {
+                    result = Context { base: self.base.clone(), ..Context::default() };
+                },
+                Value::String(url) => {
+                    return Err(invalid(format!("remote context {} is not supported; inline it instead", url)));
+                },
+                Value::Object(definitions) => result.apply(definitions)?,
+                _ => return Err(invalid("@context must be an object, array, string or null")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn apply(&mut self, definitions: &Map<String, Value>) -> Result<(), RdfEditorError> {
+        match definitions.get("@base") {
+            Some(Value::Null) => self.base = None,
+            Some(Value::String(base)) => {
+                self.base = Some(match &self.base {
+                    Some(current) => resolve_iri(current, base),
+                    None => base.clone(),
+                });
+            },
+            Some(_) => return Err(invalid("@base must be a string or null")),
+            None => {},
+        }
+
+        match definitions.get("@vocab") {
+            Some(Value::Null) => self.vocab = None,
+            Some(Value::String(vocab)) => self.vocab = self.expand_iri(vocab, true, true),
+            Some(_) => return Err(invalid("@vocab must be a string or null")),
+            None => {},
+        }
+
+        match definitions.get("@language") {
+            Some(Value::Null) => self.language = None,
+            Some(Value::String(lang)) => self.language = Some(lang.to_lowercase()),
+            Some(_) => return Err(invalid("@language must be a string or null")),
+            None => {},
+        }
+
+        let mut defining = HashSet::new();
+        for term in definitions.keys() {
+            self.define_term(definitions, term, &mut defining)?;
+        }
+        Ok(())
+    }
+
+    fn define_term(
+        &mut self,
+        definitions: &Map<String, Value>,
+        term: &str,
+        defining: &mut HashSet<String>,
+    ) -> Result<(), RdfEditorError> {
+        if term.starts_with('@') || defining.contains(term) {
+            return Ok(());
+        }
+        defining.insert(term.to_string());
+
+        let value = &definitions[term];
+
+        // Define prefixes used by this term first, so `"foaf:name"` expands correctly
+        let references: Vec<&str> = match value {
+            Value::String(id) => vec![id.as_str()],
+            Value::Object(object) => ["@id", "@reverse", "@type"].iter()
+                .filter_map(|key| object.get(*key).and_then(|v| v.as_str()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for reference in references {
+            if let Some((prefix, _)) = reference.split_once(':') {
+                if prefix != term && definitions.contains_key(prefix) {
+                    self.define_term(definitions, prefix, defining)?;
+                }
+            }
+        }
+
+        let definition = match value {
+            Value::Null => TermDefinition::default(),
+            Value::String(id) => TermDefinition {
+                id: self.expand_iri(id, true, false),
+                ..TermDefinition::default()
+            },
+            Value::Object(object) => {
+                let mut definition = TermDefinition::default();
+
+                if let Some(reverse) = object.get("@reverse") {
+                    let reverse = reverse.as_str().ok_or_else(|| invalid("@reverse must be a string"))?;
+                    definition.id = self.expand_iri(reverse, true, false);
+                    definition.reverse = true;
+                } else {
+                    definition.id = match object.get("@id") {
+                        Some(Value::Null) => None,
+                        Some(Value::String(id)) => self.expand_iri(id, true, false),
+                        Some(_) => return Err(invalid(format!("@id of term '{}' must be a string", term))),
+                        None => self.expand_iri(term, true, false),
+                    };
+                }
+
+                if let Some(type_mapping) = object.get("@type") {
+                    let type_mapping = type_mapping.as_str()
+                        .ok_or_else(|| invalid(format!("@type of term '{}' must be a string", term)))?;
+                    definition.type_mapping = match type_mapping {
+                        "@id" | "@vocab" | "@json" | "@none" => Some(type_mapping.to_string()),
+                        other => self.expand_iri(other, true, false),
+                    };
+                }
+
+                match object.get("@language") {
+                    Some(Value::Null) => definition.language = Some(None),
+                    Some(Value::String(lang)) => definition.language = Some(Some(lang.to_lowercase())),
+                    Some(_) => return Err(invalid("@language must be a string or null")),
+                    None => {},
+                }
+
+                definition.container = match object.get("@container") {
+                    Some(Value::String(container)) => Some(container.clone()),
+                    Some(Value::Array(containers)) => containers.iter()
+                        .filter_map(|c| c.as_str())
+                        .find(|c| *c != "@set")
+                        .or(Some("@set"))
+                        .map(String::from),
+                    _ => None,
+                };
+
+                definition
+            },
+            _ => return Err(invalid(format!("invalid definition for term '{}'", term))),
+        };
+
+        self.terms.insert(term.to_string(), definition);
+        Ok(())
+    }
+
+    /// JSON-LD IRI expansion. `vocab` allows terms and `@vocab`; `document_relative` resolves against `@base`.
+    fn expand_iri(&self, value: &str, vocab: bool, document_relative: bool) -> Option<String> {
+        if KEYWORDS.contains(&value) {
+            return Some(value.to_string());
+        }
+        if value.starts_with('@') && value[1..].chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        if vocab {
+            if let Some(definition) = self.terms.get(value) {
+                return definition.id.clone();
+            }
+        }
+
+        if let Some((prefix, suffix)) = value.split_once(':') {
+            if prefix == "_" || suffix.starts_with("//") {
+                return Some(value.to_string());
+            }
+            if let Some(TermDefinition { id: Some(namespace), reverse: false, .. }) = self.terms.get(prefix) {
+                return Some(format!("{}{}", namespace, suffix));
+            }
+            if is_absolute_iri(value) {
+                return Some(value.to_string());
+            }
+        }
+
+        if vocab {
+            if let Some(vocab) = &self.vocab {
+                return Some(format!("{}{}", vocab, value));
+            }
+        }
+
+        if document_relative {
+            if let Some(base) = &self.base {
+                return Some(resolve_iri(base, value));
+            }
+        }
+
+        Some(value.to_string())
+    }
+}
+
+struct JsonLdParser {
+    triples: Vec<Triple>,
+    blank_ids: HashMap<String, String>,
+    next_blank: usize,
+}
+
+impl JsonLdParser {
+    fn fresh_blank(&mut self) -> String {
+        let id = format!("_:b{}", self.next_blank);
+        self.next_blank += 1;
+        id
+    }
+
+    /// Map document blank node labels onto fresh ones so they never clash with generated nodes
+    fn blank_for_label(&mut self, label: &str) -> String {
+        if let Some(id) = self.blank_ids.get(label) {
+            return id.clone();
+        }
+        let id = self.fresh_blank();
+        self.blank_ids.insert(label.to_string(), id.clone());
+        id
+    }
+
+    /// Turn an expanded IRI into a subject string, dropping ones that are still relative
+    fn resource(&mut self, iri: Option<String>) -> Option<String> {
+        let iri = iri?;
+        if iri.starts_with("_:") {
+            Some(self.blank_for_label(&iri))
+        } else if is_absolute_iri(&iri) {
+            Some(iri)
+        } else {
+            None
+        }
+    }
+
+    fn emit(&mut self, subject: &str, predicate: &str, object: RdfNode, graph: Option<&str>) {
+        // Generalized RDF (blank node predicates) is not representable in the store
+        if predicate.starts_with("_:") {
+            return;
+        }
+        self.triples.push(Triple {
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            object,
+            graph: graph.map(String::from),
+        });
+    }
+
+    fn top_level_item(&mut self, item: &Value, context: &Context) -> Result<(), RdfEditorError> {
+        let Value::Object(object) = item else {
+            return Err(invalid("top-level array items must be node objects"));
+        };
+
+        // A document that only wraps @graph describes the default graph
+        let only_graph = object.keys().all(|k| k == "@context" || k == "@graph");
+        if only_graph && object.contains_key("@graph") {
+            let context = match object.get("@context") {
+                Some(local) => context.with_local(local)?,
+                None => context.clone(),
+            };
+            for node in as_array(&object["@graph"]) {
+                if let Value::Object(_) = node {
+                    self.node(node, &context, None)?;
+                }
+            }
+            return Ok(());
+        }
+
+        self.node(item, context, None)?;
+        Ok(())
+    }
+
+    /// Process a node object and return its subject string
+    fn node(&mut self, value: &Value, context: &Context, graph: Option<&str>) -> Result<Option<String>, RdfEditorError> {
+        let Value::Object(object) = value else {
+            return Err(invalid("expected a node object"));
+        };
+
+        let context = match object.get("@context") {
+            Some(local) => context.with_local(local)?,
+            None => context.clone(),
+        };
+
+        let subject = match object.get("@id") {
+            Some(Value::String(id)) => {
+                let expanded = context.expand_iri(id, false, true);
+                match self.resource(expanded) {
+                    Some(subject) => subject,
+                    // Nodes with a relative @id and no base are dropped, as in JSON-LD toRdf
+                    None => return Ok(None),
+                }
+            },
+            Some(_) => return Err(invalid("@id must be a string")),
+            None => self.fresh_blank(),
+        };
+
+        if let Some(types) = object.get("@type") {
+            for t in as_array(types) {
+                let t = t.as_str().ok_or_else(|| invalid("@type values must be strings"))?;
+                if let Some(type_iri) = self.resource(context.expand_iri(t, true, true)) {
+                    self.emit(&subject, RDF_TYPE, subject_to_node(type_iri), graph);
+                }
+            }
+        }
+
+        if let Some(reverse) = object.get("@reverse") {
+            let Value::Object(reverse) = reverse else {
+                return Err(invalid("@reverse must be an object"));
+            };
+            for (key, values) in reverse {
+                let Some(predicate) = context.expand_iri(key, true, false).filter(|p| is_absolute_iri(p)) else {
+                    continue;
+                };
+                for item in as_array(values) {
+                    if let Some(other) = self.node(item, &context, graph)? {
+                        self.emit(&other, &predicate, subject_to_node(subject.clone()), graph);
+                    }
+                }
+            }
+        }
+
+        for (key, values) in object {
+            if key.starts_with('@') {
+                continue;
+            }
+
+            let definition = context.terms.get(key).cloned().unwrap_or_default();
+            let Some(predicate) = context.expand_iri(key, true, false) else { continue };
+            if !(is_absolute_iri(&predicate) || predicate.starts_with("_:")) || KEYWORDS.contains(&predicate.as_str()) {
+                // Properties that do not map to an IRI are dropped, as the spec requires
+                continue;
+            }
+
+            if definition.reverse {
+                for item in as_array(values) {
+                    if let Some(other) = self.node(item, &context, graph)? {
+                        self.emit(&other, &predicate, subject_to_node(subject.clone()), graph);
+                    }
+                }
+                continue;
+            }
+
+            for object in self.property_values(values, &definition, &context, graph)? {
+                self.emit(&subject, &predicate, object, graph);
+            }
+        }
+
+        if let Some(nested) = object.get("@graph") {
+            for node in as_array(nested) {
+                if let Value::Object(_) = node {
+                    self.node(node, &context, Some(&subject))?;
+                }
+            }
+        }
+
+        Ok(Some(subject))
+    }
+
+    fn property_values(
+        &mut self,
+        values: &Value,
+        definition: &TermDefinition,
+        context: &Context,
+        graph: Option<&str>,
+    ) -> Result<Vec<RdfNode>, RdfEditorError> {
+        let mut objects = Vec::new();
+
+        match (definition.container.as_deref(), values) {
+            (Some("@list"), _) if !is_list_object(values) => {
+                let items: Vec<&Value> = as_array(values).collect();
+                objects.push(self.list(&items, definition, context, graph)?);
+            },
+            (Some("@language"), Value::Object(by_language)) => {
+                for (lang, strings) in by_language {
+                    for s in as_array(strings) {
+                        let Value::String(s) = s else { continue };
+                        let language = if lang == "@none" { None } else { Some(lang.to_lowercase()) };
+                        objects.push(RdfNode::Literal { value: s.clone(), datatype: None, language });
+                    }
+                }
+            },
+            (Some("@index"), Value::Object(by_index)) => {
+                for item in by_index.values().flat_map(as_array) {
+                    objects.extend(self.value(item, definition, context, graph)?);
+                }
+            },
+            (Some("@id"), Value::Object(by_id)) => {
+                for (id, item) in by_id {
+                    for node in as_array(item) {
+                        let mut node = node.clone();
+                        if let Value::Object(map) = &mut node {
+                            map.entry("@id").or_insert_with(|| json!(id));
+                        }
+                        objects.extend(self.value(&node, definition, context, graph)?);
+                    }
+                }
+            },
+            _ => {
+                for item in as_array(values) {
+                    objects.extend(self.value(item, definition, context, graph)?);
+                }
+            },
+        }
+
+        Ok(objects)
+    }
+
+    fn value(
+        &mut self,
+        value: &Value,
+        definition: &TermDefinition,
+        context: &Context,
+        graph: Option<&str>,
+    ) -> Result<Option<RdfNode>, RdfEditorError> {
+        if definition.type_mapping.as_deref() == Some("@json") {
+            let json = serde_json::to_string(value)?;
+            return Ok(Some(typed_literal(json, &format!("{}JSON", RDF_NS))));
+        }
+
+        match value {
+            Value::Null => Ok(None),
+            Value::String(s) => match definition.type_mapping.as_deref() {
+                Some("@id") => Ok(self.resource(context.expand_iri(s, false, true)).map(subject_to_node)),
+                Some("@vocab") => Ok(self.resource(context.expand_iri(s, true, true)).map(subject_to_node)),
+                Some(dt) if dt != "@none" => Ok(Some(typed_literal(s.clone(), dt))),
+                _ => {
+                    let language = match &definition.language {
+                        Some(language) => language.clone(),
+                        None => context.language.clone(),
+                    };
+                    Ok(Some(RdfNode::Literal { value: s.clone(), datatype: None, language }))
+                },
+            },
+            Value::Bool(_) | Value::Number(_) => {
+                let datatype = definition.type_mapping.as_deref().filter(|t| !t.starts_with('@'));
+                Ok(Some(native_literal(value, datatype)))
+            },
+            Value::Array(items) => {
+                // Arrays nested inside a list are lists of lists
+                let items: Vec<&Value> = items.iter().collect();
+                Ok(Some(self.list(&items, definition, context, graph)?))
+            },
+            Value::Object(object) => {
+                if object.contains_key("@value") {
+                    return value_object(object, context);
+                }
+                if let Some(list) = object.get("@list") {
+                    let items: Vec<&Value> = as_array(list).collect();
+                    return Ok(Some(self.list(&items, definition, context, graph)?));
+                }
+                if let Some(set) = object.get("@set") {
+                    return match as_array(set).next() {
+                        Some(first) => self.value(first, definition, context, graph),
+                        None => Ok(None),
+                    };
+                }
+                Ok(self.node(value, context, graph)?.map(subject_to_node))
+            },
+        }
+    }
+
+    fn list(
+        &mut self,
+        items: &[&Value],
+        definition: &TermDefinition,
+        context: &Context,
+        graph: Option<&str>,
+    ) -> Result<RdfNode, RdfEditorError> {
+        let mut objects = Vec::new();
+        for item in items {
+            if let Some(object) = self.value(item, definition, context, graph)? {
+                objects.push(object);
+            }
+        }
+
+        let rdf_nil = RdfNode::Uri { value: format!("{}nil", RDF_NS) };
+        if objects.is_empty() {
+            return Ok(rdf_nil);
+        }
+
+        let cells: Vec<String> = objects.iter().map(|_| self.fresh_blank()).collect();
+        for (i, object) in objects.into_iter().enumerate() {
+            let rest = cells.get(i + 1).cloned().map(subject_to_node).unwrap_or_else(|| rdf_nil.clone());
+            self.emit(&cells[i], &format!("{}first", RDF_NS), object, graph);
+            self.emit(&cells[i], &format!("{}rest", RDF_NS), rest, graph);
+        }
+        Ok(subject_to_node(cells[0].clone()))
+    }
+}
+
+fn as_array(value: &Value) -> impl Iterator<Item = &Value> {
+    match value {
+        Value::Array(items) => items.iter().collect::<Vec<_>>().into_iter(),
+        other => vec![other].into_iter(),
+    }
+}
+
+fn is_list_object(value: &Value) -> bool {
+    value.as_object().is_some_and(|object| object.contains_key("@list"))
+}
+
+fn subject_to_node(subject: String) -> RdfNode {
+    match subject.strip_prefix("_:") {
+        Some(label) => RdfNode::Blank { value: label.to_string() },
+        None => RdfNode::Uri { value: subject },
+    }
+}
+
+fn typed_literal(value: String, datatype: &str) -> RdfNode {
+    RdfNode::Literal {
+        value,
+        datatype: if datatype == XSD_STRING { None } else { Some(datatype.to_string()) },
+        language: None,
+    }
+}
+
+fn value_object(object: &Map<String, Value>, context: &Context) -> Result<Option<RdfNode>, RdfEditorError> {
+    let datatype = match object.get("@type") {
+        Some(Value::String(t)) if t == "@json" => {
+            let json = serde_json::to_string(&object["@value"])?;
+            return Ok(Some(typed_literal(json, &format!("{}JSON", RDF_NS))));
+        },
+        Some(Value::String(t)) => context.expand_iri(t, true, true),
+        Some(_) => return Err(invalid("@type of a value object must be a string")),
+        None => None,
+    };
+
+    match &object["@value"] {
+        Value::Null => Ok(None),
+        Value::String(s) => {
+            if let Some(datatype) = datatype {
+                return Ok(Some(typed_literal(s.clone(), &datatype)));
+            }
+            let language = match object.get("@language") {
+                Some(Value::String(lang)) => Some(lang.to_lowercase()),
+                Some(_) => return Err(invalid("@language must be a string")),
+                None => None,
+            };
+            Ok(Some(RdfNode::Literal { value: s.clone(), datatype: None, language }))
+        },
+        v @ (Value::Bool(_) | Value::Number(_)) => Ok(Some(native_literal(v, datatype.as_deref()))),
+        _ => Err(invalid("@value must be a string, number, boolean or null")),
+    }
+}
+
+/// Convert a JSON boolean or number using the JSON-LD canonical lexical forms
+fn native_literal(value: &Value, datatype: Option<&str>) -> RdfNode {
+    let (lexical, default_type) = match value {
+        Value::Bool(b) => (b.to_string(), XSD_BOOLEAN),
+        Value::Number(n) => {
+            let as_double = n.as_f64().unwrap_or_default();
+            let integral = n.is_i64() || n.is_u64() || (as_double.fract() == 0.0 && as_double.abs() < 1e21);
+            if integral && datatype != Some(XSD_DOUBLE) {
+                let lexical = if n.is_f64() { format!("{}", as_double as i128) } else { n.to_string() };
+                (lexical, XSD_INTEGER)
+            } else {
+                (canonical_double(as_double), XSD_DOUBLE)
+            }
+        },
+        _ => (value.to_string(), XSD_STRING),
+    };
+
+    typed_literal(lexical, datatype.unwrap_or(default_type))
+}
+
+fn canonical_double(value: f64) -> String {
+    let formatted = format!("{:E}", value);
+    match formatted.split_once('E') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => format!("{}.0E{}", mantissa, exponent),
+        _ => formatted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{default_namespaces, turtle};
+
+    fn sorted(mut triples: Vec<Triple>) -> Vec<Triple> {
+        triples.sort_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+        triples
+    }
+
+    fn namespaces() -> Vec<Namespace> {
+        let mut namespaces = default_namespaces();
+        namespaces.push(Namespace { prefix: "ex".to_string(), uri: "http://example.com/".to_string() });
+        namespaces
+    }
+
+    #[test]
+    fn test_parse_context_language_type_and_list() {
+        let input = r#"{
+            "@context": {
+                "ex": "http://example.com/",
+                "xsd": "http://www.w3.org/2001/XMLSchema#",
+                "name": { "@id": "ex:name", "@language": "en" },
+                "born": { "@id": "ex:born", "@type": "xsd:date" },
+                "knows": { "@id": "ex:knows", "@type": "@id" },
+                "steps": { "@id": "ex:steps", "@container": "@list" },
+                "label": { "@id": "ex:label", "@container": "@language" }
+            },
+            "@id": "ex:alice",
+            "@type": "ex:Person",
+            "name": "Alice",
+            "born": "1990-01-01",
+            "knows": "ex:bob",
+            "steps": ["one", 2, true],
+            "label": { "fr": "Alice (fr)", "de": ["Alice (de)"] },
+            "ex:age": 30,
+            "ex:height": 1.75,
+            "unmapped": "dropped"
+        }"#;
+
+        let triples = parse(input, None).unwrap();
+        let find = |p: &str| triples.iter().filter(|t| t.predicate == format!("http://example.com/{}", p)).collect::<Vec<_>>();
+
+        assert!(triples.iter().any(|t| t.predicate == RDF_TYPE
+            && t.object == RdfNode::Uri { value: "http://example.com/Person".to_string() }));
+        assert_eq!(find("name")[0].object, RdfNode::Literal {
+            value: "Alice".to_string(), datatype: None, language: Some("en".to_string()),
+        });
+        assert_eq!(find("born")[0].object, RdfNode::Literal {
+            value: "1990-01-01".to_string(),
+            datatype: Some("http://www.w3.org/2001/XMLSchema#date".to_string()),
+            language: None,
+        });
+        assert_eq!(find("knows")[0].object, RdfNode::Uri { value: "http://example.com/bob".to_string() });
+        assert_eq!(find("label").len(), 2);
+        assert_eq!(find("age")[0].object, typed_literal("30".to_string(), XSD_INTEGER));
+        assert_eq!(find("height")[0].object, typed_literal("1.75E0".to_string(), XSD_DOUBLE));
+        assert_eq!(triples.iter().filter(|t| t.predicate == format!("{}first", RDF_NS)).count(), 3);
+        assert!(!triples.iter().any(|t| t.predicate.contains("unmapped")));
+    }
+
+    #[test]
+    fn test_parse_vocab_base_value_objects_and_graphs() {
+        let input = r#"{
+            "@context": { "@vocab": "http://example.com/vocab#", "@base": "http://example.com/data/" },
+            "@graph": [
+                {
+                    "@id": "item1",
+                    "title": { "@value": "Hello", "@language": "EN" },
+                    "code": { "@value": "42", "@type": "http://www.w3.org/2001/XMLSchema#int" },
+                    "part": { "title": "nested" }
+                },
+                {
+                    "@id": "http://example.com/graphs/g1",
+                    "@graph": [ { "@id": "item2", "title": "in named graph" } ]
+                }
+            ]
+        }"#;
+
+        let triples = parse(input, None).unwrap();
+
+        let title = triples.iter().find(|t| t.subject == "http://example.com/data/item1"
+            && t.predicate == "http://example.com/vocab#title").unwrap();
+        assert_eq!(title.object, RdfNode::Literal {
+            value: "Hello".to_string(), datatype: None, language: Some("en".to_string()),
+        });
+        assert!(title.graph.is_none());
+
+        let part = triples.iter().find(|t| t.predicate == "http://example.com/vocab#part").unwrap();
+        assert!(matches!(part.object, RdfNode::Blank { .. }));
+
+        let named = triples.iter().find(|t| t.subject == "http://example.com/data/item2").unwrap();
+        assert_eq!(named.graph.as_deref(), Some("http://example.com/graphs/g1"));
+    }
+
+    #[test]
+    fn test_parse_rejects_remote_context_and_bad_json() {
+        let err = parse(r#"{ "@context": "https://schema.org/", "name": "x" }"#, None).unwrap_err();
+        assert!(err.to_string().contains("remote context"), "{}", err);
+
+        let err = parse("{ \"@id\": ", None).unwrap_err();
+        assert!(matches!(err, RdfEditorError::InvalidInput(ref msg) if msg.contains("line 1")), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_rejects_iris_and_language_tags_that_break_out_of_a_query() {
+        let err = parse(r#"{ "@id": "http://x/> } ; DROP ALL ; INSERT DATA { <a", "http://x/p": "v" }"#, None).unwrap_err();
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+
+        let err = parse(r#"{ "@id": "http://x/s", "http://x/p": { "@id": "http://x/o>" } }"#, None).unwrap_err();
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+
+        let err = parse(r#"{
+            "@id": "http://x/s",
+            "http://x/p": { "@value": "v", "@language": "en . } ; DROP ALL ; INSERT DATA { <a> <b> <c>" }
+        }"#, None).unwrap_err();
+        assert!(err.to_string().contains("invalid language tag"), "{}", err);
+    }
+
+    #[test]
+    fn test_compacted_output_uses_namespace_context() {
+        let triples = turtle::parse(r#"
+            @prefix ex: <http://example.com/> .
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+            ex:a a ex:Class ;
+                rdfs:label "A"@en ;
+                ex:count 3 ;
+                ex:address [ ex:city "Paris" ] ;
+                ex:steps ( "x" "y" ) .
+        "#, None).unwrap();
+
+        let document = to_json_ld(&triples, &namespaces(), JsonLdForm::Compacted);
+
+        assert_eq!(document["@context"]["ex"], "http://example.com/");
+        assert_eq!(document["@context"]["rdfs"], "http://www.w3.org/2000/01/rdf-schema#");
+        assert_eq!(document["@id"], "ex:a");
+        assert_eq!(document["@type"], "ex:Class");
+        assert_eq!(document["rdfs:label"], json!({ "@value": "A", "@language": "en" }));
+        assert_eq!(document["ex:count"], json!({ "@value": "3", "@type": "xsd:integer" }));
+        assert_eq!(document["ex:address"], json!({ "ex:city": "Paris" }));
+        assert_eq!(document["ex:steps"], json!({ "@list": ["x", "y"] }));
+    }
+
+    #[test]
+    fn test_expanded_and_flattened_forms() {
+        let triples = turtle::parse(r#"
+            @prefix ex: <http://example.com/> .
+            ex:b ex:p [ ex:q "v" ] .
+            ex:a ex:p ex:b .
+        "#, None).unwrap();
+
+        let expanded = to_json_ld(&triples, &namespaces(), JsonLdForm::Expanded);
+        let nodes = expanded.as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        let b = nodes.iter().find(|n| n["@id"] == "http://example.com/b").unwrap();
+        let blank = b["http://example.com/p"][0]["@id"].as_str().unwrap();
+        assert!(nodes.iter().any(|n| n["@id"] == blank && n["http://example.com/q"][0]["@value"] == "v"));
+
+        let flattened = to_json_ld(&triples, &namespaces(), JsonLdForm::Flattened);
+        let graph = flattened["@graph"].as_array().unwrap();
+        let ids: Vec<&str> = graph.iter().map(|n| n["@id"].as_str().unwrap()).collect();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(&ids[1..], &["ex:a", "ex:b"]);
+        assert!(ids[0].starts_with("_:"));
+    }
+
+    #[test]
+    fn test_round_trip_through_every_form() {
+        let original = turtle::parse(r#"
+            @prefix ex: <http://example.com/> .
+            ex:a a ex:Class , ex:Other ;
+                ex:label "plain" , "tagged"@fr , "typed"^^ex:dt ;
+                ex:n 1 , 2.5 , true ;
+                ex:list ( 1 ( 2 3 ) ) ;
+                ex:nested [ ex:inner [ ex:leaf "deep" ] ] ;
+                ex:link ex:b .
+        "#, None).unwrap();
+
+        let ground = |triples: &[Triple]| -> Vec<Triple> {
+            sorted(triples.iter()
+                .filter(|t| !t.subject_is_blank() && !matches!(t.object, RdfNode::Blank { .. }))
+                .cloned()
+                .collect())
+        };
+
+        for form in [JsonLdForm::Expanded, JsonLdForm::Compacted, JsonLdForm::Flattened] {
+            let output = serialize(&original, &namespaces(), form).unwrap();
+            let reparsed = parse(&output, None).unwrap();
+            assert_eq!(reparsed.len(), original.len(), "{:?}: {}", form, output);
+            assert_eq!(ground(&reparsed), ground(&original), "{:?}", form);
+        }
+    }
+}
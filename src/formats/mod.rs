@@ -0,0 +1,466 @@
+//! Readers and writers for the RDF syntaxes listed in `RdfFormat`.
+//!
+//! Everything here works on plain `Triple` values so the handlers can move
+//! data between the SPARQL endpoint and files without an external RDF library.
+//! Blank nodes follow the convention used throughout the crate: a subject
+//! string starting with `_:` is a blank node, objects use `RdfNode::Blank`.
+
+pub mod changeset;
+pub mod jsonld;
+pub mod ntriples;
+pub mod rdfxml;
+pub mod turtle;
+
+use crate::error::RdfEditorError;
+use crate::models::{JsonLdForm, Namespace, RdfFormat, RdfNode, Triple};
+use crate::validation::validate_language_tag;
+
+pub const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const XSD_NS: &str = "http://www.w3.org/2001/XMLSchema#";
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+pub const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+pub const XSD_DECIMAL: &str = "http://www.w3.org/2001/XMLSchema#decimal";
+pub const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+
+/// Prefixes known to the editor, matching the defaults seeded by the web UI
+pub fn default_namespaces() -> Vec<Namespace> {
+    [
+        ("rdf", RDF_NS),
+        ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+        ("xsd", XSD_NS),
+        ("owl", "http://www.w3.org/2002/07/owl#"),
+        ("skos", "http://www.w3.org/2004/02/skos/core#"),
+        ("foaf", "http://xmlns.com/foaf/0.1/"),
+        ("dc", "http://purl.org/dc/elements/1.1/"),
+        ("dcterms", "http://purl.org/dc/terms/"),
+        ("schema", "http://schema.org/"),
+        ("sh", "http://www.w3.org/ns/shacl#"),
+    ]
+    .iter()
+    .map(|(prefix, uri)| Namespace {
+        prefix: prefix.to_string(),
+        uri: uri.to_string(),
+    })
+    .collect()
+}
+
+/// Parse a whole document in `format`, resolving relative IRIs against `base`
+pub fn parse(format: RdfFormat, data: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    match format {
+        // N-Triples is a subset of Turtle, so one parser covers both
+        RdfFormat::NTriples | RdfFormat::Turtle => turtle::parse(data, base),
+        RdfFormat::TriG => turtle::parse_trig(data, base),
+        RdfFormat::NQuads => turtle::parse_nquads(data, base),
+        RdfFormat::JsonLd => jsonld::parse(data, base),
+        RdfFormat::RdfXml => rdfxml::parse(data, base),
+    }
+}
+
+/// Incremental serializer for exports too large to hold in memory.
+///
+/// Triples must arrive ordered by subject (and by graph first for quad formats)
+/// so that statements about one subject can be grouped. Blank nodes are never
+/// nested, and the full namespace list is declared up front.
+pub trait StreamWriter {
+    fn start(&mut self) -> Result<String, RdfEditorError>;
+    fn write(&mut self, triples: &[Triple]) -> Result<String, RdfEditorError>;
+    fn finish(&mut self) -> Result<String, RdfEditorError>;
+}
+
+pub fn stream_writer(format: RdfFormat, namespaces: &[Namespace], jsonld_form: JsonLdForm) -> Box<dyn StreamWriter> {
+    match format {
+        RdfFormat::NTriples => Box::new(ntriples::NTriplesWriter::new(false)),
+        RdfFormat::NQuads => Box::new(ntriples::NTriplesWriter::new(true)),
+        RdfFormat::Turtle => Box::new(turtle::TurtleStreamWriter::new(namespaces, false)),
+        RdfFormat::TriG => Box::new(turtle::TurtleStreamWriter::new(namespaces, true)),
+        RdfFormat::JsonLd => Box::new(jsonld::JsonLdStreamWriter::new(namespaces, jsonld_form)),
+        RdfFormat::RdfXml => Box::new(rdfxml::RdfXmlStreamWriter::new(namespaces)),
+    }
+}
+
+/// Blank node label that is valid in every syntax we write (Turtle, N-Triples, XML NCName).
+///
+/// Simple alphanumeric labels are kept; anything else is escaped, so distinct store
+/// labels stay distinct without keeping a renumbering table for the whole export.
+pub fn portable_blank_label(label: &str) -> String {
+    if label.starts_with(|c: char| c.is_ascii_alphabetic()) && label.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return label.to_string();
+    }
+
+    let mut portable = String::from("b_");
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() {
+            portable.push(c);
+        } else {
+            portable.push_str(&format!("_{:X}_", c as u32));
+        }
+    }
+    portable
+}
+
+/// Namespace table used to abbreviate IRIs into prefixed names
+pub struct PrefixMap {
+    namespaces: Vec<Namespace>,
+}
+
+impl PrefixMap {
+    pub fn new(namespaces: &[Namespace]) -> Self {
+        // Longest namespace first so the most specific prefix wins
+        let mut namespaces = namespaces.to_vec();
+        namespaces.sort_by_key(|ns| std::cmp::Reverse(ns.uri.len()));
+        PrefixMap { namespaces }
+    }
+
+    /// Split an IRI into (prefix, local name) if it can be written as a prefixed name
+    pub fn compact<'a>(&'a self, iri: &'a str) -> Option<(&'a Namespace, &'a str)> {
+        self.namespaces.iter().find_map(|ns| {
+            iri.strip_prefix(ns.uri.as_str())
+                .filter(|local| is_valid_local_name(local))
+                .map(|local| (ns, local))
+        })
+    }
+}
+
+/// Conservative check for a Turtle `PN_LOCAL` that needs no escaping
+pub fn is_valid_local_name(local: &str) -> bool {
+    if local.is_empty() {
+        return true;
+    }
+
+    let first = local.chars().next().unwrap_or('.');
+    if !(first.is_alphanumeric() || first == '_') {
+        return false;
+    }
+
+    !local.ends_with('.')
+        && local.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Escape a string for use inside a double-quoted Turtle/N-Triples literal
+pub fn escape_literal(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape characters that are not allowed inside an `<IRIREF>`
+pub fn escape_iri(iri: &str) -> String {
+    let mut escaped = String::with_capacity(iri.len());
+    for c in iri.chars() {
+        match c {
+            '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => {
+                escaped.push_str(&format!("\\u{:04X}", c as u32))
+            },
+            c if c <= ' ' => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// First character of `iri` that an `<IRIREF>` cannot hold
+pub fn invalid_iri_char(iri: &str) -> Option<char> {
+    iri.chars().find(|&c| c <= ' ' || "<>\"{}|^`\\".contains(c))
+}
+
+/// Check the IRIs and language tag of a parsed triple, which go into SPARQL
+/// updates as they are, the way the Turtle parser checks its IRIs
+pub fn check_terms(triple: &Triple) -> Result<(), String> {
+    let mut iris = vec![triple.subject.as_str(), triple.predicate.as_str()];
+    iris.extend(triple.graph.as_deref());
+    match &triple.object {
+        RdfNode::Uri { value } | RdfNode::Blank { value } => iris.push(value),
+        RdfNode::Literal { datatype, language, .. } => {
+            iris.extend(datatype.as_deref());
+            if let Some(lang) = language {
+                validate_language_tag(lang).map_err(|_| format!("invalid language tag {:?}", lang))?;
+            }
+        },
+    }
+
+    for iri in iris {
+        if let Some(c) = invalid_iri_char(iri) {
+            return Err(format!("invalid character {:?} in IRI {:?}", c, iri));
+        }
+    }
+    Ok(())
+}
+
+/// True if the IRI starts with a URI scheme (`http:`, `urn:`, ...)
+pub fn is_absolute_iri(iri: &str) -> bool {
+    split_iri(iri).scheme.is_some()
+}
+
+struct IriParts<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+fn split_iri(iri: &str) -> IriParts<'_> {
+    let (rest, fragment) = match iri.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (iri, None),
+    };
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+
+    let (scheme, rest) = match rest.find([':', '/']) {
+        Some(i) if rest.as_bytes()[i] == b':'
+            && rest[..i].starts_with(|c: char| c.is_ascii_alphabetic())
+            && rest[..i].chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') => {
+            (Some(&rest[..i]), &rest[i + 1..])
+        },
+        _ => (None, rest),
+    };
+
+    let (authority, path) = match rest.strip_prefix("//") {
+        Some(after) => {
+            let end = after.find('/').unwrap_or(after.len());
+            (Some(&after[..end]), &after[end..])
+        },
+        None => (None, rest),
+    };
+
+    IriParts { scheme, authority, path, query, fragment }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut input = path.to_string();
+    let mut output = String::new();
+
+    fn pop_segment(output: &mut String) {
+        let cut = output.rfind('/').unwrap_or(0);
+        output.truncate(cut);
+    }
+
+    while !input.is_empty() {
+        if input.starts_with("../") {
+            input.drain(..3);
+        } else if input.starts_with("./") {
+            input.drain(..2);
+        } else if input.starts_with("/./") {
+            input.replace_range(..3, "/");
+        } else if input == "/." {
+            input = "/".to_string();
+        } else if input.starts_with("/../") {
+            input.replace_range(..4, "/");
+            pop_segment(&mut output);
+        } else if input == "/.." {
+            input = "/".to_string();
+            pop_segment(&mut output);
+        } else if input == "." || input == ".." {
+            input.clear();
+        } else {
+            let start = usize::from(input.starts_with('/'));
+            let end = input[start..].find('/').map(|i| i + start).unwrap_or(input.len());
+            output.push_str(&input[..end]);
+            input.drain(..end);
+        }
+    }
+
+    output
+}
+
+/// Resolve an IRI reference against a base IRI (RFC 3986, section 5.2)
+pub fn resolve_iri(base: &str, reference: &str) -> String {
+    let r = split_iri(reference);
+    if r.scheme.is_some() {
+        return reference.to_string();
+    }
+
+    let b = split_iri(base);
+    let (authority, path, query) = if r.authority.is_some() {
+        (r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.path.is_empty() {
+        (b.authority, b.path.to_string(), r.query.or(b.query))
+    } else if r.path.starts_with('/') {
+        (b.authority, remove_dot_segments(r.path), r.query)
+    } else {
+        let merged = if b.authority.is_some() && b.path.is_empty() {
+            format!("/{}", r.path)
+        } else {
+            match b.path.rfind('/') {
+                Some(i) => format!("{}{}", &b.path[..=i], r.path),
+                None => r.path.to_string(),
+            }
+        };
+        (b.authority, remove_dot_segments(&merged), r.query)
+    };
+
+    let mut resolved = String::new();
+    if let Some(scheme) = b.scheme {
+        resolved.push_str(scheme);
+        resolved.push(':');
+    }
+    if let Some(authority) = authority {
+        resolved.push_str("//");
+        resolved.push_str(authority);
+    }
+    resolved.push_str(&path);
+    if let Some(query) = query {
+        resolved.push('?');
+        resolved.push_str(query);
+    }
+    if let Some(fragment) = r.fragment {
+        resolved.push('#');
+        resolved.push_str(fragment);
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_iri_rfc3986_examples() {
+        let base = "http://a/b/c/d;p?q";
+        let cases = [
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("", "http://a/b/c/d;p?q"),
+            ("..", "http://a/b/"),
+            ("../..", "http://a/"),
+            ("../../../g", "http://a/g"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("urn:isbn:123", "urn:isbn:123"),
+        ];
+
+        for (reference, expected) in cases {
+            assert_eq!(resolve_iri(base, reference), expected, "resolving {:?}", reference);
+        }
+    }
+
+    #[test]
+    fn test_prefix_map_prefers_longest_namespace() {
+        let prefixes = PrefixMap::new(&[
+            Namespace { prefix: "ex".to_string(), uri: "http://example.com/".to_string() },
+            Namespace { prefix: "exv".to_string(), uri: "http://example.com/vocab#".to_string() },
+        ]);
+
+        let (ns, local) = prefixes.compact("http://example.com/vocab#Thing").unwrap();
+        assert_eq!((ns.prefix.as_str(), local), ("exv", "Thing"));
+        assert!(prefixes.compact("http://example.com/has space").is_none());
+    }
+
+    #[test]
+    fn test_portable_blank_label_is_injective() {
+        assert_eq!(portable_blank_label("b12"), "b12");
+        assert_eq!(portable_blank_label("nodeID://b1"), "b_nodeID_3A__2F__2F_b1");
+        assert_eq!(portable_blank_label("1a"), "b_1a");
+        assert_ne!(portable_blank_label("a_b"), portable_blank_label("a-b"));
+        assert_ne!(portable_blank_label("b_1a"), portable_blank_label("1a"));
+    }
+
+    #[test]
+    fn test_stream_writers_round_trip_across_pages() {
+        use crate::models::RdfNode;
+
+        let triple = |s: &str, p: &str, object: RdfNode, graph: Option<&str>| Triple {
+            subject: s.to_string(),
+            predicate: format!("http://example.com/{}", p),
+            object,
+            graph: graph.map(String::from),
+        };
+        let uri = |value: &str| RdfNode::Uri { value: value.to_string() };
+        let literal = |value: &str, language: Option<&str>| RdfNode::Literal {
+            value: value.to_string(),
+            datatype: None,
+            language: language.map(String::from),
+        };
+        // Ordered by graph then subject, as the paged SPARQL queries return them
+        let dataset = vec![
+            triple("_:node-1", "label", literal("blank", None), None),
+            triple("http://example.com/a", "label", literal("a", Some("en")), None),
+            triple("http://example.com/a", "label", literal("b", None), None),
+            triple("http://example.com/a", "ref", RdfNode::Blank { value: "node-1".to_string() }, None),
+            triple("http://example.com/b", "ref", uri("http://example.com/a"), Some("http://example.com/g")),
+            triple("http://example.com/b", "type", uri("http://example.com/Class"), Some("http://example.com/g")),
+            triple("http://example.com/c", "label", literal("c\n\"q\"", None), Some("http://example.com/g")),
+        ];
+        let ground = |triples: &[Triple], keep_graph: bool| -> Vec<String> {
+            let mut ground: Vec<String> = triples.iter()
+                .filter(|t| !t.subject_is_blank() && !matches!(t.object, RdfNode::Blank { .. }))
+                .map(|t| format!("{} {} {:?} {:?}", t.subject, t.predicate, t.object, t.graph.as_ref().filter(|_| keep_graph)))
+                .collect();
+            ground.sort();
+            ground
+        };
+
+        let formats = [
+            RdfFormat::NTriples, RdfFormat::NQuads, RdfFormat::Turtle,
+            RdfFormat::TriG, RdfFormat::JsonLd, RdfFormat::RdfXml,
+        ];
+        for format in formats {
+            let input: Vec<Triple> = if format.is_dataset() {
+                dataset.clone()
+            } else {
+                dataset.iter().cloned().map(|t| Triple { graph: None, ..t }).collect()
+            };
+
+            let mut writer = stream_writer(format, &default_namespaces(), JsonLdForm::Compacted);
+            let mut output = writer.start().unwrap();
+            // Pages of two split subjects and graphs across chunk boundaries
+            for page in input.chunks(2) {
+                output.push_str(&writer.write(page).unwrap());
+            }
+            output.push_str(&writer.finish().unwrap());
+
+            let reparsed = match format {
+                RdfFormat::NTriples | RdfFormat::Turtle => turtle::parse(&output, None),
+                RdfFormat::NQuads => turtle::parse_nquads(&output, None),
+                RdfFormat::TriG => turtle::parse_trig(&output, None),
+                RdfFormat::JsonLd => jsonld::parse(&output, None),
+                RdfFormat::RdfXml => rdfxml::parse(&output, None),
+            }.unwrap_or_else(|e| panic!("{:?}: {}\n{}", format, e, output));
+
+            assert_eq!(reparsed.len(), input.len(), "{:?}: {}", format, output);
+            assert_eq!(ground(&reparsed, format.is_dataset()), ground(&input, format.is_dataset()), "{:?}", format);
+            let blank_subject = reparsed.iter().find(|t| t.subject_is_blank()).unwrap();
+            let blank_ref = reparsed.iter().find(|t| matches!(t.object, RdfNode::Blank { .. })).unwrap();
+            assert_eq!(blank_ref.object, RdfNode::Blank { value: blank_subject.subject[2..].to_string() }, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_sparql_pattern_escapes_terms_that_were_never_checked() {
+        let triple = Triple {
+            subject: "_:a} ; DROP ALL ; INSERT DATA {".to_string(),
+            predicate: "http://x/p> } ; DROP ALL ; INSERT DATA { <a".to_string(),
+            object: RdfNode::Literal {
+                value: "v".to_string(),
+                datatype: None,
+                language: Some("en . } ; DROP ALL".to_string()),
+            },
+            graph: None,
+        };
+        let pattern = triple.to_sparql_pattern();
+        assert_eq!(pattern.matches(' ').count(), 2, "{}", pattern);
+        assert!(pattern.contains("<http://x/p%3E%20%7D%20;%20DROP%20ALL%20;%20INSERT%20DATA%20%7B%20%3Ca>"), "{}", pattern);
+        assert!(pattern.ends_with("\"v\"@enDROPALL"), "{}", pattern);
+        assert!(pattern.starts_with("_:b_a_7D__20__3B__20_DROP"), "{}", pattern);
+    }
+}
@@ -0,0 +1,124 @@
+//! N-Triples and N-Quads writing.
+//!
+//! One statement per line with no abbreviations, which makes these the safest
+//! formats for backups. Reading goes through `turtle::TurtleParser`.
+
+use crate::error::RdfEditorError;
+use crate::models::{RdfNode, Triple};
+use super::{escape_iri, escape_literal, portable_blank_label, StreamWriter, XSD_STRING};
+
+/// Serialize triples as N-Triples, ignoring graph names
+pub fn serialize(triples: &[Triple]) -> String {
+    NTriplesWriter::new(false).lines(triples)
+}
+
+/// Serialize quads as N-Quads; triples without a graph are written to the default graph
+pub fn serialize_quads(triples: &[Triple]) -> String {
+    NTriplesWriter::new(true).lines(triples)
+}
+
+/// Line writer for N-Triples, or N-Quads when `quads` is set. Keeps no state between lines.
+pub struct NTriplesWriter {
+    quads: bool,
+}
+
+impl NTriplesWriter {
+    pub fn new(quads: bool) -> Self {
+        NTriplesWriter { quads }
+    }
+
+    fn lines(&self, triples: &[Triple]) -> String {
+        let mut output = String::new();
+        for triple in triples {
+            output.push_str(&subject(&triple.subject));
+            output.push_str(&format!(" <{}> ", escape_iri(&triple.predicate)));
+            output.push_str(&object(&triple.object));
+            if let Some(graph) = triple.graph.as_deref().filter(|_| self.quads) {
+                output.push(' ');
+                output.push_str(&subject(graph));
+            }
+            output.push_str(" .\n");
+        }
+        output
+    }
+}
+
+impl StreamWriter for NTriplesWriter {
+    fn start(&mut self) -> Result<String, RdfEditorError> {
+        Ok(String::new())
+    }
+
+    fn write(&mut self, triples: &[Triple]) -> Result<String, RdfEditorError> {
+        Ok(self.lines(triples))
+    }
+
+    fn finish(&mut self) -> Result<String, RdfEditorError> {
+        Ok(String::new())
+    }
+}
+
+fn subject(subject: &str) -> String {
+    match subject.strip_prefix("_:") {
+        Some(label) => format!("_:{}", portable_blank_label(label)),
+        None => format!("<{}>", escape_iri(subject)),
+    }
+}
+
+fn object(object: &RdfNode) -> String {
+    match object {
+        RdfNode::Uri { value } => format!("<{}>", escape_iri(value)),
+        RdfNode::Blank { value } => format!("_:{}", portable_blank_label(value)),
+        RdfNode::Literal { value, datatype, language } => {
+            let mut literal = format!("\"{}\"", escape_literal(value));
+            if let Some(lang) = language {
+                literal.push_str(&format!("@{}", lang));
+            } else if let Some(dt) = datatype.as_deref().filter(|dt| *dt != XSD_STRING) {
+                literal.push_str(&format!("^^<{}>", escape_iri(dt)));
+            }
+            literal
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::turtle;
+
+    #[test]
+    fn test_nquads_round_trip() {
+        let triples = vec![
+            Triple {
+                subject: "_:node-1".to_string(),
+                predicate: "http://example.com/p".to_string(),
+                object: RdfNode::Literal {
+                    value: "line\n\"quoted\"".to_string(),
+                    datatype: Some(XSD_STRING.to_string()),
+                    language: None,
+                },
+                graph: Some("http://example.com/g".to_string()),
+            },
+            Triple {
+                subject: "http://example.com/s".to_string(),
+                predicate: "http://example.com/p".to_string(),
+                object: RdfNode::Blank { value: "node-1".to_string() },
+                graph: None,
+            },
+        ];
+
+        let output = serialize_quads(&triples);
+        assert_eq!(output, concat!(
+            "_:b_node_2D_1 <http://example.com/p> \"line\\n\\\"quoted\\\"\" <http://example.com/g> .\n",
+            "<http://example.com/s> <http://example.com/p> _:b_node_2D_1 .\n",
+        ));
+
+        let reparsed = turtle::parse_nquads(&output, None).unwrap();
+        assert_eq!(reparsed[0].graph.as_deref(), Some("http://example.com/g"));
+        assert_eq!(reparsed[1].graph, None);
+        assert_eq!(reparsed[0].subject, format!("_:{}", match &reparsed[1].object {
+            RdfNode::Blank { value } => value.as_str(),
+            other => panic!("expected blank node, got {:?}", other),
+        }));
+        assert!(!serialize(&triples).contains("<http://example.com/g>"));
+    }
+}
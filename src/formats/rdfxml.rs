@@ -0,0 +1,840 @@
+//! RDF/XML reading and writing.
+//!
+//! The parser walks an XML tree and follows the grammar of the RDF 1.1 XML
+//! Syntax specification: node and property elements, `rdf:about`/`rdf:ID`/
+//! `rdf:nodeID`, `rdf:resource`, property attributes, `rdf:li`, reification
+//! through `rdf:ID` on property elements and the `Resource`, `Collection` and
+//! `Literal` parse types. `xml:lang` and `xml:base` are inherited down the tree.
+//! XML literals keep their source text; they are not canonicalized.
+//!
+//! The writer produces striped RDF/XML. Blank nodes referenced exactly once
+//! are nested inside their property element, mirroring the Turtle writer.
+
+use std::collections::{HashMap, HashSet};
+use roxmltree::{Document, Node, ParsingOptions, TextPos};
+use crate::error::RdfEditorError;
+use crate::models::{Namespace, RdfNode, Triple};
+use super::{check_terms, is_absolute_iri, portable_blank_label, resolve_iri, StreamWriter, RDF_NS, RDF_TYPE, XSD_STRING};
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+const RDF_XML_LITERAL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#XMLLiteral";
+
+const CORE_SYNTAX_TERMS: &[&str] = &["RDF", "ID", "about", "parseType", "resource", "nodeID", "datatype"];
+const OLD_TERMS: &[&str] = &["aboutEach", "aboutEachPrefix", "bagID"];
+
+/// Parse an RDF/XML document into triples
+pub fn parse(input: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    // OWL files routinely declare entities such as `&owl;` in an internal DTD
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(input, options)
+        .map_err(|e| syntax_error(e.pos(), &e.to_string()))?;
+
+    let mut parser = RdfXmlParser {
+        input,
+        document: &document,
+        triples: Vec::new(),
+        blank_ids: HashMap::new(),
+        next_blank: 0,
+        used_ids: HashSet::new(),
+    };
+
+    let scope = Scope { base: base.map(String::from), lang: None };
+    let root = document.root_element();
+    if is_rdf(root, "RDF") {
+        let scope = parser.enter(root, &scope)?;
+        for child in parser.element_children(root)? {
+            parser.node_element(child, &scope)?;
+        }
+    } else {
+        parser.node_element(root, &scope)?;
+    }
+
+    // rdf:about, rdf:resource, xml:base and xml:lang are taken as written
+    for triple in &parser.triples {
+        check_terms(triple).map_err(|e| RdfEditorError::InvalidInput(format!("Invalid RDF/XML: {}", e)))?;
+    }
+    Ok(parser.triples)
+}
+
+fn syntax_error(pos: TextPos, message: &str) -> RdfEditorError {
+    RdfEditorError::InvalidInput(format!(
+        "RDF/XML syntax error at line {}, column {}: {}", pos.row, pos.col, message
+    ))
+}
+
+fn is_rdf(node: Node, local: &str) -> bool {
+    node.tag_name().namespace() == Some(RDF_NS) && node.tag_name().name() == local
+}
+
+/// `xml:base` and `xml:lang` in effect for an element
+#[derive(Clone)]
+struct Scope {
+    base: Option<String>,
+    lang: Option<String>,
+}
+
+/// The attributes of a node or property element, split by role
+#[derive(Default)]
+struct Attributes<'a> {
+    rdf: Vec<(&'a str, &'a str)>,
+    properties: Vec<(String, &'a str)>,
+}
+
+struct RdfXmlParser<'a, 'input> {
+    input: &'input str,
+    document: &'a Document<'input>,
+    triples: Vec<Triple>,
+    blank_ids: HashMap<String, String>,
+    next_blank: usize,
+    used_ids: HashSet<String>,
+}
+
+impl<'a, 'input> RdfXmlParser<'a, 'input> {
+    fn error(&self, node: Node, message: &str) -> RdfEditorError {
+        syntax_error(self.document.text_pos_at(node.range().start), message)
+    }
+
+    fn fresh_blank(&mut self) -> String {
+        let id = format!("_:b{}", self.next_blank);
+        self.next_blank += 1;
+        id
+    }
+
+    fn blank_for_label(&mut self, node: Node, label: &str) -> Result<String, RdfEditorError> {
+        if !is_nc_name(label) {
+            return Err(self.error(node, &format!("rdf:nodeID '{}' is not a valid XML name", label)));
+        }
+        if let Some(id) = self.blank_ids.get(label) {
+            return Ok(id.clone());
+        }
+        let id = self.fresh_blank();
+        self.blank_ids.insert(label.to_string(), id.clone());
+        Ok(id)
+    }
+
+    fn emit(&mut self, subject: &str, predicate: &str, object: RdfNode) {
+        self.triples.push(Triple {
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            object,
+            graph: None,
+        });
+    }
+
+    fn enter(&self, node: Node, scope: &Scope) -> Result<Scope, RdfEditorError> {
+        let mut scope = scope.clone();
+        if let Some(base) = node.attribute((XML_NS, "base")) {
+            // The fragment of a base IRI never takes part in resolution
+            let base = base.split('#').next().unwrap_or_default();
+            scope.base = Some(match &scope.base {
+                Some(current) => resolve_iri(current, base),
+                None if is_absolute_iri(base) => base.to_string(),
+                None => return Err(self.error(node, &format!("xml:base '{}' is relative and there is no base IRI", base))),
+            });
+        }
+        if let Some(lang) = node.attribute((XML_NS, "lang")) {
+            scope.lang = if lang.is_empty() { None } else { Some(lang.to_lowercase()) };
+        }
+        Ok(scope)
+    }
+
+    fn resolve(&self, node: Node, scope: &Scope, iri: &str) -> Result<String, RdfEditorError> {
+        if is_absolute_iri(iri) {
+            return Ok(iri.to_string());
+        }
+        match &scope.base {
+            Some(base) => Ok(resolve_iri(base, iri)),
+            None => Err(self.error(node, &format!("relative IRI <{}> with no base IRI", iri))),
+        }
+    }
+
+    /// `rdf:ID` values become fragment IRIs and may only be used once per base
+    fn rdf_id(&mut self, node: Node, scope: &Scope, id: &str) -> Result<String, RdfEditorError> {
+        if !is_nc_name(id) {
+            return Err(self.error(node, &format!("rdf:ID '{}' is not a valid XML name", id)));
+        }
+        let iri = self.resolve(node, scope, &format!("#{}", id))?;
+        if !self.used_ids.insert(iri.clone()) {
+            return Err(self.error(node, &format!("rdf:ID '{}' is used more than once", id)));
+        }
+        Ok(iri)
+    }
+
+    fn element_iri(&self, node: Node) -> Result<String, RdfEditorError> {
+        let name = node.tag_name();
+        match name.namespace() {
+            Some(ns) => Ok(format!("{}{}", ns, name.name())),
+            None => Err(self.error(node, &format!("element <{}> has no namespace", name.name()))),
+        }
+    }
+
+    fn element_children(&self, node: Node<'a, 'input>) -> Result<Vec<Node<'a, 'input>>, RdfEditorError> {
+        let mut children = Vec::new();
+        for child in node.children() {
+            if child.is_element() {
+                children.push(child);
+            } else if child.is_text() && !child.text().unwrap_or_default().trim().is_empty() {
+                return Err(self.error(child, "unexpected text content"));
+            }
+        }
+        Ok(children)
+    }
+
+    fn attributes(&self, node: Node<'a, 'input>, forbidden_rdf: &[&str]) -> Result<Attributes<'a>, RdfEditorError> {
+        let mut attributes = Attributes::default();
+        for attribute in node.attributes() {
+            let name = attribute.name();
+            match attribute.namespace() {
+                Some(XML_NS) => {},
+                Some(RDF_NS) if forbidden_rdf.contains(&name) || OLD_TERMS.contains(&name) => {
+                    return Err(self.error(node, &format!("rdf:{} is not allowed here", name)));
+                },
+                Some(RDF_NS) if CORE_SYNTAX_TERMS.contains(&name) => attributes.rdf.push((name, attribute.value())),
+                Some(ns) => {
+                    // Names starting with "xml" are reserved and ignored by RDF/XML processors
+                    if !name.to_lowercase().starts_with("xml") {
+                        attributes.properties.push((format!("{}{}", ns, name), attribute.value()));
+                    }
+                },
+                None => return Err(self.error(node, &format!("attribute '{}' has no namespace", name))),
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn property_attributes(&mut self, node: Node, scope: &Scope, subject: &str, properties: &[(String, &str)]) -> Result<(), RdfEditorError> {
+        for (predicate, value) in properties {
+            let object = if predicate == RDF_TYPE {
+                RdfNode::Uri { value: self.resolve(node, scope, value)? }
+            } else {
+                RdfNode::Literal { value: value.to_string(), datatype: None, language: scope.lang.clone() }
+            };
+            self.emit(subject, predicate, object);
+        }
+        Ok(())
+    }
+
+    /// Parse a node element and return its subject
+    fn node_element(&mut self, node: Node<'a, 'input>, scope: &Scope) -> Result<String, RdfEditorError> {
+        let scope = self.enter(node, scope)?;
+        let element = self.element_iri(node)?;
+        if let Some(local) = element.strip_prefix(RDF_NS) {
+            if CORE_SYNTAX_TERMS.contains(&local) || OLD_TERMS.contains(&local) || local == "li" {
+                return Err(self.error(node, &format!("rdf:{} cannot be used as a node element", local)));
+            }
+        }
+
+        let attributes = self.attributes(node, &["parseType", "resource", "datatype", "li", "Description"])?;
+        let mut subject = None;
+        for (name, value) in &attributes.rdf {
+            let id = match *name {
+                "about" => self.resolve(node, &scope, value)?,
+                "ID" => self.rdf_id(node, &scope, value)?,
+                "nodeID" => self.blank_for_label(node, value)?,
+                _ => continue,
+            };
+            if subject.is_some() {
+                return Err(self.error(node, "only one of rdf:about, rdf:ID and rdf:nodeID may be used"));
+            }
+            subject = Some(id);
+        }
+        let subject = match subject {
+            Some(subject) => subject,
+            None => self.fresh_blank(),
+        };
+
+        if !is_rdf(node, "Description") {
+            self.emit(&subject, RDF_TYPE, RdfNode::Uri { value: element });
+        }
+        self.property_attributes(node, &scope, &subject, &attributes.properties)?;
+
+        let mut li = 1;
+        for child in self.element_children(node)? {
+            self.property_element(child, &scope, &subject, &mut li)?;
+        }
+
+        Ok(subject)
+    }
+
+    fn property_element(&mut self, node: Node<'a, 'input>, scope: &Scope, subject: &str, li: &mut usize) -> Result<(), RdfEditorError> {
+        let scope = self.enter(node, scope)?;
+        let mut predicate = self.element_iri(node)?;
+        if let Some(local) = predicate.strip_prefix(RDF_NS) {
+            if local == "li" {
+                predicate = format!("{}_{}", RDF_NS, li);
+                *li += 1;
+            } else if CORE_SYNTAX_TERMS.contains(&local) || OLD_TERMS.contains(&local) || local == "Description" {
+                return Err(self.error(node, &format!("rdf:{} cannot be used as a property element", local)));
+            }
+        }
+
+        let attributes = self.attributes(node, &["about", "li", "Description"])?;
+        let rdf_attribute = |name: &str| attributes.rdf.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+        let reification = match rdf_attribute("ID") {
+            Some(id) => Some(self.rdf_id(node, &scope, id)?),
+            None => None,
+        };
+        let parse_type = rdf_attribute("parseType");
+        let resource = rdf_attribute("resource");
+        let node_id = rdf_attribute("nodeID");
+        let datatype = rdf_attribute("datatype");
+
+        let object = match parse_type {
+            Some("Resource") => {
+                if resource.is_some() || node_id.is_some() || datatype.is_some() || !attributes.properties.is_empty() {
+                    return Err(self.error(node, "rdf:parseType=\"Resource\" cannot be combined with other attributes"));
+                }
+                let object = self.fresh_blank();
+                let mut inner_li = 1;
+                for child in self.element_children(node)? {
+                    self.property_element(child, &scope, &object, &mut inner_li)?;
+                }
+                subject_to_node(object)
+            },
+            Some("Collection") => {
+                let mut items = Vec::new();
+                for child in self.element_children(node)? {
+                    items.push(self.node_element(child, &scope)?);
+                }
+                self.collection(items)
+            },
+            // Any other parse type is treated as "Literal"
+            Some(_) => RdfNode::Literal {
+                value: self.inner_xml(node).to_string(),
+                datatype: Some(RDF_XML_LITERAL.to_string()),
+                language: None,
+            },
+            None if node.children().any(|child| child.is_element()) => {
+                let children = self.element_children(node)?;
+                if children.len() > 1 {
+                    return Err(self.error(children[1], "a property element may contain only one node element"));
+                }
+                if resource.is_some() || node_id.is_some() || datatype.is_some() || !attributes.properties.is_empty() {
+                    return Err(self.error(node, "a property element with a node element cannot have resource attributes"));
+                }
+                subject_to_node(self.node_element(children[0], &scope)?)
+            },
+            None if resource.is_some() || node_id.is_some() || !attributes.properties.is_empty() => {
+                if datatype.is_some() {
+                    return Err(self.error(node, "rdf:datatype is only allowed on literal property elements"));
+                }
+                if node.children().any(|child| child.is_text() && !child.text().unwrap_or_default().trim().is_empty()) {
+                    return Err(self.error(node, "a property element with resource attributes must be empty"));
+                }
+                let object = match (resource, node_id) {
+                    (Some(_), Some(_)) => {
+                        return Err(self.error(node, "rdf:resource and rdf:nodeID cannot be used together"));
+                    },
+                    (Some(resource), None) => self.resolve(node, &scope, resource)?,
+                    (None, Some(label)) => self.blank_for_label(node, label)?,
+                    (None, None) => self.fresh_blank(),
+                };
+                self.property_attributes(node, &scope, &object, &attributes.properties)?;
+                subject_to_node(object)
+            },
+            None => {
+                let value: String = node.children().filter_map(|child| child.text()).collect();
+                match datatype {
+                    Some(datatype) => RdfNode::Literal {
+                        value,
+                        datatype: Some(self.resolve(node, &scope, datatype)?).filter(|dt| dt != XSD_STRING),
+                        language: None,
+                    },
+                    None => RdfNode::Literal { value, datatype: None, language: scope.lang.clone() },
+                }
+            },
+        };
+
+        if let Some(statement) = reification {
+            self.emit(&statement, RDF_TYPE, RdfNode::Uri { value: format!("{}Statement", RDF_NS) });
+            self.emit(&statement, &format!("{}subject", RDF_NS), subject_to_node(subject.to_string()));
+            self.emit(&statement, &format!("{}predicate", RDF_NS), RdfNode::Uri { value: predicate.clone() });
+            self.emit(&statement, &format!("{}object", RDF_NS), object.clone());
+        }
+        self.emit(subject, &predicate, object);
+        Ok(())
+    }
+
+    fn collection(&mut self, items: Vec<String>) -> RdfNode {
+        let rdf_nil = RdfNode::Uri { value: format!("{}nil", RDF_NS) };
+        let cells: Vec<String> = items.iter().map(|_| self.fresh_blank()).collect();
+        for (i, item) in items.into_iter().enumerate() {
+            let rest = cells.get(i + 1).cloned().map(subject_to_node).unwrap_or_else(|| rdf_nil.clone());
+            self.emit(&cells[i], &format!("{}first", RDF_NS), subject_to_node(item));
+            self.emit(&cells[i], &format!("{}rest", RDF_NS), rest);
+        }
+        cells.first().cloned().map(subject_to_node).unwrap_or(rdf_nil)
+    }
+
+    /// Source text between the start and end tags of an element
+    fn inner_xml(&self, node: Node) -> &'input str {
+        match (node.first_child(), node.last_child()) {
+            (Some(first), Some(last)) => &self.input[first.range().start..last.range().end],
+            _ => "",
+        }
+    }
+}
+
+fn subject_to_node(subject: String) -> RdfNode {
+    match subject.strip_prefix("_:") {
+        Some(label) => RdfNode::Blank { value: label.to_string() },
+        None => RdfNode::Uri { value: subject },
+    }
+}
+
+/// Approximation of the XML `NCName` production
+fn is_nc_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '\u{B7}')
+}
+
+/// Serialize triples as RDF/XML
+pub fn serialize(triples: &[Triple], namespaces: &[Namespace]) -> Result<String, RdfEditorError> {
+    RdfXmlSerializer::new(triples, namespaces).serialize()
+}
+
+struct RdfXmlSerializer<'a> {
+    subjects: Vec<(&'a str, Vec<&'a Triple>)>,
+    subject_index: HashMap<&'a str, usize>,
+    blank_refs: HashMap<&'a str, usize>,
+    written: HashSet<String>,
+    namespaces: Vec<Namespace>,
+    declared: Vec<(String, String)>,
+    out: String,
+}
+
+const INDENT: &str = "  ";
+
+impl<'a> RdfXmlSerializer<'a> {
+    fn new(triples: &'a [Triple], namespaces: &[Namespace]) -> Self {
+        let mut subjects: Vec<(&str, Vec<&Triple>)> = Vec::new();
+        let mut subject_index = HashMap::new();
+        let mut blank_refs = HashMap::new();
+
+        for triple in triples {
+            let i = *subject_index.entry(triple.subject.as_str()).or_insert_with(|| {
+                subjects.push((triple.subject.as_str(), Vec::new()));
+                subjects.len() - 1
+            });
+            if !subjects[i].1.contains(&triple) {
+                subjects[i].1.push(triple);
+                if let RdfNode::Blank { value } = &triple.object {
+                    *blank_refs.entry(value.as_str()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // Longest namespace first so the most specific prefix wins
+        let mut namespaces: Vec<Namespace> = namespaces.iter()
+            .filter(|ns| is_nc_name(&ns.prefix) && !ns.prefix.to_lowercase().starts_with("xml"))
+            .cloned()
+            .collect();
+        namespaces.sort_by_key(|ns| std::cmp::Reverse(ns.uri.len()));
+
+        RdfXmlSerializer {
+            subjects,
+            subject_index,
+            blank_refs,
+            written: HashSet::new(),
+            namespaces,
+            declared: vec![("rdf".to_string(), RDF_NS.to_string())],
+            out: String::new(),
+        }
+    }
+
+    fn serialize(mut self) -> Result<String, RdfEditorError> {
+        let subjects: Vec<&str> = self.subjects.iter().map(|(s, _)| *s).collect();
+
+        for subject in &subjects {
+            if !self.nestable(subject) {
+                self.write_node(subject, 1, false)?;
+            }
+        }
+        // Blank nodes that only reference each other are written at the top level
+        for subject in &subjects {
+            if !self.written.contains(*subject) {
+                self.write_node(subject, 1, false)?;
+            }
+        }
+
+        let mut document = document_header(&self.declared)?;
+        document.push_str(&self.out);
+        document.push_str("</rdf:RDF>\n");
+        Ok(document)
+    }
+
+    fn nestable(&self, subject: &str) -> bool {
+        subject.strip_prefix("_:").is_some_and(|label| self.blank_refs.get(label) == Some(&1))
+    }
+
+    /// Split an IRI into a declared prefix and an XML local name
+    fn qname(&mut self, iri: &str) -> Result<String, RdfEditorError> {
+        if let Some(ns) = self.namespaces.iter()
+            .find(|ns| iri.strip_prefix(ns.uri.as_str()).is_some_and(is_nc_name))
+        {
+            let (prefix, uri) = (ns.prefix.clone(), ns.uri.clone());
+            let local = &iri[uri.len()..];
+            if !self.declared.iter().any(|(p, _)| *p == prefix) {
+                self.declared.push((prefix.clone(), uri));
+            }
+            return Ok(format!("{}:{}", prefix, local));
+        }
+
+        // Use the longest suffix that is a valid local name and invent a prefix for the rest
+        let split = iri.char_indices()
+            .map(|(i, _)| i)
+            .find(|&i| i > 0 && is_nc_name(&iri[i..]))
+            .ok_or_else(|| RdfEditorError::InvalidInput(format!(
+                "<{}> cannot be written as an RDF/XML element name", iri
+            )))?;
+        let (uri, local) = iri.split_at(split);
+
+        let prefix = match self.declared.iter().find(|(_, u)| u == uri) {
+            Some((prefix, _)) => prefix.clone(),
+            None => {
+                let prefix = format!("ns{}", self.declared.len());
+                self.declared.push((prefix.clone(), uri.to_string()));
+                prefix
+            },
+        };
+        Ok(format!("{}:{}", prefix, local))
+    }
+
+    fn write_node(&mut self, subject: &str, depth: usize, nested: bool) -> Result<(), RdfEditorError> {
+        self.written.insert(subject.to_string());
+        let mut properties: Vec<&Triple> = self.subject_index.get(subject)
+            .map(|&i| self.subjects[i].1.clone())
+            .unwrap_or_default();
+
+        // Use the first type as the element name when it has a valid local name
+        let mut element = "rdf:Description".to_string();
+        if let Some(position) = properties.iter().position(|t| t.predicate == RDF_TYPE && matches!(t.object, RdfNode::Uri { .. })) {
+            if let RdfNode::Uri { value } = &properties[position].object {
+                if let Ok(name) = self.qname(value) {
+                    element = name;
+                    properties.remove(position);
+                }
+            }
+        }
+
+        let indent = INDENT.repeat(depth);
+        let identifier = match subject.strip_prefix("_:") {
+            Some(_) if nested => String::new(),
+            Some(label) => format!(" rdf:nodeID=\"{}\"", portable_blank_label(label)),
+            None => format!(" rdf:about=\"{}\"", escape_attribute(subject)?),
+        };
+
+        if properties.is_empty() {
+            self.out.push_str(&format!("{}<{}{}/>\n", indent, element, identifier));
+            return Ok(());
+        }
+
+        self.out.push_str(&format!("{}<{}{}>\n", indent, element, identifier));
+        for triple in properties {
+            self.write_property(triple, depth + 1)?;
+        }
+        self.out.push_str(&format!("{}</{}>\n", indent, element));
+        Ok(())
+    }
+
+    fn write_property(&mut self, triple: &Triple, depth: usize) -> Result<(), RdfEditorError> {
+        let indent = INDENT.repeat(depth);
+        let name = self.qname(&triple.predicate)?;
+
+        match &triple.object {
+            RdfNode::Uri { value } => {
+                self.out.push_str(&format!("{}<{} rdf:resource=\"{}\"/>\n", indent, name, escape_attribute(value)?));
+            },
+            RdfNode::Blank { value } => {
+                let subject = format!("_:{}", value);
+                let has_properties = self.subject_index.contains_key(subject.as_str());
+                if has_properties && self.nestable(&subject) && !self.written.contains(&subject) {
+                    let typed = self.subject_index.get(subject.as_str())
+                        .is_some_and(|&i| self.subjects[i].1.iter().any(|t| t.predicate == RDF_TYPE));
+                    if typed {
+                        self.out.push_str(&format!("{}<{}>\n", indent, name));
+                        self.write_node(&subject, depth + 1, true)?;
+                        self.out.push_str(&format!("{}</{}>\n", indent, name));
+                    } else {
+                        self.written.insert(subject.clone());
+                        self.out.push_str(&format!("{}<{} rdf:parseType=\"Resource\">\n", indent, name));
+                        let properties = self.subjects[self.subject_index[subject.as_str()]].1.clone();
+                        for nested in properties {
+                            self.write_property(nested, depth + 1)?;
+                        }
+                        self.out.push_str(&format!("{}</{}>\n", indent, name));
+                    }
+                } else {
+                    let label = portable_blank_label(value);
+                    self.out.push_str(&format!("{}<{} rdf:nodeID=\"{}\"/>\n", indent, name, label));
+                }
+            },
+            RdfNode::Literal { value, datatype, language } => {
+                let attributes = match (language, datatype.as_deref()) {
+                    (Some(lang), _) => format!(" xml:lang=\"{}\"", escape_attribute(lang)?),
+                    (None, Some(RDF_XML_LITERAL)) => {
+                        self.out.push_str(&format!("{}<{} rdf:parseType=\"Literal\">{}</{}>\n", indent, name, value, name));
+                        return Ok(());
+                    },
+                    (None, Some(dt)) if dt != XSD_STRING => format!(" rdf:datatype=\"{}\"", escape_attribute(dt)?),
+                    _ => String::new(),
+                };
+                self.out.push_str(&format!("{}<{}{}>{}</{}>\n", indent, name, attributes, escape_text(value)?, name));
+            },
+        }
+        Ok(())
+    }
+}
+
+fn document_header(declared: &[(String, String)]) -> Result<String, RdfEditorError> {
+    let mut header = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rdf:RDF");
+    for (prefix, uri) in declared {
+        header.push_str(&format!("\n{}xmlns:{}=\"{}\"", INDENT, prefix, escape_attribute(uri)?));
+    }
+    header.push_str(">\n");
+    Ok(header)
+}
+
+/// Streaming RDF/XML writer. Each subject becomes one node element; namespaces that
+/// were not declared on `rdf:RDF` are declared on the node element that needs them.
+pub struct RdfXmlStreamWriter {
+    namespaces: Vec<Namespace>,
+    declared: Vec<(String, String)>,
+    current: Vec<Triple>,
+}
+
+impl RdfXmlStreamWriter {
+    pub fn new(namespaces: &[Namespace]) -> Self {
+        let mut declared = vec![("rdf".to_string(), RDF_NS.to_string())];
+        declared.extend(namespaces.iter()
+            .filter(|ns| ns.uri != RDF_NS && is_nc_name(&ns.prefix) && !ns.prefix.to_lowercase().starts_with("xml"))
+            .map(|ns| (ns.prefix.clone(), ns.uri.clone())));
+        RdfXmlStreamWriter { namespaces: namespaces.to_vec(), declared, current: Vec::new() }
+    }
+
+    fn flush_node(&mut self, out: &mut String) -> Result<(), RdfEditorError> {
+        let Some(subject) = self.current.first().map(|t| t.subject.clone()) else {
+            return Ok(());
+        };
+
+        let triples = std::mem::take(&mut self.current);
+        let mut serializer = RdfXmlSerializer::new(&triples, &self.namespaces);
+        serializer.declared = self.declared.clone();
+        serializer.write_node(&subject, 1, false)?;
+
+        let mut element = serializer.out;
+        let local_declarations: String = serializer.declared[self.declared.len()..].iter()
+            .map(|(prefix, uri)| Ok(format!(" xmlns:{}=\"{}\"", prefix, escape_attribute(uri)?)))
+            .collect::<Result<_, RdfEditorError>>()?;
+        if !local_declarations.is_empty() {
+            // Insert right after the element name of the opening tag
+            let start = element.find('<').map(|i| i + 1).unwrap_or(0);
+            let name_end = element[start..].find([' ', '>', '/']).map(|i| i + start).unwrap_or(element.len());
+            element.insert_str(name_end, &local_declarations);
+        }
+        out.push_str(&element);
+        Ok(())
+    }
+}
+
+impl StreamWriter for RdfXmlStreamWriter {
+    fn start(&mut self) -> Result<String, RdfEditorError> {
+        document_header(&self.declared)
+    }
+
+    fn write(&mut self, triples: &[Triple]) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        for triple in triples {
+            if self.current.first().is_some_and(|t| t.subject != triple.subject) {
+                self.flush_node(&mut out)?;
+            }
+            self.current.push(triple.clone());
+        }
+        Ok(out)
+    }
+
+    fn finish(&mut self) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        self.flush_node(&mut out)?;
+        out.push_str("</rdf:RDF>\n");
+        Ok(out)
+    }
+}
+
+fn check_xml_chars(value: &str) -> Result<(), RdfEditorError> {
+    match value.chars().find(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) {
+        Some(c) => Err(RdfEditorError::InvalidInput(format!(
+            "character U+{:04X} cannot be represented in RDF/XML", c as u32
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn escape_text(value: &str) -> Result<String, RdfEditorError> {
+    check_xml_chars(value)?;
+    Ok(value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\r', "&#13;"))
+}
+
+fn escape_attribute(value: &str) -> Result<String, RdfEditorError> {
+    check_xml_chars(value)?;
+    Ok(value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+        .replace('\t', "&#9;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::path::Path;
+    use crate::formats::turtle;
+
+    const FIXTURES: &str = "src/formats/fixtures/rdfxml";
+
+    /// N-Triples lines with blank nodes relabelled from their surroundings, so isomorphic graphs compare equal
+    fn canonical(triples: &[Triple]) -> Vec<String> {
+        let mut labels: HashMap<String, u64> = HashMap::new();
+        for triple in triples {
+            if let Some(label) = triple.subject.strip_prefix("_:") {
+                labels.insert(label.to_string(), 0);
+            }
+            if let RdfNode::Blank { value } = &triple.object {
+                labels.insert(value.clone(), 0);
+            }
+        }
+
+        let render = |triple: &Triple, labels: &HashMap<String, u64>, focus: Option<&str>| {
+            let mut triple = triple.clone();
+            let relabel = |label: &str| match focus {
+                Some(focus) if focus == label => "*".to_string(),
+                _ => format!("{:x}", labels[label]),
+            };
+            if let Some(label) = triple.subject.strip_prefix("_:") {
+                triple.subject = format!("_:{}", relabel(label));
+            }
+            if let RdfNode::Blank { value } = &triple.object {
+                triple.object = RdfNode::Blank { value: relabel(value) };
+            }
+            triple.to_sparql_pattern()
+        };
+
+        for _ in 0..6 {
+            let mut next = HashMap::new();
+            for label in labels.keys() {
+                let blank = RdfNode::Blank { value: label.clone() };
+                let subject = format!("_:{}", label);
+                let mut edges: Vec<String> = triples.iter()
+                    .filter(|t| t.subject == subject || t.object == blank)
+                    .map(|t| render(t, &labels, Some(label)))
+                    .collect();
+                edges.sort();
+                let mut hasher = DefaultHasher::new();
+                edges.hash(&mut hasher);
+                next.insert(label.clone(), hasher.finish());
+            }
+            labels = next;
+        }
+
+        let mut lines: Vec<String> = triples.iter().map(|t| render(t, &labels, None)).collect();
+        lines.sort();
+        lines.dedup();
+        lines
+    }
+
+    #[test]
+    fn test_w3c_style_fixtures() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES);
+        let mut entries: Vec<_> = std::fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rdf"))
+            .collect();
+        entries.sort();
+        assert!(!entries.is_empty());
+
+        for path in entries {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let base = format!("http://www.w3.org/2013/RDFXMLTests/{}", name);
+            let input = std::fs::read_to_string(&path).unwrap();
+            let result = parse(&input, Some(&base));
+
+            if name.starts_with("error-") {
+                assert!(result.is_err(), "{} should be rejected", name);
+                continue;
+            }
+
+            let triples = result.unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let expected = std::fs::read_to_string(path.with_extension("nt")).unwrap();
+            let expected = turtle::parse(&expected, None).unwrap();
+            assert_eq!(canonical(&triples), canonical(&expected), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_error_reports_position() {
+        let input = "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  <rdf:li/>\n</rdf:RDF>";
+        let err = parse(input, None).unwrap_err().to_string();
+        assert!(err.contains("line 2, column 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_serializer_nests_blank_nodes_and_uses_typed_elements() {
+        let triples = turtle::parse(r#"
+            @prefix foaf: <http://xmlns.com/foaf/0.1/> .
+            <http://example.com/alice> a foaf:Person ;
+                foaf:name "Alice & <Bob>"@en ;
+                foaf:age 30 ;
+                foaf:knows [ foaf:name "Carol" ] , [ a foaf:Person ] .
+        "#, None).unwrap();
+
+        let output = serialize(&triples, &crate::formats::default_namespaces()).unwrap();
+        assert!(output.contains("xmlns:foaf=\"http://xmlns.com/foaf/0.1/\""), "{}", output);
+        assert!(output.contains("<foaf:Person rdf:about=\"http://example.com/alice\">"), "{}", output);
+        assert!(output.contains("<foaf:name xml:lang=\"en\">Alice &amp; &lt;Bob&gt;</foaf:name>"), "{}", output);
+        assert!(output.contains("<foaf:knows rdf:parseType=\"Resource\">"), "{}", output);
+        assert!(!output.contains("rdf:nodeID"), "{}", output);
+    }
+
+    #[test]
+    fn test_parse_rejects_iris_and_language_tags_that_break_out_of_a_query() {
+        let err = parse(r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:ex="http://x/">
+            <rdf:Description rdf:about="http://x/&gt; } ; DROP ALL ; INSERT DATA { &lt;a"><ex:p>v</ex:p></rdf:Description>
+        </rdf:RDF>"#, None).unwrap_err();
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+
+        let err = parse(r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:ex="http://x/">
+            <rdf:Description rdf:about="http://x/s"><ex:p rdf:resource="o}"/></rdf:Description>
+        </rdf:RDF>"#, Some("http://x/")).unwrap_err();
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+
+        let err = parse(r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:ex="http://x/">
+            <rdf:Description rdf:about="http://x/s"><ex:p xml:lang="en . } ; DROP ALL">v</ex:p></rdf:Description>
+        </rdf:RDF>"#, None).unwrap_err();
+        assert!(err.to_string().contains("invalid language tag"), "{}", err);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = turtle::parse(r#"
+            @prefix ex: <http://example.com/vocab#> .
+            <http://example.com/a> a ex:Thing , ex:Other ;
+                ex:label "plain" , "tagged"@fr , "typed"^^ex:dt , "line\nbreak" ;
+                ex:list ( <http://example.com/b> "x" ) ;
+                ex:nested [ a ex:Inner ; ex:leaf [ ex:deep true ] ] ;
+                <http://example.com/p/1x> "generated prefix" .
+            _:c1 ex:next _:c2 .
+            _:c2 ex:next _:c1 .
+        "#, None).unwrap();
+
+        let output = serialize(&original, &[]).unwrap();
+        let reparsed = parse(&output, None).unwrap_or_else(|e| panic!("{}\n{}", e, output));
+        assert_eq!(canonical(&reparsed), canonical(&original), "{}", output);
+    }
+}
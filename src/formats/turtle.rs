@@ -0,0 +1,1639 @@
+//! Turtle and TriG reading and writing.
+//!
+//! Output groups triples by subject (`;`) and by predicate (`,`), abbreviates
+//! IRIs with the supplied prefixes and nests blank nodes that are referenced
+//! exactly once as `[ ... ]`. The parser follows the W3C Turtle and TriG
+//! grammars, including collections, blank node property lists and the
+//! numeric/boolean literal shorthands. N-Triples input is a subset of Turtle
+//! and goes through the same parser, as does N-Quads via `Syntax::NQuads`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::error::RdfEditorError;
+use crate::models::{Namespace, RdfNode, Triple};
+use super::{
+    escape_iri, escape_literal, invalid_iri_char, is_absolute_iri, portable_blank_label, resolve_iri, PrefixMap, StreamWriter,
+    RDF_NS, RDF_TYPE, XSD_BOOLEAN, XSD_DECIMAL, XSD_DOUBLE, XSD_INTEGER, XSD_STRING,
+};
+
+const INDENT: &str = "    ";
+
+/// Serialize triples as a Turtle document using `namespaces` for `@prefix` declarations
+pub fn serialize(triples: &[Triple], namespaces: &[Namespace]) -> String {
+    let mut serializer = TurtleSerializer::new(triples, namespaces);
+    let body = serializer.write_body();
+    prefix_header(namespaces, &serializer.used_prefixes) + &body
+}
+
+/// Serialize a dataset as TriG: default graph triples first, then one block per named graph
+pub fn serialize_trig(triples: &[Triple], namespaces: &[Namespace]) -> String {
+    let mut graphs: Vec<(Option<&str>, Vec<&Triple>)> = Vec::new();
+    for triple in triples {
+        let graph = triple.graph.as_deref();
+        match graphs.iter_mut().find(|(g, _)| *g == graph) {
+            Some((_, graph_triples)) => graph_triples.push(triple),
+            None => graphs.push((graph, vec![triple])),
+        }
+    }
+    graphs.sort_by_key(|(graph, _)| graph.is_some());
+
+    // Blank nodes used in more than one graph must keep a label so the graphs still share them
+    let mut blank_graphs: HashMap<&str, HashSet<Option<&str>>> = HashMap::new();
+    for triple in triples {
+        let graph = triple.graph.as_deref();
+        if let Some(label) = triple.subject.strip_prefix("_:") {
+            blank_graphs.entry(label).or_default().insert(graph);
+        }
+        if let RdfNode::Blank { value } = &triple.object {
+            blank_graphs.entry(value.as_str()).or_default().insert(graph);
+        }
+    }
+    let shared: HashSet<&str> = blank_graphs.into_iter()
+        .filter(|(_, graphs)| graphs.len() > 1)
+        .map(|(label, _)| label)
+        .collect();
+
+    let mut labels = HashMap::new();
+    let mut used_prefixes = HashSet::new();
+    let mut body = String::new();
+    for (graph, graph_triples) in graphs {
+        let mut serializer = TurtleSerializer::new(graph_triples, namespaces);
+        serializer.labels = labels;
+        serializer.shared = shared.clone();
+        let statements = serializer.write_body();
+
+        match graph {
+            None => body.push_str(&statements),
+            Some(graph) => {
+                let name = match graph.strip_prefix("_:") {
+                    Some(label) => serializer.blank_label(label),
+                    None => format!("<{}>", escape_iri(graph)),
+                };
+                body.push_str(&format!("{} {{\n", name));
+                for line in statements.trim_end().lines() {
+                    if !line.is_empty() {
+                        body.push_str(INDENT);
+                        body.push_str(line);
+                    }
+                    body.push('\n');
+                }
+                body.push_str("}\n\n");
+            },
+        }
+
+        labels = serializer.labels;
+        used_prefixes.extend(serializer.used_prefixes);
+    }
+
+    prefix_header(namespaces, &used_prefixes) + &body
+}
+
+fn prefix_header(namespaces: &[Namespace], used_prefixes: &HashSet<String>) -> String {
+    let mut output = String::new();
+    for ns in namespaces {
+        if used_prefixes.contains(&ns.prefix) {
+            output.push_str(&format!("@prefix {}: <{}> .\n", ns.prefix, escape_iri(&ns.uri)));
+        }
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+type PredicateObjects<'a> = Vec<(&'a str, Vec<&'a RdfNode>)>;
+
+struct TurtleSerializer<'a> {
+    prefixes: PrefixMap,
+    subjects: Vec<&'a str>,
+    properties: HashMap<&'a str, PredicateObjects<'a>>,
+    // Blank node label -> number of times it is used as an object
+    object_refs: HashMap<&'a str, usize>,
+    emitted: HashSet<&'a str>,
+    labels: HashMap<&'a str, String>,
+    used_prefixes: HashSet<String>,
+    // Blank nodes that also appear outside these triples and so are never nested
+    shared: HashSet<&'a str>,
+}
+
+impl<'a> TurtleSerializer<'a> {
+    fn new(triples: impl IntoIterator<Item = &'a Triple>, namespaces: &[Namespace]) -> Self {
+        let mut subjects = Vec::new();
+        let mut properties: HashMap<&str, PredicateObjects> = HashMap::new();
+        let mut object_refs: HashMap<&str, usize> = HashMap::new();
+
+        for triple in triples {
+            let predicates = properties.entry(triple.subject.as_str()).or_insert_with(|| {
+                subjects.push(triple.subject.as_str());
+                Vec::new()
+            });
+
+            let objects = match predicates.iter().position(|(p, _)| *p == triple.predicate) {
+                Some(index) => &mut predicates[index].1,
+                None => {
+                    // Keep rdf:type first so it reads as `subject a Class`
+                    if triple.predicate == RDF_TYPE {
+                        predicates.insert(0, (triple.predicate.as_str(), Vec::new()));
+                        &mut predicates[0].1
+                    } else {
+                        predicates.push((triple.predicate.as_str(), Vec::new()));
+                        &mut predicates.last_mut().unwrap().1
+                    }
+                }
+            };
+
+            if objects.contains(&&triple.object) {
+                continue;
+            }
+            objects.push(&triple.object);
+
+            if let RdfNode::Blank { value } = &triple.object {
+                *object_refs.entry(value.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        TurtleSerializer {
+            prefixes: PrefixMap::new(namespaces),
+            subjects,
+            properties,
+            object_refs,
+            emitted: HashSet::new(),
+            labels: HashMap::new(),
+            used_prefixes: HashSet::new(),
+            shared: HashSet::new(),
+        }
+    }
+
+    fn write_body(&mut self) -> String {
+        let mut body = String::new();
+        let subjects = self.subjects.clone();
+
+        for subject in &subjects {
+            if self.can_nest(subject) {
+                continue;
+            }
+            self.write_statement(subject, &mut body);
+        }
+
+        // Blank nodes that only reference each other in a cycle have no root
+        // yet; write the first remaining one at the top level to break it.
+        for subject in &subjects {
+            if let Some(label) = subject.strip_prefix("_:") {
+                if !self.emitted.contains(label) {
+                    self.write_statement(subject, &mut body);
+                }
+            }
+        }
+
+        body
+    }
+
+    /// A blank subject referenced exactly once can be written inline where it is used
+    fn can_nest(&self, subject: &str) -> bool {
+        subject
+            .strip_prefix("_:")
+            .map(|label| self.object_refs.get(label) == Some(&1) && !self.shared.contains(label))
+            .unwrap_or(false)
+    }
+
+    fn write_statement(&mut self, subject: &'a str, out: &mut String) {
+        if let Some(label) = subject.strip_prefix("_:") {
+            self.emitted.insert(label);
+            let term = self.blank_label(label);
+            out.push_str(&term);
+        } else {
+            let term = self.iri(subject);
+            out.push_str(&term);
+        }
+
+        let predicates = self.properties.get(subject).cloned().unwrap_or_default();
+        for (i, (predicate, objects)) in predicates.iter().enumerate() {
+            if i == 0 {
+                out.push(' ');
+            } else {
+                out.push_str(" ;\n");
+                out.push_str(INDENT);
+            }
+            self.write_predicate_objects(predicate, objects, 1, out);
+        }
+        out.push_str(" .\n\n");
+    }
+
+    fn write_predicate_objects(&mut self, predicate: &str, objects: &[&'a RdfNode], depth: usize, out: &mut String) {
+        let predicate_term = if predicate == RDF_TYPE {
+            "a".to_string()
+        } else {
+            self.iri(predicate)
+        };
+        out.push_str(&predicate_term);
+
+        for (i, object) in objects.iter().enumerate() {
+            if i > 0 {
+                out.push_str(" ,");
+            }
+            out.push(' ');
+            self.write_object(object, depth, out);
+        }
+    }
+
+    fn write_object(&mut self, object: &'a RdfNode, depth: usize, out: &mut String) {
+        match object {
+            RdfNode::Uri { value } => {
+                let term = self.iri(value);
+                out.push_str(&term);
+            },
+            RdfNode::Literal { value, datatype, language } => {
+                let term = self.literal(value, datatype.as_deref(), language.as_deref());
+                out.push_str(&term);
+            },
+            RdfNode::Blank { value } => {
+                let label = value.as_str();
+                if self.object_refs.get(label) != Some(&1) || self.emitted.contains(label) || self.shared.contains(label) {
+                    let term = self.blank_label(label);
+                    out.push_str(&term);
+                    return;
+                }
+
+                self.emitted.insert(label);
+                let predicates = self.properties.get(format!("_:{}", label).as_str()).cloned();
+                match predicates {
+                    Some(predicates) if !predicates.is_empty() => {
+                        out.push_str("[\n");
+                        for (i, (predicate, objects)) in predicates.iter().enumerate() {
+                            if i > 0 {
+                                out.push_str(" ;\n");
+                            }
+                            out.push_str(&INDENT.repeat(depth + 1));
+                            self.write_predicate_objects(predicate, objects, depth + 1, out);
+                        }
+                        out.push('\n');
+                        out.push_str(&INDENT.repeat(depth));
+                        out.push(']');
+                    },
+                    _ => out.push_str("[]"),
+                }
+            },
+        }
+    }
+
+    fn iri(&mut self, iri: &str) -> String {
+        match self.prefixes.compact(iri) {
+            Some((ns, local)) => {
+                self.used_prefixes.insert(ns.prefix.clone());
+                format!("{}:{}", ns.prefix, local)
+            },
+            None => format!("<{}>", escape_iri(iri)),
+        }
+    }
+
+    fn blank_label(&mut self, label: &'a str) -> String {
+        // Store-generated labels are not always valid Turtle, so renumber them
+        let next = self.labels.len();
+        let assigned = self.labels.entry(label).or_insert_with(|| format!("b{}", next));
+        format!("_:{}", assigned)
+    }
+
+    fn literal(&mut self, value: &str, datatype: Option<&str>, language: Option<&str>) -> String {
+        literal_term(value, datatype, language, &mut |iri| self.iri(iri))
+    }
+}
+
+fn literal_term(value: &str, datatype: Option<&str>, language: Option<&str>, iri: &mut dyn FnMut(&str) -> String) -> String {
+    if let Some(lang) = language {
+        return format!("\"{}\"@{}", escape_literal(value), lang);
+    }
+
+    match datatype {
+        None | Some(XSD_STRING) => format!("\"{}\"", escape_literal(value)),
+        Some(XSD_INTEGER) if is_integer(value) => value.to_string(),
+        Some(XSD_DECIMAL) if is_decimal(value) => value.to_string(),
+        Some(XSD_DOUBLE) if is_double(value) => value.to_string(),
+        Some(XSD_BOOLEAN) if value == "true" || value == "false" => value.to_string(),
+        Some(dt) => format!("\"{}\"^^{}", escape_literal(value), iri(dt)),
+    }
+}
+
+/// Streaming Turtle/TriG writer. Consecutive triples about the same subject are
+/// grouped with `;` and `,`; blank nodes are always written as labels.
+pub struct TurtleStreamWriter {
+    prefixes: PrefixMap,
+    namespaces: Vec<Namespace>,
+    trig: bool,
+    // Graph block currently open; `None` until the first triple is written
+    graph: Option<Option<String>>,
+    subject: Option<String>,
+    predicate: Option<String>,
+}
+
+impl TurtleStreamWriter {
+    pub fn new(namespaces: &[Namespace], trig: bool) -> Self {
+        TurtleStreamWriter {
+            prefixes: PrefixMap::new(namespaces),
+            namespaces: namespaces.to_vec(),
+            trig,
+            graph: None,
+            subject: None,
+            predicate: None,
+        }
+    }
+
+    fn iri(&self, iri: &str) -> String {
+        match self.prefixes.compact(iri) {
+            Some((ns, local)) => format!("{}:{}", ns.prefix, local),
+            None => format!("<{}>", escape_iri(iri)),
+        }
+    }
+
+    fn resource(&self, subject: &str) -> String {
+        match subject.strip_prefix("_:") {
+            Some(label) => format!("_:{}", portable_blank_label(label)),
+            None => self.iri(subject),
+        }
+    }
+
+    fn object(&self, object: &RdfNode) -> String {
+        match object {
+            RdfNode::Uri { value } => self.iri(value),
+            RdfNode::Blank { value } => format!("_:{}", portable_blank_label(value)),
+            RdfNode::Literal { value, datatype, language } => {
+                literal_term(value, datatype.as_deref(), language.as_deref(), &mut |iri| self.iri(iri))
+            },
+        }
+    }
+
+    fn indent(&self) -> &'static str {
+        match self.graph {
+            Some(Some(_)) => INDENT,
+            _ => "",
+        }
+    }
+
+    fn end_statement(&mut self, out: &mut String) {
+        if self.subject.take().is_some() {
+            out.push_str(" .\n");
+        }
+        self.predicate = None;
+    }
+
+    fn end_graph(&mut self, out: &mut String) {
+        self.end_statement(out);
+        if let Some(Some(_)) = self.graph.take() {
+            out.push_str("}\n\n");
+        }
+    }
+}
+
+impl StreamWriter for TurtleStreamWriter {
+    fn start(&mut self) -> Result<String, RdfEditorError> {
+        let used: HashSet<String> = self.namespaces.iter().map(|ns| ns.prefix.clone()).collect();
+        Ok(prefix_header(&self.namespaces, &used))
+    }
+
+    fn write(&mut self, triples: &[Triple]) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        for triple in triples {
+            let graph = if self.trig { triple.graph.clone() } else { None };
+            if self.graph.as_ref() != Some(&graph) {
+                self.end_graph(&mut out);
+                if let Some(name) = &graph {
+                    out.push_str(&format!("{} {{\n", self.resource(name)));
+                }
+                self.graph = Some(graph);
+            }
+
+            let predicate = if triple.predicate == RDF_TYPE { "a".to_string() } else { self.iri(&triple.predicate) };
+            let object = self.object(&triple.object);
+
+            if self.subject.as_deref() != Some(triple.subject.as_str()) {
+                self.end_statement(&mut out);
+                out.push_str(&format!("{}{} {} {}", self.indent(), self.resource(&triple.subject), predicate, object));
+                self.subject = Some(triple.subject.clone());
+            } else if self.predicate.as_deref() != Some(triple.predicate.as_str()) {
+                out.push_str(&format!(" ;\n{}{}{} {}", self.indent(), INDENT, predicate, object));
+            } else {
+                out.push_str(&format!(" , {}", object));
+            }
+            self.predicate = Some(triple.predicate.clone());
+        }
+        Ok(out)
+    }
+
+    fn finish(&mut self) -> Result<String, RdfEditorError> {
+        let mut out = String::new();
+        self.end_graph(&mut out);
+        Ok(out)
+    }
+}
+
+fn strip_sign(value: &str) -> &str {
+    value.strip_prefix(['+', '-']).unwrap_or(value)
+}
+
+fn all_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+pub(crate) fn is_integer(value: &str) -> bool {
+    all_digits(strip_sign(value))
+}
+
+pub(crate) fn is_decimal(value: &str) -> bool {
+    match strip_sign(value).split_once('.') {
+        Some((int, frac)) => (int.is_empty() || all_digits(int)) && all_digits(frac),
+        None => false,
+    }
+}
+
+pub(crate) fn is_double(value: &str) -> bool {
+    let Some((mantissa, exponent)) = strip_sign(value).split_once(['e', 'E']) else {
+        return false;
+    };
+
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((int, frac)) => {
+            (all_digits(int) && (frac.is_empty() || all_digits(frac))) || (int.is_empty() && all_digits(frac))
+        },
+        None => all_digits(mantissa),
+    };
+
+    mantissa_ok && all_digits(strip_sign(exponent))
+}
+
+/// Parse a Turtle document. Relative IRIs are resolved against `base`.
+pub fn parse(input: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    TurtleParser::new(input, base, Syntax::Turtle).collect()
+}
+
+/// Parse a TriG document. Triples inside a graph block carry its name in `Triple.graph`.
+pub fn parse_trig(input: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    TurtleParser::new(input, base, Syntax::TriG).collect()
+}
+
+/// Parse an N-Quads document. The optional fourth term becomes `Triple.graph`.
+pub fn parse_nquads(input: &str, base: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
+    TurtleParser::new(input, base, Syntax::NQuads).collect()
+}
+
+/// Grammar accepted by `TurtleParser`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Turtle,
+    TriG,
+    NQuads,
+}
+
+type ParseResult<T> = Result<T, RdfEditorError>;
+
+/// Statement-at-a-time Turtle/TriG reader, yielding triples as an iterator
+pub struct TurtleParser<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+    column: usize,
+    syntax: Syntax,
+    base: Option<String>,
+    prefixes: HashMap<String, String>,
+    blank_ids: HashMap<String, String>,
+    next_blank: usize,
+    graph: Option<String>,
+    pending: VecDeque<Triple>,
+    failed: bool,
+}
+
+impl<'a> TurtleParser<'a> {
+    pub fn new(input: &'a str, base: Option<&str>, syntax: Syntax) -> Self {
+        TurtleParser {
+            input,
+            pos: 0,
+            line: 1,
+            column: 1,
+            syntax,
+            base: base.map(String::from),
+            prefixes: HashMap::new(),
+            blank_ids: HashMap::new(),
+            next_blank: 0,
+            graph: None,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    /// Carry on with the next piece of a document read in pieces. Prefixes, base, blank
+    /// node labels and line numbers carry over; each piece must end between statements.
+    pub fn continue_with<'b>(self, input: &'b str) -> TurtleParser<'b> {
+        TurtleParser {
+            input,
+            pos: 0,
+            line: self.line,
+            column: self.column,
+            syntax: self.syntax,
+            base: self.base,
+            prefixes: self.prefixes,
+            blank_ids: self.blank_ids,
+            next_blank: self.next_blank,
+            graph: self.graph,
+            pending: self.pending,
+            failed: self.failed,
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> RdfEditorError {
+        RdfEditorError::InvalidInput(format!(
+            "{} syntax error at line {}, column {}: {}",
+            match self.syntax {
+                Syntax::Turtle => "Turtle",
+                Syntax::TriG => "TriG",
+                Syntax::NQuads => "N-Quads",
+            },
+            self.line,
+            self.column,
+            message
+        ))
+    }
+
+    // Character handling
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> ParseResult<()> {
+        self.skip_ws();
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", expected)))
+        }
+    }
+
+    fn unexpected(&self, wanted: &str) -> RdfEditorError {
+        match self.peek() {
+            Some(c) => self.error(format!("expected {}, found '{}'", wanted, c)),
+            None => self.error(format!("expected {}, found end of input", wanted)),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.advance();
+            } else if c == '#' {
+                while let Some(c) = self.advance() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// A bare word (`a`, `true`, `PREFIX`, `GRAPH`) at the cursor, if it is not the start of a prefixed name
+    fn peek_keyword(&self) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        let end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        match rest[end..].chars().next() {
+            Some(c) if is_pn_chars(c) || c == ':' || c == '.' => None,
+            _ => Some(&rest[..end]),
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) {
+        for _ in keyword.chars() {
+            self.advance();
+        }
+    }
+
+    // Statements
+
+    fn parse_statement(&mut self) -> ParseResult<()> {
+        if self.peek() == Some('@') {
+            return self.parse_at_directive();
+        }
+
+        if let Some(keyword) = self.peek_keyword() {
+            if keyword.eq_ignore_ascii_case("PREFIX") {
+                self.consume_keyword(keyword);
+                return self.parse_prefix_declaration();
+            }
+            if keyword.eq_ignore_ascii_case("BASE") {
+                self.consume_keyword(keyword);
+                return self.parse_base_declaration();
+            }
+        }
+
+        match self.syntax {
+            Syntax::Turtle => {
+                self.parse_triples()?;
+                self.expect('.')
+            },
+            Syntax::TriG => self.parse_trig_block(),
+            Syntax::NQuads => self.parse_quad(),
+        }
+    }
+
+    /// `subject predicate object graph? .` with no abbreviations
+    fn parse_quad(&mut self) -> ParseResult<()> {
+        let subject = self.parse_label_or_subject()?;
+        self.skip_ws();
+        let predicate = self.parse_iri()?;
+        let object = self.parse_object()?;
+        self.skip_ws();
+        let graph = if self.peek() == Some('.') {
+            None
+        } else {
+            Some(self.parse_label_or_subject()?)
+        };
+
+        self.graph = graph;
+        self.emit(&subject, &predicate, object);
+        self.graph = None;
+        self.expect('.')
+    }
+
+    fn parse_at_directive(&mut self) -> ParseResult<()> {
+        self.advance();
+        match self.peek_keyword() {
+            Some("prefix") => {
+                self.consume_keyword("prefix");
+                self.parse_prefix_declaration()?;
+            },
+            Some("base") => {
+                self.consume_keyword("base");
+                self.parse_base_declaration()?;
+            },
+            _ => return Err(self.error("expected @prefix or @base")),
+        }
+        self.expect('.')
+    }
+
+    fn parse_prefix_declaration(&mut self) -> ParseResult<()> {
+        self.skip_ws();
+        let prefix = self.read_pn_prefix()?;
+        if !self.eat(':') {
+            return Err(self.unexpected("':' after prefix name"));
+        }
+        self.skip_ws();
+        let namespace = self.parse_iriref()?;
+        self.prefixes.insert(prefix, namespace);
+        Ok(())
+    }
+
+    fn parse_base_declaration(&mut self) -> ParseResult<()> {
+        self.skip_ws();
+        let base = self.parse_iriref()?;
+        self.base = Some(base);
+        Ok(())
+    }
+
+    fn parse_trig_block(&mut self) -> ParseResult<()> {
+        match self.peek() {
+            Some('{') => self.parse_wrapped_graph(None),
+            Some('[') => {
+                let (subject, empty) = self.parse_blank_node_property_list()?;
+                self.skip_ws();
+                if empty && self.peek() == Some('{') {
+                    return self.parse_wrapped_graph(Some(subject));
+                }
+                if self.peek() != Some('.') {
+                    self.parse_predicate_object_list(&subject)?;
+                }
+                self.expect('.')
+            },
+            Some('(') => {
+                let subject = self.parse_collection()?;
+                self.parse_predicate_object_list(&subject)?;
+                self.expect('.')
+            },
+            _ => {
+                if let Some(keyword) = self.peek_keyword() {
+                    if keyword.eq_ignore_ascii_case("GRAPH") {
+                        self.consume_keyword(keyword);
+                        self.skip_ws();
+                        let name = self.parse_label_or_subject()?;
+                        self.skip_ws();
+                        return self.parse_wrapped_graph(Some(name));
+                    }
+                }
+
+                let subject = self.parse_label_or_subject()?;
+                self.skip_ws();
+                if self.peek() == Some('{') {
+                    return self.parse_wrapped_graph(Some(subject));
+                }
+                self.parse_predicate_object_list(&subject)?;
+                self.expect('.')
+            },
+        }
+    }
+
+    fn parse_wrapped_graph(&mut self, graph: Option<String>) -> ParseResult<()> {
+        self.expect('{')?;
+        self.graph = graph;
+        loop {
+            self.skip_ws();
+            if self.eat('}') {
+                break;
+            }
+            self.parse_triples()?;
+            self.skip_ws();
+            if self.eat('.') {
+                continue;
+            }
+            self.expect('}')?;
+            break;
+        }
+        self.graph = None;
+        Ok(())
+    }
+
+    fn parse_triples(&mut self) -> ParseResult<()> {
+        self.skip_ws();
+        match self.peek() {
+            Some('[') => {
+                let (subject, _) = self.parse_blank_node_property_list()?;
+                self.skip_ws();
+                if !matches!(self.peek(), Some('.') | Some('}') | None) {
+                    self.parse_predicate_object_list(&subject)?;
+                }
+                Ok(())
+            },
+            Some('(') => {
+                let subject = self.parse_collection()?;
+                self.parse_predicate_object_list(&subject)
+            },
+            _ => {
+                let subject = self.parse_label_or_subject()?;
+                self.parse_predicate_object_list(&subject)
+            },
+        }
+    }
+
+    fn parse_predicate_object_list(&mut self, subject: &str) -> ParseResult<()> {
+        loop {
+            self.skip_ws();
+            let predicate = self.parse_verb()?;
+            self.parse_object_list(subject, &predicate)?;
+
+            self.skip_ws();
+            if !self.eat(';') {
+                return Ok(());
+            }
+            loop {
+                self.skip_ws();
+                if !self.eat(';') {
+                    break;
+                }
+            }
+            if matches!(self.peek(), Some('.') | Some(']') | Some('}') | None) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_object_list(&mut self, subject: &str, predicate: &str) -> ParseResult<()> {
+        loop {
+            let object = self.parse_object()?;
+            self.emit(subject, predicate, object);
+            self.skip_ws();
+            if !self.eat(',') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn emit(&mut self, subject: &str, predicate: &str, object: RdfNode) {
+        self.pending.push_back(Triple {
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            object,
+            graph: self.graph.clone(),
+        });
+    }
+
+    // Terms
+
+    fn parse_verb(&mut self) -> ParseResult<String> {
+        if self.peek_keyword() == Some("a") {
+            self.advance();
+            return Ok(RDF_TYPE.to_string());
+        }
+        self.parse_iri()
+    }
+
+    /// Subject or graph label: an IRI or a labelled blank node, as a subject string
+    fn parse_label_or_subject(&mut self) -> ParseResult<String> {
+        if self.peek() == Some('_') && self.peek_nth(1) == Some(':') {
+            let label = self.parse_blank_node_label()?;
+            return Ok(format!("_:{}", label));
+        }
+        self.parse_iri()
+    }
+
+    fn parse_object(&mut self) -> ParseResult<RdfNode> {
+        self.skip_ws();
+        match self.peek() {
+            Some('<') => Ok(RdfNode::Uri { value: self.parse_iriref()? }),
+            Some('_') if self.peek_nth(1) == Some(':') => {
+                let label = self.parse_blank_node_label()?;
+                Ok(RdfNode::Blank { value: label })
+            },
+            Some('[') => {
+                let (subject, _) = self.parse_blank_node_property_list()?;
+                Ok(subject_to_node(subject))
+            },
+            Some('(') => {
+                let subject = self.parse_collection()?;
+                Ok(subject_to_node(subject))
+            },
+            Some('"') | Some('\'') => self.parse_rdf_literal(),
+            Some(c) if c == '+' || c == '-' || c == '.' || c.is_ascii_digit() => self.parse_numeric_literal(),
+            Some(_) => match self.peek_keyword() {
+                Some(keyword @ ("true" | "false")) => {
+                    self.consume_keyword(keyword);
+                    Ok(RdfNode::Literal {
+                        value: keyword.to_string(),
+                        datatype: Some(XSD_BOOLEAN.to_string()),
+                        language: None,
+                    })
+                },
+                _ => Ok(RdfNode::Uri { value: self.parse_iri()? }),
+            },
+            None => Err(self.unexpected("an object")),
+        }
+    }
+
+    fn parse_iri(&mut self) -> ParseResult<String> {
+        self.skip_ws();
+        if self.peek() == Some('<') {
+            self.parse_iriref()
+        } else {
+            self.parse_prefixed_name()
+        }
+    }
+
+    fn parse_iriref(&mut self) -> ParseResult<String> {
+        if !self.eat('<') {
+            return Err(self.unexpected("an IRI"));
+        }
+
+        let mut iri = String::new();
+        loop {
+            match self.advance() {
+                Some('>') => break,
+                Some('\\') => {
+                    let c = match self.advance() {
+                        Some('u') => self.read_hex_escape(4)?,
+                        Some('U') => self.read_hex_escape(8)?,
+                        _ => return Err(self.error("invalid escape in IRI")),
+                    };
+                    iri.push(c);
+                },
+                Some(c) => iri.push(c),
+                None => return Err(self.error("unterminated IRI")),
+            }
+        }
+
+        if let Some(c) = invalid_iri_char(&iri) {
+            return Err(self.error(format!("invalid character {:?} in IRI", c)));
+        }
+
+        if is_absolute_iri(&iri) {
+            Ok(iri)
+        } else {
+            match &self.base {
+                Some(base) => Ok(resolve_iri(base, &iri)),
+                None => Err(self.error(format!("relative IRI <{}> with no base IRI", iri))),
+            }
+        }
+    }
+
+    fn read_hex_escape(&mut self, digits: usize) -> ParseResult<char> {
+        let mut code = 0u32;
+        for _ in 0..digits {
+            let digit = self.advance()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid hex digit in escape sequence"))?;
+            code = code * 16 + digit;
+        }
+        char::from_u32(code).ok_or_else(|| self.error(format!("invalid code point U+{:X}", code)))
+    }
+
+    fn read_pn_prefix(&mut self) -> ParseResult<String> {
+        let mut prefix = String::new();
+        match self.peek() {
+            Some(c) if is_pn_chars_base(c) => {},
+            _ => return Ok(prefix),
+        }
+        while let Some(c) = self.peek() {
+            if is_pn_chars(c) || c == '.' {
+                prefix.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        if prefix.ends_with('.') {
+            return Err(self.error("prefix name cannot end with '.'"));
+        }
+        Ok(prefix)
+    }
+
+    fn parse_prefixed_name(&mut self) -> ParseResult<String> {
+        let prefix = self.read_pn_prefix()?;
+        if self.peek() != Some(':') {
+            return Err(self.unexpected("an IRI or prefixed name"));
+        }
+        let namespace = self.prefixes.get(&prefix).cloned()
+            .ok_or_else(|| self.error(format!("undefined prefix '{}:'", prefix)))?;
+        self.advance();
+        let local = self.read_pn_local()?;
+        Ok(format!("{}{}", namespace, local))
+    }
+
+    fn read_pn_local(&mut self) -> ParseResult<String> {
+        let mut local = String::new();
+        // Position after the last character that may end a local name; a
+        // trailing '.' belongs to the statement, not the name.
+        let mut committed = (self.pos, self.line, self.column, 0);
+
+        while let Some(c) = self.peek() {
+            let first = local.is_empty();
+            match c {
+                '%' => {
+                    self.advance();
+                    local.push('%');
+                    for _ in 0..2 {
+                        match self.advance() {
+                            Some(h) if h.is_ascii_hexdigit() => local.push(h),
+                            _ => return Err(self.error("invalid percent-encoding in local name")),
+                        }
+                    }
+                },
+                '\\' => {
+                    self.advance();
+                    match self.advance() {
+                        Some(e) if "_~.-!$&'()*+,;=/?#@%".contains(e) => local.push(e),
+                        _ => return Err(self.error("invalid escape in local name")),
+                    }
+                },
+                '.' if !first => {
+                    self.advance();
+                    local.push('.');
+                    continue;
+                },
+                c if c == ':' || c.is_ascii_digit() || is_pn_chars_u(c) || (!first && is_pn_chars(c)) => {
+                    self.advance();
+                    local.push(c);
+                },
+                _ => break,
+            }
+            committed = (self.pos, self.line, self.column, local.len());
+        }
+
+        let (pos, line, column, len) = committed;
+        self.pos = pos;
+        self.line = line;
+        self.column = column;
+        local.truncate(len);
+        Ok(local)
+    }
+
+    fn parse_blank_node_label(&mut self) -> ParseResult<String> {
+        self.advance();
+        self.advance();
+
+        let mut label = String::new();
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() => {},
+            _ => return Err(self.unexpected("a blank node label")),
+        }
+
+        let mut committed = (self.pos, self.line, self.column, 0);
+        while let Some(c) = self.peek() {
+            if c == '.' {
+                self.advance();
+                label.push(c);
+                continue;
+            }
+            if !(is_pn_chars(c) || (label.is_empty() && c.is_ascii_digit())) {
+                break;
+            }
+            self.advance();
+            label.push(c);
+            committed = (self.pos, self.line, self.column, label.len());
+        }
+
+        let (pos, line, column, len) = committed;
+        self.pos = pos;
+        self.line = line;
+        self.column = column;
+        label.truncate(len);
+
+        Ok(self.blank_for_label(&label))
+    }
+
+    fn blank_for_label(&mut self, label: &str) -> String {
+        if let Some(id) = self.blank_ids.get(label) {
+            return id.clone();
+        }
+        let id = self.fresh_blank();
+        self.blank_ids.insert(label.to_string(), id.clone());
+        id
+    }
+
+    fn fresh_blank(&mut self) -> String {
+        let id = format!("b{}", self.next_blank);
+        self.next_blank += 1;
+        id
+    }
+
+    /// Returns the blank node's subject string and whether the list was empty (`[]`)
+    fn parse_blank_node_property_list(&mut self) -> ParseResult<(String, bool)> {
+        self.advance();
+        let subject = format!("_:{}", self.fresh_blank());
+        self.skip_ws();
+        if self.eat(']') {
+            return Ok((subject, true));
+        }
+        self.parse_predicate_object_list(&subject)?;
+        self.expect(']')?;
+        Ok((subject, false))
+    }
+
+    fn parse_collection(&mut self) -> ParseResult<String> {
+        self.advance();
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.eat(')') {
+                break;
+            }
+            if self.peek().is_none() {
+                return Err(self.unexpected("')'"));
+            }
+            items.push(self.parse_object()?);
+        }
+
+        let rdf_nil = format!("{}nil", RDF_NS);
+        if items.is_empty() {
+            return Ok(rdf_nil);
+        }
+
+        let nodes: Vec<String> = items.iter().map(|_| format!("_:{}", self.fresh_blank())).collect();
+        for (i, item) in items.into_iter().enumerate() {
+            let rest = match nodes.get(i + 1) {
+                Some(next) => subject_to_node(next.clone()),
+                None => RdfNode::Uri { value: rdf_nil.clone() },
+            };
+            self.emit(&nodes[i], &format!("{}first", RDF_NS), item);
+            self.emit(&nodes[i], &format!("{}rest", RDF_NS), rest);
+        }
+        Ok(nodes[0].clone())
+    }
+
+    // Literals
+
+    fn parse_rdf_literal(&mut self) -> ParseResult<RdfNode> {
+        let value = self.parse_string()?;
+
+        if self.eat('@') {
+            let mut language = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_ascii_alphabetic() || (!language.is_empty() && (c == '-' || c.is_ascii_digit())) {
+                    language.push(c);
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            if language.is_empty() || language.ends_with('-') {
+                return Err(self.error("invalid language tag"));
+            }
+            return Ok(RdfNode::Literal { value, datatype: None, language: Some(language) });
+        }
+
+        if self.input[self.pos..].starts_with("^^") {
+            self.advance();
+            self.advance();
+            let datatype = self.parse_iri()?;
+            return Ok(RdfNode::Literal { value, datatype: Some(datatype), language: None });
+        }
+
+        Ok(RdfNode::Literal { value, datatype: None, language: None })
+    }
+
+    fn parse_string(&mut self) -> ParseResult<String> {
+        let quote = self.advance().unwrap_or('"');
+        let long = self.peek() == Some(quote) && self.peek_nth(1) == Some(quote);
+        if long {
+            self.advance();
+            self.advance();
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                None => return Err(self.error("unterminated string literal")),
+                Some(c) if c == quote => {
+                    if !long {
+                        break;
+                    }
+                    if self.peek() == Some(quote) && self.peek_nth(1) == Some(quote) {
+                        self.advance();
+                        self.advance();
+                        // Quotes directly before the closing delimiter belong to the value
+                        while self.peek() == Some(quote) {
+                            value.push(quote);
+                            self.advance();
+                        }
+                        break;
+                    }
+                    value.push(c);
+                },
+                Some('\\') => {
+                    let c = match self.advance() {
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('f') => '\u{c}',
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('\\') => '\\',
+                        Some('u') => self.read_hex_escape(4)?,
+                        Some('U') => self.read_hex_escape(8)?,
+                        _ => return Err(self.error("invalid escape sequence in string")),
+                    };
+                    value.push(c);
+                },
+                Some(c @ ('\n' | '\r')) if !long => {
+                    return Err(self.error(format!("line break {:?} in single-quoted string", c)));
+                },
+                Some(c) => value.push(c),
+            }
+        }
+        Ok(value)
+    }
+
+    fn parse_numeric_literal(&mut self) -> ParseResult<RdfNode> {
+        let start = self.pos;
+        if matches!(self.peek(), Some('+') | Some('-')) {
+            self.advance();
+        }
+        let int_digits = self.skip_digits();
+
+        let mut datatype = XSD_INTEGER;
+        let mut frac_digits = 0;
+        if self.peek() == Some('.') {
+            let next = self.peek_nth(1);
+            let exponent_follows = int_digits > 0 && matches!(next, Some('e') | Some('E'));
+            if next.is_some_and(|c| c.is_ascii_digit()) || exponent_follows {
+                self.advance();
+                frac_digits = self.skip_digits();
+                datatype = XSD_DECIMAL;
+            }
+        }
+        if int_digits == 0 && frac_digits == 0 {
+            return Err(self.error("invalid numeric literal"));
+        }
+
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let sign = usize::from(matches!(self.peek_nth(1), Some('+') | Some('-')));
+            if self.peek_nth(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..=sign {
+                    self.advance();
+                }
+                self.skip_digits();
+                datatype = XSD_DOUBLE;
+            }
+        }
+
+        Ok(RdfNode::Literal {
+            value: self.input[start..self.pos].to_string(),
+            datatype: Some(datatype.to_string()),
+            language: None,
+        })
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let mut count = 0;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+            count += 1;
+        }
+        count
+    }
+}
+
+impl Iterator for TurtleParser<'_> {
+    type Item = Result<Triple, RdfEditorError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(triple) = self.pending.pop_front() {
+                return Some(Ok(triple));
+            }
+            if self.failed {
+                return None;
+            }
+
+            self.skip_ws();
+            self.peek()?;
+
+            if let Err(e) = self.parse_statement() {
+                self.failed = true;
+                self.pending.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn subject_to_node(subject: String) -> RdfNode {
+    match subject.strip_prefix("_:") {
+        Some(label) => RdfNode::Blank { value: label.to_string() },
+        None => RdfNode::Uri { value: subject },
+    }
+}
+
+fn is_pn_chars_base(c: char) -> bool {
+    c.is_alphabetic() && c != '_'
+}
+
+fn is_pn_chars_u(c: char) -> bool {
+    is_pn_chars_base(c) || c == '_'
+}
+
+fn is_pn_chars(c: char) -> bool {
+    is_pn_chars_u(c)
+        || c == '-'
+        || c.is_ascii_digit()
+        || c == '\u{B7}'
+        || ('\u{300}'..='\u{36F}').contains(&c)
+        || ('\u{203F}'..='\u{2040}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::default_namespaces;
+
+    fn iri_triple(s: &str, p: &str, o: &str) -> Triple {
+        Triple {
+            subject: s.to_string(),
+            predicate: p.to_string(),
+            object: RdfNode::Uri { value: o.to_string() },
+            graph: None,
+        }
+    }
+
+    fn literal_triple(s: &str, p: &str, value: &str, datatype: Option<&str>, language: Option<&str>) -> Triple {
+        Triple {
+            subject: s.to_string(),
+            predicate: p.to_string(),
+            object: RdfNode::Literal {
+                value: value.to_string(),
+                datatype: datatype.map(String::from),
+                language: language.map(String::from),
+            },
+            graph: None,
+        }
+    }
+
+    #[test]
+    fn test_groups_by_subject_and_predicate() {
+        let triples = vec![
+            literal_triple("http://example.com/a", "http://www.w3.org/2000/01/rdf-schema#label", "A", None, Some("en")),
+            iri_triple("http://example.com/a", RDF_TYPE, "http://www.w3.org/2002/07/owl#Class"),
+            literal_triple("http://example.com/a", "http://www.w3.org/2000/01/rdf-schema#label", "Ah", None, Some("fr")),
+        ];
+
+        let output = serialize(&triples, &default_namespaces());
+
+        assert!(output.contains("@prefix owl: <http://www.w3.org/2002/07/owl#> ."));
+        assert!(output.contains("@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> ."));
+        assert!(!output.contains("@prefix skos:"));
+        assert!(output.contains(
+            "<http://example.com/a> a owl:Class ;\n    rdfs:label \"A\"@en , \"Ah\"@fr .\n"
+        ));
+    }
+
+    #[test]
+    fn test_nests_single_use_blank_nodes() {
+        let triples = vec![
+            Triple {
+                subject: "http://example.com/a".to_string(),
+                predicate: "http://example.com/p".to_string(),
+                object: RdfNode::Blank { value: "node1".to_string() },
+                graph: None,
+            },
+            literal_triple("_:node1", "http://example.com/q", "x", None, None),
+        ];
+
+        let output = serialize(&triples, &default_namespaces());
+
+        assert!(output.contains("<http://example.com/p> [\n        <http://example.com/q> \"x\"\n    ] ."));
+        assert!(!output.contains("_:"));
+    }
+
+    #[test]
+    fn test_blank_node_cycle_is_labelled() {
+        let triples = vec![
+            Triple {
+                subject: "_:x".to_string(),
+                predicate: "http://example.com/p".to_string(),
+                object: RdfNode::Blank { value: "y".to_string() },
+                graph: None,
+            },
+            Triple {
+                subject: "_:y".to_string(),
+                predicate: "http://example.com/p".to_string(),
+                object: RdfNode::Blank { value: "x".to_string() },
+                graph: None,
+            },
+        ];
+
+        let output = serialize(&triples, &[]);
+
+        assert!(output.starts_with("_:b0 <http://example.com/p> [\n"));
+        assert!(output.contains("<http://example.com/p> _:b0\n"));
+    }
+
+    #[test]
+    fn test_literal_shorthands() {
+        let xsd = "http://www.w3.org/2001/XMLSchema#";
+        let triples = vec![
+            literal_triple("http://example.com/a", "http://example.com/int", "42", Some(&format!("{}integer", xsd)), None),
+            literal_triple("http://example.com/a", "http://example.com/dec", "-1.5", Some(&format!("{}decimal", xsd)), None),
+            literal_triple("http://example.com/a", "http://example.com/dbl", "1.0E3", Some(&format!("{}double", xsd)), None),
+            literal_triple("http://example.com/a", "http://example.com/bool", "true", Some(&format!("{}boolean", xsd)), None),
+            literal_triple("http://example.com/a", "http://example.com/bad", "4x", Some(&format!("{}integer", xsd)), None),
+            literal_triple("http://example.com/a", "http://example.com/str", "say \"hi\"\n", None, None),
+        ];
+
+        let output = serialize(&triples, &default_namespaces());
+
+        assert!(output.contains("<http://example.com/int> 42"));
+        assert!(output.contains("<http://example.com/dec> -1.5"));
+        assert!(output.contains("<http://example.com/dbl> 1.0E3"));
+        assert!(output.contains("<http://example.com/bool> true"));
+        assert!(output.contains("<http://example.com/bad> \"4x\"^^xsd:integer"));
+        assert!(output.contains("<http://example.com/str> \"say \\\"hi\\\"\\n\""));
+    }
+
+    fn sorted(mut triples: Vec<Triple>) -> Vec<Triple> {
+        triples.sort_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+        triples
+    }
+
+    #[test]
+    fn test_parse_prefixes_and_shorthands() {
+        let input = r#"
+            @prefix ex: <http://example.com/> .
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+            @base <http://example.com/base/> .
+
+            ex:a a ex:Thing ;
+                ex:count 42 , -3.5 , 1e10 ;
+                ex:flag true ;
+                ex:name "A"@en-GB , 'single' , """multi
+            line""" ;
+                ex:typed "7"^^xsd:int ;
+                ex:rel <other> .
+        "#;
+
+        let triples = parse(input, None).unwrap();
+        let object = |p: &str| -> Vec<RdfNode> {
+            triples.iter()
+                .filter(|t| t.predicate == format!("http://example.com/{}", p))
+                .map(|t| t.object.clone())
+                .collect()
+        };
+
+        assert_eq!(triples.len(), 10);
+        assert_eq!(triples[0].predicate, RDF_TYPE);
+        assert_eq!(object("count")[0], RdfNode::Literal {
+            value: "42".to_string(), datatype: Some(XSD_INTEGER.to_string()), language: None,
+        });
+        assert_eq!(object("count")[1], RdfNode::Literal {
+            value: "-3.5".to_string(), datatype: Some(XSD_DECIMAL.to_string()), language: None,
+        });
+        assert_eq!(object("count")[2], RdfNode::Literal {
+            value: "1e10".to_string(), datatype: Some(XSD_DOUBLE.to_string()), language: None,
+        });
+        assert_eq!(object("flag")[0], RdfNode::Literal {
+            value: "true".to_string(), datatype: Some(XSD_BOOLEAN.to_string()), language: None,
+        });
+        assert_eq!(object("name")[0], RdfNode::Literal {
+            value: "A".to_string(), datatype: None, language: Some("en-GB".to_string()),
+        });
+        assert_eq!(object("name")[2], RdfNode::Literal {
+            value: "multi\n            line".to_string(), datatype: None, language: None,
+        });
+        assert_eq!(object("typed")[0], RdfNode::Literal {
+            value: "7".to_string(), datatype: Some("http://www.w3.org/2001/XMLSchema#int".to_string()), language: None,
+        });
+        assert_eq!(object("rel")[0], RdfNode::Uri { value: "http://example.com/base/other".to_string() });
+    }
+
+    #[test]
+    fn test_parse_blank_nodes_and_collections() {
+        let input = r#"
+            @prefix ex: <http://example.com/> .
+            ex:a ex:knows [ ex:name "Bob" ; ex:age 30 ] ;
+                ex:list ( 1 ex:b "c" ) ;
+                ex:empty () .
+            _:x ex:p _:x .
+            [ ex:q 1 ] .
+        "#;
+
+        let triples = parse(input, None).unwrap();
+        let rdf_first = format!("{}first", RDF_NS);
+        let rdf_rest = format!("{}rest", RDF_NS);
+
+        let knows = triples.iter().find(|t| t.predicate == "http://example.com/knows").unwrap();
+        let RdfNode::Blank { value: bob } = &knows.object else { panic!("expected blank node") };
+        assert_eq!(triples.iter().filter(|t| t.subject == format!("_:{}", bob)).count(), 2);
+
+        assert_eq!(triples.iter().filter(|t| t.predicate == rdf_first).count(), 3);
+        assert_eq!(triples.iter().filter(|t| t.predicate == rdf_rest).count(), 3);
+        let empty = triples.iter().find(|t| t.predicate == "http://example.com/empty").unwrap();
+        assert_eq!(empty.object, RdfNode::Uri { value: format!("{}nil", RDF_NS) });
+
+        let cycle = triples.iter().find(|t| t.predicate == "http://example.com/p").unwrap();
+        assert_eq!(cycle.object, subject_to_node(cycle.subject.clone()));
+        assert!(triples.iter().any(|t| t.predicate == "http://example.com/q" && t.subject.starts_with("_:")));
+    }
+
+    #[test]
+    fn test_parse_local_name_edge_cases() {
+        let input = "@prefix ex: <http://example.com/> .\nex:a.b ex:p ex:c.\nex:d ex:p ex:e\\,f , ex:g%20h .";
+
+        let triples = parse(input, None).unwrap();
+
+        assert_eq!(triples[0].subject, "http://example.com/a.b");
+        assert_eq!(triples[0].object, RdfNode::Uri { value: "http://example.com/c".to_string() });
+        assert_eq!(triples[1].object, RdfNode::Uri { value: "http://example.com/e,f".to_string() });
+        assert_eq!(triples[2].object, RdfNode::Uri { value: "http://example.com/g%20h".to_string() });
+    }
+
+    #[test]
+    fn test_parse_trig_graphs() {
+        let input = r#"
+            @prefix ex: <http://example.com/> .
+            ex:s ex:p ex:o .
+            ex:g1 { ex:s ex:p 1 . ex:s ex:p 2 }
+            GRAPH <http://example.com/g2> { ex:s ex:p 3 }
+            { ex:s ex:p 4 . }
+        "#;
+
+        let triples = parse_trig(input, None).unwrap();
+
+        let graphs: Vec<Option<&str>> = triples.iter().map(|t| t.graph.as_deref()).collect();
+        assert_eq!(graphs, vec![
+            None,
+            Some("http://example.com/g1"),
+            Some("http://example.com/g1"),
+            Some("http://example.com/g2"),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let err = parse("@prefix ex: <http://example.com/> .\nex:a ex:p \"unterminated .", None).unwrap_err();
+        assert!(matches!(err, RdfEditorError::InvalidInput(ref msg) if msg.contains("line 2")), "{}", err);
+
+        let err = parse("ex:a ex:b ex:c .", None).unwrap_err();
+        assert!(err.to_string().contains("undefined prefix 'ex:'"), "{}", err);
+
+        let err = parse("<a> <b> <c> .", None).unwrap_err();
+        assert!(err.to_string().contains("relative IRI"), "{}", err);
+
+        let err = parse("<http://a> <http://b> <http://c>", None).unwrap_err();
+        assert!(err.to_string().contains("line 1, column 33"), "{}", err);
+
+        let err = parse("<http://a> <http://b> <http://c\\u0020d> .", None).unwrap_err();
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+    }
+
+    #[test]
+    fn test_serializer_output_round_trips() {
+        let input = r#"
+            @prefix ex: <http://example.com/> .
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+            ex:a a ex:Class ;
+                rdfs:label "A \"quoted\"\n"@en , "plain" ;
+                ex:n 1 , 2.5 , 3.0E1 , false , "x"^^ex:dt ;
+                ex:nested [ ex:inner [ ex:leaf "deep" ] ] ;
+                ex:other <http://example.com/has%20escape#frag> .
+            _:shared ex:p "s" .
+            ex:b ex:ref _:shared .
+            ex:c ex:ref _:shared .
+        "#;
+
+        let mut namespaces = default_namespaces();
+        namespaces.push(Namespace { prefix: "ex".to_string(), uri: "http://example.com/".to_string() });
+
+        let original = parse(input, None).unwrap();
+        let output = serialize(&original, &namespaces);
+        let reparsed = parse(&output, None).unwrap();
+
+        assert_eq!(reparsed.len(), original.len());
+        let ground = |triples: &[Triple]| -> Vec<Triple> {
+            sorted(triples.iter()
+                .filter(|t| !t.subject_is_blank() && !matches!(t.object, RdfNode::Blank { .. }))
+                .cloned()
+                .collect())
+        };
+        assert_eq!(ground(&reparsed), ground(&original));
+        assert!(output.contains("ex:nested [\n        ex:inner [\n            ex:leaf \"deep\""));
+
+        let shared_refs: Vec<&RdfNode> = reparsed.iter()
+            .filter(|t| t.predicate == "http://example.com/ref")
+            .map(|t| &t.object)
+            .collect();
+        assert_eq!(shared_refs.len(), 2);
+        assert_eq!(shared_refs[0], shared_refs[1]);
+    }
+
+    #[test]
+    fn test_parse_nquads() {
+        let input = concat!(
+            "<http://example.com/s> <http://example.com/p> \"o\"@en <http://example.com/g> .\n",
+            "# comment\n",
+            "_:a <http://example.com/p> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n",
+            "_:a <http://example.com/p> _:b _:g .\n",
+        );
+
+        let triples = parse_nquads(input, None).unwrap();
+        let graphs: Vec<Option<&str>> = triples.iter().map(|t| t.graph.as_deref()).collect();
+        assert_eq!(graphs[0], Some("http://example.com/g"));
+        assert_eq!(graphs[1], None);
+        assert!(graphs[2].is_some_and(|g| g.starts_with("_:")));
+        assert_eq!(triples[1].subject, triples[2].subject);
+
+        let err = parse_nquads("<http://example.com/s> <http://example.com/p> .", None).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid input: N-Quads syntax error"), "{}", err);
+    }
+
+    #[test]
+    fn test_trig_dataset_round_trip_keeps_graphs_and_shared_blank_nodes() {
+        let input = r#"
+            @prefix ex: <http://example.com/> .
+            ex:s ex:p "default" .
+            ex:g1 { ex:s ex:p [ ex:q "nested" ] ; ex:shared _:x . }
+            ex:g2 { _:x ex:label "in g2" . }
+        "#;
+
+        let mut namespaces = default_namespaces();
+        namespaces.push(Namespace { prefix: "ex".to_string(), uri: "http://example.com/".to_string() });
+
+        let original = parse_trig(input, None).unwrap();
+        let output = serialize_trig(&original, &namespaces);
+        let reparsed = parse_trig(&output, None).unwrap();
+
+        assert_eq!(reparsed.len(), original.len(), "{}", output);
+        assert!(output.starts_with("@prefix ex: <http://example.com/> .\n\nex:s ex:p \"default\" .\n"), "{}", output);
+        assert!(output.contains("<http://example.com/g1> {\n    ex:s ex:p [\n"), "{}", output);
+
+        let graph_of = |predicate: &str| reparsed.iter()
+            .find(|t| t.predicate == format!("http://example.com/{}", predicate))
+            .and_then(|t| t.graph.clone());
+        assert_eq!(graph_of("q").as_deref(), Some("http://example.com/g1"));
+        assert_eq!(graph_of("label").as_deref(), Some("http://example.com/g2"));
+
+        let shared = reparsed.iter().find(|t| t.predicate == "http://example.com/shared").unwrap();
+        let labelled = reparsed.iter().find(|t| t.predicate == "http://example.com/label").unwrap();
+        assert_eq!(shared.object, subject_to_node(labelled.subject.clone()));
+    }
+}
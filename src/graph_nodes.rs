@@ -0,0 +1,82 @@
+//! Cached reads behind the graph visualization endpoints.
+//!
+//! A node's details come from the profile's label and type caches; the URIs
+//! missing from them are resolved together with one VALUES query, which fills
+//! both caches. Connections and searches are cached whole in the graph cache.
+//! Entries are tagged with the resources they were read from, so a write only
+//! drops what it may have changed. A search can gain matches from any write and
+//! is dropped by every one.
+
+use std::collections::HashMap;
+use crate::cache::CacheTag;
+use crate::error::RdfEditorError;
+use crate::models::{GraphEdge, GraphNode};
+use crate::profiles::ProfileState;
+
+/// Nodes for `uris`, in the same order, with their labels and types
+pub async fn resolve_nodes(profile: &ProfileState, uris: &[String]) -> Result<Vec<GraphNode>, RdfEditorError> {
+    let mut resolved: HashMap<String, GraphNode> = HashMap::new();
+    let mut missing: Vec<String> = Vec::new();
+    for uri in uris {
+        if resolved.contains_key(uri) || missing.contains(uri) {
+            continue;
+        }
+        if let Some(node) = profile.graph_cache.nodes.get(uri).await {
+            resolved.insert(uri.clone(), node);
+            continue;
+        }
+        match (profile.label_cache.get(uri).await, profile.type_cache.get(uri).await) {
+            (Some(labels), Some(types)) => {
+                let node = profile.sparql_client.graph_node(uri, &labels, types);
+                cache_node(profile, &node).await;
+                resolved.insert(uri.clone(), node);
+            },
+            _ => missing.push(uri.clone()),
+        }
+    }
+    
+    for (uri, (labels, types)) in profile.sparql_client.get_labels_and_types(&missing).await? {
+        let node = profile.sparql_client.graph_node(&uri, &labels, types.clone());
+        let tags = vec![CacheTag::Resource(uri.clone())];
+        profile.label_cache.set_tagged(uri.clone(), labels, tags.clone()).await;
+        profile.type_cache.set_tagged(uri.clone(), types, tags).await;
+        cache_node(profile, &node).await;
+        resolved.insert(uri, node);
+    }
+    
+    Ok(uris.iter().filter_map(|uri| resolved.get(uri).cloned()).collect())
+}
+
+async fn cache_node(profile: &ProfileState, node: &GraphNode) {
+    profile.graph_cache.nodes.set_tagged(node.uri.clone(), node.clone(), vec![CacheTag::Resource(node.uri.clone())]).await;
+}
+
+/// Up to `limit` edges into and out of `uri` and the nodes at their other end,
+/// as the store returned them; neighbours carry no types
+pub async fn connections(profile: &ProfileState, uri: &str, limit: usize) -> Result<(Vec<GraphNode>, Vec<GraphEdge>), RdfEditorError> {
+    let key = format!("{}|{}", limit, uri);
+    if let Some(found) = profile.graph_cache.connections.get(&key).await {
+        return Ok(found);
+    }
+    
+    let (nodes, edges) = profile.sparql_client.get_node_connections(uri, limit).await?;
+    // Neighbour labels were read too, so their triples matter as well
+    let tags = std::iter::once(uri)
+        .chain(nodes.iter().map(|node| node.uri.as_str()))
+        .map(|uri| CacheTag::Resource(uri.to_string()))
+        .collect();
+    profile.graph_cache.connections.set_tagged(key, (nodes.clone(), edges.clone()), tags).await;
+    Ok((nodes, edges))
+}
+
+/// Up to `limit` nodes with a label containing `term`
+pub async fn search(profile: &ProfileState, term: &str, limit: usize) -> Result<Vec<GraphNode>, RdfEditorError> {
+    let key = format!("{}|{}", limit, term);
+    if let Some(found) = profile.graph_cache.search_results.get(&key).await {
+        return Ok(found);
+    }
+    
+    let nodes = profile.sparql_client.search_nodes_by_label(term, limit).await?;
+    profile.graph_cache.search_results.set_tagged(key, nodes.clone(), vec![CacheTag::Store]).await;
+    Ok(nodes)
+}
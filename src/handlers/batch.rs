@@ -1,13 +1,18 @@
 use actix_web::{HttpResponse, web};
 use crate::{
-    AppState, 
-    models::{BatchTripleRequest, BatchOperationResponse},
+    profiles::Profile,
+    models::{BatchTripleRequest, BatchOperationResponse, Triple},
     error::RdfEditorError,
     transaction_log::TransactionType,
+    transactions::TransactionId,
 };
 
+/// Triples per update for batch writes, to avoid overly large SPARQL updates
+pub const BATCH_SIZE: usize = 100;
+
 pub async fn add_triples_batch(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
     request: web::Json<BatchTripleRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
@@ -28,16 +33,21 @@ pub async fn add_triples_batch(
         }
     }
     
+    if let Some(id) = transaction.get() {
+        let triples = in_graph(&request.triples, &graph_name);
+        let staged = triples.len();
+        profile.transactions.stage(id, |transaction| triples.into_iter().for_each(|triple| transaction.add(triple))).await?;
+        return Ok(staged_response(staged));
+    }
+    
     // Start batch mode for efficient logging
-    data.transaction_logger.start_batch().await;
+    profile.transaction_logger.start_batch().await;
     
-    // Process in batches of 100 to avoid overly large SPARQL updates
-    let batch_size = 100;
     let mut processed = 0;
     let mut errors = Vec::new();
     
-    for chunk in request.triples.chunks(batch_size) {
-        match data.sparql_client.add_triples_batch(chunk, &graph_name).await {
+    for chunk in request.triples.chunks(BATCH_SIZE) {
+        match profile.sparql_client.add_triples_batch(chunk, &graph_name).await {
             Ok(_) => processed += chunk.len(),
             Err(e) => {
                 errors.push(format!("Failed to add batch starting at index {}: {}", processed, e));
@@ -46,20 +56,21 @@ pub async fn add_triples_batch(
         }
     }
     
-    // Log the batch operation
-    if errors.is_empty() {
-        data.transaction_logger.log_batch_operation(
+    // Log what was written, also when a later chunk failed, so it can be undone
+    if processed > 0 {
+        profile.transaction_logger.log_batch_operation(
             TransactionType::AddTriplesBatch,
             Some(&graph_name),
             None,
-            Some(&request.triples),
+            Some(&request.triples[..processed]),
             &format!("Added {} triples in batch", processed),
             None,
         ).await?;
+        profile.invalidate_writes(&request.triples[..processed], &graph_name).await;
     }
     
     // End batch mode and flush
-    data.transaction_logger.end_batch().await?;
+    profile.transaction_logger.end_batch().await?;
     
     Ok(HttpResponse::Ok().json(BatchOperationResponse {
         success: errors.is_empty(),
@@ -70,7 +81,8 @@ pub async fn add_triples_batch(
 }
 
 pub async fn delete_triples_batch(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
     request: web::Json<BatchTripleRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
@@ -80,12 +92,18 @@ pub async fn delete_triples_batch(
         return Err(RdfEditorError::InvalidInput("No triples provided".to_string()));
     }
     
-    let batch_size = 100;
+    if let Some(id) = transaction.get() {
+        let triples = in_graph(&request.triples, &graph_name);
+        let staged = triples.len();
+        profile.transactions.stage(id, |transaction| triples.into_iter().for_each(|triple| transaction.delete(triple))).await?;
+        return Ok(staged_response(staged));
+    }
+    
     let mut processed = 0;
     let mut errors = Vec::new();
     
-    for chunk in request.triples.chunks(batch_size) {
-        match data.sparql_client.delete_triples_batch(chunk, &graph_name).await {
+    for chunk in request.triples.chunks(BATCH_SIZE) {
+        match profile.sparql_client.delete_triples_batch(chunk, &graph_name).await {
             Ok(_) => processed += chunk.len(),
             Err(e) => {
                 errors.push(format!("Failed to delete batch starting at index {}: {}", processed, e));
@@ -94,10 +112,37 @@ pub async fn delete_triples_batch(
         }
     }
     
+    if processed > 0 {
+        profile.transaction_logger.log_batch_operation(
+            TransactionType::DeleteTriplesBatch,
+            Some(&graph_name),
+            Some(&request.triples[..processed]),
+            None,
+            &format!("Deleted {} triples in batch", processed),
+            None,
+        ).await?;
+        profile.invalidate_writes(&request.triples[..processed], &graph_name).await;
+    }
+    
     Ok(HttpResponse::Ok().json(BatchOperationResponse {
         success: errors.is_empty(),
         processed,
         failed: request.triples.len() - processed,
         errors,
     }))
-}
\ No newline at end of file
+}
+/// `triples` naming `graph_name` as their graph, as staged transactions keep them
+fn in_graph(triples: &[Triple], graph_name: &str) -> Vec<Triple> {
+    let graph = (graph_name != "default").then(|| graph_name.to_string());
+    triples.iter().map(|triple| Triple { graph: graph.clone(), ..triple.clone() }).collect()
+}
+
+/// Staged triples count as processed; they are written on commit
+fn staged_response(staged: usize) -> HttpResponse {
+    HttpResponse::Ok().json(BatchOperationResponse {
+        success: true,
+        processed: staged,
+        failed: 0,
+        errors: Vec::new(),
+    })
+}
@@ -0,0 +1,22 @@
+use actix_web::HttpResponse;
+use crate::profiles::Profile;
+
+/// Entry counts, sizes, hits, misses and evictions of every cache of the profile
+pub async fn stats(profile: Profile) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "profile": profile.name(),
+        "labels": profile.label_cache.stats().await,
+        "types": profile.type_cache.stats().await,
+        "queries": profile.query_cache.stats().await,
+        "graph": profile.graph_cache.get_stats().await
+    }))
+}
+
+/// Empty every cache of the profile, including the files of persistent caches
+pub async fn flush(profile: Profile) -> HttpResponse {
+    profile.flush_caches().await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "profile": profile.name()
+    }))
+}
@@ -1,15 +1,19 @@
-use actix_web::{HttpResponse, web};
-use crate::AppState;
+use actix_web::HttpResponse;
+use crate::profiles::Profile;
 
-pub async fn check(data: web::Data<AppState>) -> HttpResponse {
+pub async fn check(profile: Profile) -> HttpResponse {
     // Try a simple ASK query to verify SPARQL endpoint connectivity
-    match data.sparql_client.query("ASK { ?s ?p ?o }").await {
+    match profile.sparql_client.query("ASK { ?s ?p ?o }").await {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
             "status": "healthy",
+            "profile": profile.name(),
+            "dialect": profile.config.dialect,
             "sparql_endpoint": "connected"
         })),
         Err(e) => HttpResponse::ServiceUnavailable().json(serde_json::json!({
             "status": "unhealthy",
+            "profile": profile.name(),
+            "dialect": profile.config.dialect,
             "sparql_endpoint": "disconnected",
             "error": e.to_string()
         }))
@@ -1,78 +1,416 @@
-use actix_web::{HttpResponse, web};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use actix_multipart::Multipart;
+use actix_web::{HttpRequest, HttpResponse, http::header, web, web::Bytes};
+use flate2::{write::GzEncoder, Compression};
+use futures_util::{stream, Stream, StreamExt};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
 use crate::{
     AppState, 
-    models::{ExportRequest, ImportRequest, RdfFormat},
-    error::RdfEditorError
+    profiles::Profile,
+    sparql::SparqlClient,
+    models::{ExportRequest, GraphReplaceRequest, ImportJobRequest, ImportRequest, RdfFormat, Triple},
+    error::RdfEditorError,
+    formats::{self, jsonld, ntriples, rdfxml, turtle, StreamWriter},
+    import_jobs::ImportJob,
+    transaction_log::TransactionType,
+    transactions::TransactionId,
 };
 
 pub async fn export_graph(
     data: web::Data<AppState>,
+    profile: Profile,
     query: web::Query<ExportRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
-    // For now, we'll export as N-Triples which is simple to generate
-    // In a real implementation, you'd use a proper RDF library for other formats
+    let query = query.into_inner();
+    let page_size = data.config.export_page_size;
+    // Without a graph, quad formats dump every graph with its name
+    let dataset = query.graph.is_none() && query.format.is_dataset();
+    let namespaces = formats::default_namespaces();
     
-    let triples = data.sparql_client.get_triples(query.graph.as_deref()).await?;
+    let client = profile.sparql_client.clone();
     
-    match query.format {
-        RdfFormat::NTriples => {
-            let mut output = String::new();
-            for triple in triples {
-                output.push_str(&format!("{} .\n", triple.to_sparql_pattern()));
+    let mut response = HttpResponse::Ok();
+    let extension = query.format.file_extension();
+    if query.gzip {
+        response.content_type("application/gzip").append_header(("Content-Disposition",
+            format!("attachment; filename=\"export.{}.gz\"", extension)));
+    } else {
+        response.content_type(query.format.content_type()).append_header(("Content-Disposition",
+            format!("attachment; filename=\"export.{}\"", extension)));
+    }
+    
+    // With the Graph Store Protocol the store serializes a named graph itself, and the
+    // body is passed through as it arrives. JSON-LD stays local to honour jsonld_form.
+    let store_native = matches!(query.format, RdfFormat::NTriples | RdfFormat::Turtle | RdfFormat::RdfXml);
+    if let Some(graph) = query.graph.as_deref().filter(|_| store_native && client.has_graph_store()) {
+        // A missing graph falls through to SPARQL, which writes a valid empty document
+        if let Some(body) = client.graph_store_get(graph, query.format).await? {
+            if query.gzip {
+                return Ok(response.streaming(gzip_stream(body)));
             }
-            
-            Ok(HttpResponse::Ok()
-                .content_type("application/n-triples")
-                .append_header(("Content-Disposition", 
-                    format!("attachment; filename=\"export.{}\"", query.format.file_extension())))
-                .body(output))
-        },
-        _ => Err(RdfEditorError::InvalidInput(
-            format!("Export format {:?} not yet implemented", query.format)
-        )),
+            return Ok(response.streaming(body));
+        }
+    }
+    
+    let first_page = fetch_page(&client, query.graph.as_deref(), dataset, page_size, 0).await?;
+    
+    // A graph that fits in one page gets the fully abbreviated serializers
+    if first_page.len() < page_size {
+        let output = match query.format {
+            RdfFormat::NTriples => ntriples::serialize(&first_page),
+            RdfFormat::NQuads => ntriples::serialize_quads(&first_page),
+            RdfFormat::Turtle => turtle::serialize(&first_page, &namespaces),
+            RdfFormat::TriG => turtle::serialize_trig(&first_page, &namespaces),
+            RdfFormat::JsonLd => jsonld::serialize(&first_page, &namespaces, query.jsonld_form)?,
+            RdfFormat::RdfXml => rdfxml::serialize(&first_page, &namespaces)?,
+        };
+        
+        if query.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(output.as_bytes()).map_err(gzip_error)?;
+            return Ok(response.body(encoder.finish().map_err(gzip_error)?));
+        }
+        return Ok(response.body(output));
+    }
+    
+    let stream = ExportStream {
+        client,
+        graph: query.graph,
+        dataset,
+        page_size,
+        offset: 0,
+        first_page: Some(first_page),
+        writer: formats::stream_writer(query.format, &namespaces, query.jsonld_form),
+        encoder: query.gzip.then(|| GzEncoder::new(Vec::new(), Compression::default())),
+        exhausted: false,
+        finished: false,
+    };
+    
+    Ok(response.streaming(stream::try_unfold(stream, |mut stream| async move {
+        // Skip empty chunks; an empty chunk would end a chunked response early
+        while !stream.finished {
+            let chunk = stream.next_chunk().await.inspect_err(|e| {
+                log::error!("Export stream aborted: {}", e);
+            })?;
+            if !chunk.is_empty() {
+                return Ok(Some((Bytes::from(chunk), stream)));
+            }
+        }
+        Ok::<_, RdfEditorError>(None)
+    })))
+}
+
+async fn fetch_page(
+    client: &SparqlClient,
+    graph: Option<&str>,
+    dataset: bool,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<Triple>, RdfEditorError> {
+    if dataset {
+        client.get_quads_paginated(limit, offset).await
+    } else {
+        client.get_triples_paginated(graph, limit, offset).await
+    }
+}
+
+fn gzip_error(e: std::io::Error) -> RdfEditorError {
+    RdfEditorError::Configuration(format!("Failed to compress export: {}", e))
+}
+
+/// Compress a streamed body chunk by chunk
+fn gzip_stream<S>(body: S) -> impl Stream<Item = Result<Bytes, RdfEditorError>>
+where
+    S: Stream<Item = Result<Bytes, RdfEditorError>> + Unpin,
+{
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    stream::try_unfold((body, Some(encoder)), |(mut body, mut encoder)| async move {
+        while let Some(mut gzip) = encoder.take() {
+            match body.next().await {
+                Some(bytes) => {
+                    gzip.write_all(&bytes?).map_err(gzip_error)?;
+                    let chunk = std::mem::take(gzip.get_mut());
+                    encoder = Some(gzip);
+                    // Skip empty chunks; an empty chunk would end a chunked response early
+                    if !chunk.is_empty() {
+                        return Ok(Some((Bytes::from(chunk), (body, encoder))));
+                    }
+                },
+                None => {
+                    let chunk = gzip.finish().map_err(gzip_error)?;
+                    return Ok(Some((Bytes::from(chunk), (body, None))));
+                },
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Export body produced one SPARQL page at a time, so memory stays flat for any graph size
+struct ExportStream {
+    client: Arc<SparqlClient>,
+    graph: Option<String>,
+    dataset: bool,
+    page_size: usize,
+    offset: usize,
+    first_page: Option<Vec<Triple>>,
+    writer: Box<dyn StreamWriter>,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+    exhausted: bool,
+    finished: bool,
+}
+
+impl ExportStream {
+    async fn next_chunk(&mut self) -> Result<Vec<u8>, RdfEditorError> {
+        let mut text = String::new();
+        
+        let page = match self.first_page.take() {
+            Some(page) => {
+                text.push_str(&self.writer.start()?);
+                Some(page)
+            },
+            None if !self.exhausted => {
+                Some(fetch_page(&self.client, self.graph.as_deref(), self.dataset, self.page_size, self.offset).await?)
+            },
+            None => None,
+        };
+        
+        match page {
+            Some(page) if !page.is_empty() => {
+                self.offset += page.len();
+                text.push_str(&self.writer.write(&page)?);
+                // A short page is the last one
+                self.exhausted = page.len() < self.page_size;
+            },
+            _ => {
+                text.push_str(&self.writer.finish()?);
+                self.finished = true;
+            },
+        }
+        
+        self.encode(text.as_bytes())
+    }
+    
+    fn encode(&mut self, text: &[u8]) -> Result<Vec<u8>, RdfEditorError> {
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(text.to_vec());
+        };
+        
+        encoder.write_all(text).map_err(gzip_error)?;
+        if self.finished {
+            let encoder = self.encoder.take().expect("gzip encoder present");
+            return encoder.finish().map_err(gzip_error);
+        }
+        Ok(std::mem::take(encoder.get_mut()))
     }
 }
 
 pub async fn import_data(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     request: web::Json<ImportRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
-    // For now, we only support N-Triples import
-    // In a real implementation, you'd use a proper RDF parser
-    
-    match request.format {
-        RdfFormat::NTriples => {
-            // Very basic N-Triples parsing (production code should use a proper parser)
-            let lines: Vec<&str> = request.data.lines()
-                .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
-                .collect();
-                
-            if lines.is_empty() {
-                return Err(RdfEditorError::InvalidInput("No valid triples found".to_string()));
-            }
-            
-            // Build SPARQL INSERT DATA query
-            let mut update = format!("INSERT DATA {{ GRAPH <{}> {{", request.graph);
-            let line_count = lines.len();
-            for line in &lines {
-                let trimmed = line.trim();
-                if trimmed.ends_with('.') {
-                    update.push_str(&format!(" {}", trimmed));
-                } else {
-                    update.push_str(&format!(" {} .", trimmed));
-                }
-            }
-            update.push_str(" } }");
-            
-            data.sparql_client.update(&update).await?;
-            
-            Ok(HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "message": format!("Imported {} triples", line_count)
-            })))
-        },
-        _ => Err(RdfEditorError::InvalidInput(
-            format!("Import format {:?} not yet implemented", request.format)
-        )),
+    transaction.refuse("An import")?;
+    
+    let base = request.base.as_deref().unwrap_or(&request.graph);
+    
+    let triples = formats::parse(request.format, &request.data, Some(base))?;
+    
+    if triples.is_empty() {
+        return Err(RdfEditorError::InvalidInput("No valid triples found".to_string()));
+    }
+    
+    // The log names every triple's graph. Undo removes only what the import added,
+    // so the triples already there are kept as the old data.
+    let target = (request.graph != "default").then(|| request.graph.clone());
+    let imported: Vec<Triple> = triples.iter()
+        .map(|triple| Triple { graph: triple.graph.clone().or_else(|| target.clone()), ..triple.clone() })
+        .collect();
+    let present = profile.sparql_client.present_triples(&imported).await?;
+    
+    // Quads (TriG graph blocks, N-Quads graph labels, JSON-LD named graphs) go to their own graph,
+    // everything else to the target graph
+    let graphs = profile.sparql_client.add_quads_batch(&triples, &request.graph).await?;
+    
+    profile.transaction_logger.log_batch_operation(
+        TransactionType::ImportData,
+        target.as_deref(),
+        Some(&present),
+        Some(&imported),
+        &format!("Imported {} triples into {} graph(s)", triples.len(), graphs),
+        None,
+    ).await?;
+    profile.invalidate_writes(&imported, &request.graph).await;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Imported {} triples into {} graph(s)", triples.len(), graphs)
+    })))
+}
+
+/// Replace the whole contents of a graph with the posted document
+pub async fn replace_graph(
+    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
+    path: web::Path<String>,
+    request: web::Json<GraphReplaceRequest>,
+) -> Result<HttpResponse, RdfEditorError> {
+    transaction.refuse("A graph replace")?;
+    
+    let graph_name = path.into_inner();
+    let base = request.base.as_deref().unwrap_or(&graph_name);
+    
+    let triples = formats::parse(request.format, &request.data, Some(base))?;
+    if triples.iter().any(|triple| triple.graph.as_ref().is_some_and(|g| g != &graph_name)) {
+        return Err(RdfEditorError::InvalidInput(
+            "The document names other graphs; use /import for datasets".to_string()
+        ));
+    }
+    
+    let snapshot = profile.sparql_client.snapshot_graph(&graph_name, data.config.export_page_size).await?;
+    profile.sparql_client.replace_graph(&graph_name, &triples).await?;
+    
+    profile.transaction_logger.log_batch_operation(
+        TransactionType::ReplaceGraph,
+        if graph_name == "default" { None } else { Some(&graph_name) },
+        Some(&snapshot),
+        Some(&triples),
+        &format!("Replaced graph {} with {} triples", graph_name, triples.len()),
+        None,
+    ).await?;
+    
+    let changed: Vec<Triple> = snapshot.into_iter().chain(triples.iter().cloned()).collect();
+    profile.invalidate_writes(&changed, &graph_name).await;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Replaced graph {} with {} triples", graph_name, triples.len())
+    })))
+}
+
+/// Start a background import. The document is the raw request body, or the file field
+/// of a multipart form, and is spooled to disk so it is not bound by MAX_PAYLOAD_SIZE.
+pub async fn start_import_job(
+    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
+    query: web::Query<ImportJobRequest>,
+    request: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, RdfEditorError> {
+    transaction.refuse("A background import")?;
+    
+    let query = query.into_inner();
+    let id = Uuid::new_v4().to_string();
+    let path = data.import_jobs.upload_path(&id);
+    
+    let is_multipart = request.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    let spooled = if is_multipart {
+        spool_multipart(Multipart::new(request.headers(), payload), &path).await
+    } else {
+        spool(payload, &path).await
+    };
+    let spooled = match spooled {
+        Ok(()) => check_document_size(&path, query.format, data.config.import_max_document_size).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = spooled {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
     }
-}
\ No newline at end of file
+    
+    let chunk_size = query.chunk_size.unwrap_or(data.config.import_chunk_size).max(1);
+    let job = ImportJob {
+        skolemize: query.skolemize,
+        ..ImportJob::new(id, profile.name().to_string(), query.graph, query.format, query.base, chunk_size)
+    };
+    let job = data.import_jobs.start(profile.into_inner(), job).await?;
+    
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+pub async fn list_import_jobs(
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, RdfEditorError> {
+    Ok(HttpResponse::Ok().json(data.import_jobs.list().await))
+}
+
+pub async fn get_import_job(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let id = path.into_inner();
+    let job = data.import_jobs.get(&id).await
+        .ok_or_else(|| RdfEditorError::NotFound(format!("Import job {} not found", id)))?;
+    
+    Ok(HttpResponse::Ok().json(job))
+}
+
+pub async fn resume_import_job(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let id = path.into_inner();
+    let job = data.import_jobs.get(&id).await
+        .ok_or_else(|| RdfEditorError::NotFound(format!("Import job {} not found", id)))?;
+    // A job always resumes against the store it started on
+    let profile = data.profiles.get(&job.profile)?;
+    let job = data.import_jobs.resume(profile, &id).await?;
+    
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+async fn spool_multipart(mut multipart: Multipart, path: &Path) -> Result<(), RdfEditorError> {
+    while let Some(field) = multipart.next().await {
+        let field = field.map_err(|e| RdfEditorError::InvalidInput(format!("Invalid multipart upload: {}", e)))?;
+        let is_file = field.content_disposition().is_some_and(|cd| cd.get_filename().is_some());
+        if is_file {
+            return spool(field, path).await;
+        }
+    }
+    
+    Err(RdfEditorError::InvalidInput("Multipart upload has no file field".to_string()))
+}
+
+/// Only line-based formats are parsed a block at a time; the others are read into
+/// memory whole, so their size is capped
+async fn check_document_size(path: &Path, format: RdfFormat, limit: u64) -> Result<(), RdfEditorError> {
+    if format.is_line_based() {
+        return Ok(());
+    }
+    
+    let size = tokio::fs::metadata(path).await
+        .map_err(|e| RdfEditorError::Configuration(format!("Failed to store upload: {}", e)))?
+        .len();
+    if size > limit {
+        return Err(RdfEditorError::InvalidInput(format!(
+            "Upload of {} bytes exceeds IMPORT_MAX_DOCUMENT_SIZE ({} bytes) for {:?}, which is parsed in memory; \
+             convert it to N-Triples or N-Quads to import it in a stream",
+            size, limit, format
+        )));
+    }
+    Ok(())
+}
+
+async fn spool<S, E>(mut body: S, path: &Path) -> Result<(), RdfEditorError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let write_error = |e: std::io::Error| RdfEditorError::Configuration(format!("Failed to store upload: {}", e));
+    let mut file = tokio::fs::File::create(path).await.map_err(write_error)?;
+    
+    while let Some(bytes) = body.next().await {
+        let bytes = bytes.map_err(|e| RdfEditorError::InvalidInput(format!("Upload interrupted: {}", e)))?;
+        file.write_all(&bytes).await.map_err(write_error)?;
+    }
+    
+    file.flush().await.map_err(write_error)
+}
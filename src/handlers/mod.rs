@@ -3,4 +3,6 @@ pub mod query;
 pub mod update;
 pub mod batch;
 pub mod import_export;
-pub mod transaction;
\ No newline at end of file
+pub mod transaction;
+pub mod profiles;
+pub mod namespaces;pub mod cache;
@@ -0,0 +1,18 @@
+use actix_web::HttpResponse;
+use crate::{profiles::Profile, formats, error::RdfEditorError};
+
+/// Prefixes from the store's namespace API, or the editor defaults for stores without one
+pub async fn list_namespaces(
+    profile: Profile,
+) -> Result<HttpResponse, RdfEditorError> {
+    let (source, namespaces) = match profile.sparql_client.namespaces().await? {
+        Some(namespaces) => ("store", namespaces),
+        None => ("defaults", formats::default_namespaces()),
+    };
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "dialect": profile.sparql_client.dialect(),
+        "source": source,
+        "namespaces": namespaces
+    })))
+}
@@ -0,0 +1,30 @@
+use actix_web::{HttpResponse, web};
+use crate::{AppState, models::ProfileSummary, error::RdfEditorError};
+
+pub async fn list_profiles(
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let profiles: Vec<ProfileSummary> = data.profiles
+        .iter()
+        .map(|profile| {
+            let config = &profile.config;
+            ProfileSummary {
+                name: config.name.clone(),
+                default: config.name == data.profiles.default_name(),
+                query_endpoint: config.sparql_endpoint.clone(),
+                update_endpoint: config.get_update_endpoint().to_string(),
+                graph_store_endpoint: config.graph_store_endpoint.clone(),
+                dialect: config.dialect,
+                full_text_search: config.full_text_search,
+                authenticated: config.sparql_username.is_some(),
+                verify_ssl: config.verify_ssl,
+                graph_max_nodes: config.graph_max_nodes,
+                graph_max_edges: config.graph_max_edges,
+                graph_expansion_limit: config.graph_expansion_limit,
+                graph_search_limit: config.graph_search_limit,
+            }
+        })
+        .collect();
+    
+    Ok(HttpResponse::Ok().json(profiles))
+}
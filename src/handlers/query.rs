@@ -1,12 +1,15 @@
 use actix_web::{HttpResponse, web};
 use crate::{
-    AppState, 
+    cache::CacheTag,
+    graph_nodes,
+    profiles::{Profile, ProfileState},
     models::{
         SparqlQuery, QueryResponse, PaginatedQuery, PaginatedResponse,
         GraphSearchRequest, GraphExpandRequest, GraphDataResponse, 
         GraphPathRequest, GraphPath, GraphEdge
     }, 
-    error::RdfEditorError
+    error::RdfEditorError,
+    transactions::TransactionId,
 };
 use sha2::{Sha256, Digest};
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -21,31 +24,39 @@ fn hash_query(query: &str, graph: Option<&str>) -> String {
 }
 
 pub async fn execute(
-    data: web::Data<AppState>,
+    profile: Profile,
     query: web::Json<SparqlQuery>,
 ) -> Result<HttpResponse, RdfEditorError> {
     // Check cache first for SELECT queries
     let is_select = query.query.trim().to_uppercase().starts_with("SELECT");
     let cache_key = if is_select {
-        Some(hash_query(&query.query, query.graph.as_deref()))
+        let key = hash_query(&query.query, query.graph.as_deref());
+        // Results with reasoning toggled differ from the store default
+        Some(match query.reasoning {
+            Some(reasoning) => format!("{}:reasoning={}", key, reasoning),
+            None => key,
+        })
     } else {
         None
     };
     
     if let Some(ref key) = cache_key {
-        if let Some(cached_result) = data.query_cache.get(key).await {
+        if let Some(cached_result) = profile.query_cache.get(key).await {
             return Ok(HttpResponse::Ok().json(QueryResponse {
                 results: vec![cached_result],
             }));
         }
     }
     
-    // Execute query
-    let results = data.sparql_client.query(&query.query).await?;
+    // Execute query; identical SELECTs already running share its result
+    let results = match &cache_key {
+        Some(key) => profile.sparql_client.query_coalesced(key, &query.query, query.reasoning).await?,
+        None => profile.sparql_client.query_with_reasoning(&query.query, query.reasoning).await?,
+    };
     
-    // Cache SELECT query results
+    // Cache SELECT query results; what they read is unknown, so any write drops them
     if let Some(key) = cache_key {
-        data.query_cache.set(key, results.clone()).await;
+        profile.query_cache.set_tagged(key, results.clone(), vec![CacheTag::Store]).await;
     }
     
     Ok(HttpResponse::Ok().json(QueryResponse {
@@ -53,8 +64,10 @@ pub async fn execute(
     }))
 }
 
+/// Triples of a graph; inside a transaction, as the transaction sees them
 pub async fn get_triples(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
 ) -> Result<HttpResponse, RdfEditorError> {
     let graph_name = path.into_inner();
@@ -66,23 +79,26 @@ pub async fn get_triples(
         Some(graph_name.as_str())
     };
     
-    let triples = data.sparql_client.get_triples(graph_option).await?;
+    let mut triples = profile.sparql_client.get_triples(graph_option).await?;
+    if let Some(id) = transaction.get() {
+        triples = profile.transactions.get(id).await?.overlay(graph_option, triples);
+    }
     
     Ok(HttpResponse::Ok().json(triples))
 }
 
 pub async fn get_triples_paginated(
-    data: web::Data<AppState>,
+    profile: Profile,
     path: web::Path<String>,
     query: web::Query<PaginatedQuery>,
 ) -> Result<HttpResponse, RdfEditorError> {
     let graph_name = path.into_inner();
     
     // Get total count
-    let total = data.sparql_client.count_triples(Some(&graph_name)).await?;
+    let total = profile.sparql_client.count_triples(Some(&graph_name)).await?;
     
     // Get paginated triples
-    let triples = data.sparql_client
+    let triples = profile.sparql_client
         .get_triples_paginated(Some(&graph_name), query.limit, query.offset)
         .await?;
     
@@ -98,14 +114,14 @@ pub async fn get_triples_paginated(
 }
 
 pub async fn get_resources_with_labels(
-    data: web::Data<AppState>,
+    profile: Profile,
     path: web::Path<String>,
 ) -> Result<HttpResponse, RdfEditorError> {
     let graph_name = path.into_inner();
     let cache_key = format!("labels:{}", graph_name);
     
     // Check cache first
-    if let Some(cached_result) = data.query_cache.get(&cache_key).await {
+    if let Some(cached_result) = profile.query_cache.get(&cache_key).await {
         return Ok(HttpResponse::Ok().json(cached_result));
     }
     
@@ -176,10 +192,12 @@ pub async fn get_resources_with_labels(
     "#, graph_name)
     };
     
-    let results = data.sparql_client.query(&query).await?;
+    // Requests for the same graph arriving while this one runs share its query
+    let key = hash_query(&query, Some(&graph_name));
+    let results = profile.sparql_client.query_coalesced(&key, &query, None).await?;
     
-    // Cache the results
-    data.query_cache.set(cache_key, results.clone()).await;
+    // Cache the results until the graph changes
+    profile.query_cache.set_tagged(cache_key, results.clone(), vec![CacheTag::Graph(graph_name)]).await;
     
     Ok(HttpResponse::Ok().json(results))
 }
@@ -187,12 +205,11 @@ pub async fn get_resources_with_labels(
 // New graph-specific endpoints
 
 pub async fn search_graph_nodes(
-    data: web::Data<AppState>,
+    profile: Profile,
     request: web::Json<GraphSearchRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
-    let nodes = data.sparql_client
-        .search_nodes_by_label(&request.query, request.limit)
-        .await?;
+    let limit = request.limit.min(profile.config.graph_search_limit);
+    let nodes = graph_nodes::search(&profile, &request.query, limit).await?;
     
     Ok(HttpResponse::Ok().json(GraphDataResponse {
         nodes,
@@ -201,16 +218,17 @@ pub async fn search_graph_nodes(
 }
 
 pub async fn expand_graph_node(
-    data: web::Data<AppState>,
+    profile: Profile,
     request: web::Json<GraphExpandRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
     // Check if expansion limit is reasonable
-    let config = &data.config;
+    let config = &profile.config;
     let limit = request.limit.min(config.graph_expansion_limit);
     
-    let (nodes, edges) = data.sparql_client
-        .get_node_connections(&request.uri, limit)
-        .await?;
+    let (nodes, edges) = graph_nodes::connections(&profile, &request.uri, limit).await?;
+    // Neighbours get their labels and types from the caches, missing ones in one query
+    let uris: Vec<String> = nodes.iter().map(|node| node.uri.clone()).collect();
+    let nodes = graph_nodes::resolve_nodes(&profile, &uris).await?;
     
     Ok(HttpResponse::Ok().json(GraphDataResponse {
         nodes,
@@ -219,7 +237,7 @@ pub async fn expand_graph_node(
 }
 
 pub async fn get_node_info(
-    data: web::Data<AppState>,
+    profile: Profile,
     path: web::Path<String>,
 ) -> Result<HttpResponse, RdfEditorError> {
     let encoded_uri = path.into_inner();
@@ -227,13 +245,13 @@ pub async fn get_node_info(
         .map_err(|_| RdfEditorError::InvalidInput("Invalid URI encoding".to_string()))?
         .into_owned();
     
-    let node = data.sparql_client.get_node_info(&uri).await?;
+    let node = graph_nodes::resolve_nodes(&profile, &[uri]).await?;
     
-    Ok(HttpResponse::Ok().json(node))
+    Ok(HttpResponse::Ok().json(&node[0]))
 }
 
 pub async fn find_path_between_nodes(
-    data: web::Data<AppState>,
+    profile: Profile,
     request: web::Json<GraphPathRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
     // This is a simple BFS implementation for finding shortest path
@@ -243,7 +261,7 @@ pub async fn find_path_between_nodes(
     
     // Build a subgraph around the nodes
     let path = find_shortest_path(
-        &data.sparql_client,
+        &profile,
         &request.from,
         &request.to,
         max_depth
@@ -257,14 +275,21 @@ pub async fn find_path_between_nodes(
     }
 }
 
-// Helper function for path finding
+// Helper function for path finding. Results are cached and tagged with every
+// node the search expanded: only a write touching one of them can change the outcome.
 async fn find_shortest_path(
-    client: &crate::sparql::SparqlClient,
+    profile: &ProfileState,
     from: &str,
     to: &str,
     max_depth: usize,
 ) -> Result<Option<GraphPath>, RdfEditorError> {
+    let key = (from.to_string(), to.to_string(), max_depth);
+    if let Some(path) = profile.graph_cache.paths.get(&key).await {
+        return Ok(path);
+    }
+    
     // Simple BFS implementation
+    let mut expanded: Vec<CacheTag> = Vec::new();
     let mut visited = HashSet::new();
     let mut queue = VecDeque::new();
     let mut parents: HashMap<String, (String, String)> = HashMap::new(); // node -> (parent, predicate)
@@ -293,11 +318,14 @@ async fn find_shortest_path(
             path_nodes.reverse();
             path_edges.reverse();
             
-            return Ok(Some(GraphPath {
+            let path = Some(GraphPath {
                 nodes: path_nodes,
                 edges: path_edges.clone(),
                 length: path_edges.len(),
-            }));
+            });
+            expanded.push(CacheTag::Resource(to.to_string()));
+            profile.graph_cache.paths.set_tagged(key, path.clone(), expanded).await;
+            return Ok(path);
         }
         
         if depth >= max_depth {
@@ -305,7 +333,8 @@ async fn find_shortest_path(
         }
         
         // Get connections
-        let (_nodes, edges) = client.get_node_connections(&current, 50).await?;
+        let (_nodes, edges) = graph_nodes::connections(profile, &current, 50).await?;
+        expanded.push(CacheTag::Resource(current.clone()));
         
         for edge in edges {
             let next = if edge.source == current {
@@ -322,5 +351,6 @@ async fn find_shortest_path(
         }
     }
     
+    profile.graph_cache.paths.set_tagged(key, None, expanded).await;
     Ok(None)
 }
\ No newline at end of file
@@ -1,104 +1,447 @@
 use actix_web::{HttpResponse, web};
-use crate::{AppState, error::RdfEditorError};
-use crate::transaction_log::TransactionType;
+use crate::{AppState, profiles::{Profile, ProfileState}, error::RdfEditorError};
+use crate::conflicts::{self, Conflict, ReversalOptions, Resolved};
+use crate::formats::changeset;
+use crate::models::{ChangesetExportRequest, ChangesetImportRequest, CompactRequest, RestoreRequest, Triple};
+use crate::sparql;
+use crate::transaction_log::{self, LogQuery, TransactionRecord, TransactionType};
+use crate::transactions::{TransactionId, TRANSACTION_HEADER};
 
+/// The log, newest first, filtered and paged by the query string
 pub async fn get_recent_transactions(
-    data: web::Data<AppState>,
+    profile: Profile,
+    query: web::Query<LogQuery>,
 ) -> Result<HttpResponse, RdfEditorError> {
-    let transactions = data.transaction_logger
-        .get_recent_transactions(50)
+    let page = profile.transaction_logger
+        .query(&query)
         .await?;
     
-    Ok(HttpResponse::Ok().json(transactions))
+    Ok(HttpResponse::Ok().json(page))
 }
 
+/// Reverse a transaction, logging an Undo record linked to it. Triples changed
+/// since are reported as conflicts, resolved per the optional request body.
 pub async fn undo_transaction(
     data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
+    options: Option<web::Json<ReversalOptions>>,
 ) -> Result<HttpResponse, RdfEditorError> {
-    let transaction_id = path.into_inner();
+    transaction.refuse("An undo")?;
     
-    // Get the transaction record
-    let transactions = data.transaction_logger
-        .get_recent_transactions(100)
-        .await?;
+    let transaction_id = path.into_inner();
     
-    let transaction = transactions
-        .iter()
-        .find(|t| t.record.id == transaction_id)
-        .ok_or_else(|| RdfEditorError::NotFound(format!("Transaction {} not found", transaction_id)))?;
+    // Checked under the claim, so a concurrent undo or redo cannot slip in before the write
+    let _claim = profile.transaction_logger.claim_reversal(&transaction_id)?;
+    let transaction = profile.transaction_logger.entry(&transaction_id).await?;
     
+    if matches!(transaction.record.transaction_type, TransactionType::Undo | TransactionType::Redo) {
+        return Err(RdfEditorError::InvalidInput(format!(
+            "Transaction {} is an {:?}; undo or redo the original transaction instead",
+            transaction_id, transaction.record.transaction_type
+        )));
+    }
+    if let Some(undo_id) = &transaction.undone_by {
+        return Err(RdfEditorError::InvalidInput(format!(
+            "Transaction {} is already undone by {}",
+            transaction_id, undo_id
+        )));
+    }
     if !transaction.can_undo {
         return Err(RdfEditorError::InvalidInput(
             "This transaction type cannot be undone automatically".to_string()
         ));
     }
     
-    // Perform the undo based on transaction type
-    match &transaction.record.transaction_type {
-        TransactionType::AddTriple => {
-            // Undo by deleting the added triple
-            if let Some(triples) = &transaction.record.new_data {
-                if let Some(triple) = triples.first() {
-                    if let Some(graph) = &transaction.record.graph {
-                        if graph == "default" {
-                            data.sparql_client.delete_triple_from_default(triple).await?;
-                        } else {
-                            data.sparql_client.delete_triple(triple, graph).await?;
-                        }
-                    }
-                }
-            }
+    let options = options.map(web::Json::into_inner).unwrap_or_default();
+    let (record_id, conflicts) = match reverse(
+        &profile,
+        &transaction.record,
+        TransactionType::Undo,
+        &format!("Undid transaction: {}", transaction_id),
+        &options,
+        data.config.export_page_size,
+    ).await? {
+        Ok(reversed) => reversed,
+        Err(conflicts) => return Ok(conflict_response(&transaction_id, conflicts)),
+    };
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Transaction {} has been undone", transaction_id),
+        "record_id": record_id,
+        "conflicts": conflicts
+    })))
+}
+
+/// Apply an undone transaction again by reversing its Undo. The id may name
+/// either the undone transaction or the Undo record. Conflicts are handled as for undo.
+pub async fn redo_transaction(
+    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
+    path: web::Path<String>,
+    options: Option<web::Json<ReversalOptions>>,
+) -> Result<HttpResponse, RdfEditorError> {
+    transaction.refuse("A redo")?;
+    
+    let transaction_id = path.into_inner();
+    
+    let transaction = profile.transaction_logger.entry(&transaction_id).await?;
+    let original_id = match transaction.record.transaction_type {
+        TransactionType::Undo => transaction.record.reverses.clone().unwrap_or_default(),
+        TransactionType::Redo => {
+            return Err(RdfEditorError::InvalidInput(format!(
+                "Transaction {} is a Redo; undo the original transaction instead",
+                transaction_id
+            )));
         },
-        TransactionType::DeleteTriple => {
-            // Undo by re-adding the deleted triple
-            if let Some(triples) = &transaction.record.old_data {
-                if let Some(triple) = triples.first() {
-                    if let Some(graph) = &transaction.record.graph {
-                        if graph == "default" {
-                            data.sparql_client.add_triple_to_default(triple).await?;
-                        } else {
-                            data.sparql_client.add_triple(triple, graph).await?;
-                        }
-                    }
-                }
-            }
+        _ => transaction_id.clone(),
+    };
+    
+    // Checked under the claim, so a concurrent undo or redo cannot slip in before the write
+    let _claim = profile.transaction_logger.claim_reversal(&original_id)?;
+    let original = profile.transaction_logger.entry(&original_id).await?;
+    // Only the Undo currently in effect can be redone
+    let undo_id = match (&transaction.record.transaction_type, &original.undone_by) {
+        (TransactionType::Undo, current) if current.as_deref() != Some(transaction_id.as_str()) => {
+            return Err(RdfEditorError::InvalidInput(format!(
+                "Undo {} is already redone",
+                transaction_id
+            )));
         },
-        TransactionType::ReplaceTriple => {
-            // Undo by reversing the replacement
-            if let (Some(old_triples), Some(new_triples)) = 
-                (&transaction.record.old_data, &transaction.record.new_data) {
-                if let (Some(old_triple), Some(new_triple)) = 
-                    (old_triples.first(), new_triples.first()) {
-                    if let Some(graph) = &transaction.record.graph {
-                        if graph == "default" {
-                            data.sparql_client.replace_triple_in_default(new_triple, old_triple).await?;
-                        } else {
-                            data.sparql_client.replace_triple(new_triple, old_triple, graph).await?;
-                        }
-                    }
-                }
-            }
+        (_, Some(undo_id)) => undo_id,
+        (_, None) => {
+            return Err(RdfEditorError::InvalidInput(format!(
+                "Transaction {} is not undone",
+                transaction_id
+            )));
         },
-        _ => {
-            return Err(RdfEditorError::InvalidInput(
-                "Undo not implemented for this transaction type".to_string()
-            ));
-        }
+    };
+    let undo = profile.transaction_logger.entry(undo_id).await?;
+    
+    let options = options.map(web::Json::into_inner).unwrap_or_default();
+    let (record_id, conflicts) = match reverse(
+        &profile,
+        &undo.record,
+        TransactionType::Redo,
+        &format!("Redid transaction: {}", original_id),
+        &options,
+        data.config.export_page_size,
+    ).await? {
+        Ok(reversed) => reversed,
+        Err(conflicts) => return Ok(conflict_response(&original_id, conflicts)),
+    };
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Transaction {} has been redone", original_id),
+        "record_id": record_id,
+        "conflicts": conflicts
+    })))
+}
+
+/// Check the inverse of `record` for conflicts, then apply what the resolutions
+/// keep as one atomic update and log it as an Undo or Redo pointing back at
+/// `record`. Gives the new record id and the resolved conflicts, or the
+/// conflicts when the reversal was aborted.
+async fn reverse(
+    profile: &ProfileState,
+    record: &TransactionRecord,
+    transaction_type: TransactionType,
+    description: &str,
+    options: &ReversalOptions,
+    page_size: usize,
+) -> Result<Result<(String, Vec<Conflict>), Vec<Conflict>>, RdfEditorError> {
+    let (removals, restores) = record.inverse();
+    let found = conflicts::find_conflicts(&profile.sparql_client, &removals, &restores, page_size).await?;
+    let Resolved { removals, restores, conflicts } = match conflicts::resolve(removals, restores, found, options) {
+        Ok(resolved) => resolved,
+        Err(conflicts) => return Ok(Err(conflicts)),
+    };
+    
+    profile.sparql_client.apply_changes(&removals, &restores).await?;
+    
+    let changed: Vec<Triple> = removals.iter().chain(&restores).cloned().collect();
+    profile.invalidate_writes(&changed, "default").await;
+    
+    let record_id = profile.transaction_logger.log_reversal(
+        transaction_type,
+        &record.id,
+        record.graph.as_deref(),
+        &removals,
+        &restores,
+        description,
+    ).await?;
+    Ok(Ok((record_id, conflicts)))
+}
+
+/// 409 listing the conflicts of an aborted undo or redo; nothing was written
+fn conflict_response(transaction_id: &str, conflicts: Vec<Conflict>) -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "success": false,
+        "message": format!(
+            "Transaction {} conflicts with later changes to {} triples; resend with a resolution of force or skip",
+            transaction_id, conflicts.len()
+        ),
+        "conflicts": conflicts
+    }))
+}
+
+/// Roll a graph back to how it was at a point in the log, reversing every logged
+/// change to it since as one atomic update logged as a Restore. With `preview`,
+/// only reports the triples that would be deleted and inserted.
+pub async fn restore_graph(
+    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
+    path: web::Path<String>,
+    request: web::Json<RestoreRequest>,
+) -> Result<HttpResponse, RdfEditorError> {
+    if !request.preview {
+        transaction.refuse("A graph restore")?;
     }
     
-    // Log the undo operation
-    data.transaction_logger.log_batch_operation(
-        TransactionType::AddTriple, // We could add an "Undo" transaction type
-        transaction.record.graph.as_deref(),
-        None,
+    let graph_name = path.into_inner();
+    let logger = &profile.transaction_logger;
+    
+    let start = match (&request.timestamp, &request.transaction_id) {
+        (Some(timestamp), None) => logger.position_after_time(*timestamp).await,
+        (None, Some(id)) => logger.position_after_record(id).await?,
+        _ => return Err(RdfEditorError::InvalidInput(
+            "Give either a timestamp or a transaction_id to restore to".to_string()
+        )),
+    };
+    let start = logger.restore_start(start, &graph_name).await;
+    let records = logger.records_touching(start, &graph_name).await?;
+    let prior = transaction_log::prior_state(&records, Some(&graph_name))?;
+    
+    // Diff the state before those changes against what the graph holds now
+    let current = profile.sparql_client.snapshot_graph(&graph_name, data.config.export_page_size).await?;
+    let (deletions, insertions) = prior.diff(&current);
+    
+    if request.preview || (deletions.is_empty() && insertions.is_empty()) {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "preview": request.preview,
+            "graph": graph_name,
+            "transactions": prior.transactions,
+            "deletions": deletions,
+            "insertions": insertions
+        })));
+    }
+    
+    profile.sparql_client.apply_changes(&deletions, &insertions).await?;
+    let changed: Vec<Triple> = deletions.iter().chain(&insertions).cloned().collect();
+    profile.invalidate_writes(&changed, &graph_name).await;
+    
+    let record_id = profile.transaction_logger.log_batch_operation(
+        TransactionType::Restore,
+        Some(&graph_name),
+        Some(&deletions),
+        Some(&insertions),
+        &format!(
+            "Restored graph {} past {} transactions: {} deletions, {} insertions",
+            graph_name, prior.transactions.len(), deletions.len(), insertions.len()
+        ),
         None,
-        &format!("Undid transaction: {}", transaction_id),
+    ).await?;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "preview": false,
+        "graph": graph_name,
+        "transactions": prior.transactions,
+        "deletions": deletions,
+        "insertions": insertions,
+        "record_id": record_id
+    })))
+}
+
+/// Replace the log up to a point with periodic snapshots of the graphs it touched
+pub async fn compact_transactions(
+    data: web::Data<AppState>,
+    profile: Profile,
+    request: web::Json<CompactRequest>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let interval = request.interval_hours.map(|hours| chrono::Duration::hours(hours as i64));
+    let compaction = profile.transaction_logger.compact(
+        &profile.sparql_client,
+        request.before,
+        interval,
+        data.config.export_page_size,
+    ).await?;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "snapshots": compaction.snapshots,
+        "removed": compaction.removed,
+        "skipped": compaction.skipped
+    })))
+}
+
+/// Logged changes as an RDF Patch or SPARQL Update changeset, oldest first
+pub async fn export_changeset(
+    profile: Profile,
+    query: web::Query<ChangesetExportRequest>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let logger = &profile.transaction_logger;
+    let start = match &query.from {
+        Some(id) => logger.position_of_record(id).await?,
+        None => 0,
+    };
+    let end = match &query.to {
+        Some(id) => logger.position_after_record(id).await?,
+        None => usize::MAX,
+    };
+    let log_query = LogQuery {
+        graph: query.graph.clone(),
+        since: query.since,
+        until: query.until,
+        ..LogQuery::default()
+    };
+    let records = logger.records(&log_query, start..end).await?;
+    let changes = transaction_log::changes(&records, query.graph.as_deref())?;
+    
+    Ok(HttpResponse::Ok()
+        .content_type(query.format.content_type())
+        .append_header(("Content-Disposition",
+            format!("attachment; filename=\"changeset.{}\"", query.format.file_extension())))
+        .body(changeset::write(query.format, &changes)))
+}
+
+/// Apply a changeset in one atomic update, logging each of its changes
+pub async fn import_changeset(
+    profile: Profile,
+    transaction: TransactionId,
+    request: web::Json<ChangesetImportRequest>,
+) -> Result<HttpResponse, RdfEditorError> {
+    transaction.refuse("A changeset import")?;
+    
+    let changes: Vec<changeset::Change> = changeset::parse(request.format, &request.data)?
+        .into_iter()
+        .filter(|change| !change.is_empty())
+        .collect();
+    if changes.is_empty() {
+        return Err(RdfEditorError::InvalidInput("The changeset has no changes".to_string()));
+    }
+    
+    let updates: Vec<String> = changes.iter()
+        .flat_map(|change| sparql::change_updates(&change.deletions, &change.insertions))
+        .collect();
+    profile.sparql_client.update_atomic(&updates).await?;
+    
+    let mut record_ids = Vec::with_capacity(changes.len());
+    for (i, change) in changes.iter().enumerate() {
+        let changed: Vec<Triple> = change.deletions.iter().chain(&change.insertions).cloned().collect();
+        let change_graphs: Vec<String> = sparql::by_graph(&changed).into_iter().map(|(graph, _)| graph).collect();
+        let graph = match change_graphs.as_slice() {
+            [graph] if graph != "default" => Some(graph.as_str()),
+            _ => None,
+        };
+        let mut description = format!(
+            "Applied change {} of {} from a changeset: {} deletions, {} insertions",
+            i + 1, changes.len(), change.deletions.len(), change.insertions.len()
+        );
+        if let Some(label) = &change.label {
+            description.push_str(&format!(" ({})", label));
+        }
+        
+        record_ids.push(profile.transaction_logger.log_batch_operation(
+            TransactionType::Changeset,
+            graph,
+            Some(&change.deletions),
+            Some(&change.insertions),
+            &description,
+            None,
+        ).await?);
+        profile.invalidate_writes(&changed, "default").await;
+    }
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Applied {} changes", changes.len()),
+        "record_ids": record_ids
+    })))
+}
+
+/// Open a transaction; writes sent with its id in the X-Transaction-Id header are staged
+pub async fn begin_transaction(
+    profile: Profile,
+) -> Result<HttpResponse, RdfEditorError> {
+    let transaction = profile.transactions.begin().await;
+    
+    Ok(HttpResponse::Ok()
+        .append_header((TRANSACTION_HEADER, transaction.id.clone()))
+        .json(transaction))
+}
+
+/// The changes staged in an open transaction
+pub async fn get_transaction(
+    profile: Profile,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let transaction = profile.transactions.get(&path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(transaction))
+}
+
+/// Apply the staged changes in one atomic update and log them as one record
+pub async fn commit_transaction(
+    profile: Profile,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let transaction = profile.transactions.take(&path.into_inner()).await?;
+    if transaction.is_empty() {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": format!("Transaction {} had no changes", transaction.id)
+        })));
+    }
+    
+    // A failed commit leaves the transaction open, to retry or roll back
+    if let Err(e) = profile.sparql_client.apply_changes(&transaction.deletions, &transaction.insertions).await {
+        profile.transactions.restore(transaction).await;
+        return Err(e);
+    }
+    
+    let graphs = transaction.graphs();
+    let graph = match graphs.as_slice() {
+        [graph] if graph != "default" => Some(graph.as_str()),
+        _ => None,
+    };
+    let record_id = profile.transaction_logger.log_batch_operation(
+        TransactionType::Commit,
+        graph,
+        Some(&transaction.deletions),
+        Some(&transaction.insertions),
+        &format!(
+            "Committed transaction {}: {} deletions, {} insertions",
+            transaction.id, transaction.deletions.len(), transaction.insertions.len()
+        ),
         None,
     ).await?;
     
+    let changed: Vec<Triple> = transaction.deletions.iter().chain(&transaction.insertions).cloned().collect();
+    profile.invalidate_writes(&changed, "default").await;
+    
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
-        "message": format!("Transaction {} has been undone", transaction_id)
+        "message": format!("Transaction {} committed", transaction.id),
+        "record_id": record_id
     })))
-}
\ No newline at end of file
+}
+
+/// Discard the staged changes and close the transaction
+pub async fn rollback_transaction(
+    profile: Profile,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    let transaction = profile.transactions.take(&path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("Transaction {} rolled back", transaction.id)
+    })))
+}
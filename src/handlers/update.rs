@@ -1,13 +1,17 @@
 use actix_web::{HttpResponse, web};
 use crate::{
-    AppState, 
+    AppState,
+    profiles::Profile,
     models::{SparqlUpdate, UpdateResponse, AddTripleRequest, DeleteTripleRequest, Triple, RdfNode, ReplaceTripleRequest},
     error::RdfEditorError,
+    transaction_log::TransactionType,
+    transactions::TransactionId,
     validation::{validate_uri, validate_language_tag, validate_blank_node_id}
 };
 
+#[allow(dead_code)]
 pub async fn execute(
-    data: web::Data<AppState>,
+    profile: Profile,
     update: web::Json<SparqlUpdate>,
 ) -> Result<HttpResponse, RdfEditorError> {
     // If a graph is specified, wrap the update in a GRAPH clause
@@ -17,7 +21,7 @@ pub async fn execute(
         update.update.clone()
     };
     
-    data.sparql_client.update(&final_update).await?;
+    profile.sparql_client.update(&final_update).await?;
     
     Ok(HttpResponse::Ok().json(UpdateResponse {
         success: true,
@@ -26,7 +30,8 @@ pub async fn execute(
 }
 
 pub async fn add_triple(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
     request: web::Json<AddTripleRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
@@ -47,7 +52,7 @@ pub async fn add_triple(
                 validate_uri(dt)?;
             }
         },
-        RdfNode::Blank { value } => validate_blank_node_id(&value)?,
+        RdfNode::Blank { value } => validate_blank_node_id(value)?,
     }
     
     let triple = Triple {
@@ -57,23 +62,26 @@ pub async fn add_triple(
         graph: if graph_name == "default" { None } else { Some(graph_name.clone()) },
     };
     
+    if let Some(id) = transaction.get() {
+        profile.transactions.stage(id, |staged| staged.add(triple)).await?;
+        return Ok(staged_response(id));
+    }
+    
     // Handle default graph
     if graph_name == "default" {
-        data.sparql_client.add_triple_to_default(&triple).await?;
+        profile.sparql_client.add_triple_to_default(&triple).await?;
     } else {
-        data.sparql_client.add_triple(&triple, &graph_name).await?;
+        profile.sparql_client.add_triple(&triple, &graph_name).await?;
     }
     
     // Log the transaction
-    data.transaction_logger.log_add_triple(
+    profile.transaction_logger.log_add_triple(
         if graph_name == "default" { None } else { Some(&graph_name) },
         &triple,
         None, // No user ID yet
     ).await?;
     
-    // Invalidate relevant caches
-    let cache_key = format!("labels:{}", graph_name);
-    data.query_cache.invalidate(&cache_key).await;
+    profile.invalidate_writes(&[triple], &graph_name).await;
     
     Ok(HttpResponse::Ok().json(UpdateResponse {
         success: true,
@@ -82,7 +90,8 @@ pub async fn add_triple(
 }
 
 pub async fn delete_triple(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
     request: web::Json<DeleteTripleRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
@@ -95,20 +104,27 @@ pub async fn delete_triple(
         graph: if graph_name == "default" { None } else { Some(graph_name.clone()) },
     };
     
+    if let Some(id) = transaction.get() {
+        profile.transactions.stage(id, |staged| staged.delete(triple)).await?;
+        return Ok(staged_response(id));
+    }
+    
     // Handle default graph
     if graph_name == "default" {
-        data.sparql_client.delete_triple_from_default(&triple).await?;
+        profile.sparql_client.delete_triple_from_default(&triple).await?;
     } else {
-        data.sparql_client.delete_triple(&triple, &graph_name).await?;
+        profile.sparql_client.delete_triple(&triple, &graph_name).await?;
     }
     
     // Log the transaction
-    data.transaction_logger.log_delete_triple(
+    profile.transaction_logger.log_delete_triple(
         if graph_name == "default" { None } else { Some(&graph_name) },
         &triple,
         None, // No user ID yet
     ).await?;
     
+    profile.invalidate_writes(&[triple], &graph_name).await;
+    
     Ok(HttpResponse::Ok().json(UpdateResponse {
         success: true,
         message: "Triple deleted successfully".to_string(),
@@ -116,7 +132,8 @@ pub async fn delete_triple(
 }
 
 pub async fn replace_triple(
-    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
     path: web::Path<String>,
     request: web::Json<ReplaceTripleRequest>,
 ) -> Result<HttpResponse, RdfEditorError> {
@@ -148,23 +165,73 @@ pub async fn replace_triple(
         ));
     }
     
+    if let Some(id) = transaction.get() {
+        let graph = if graph_name == "default" { None } else { Some(graph_name.clone()) };
+        let old_triple = Triple { graph: graph.clone(), ..request.old_triple.clone() };
+        let new_triple = Triple { graph, ..request.new_triple.clone() };
+        profile.transactions.stage(id, |staged| staged.replace(old_triple, new_triple)).await?;
+        return Ok(staged_response(id));
+    }
+    
     // Handle default graph
     if graph_name == "default" {
-        data.sparql_client.replace_triple_in_default(&request.old_triple, &request.new_triple).await?;
+        profile.sparql_client.replace_triple_in_default(&request.old_triple, &request.new_triple).await?;
     } else {
-        data.sparql_client.replace_triple(&request.old_triple, &request.new_triple, &graph_name).await?;
+        profile.sparql_client.replace_triple(&request.old_triple, &request.new_triple, &graph_name).await?;
     }
     
     // Log the transaction
-    data.transaction_logger.log_replace_triple(
+    profile.transaction_logger.log_replace_triple(
         if graph_name == "default" { None } else { Some(&graph_name) },
         &request.old_triple,
         &request.new_triple,
         None, // No user ID yet
     ).await?;
     
+    profile.invalidate_writes(&[request.old_triple.clone(), request.new_triple.clone()], &graph_name).await;
+    
     Ok(HttpResponse::Ok().json(UpdateResponse {
         success: true,
         message: "Triple replaced successfully".to_string(),
     }))
-}
\ No newline at end of file
+}
+
+/// Reply to a write staged in a transaction instead of applied
+fn staged_response(transaction_id: &str) -> HttpResponse {
+    HttpResponse::Ok().json(UpdateResponse {
+        success: true,
+        message: format!("Staged in transaction {}", transaction_id),
+    })
+}
+
+/// Remove a whole graph
+pub async fn drop_graph(
+    data: web::Data<AppState>,
+    profile: Profile,
+    transaction: TransactionId,
+    path: web::Path<String>,
+) -> Result<HttpResponse, RdfEditorError> {
+    transaction.refuse("A graph drop")?;
+    
+    let graph_name = path.into_inner();
+    
+    // Keep the contents so the drop can be undone
+    let snapshot = profile.sparql_client.snapshot_graph(&graph_name, data.config.export_page_size).await?;
+    profile.sparql_client.drop_graph(&graph_name).await?;
+    
+    profile.transaction_logger.log_batch_operation(
+        TransactionType::DropGraph,
+        if graph_name == "default" { None } else { Some(&graph_name) },
+        Some(&snapshot),
+        None,
+        &format!("Dropped graph {} ({} triples)", graph_name, snapshot.len()),
+        None,
+    ).await?;
+    
+    profile.invalidate_writes(&snapshot, &graph_name).await;
+    
+    Ok(HttpResponse::Ok().json(UpdateResponse {
+        success: true,
+        message: format!("Graph {} dropped", graph_name),
+    }))
+}
@@ -0,0 +1,609 @@
+//! Background import jobs for uploads too large for a single request.
+//!
+//! An upload is spooled to the import directory, parsed on a blocking thread and
+//! inserted chunk by chunk through `SparqlClient::add_quads_batch`. Each chunk is
+//! logged as an import that can be undone and invalidates the profile's cached
+//! results it touches. Job progress is saved next to the upload after every chunk,
+//! so a failed or interrupted job can be resumed from the last committed chunk.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use crate::config::DEFAULT_PROFILE;
+use crate::error::RdfEditorError;
+use crate::formats::{jsonld, rdfxml, turtle::{Syntax, TurtleParser}};
+use crate::models::{RdfFormat, RdfNode, Triple};
+use crate::profiles::ProfileState;
+use crate::transaction_log::TransactionType;
+
+/// Prefix of the IRIs that stand in for blank nodes in chunked imports
+const SKOLEM_PREFIX: &str = "urn:rdf-editor:genid:";
+
+/// How long the upload of a failed job is kept for a resume
+const FAILED_UPLOAD_RETENTION_HOURS: i64 = 24 * 7;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportJob {
+    pub id: String,
+    /// Endpoint profile the job writes to
+    #[serde(default = "default_profile")]
+    pub profile: String,
+    pub graph: String,
+    pub format: RdfFormat,
+    pub base: Option<String>,
+    pub chunk_size: usize,
+    /// Whether blank nodes are written as `urn:rdf-editor:genid:{id}:` IRIs. Jobs saved
+    /// before this was a choice always were.
+    #[serde(default = "always")]
+    pub skolemize: bool,
+    pub status: ImportJobStatus,
+    /// Triples read from the upload so far, including those skipped on resume
+    pub parsed: usize,
+    /// Triples committed to the store; a resumed job starts after these
+    pub inserted: usize,
+    /// Triples in the chunk whose insert failed
+    pub failed: usize,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ImportJob {
+    pub fn new(id: String, profile: String, graph: String, format: RdfFormat, base: Option<String>, chunk_size: usize) -> Self {
+        let now = Utc::now();
+        ImportJob {
+            id,
+            profile,
+            graph,
+            format,
+            base,
+            chunk_size,
+            skolemize: false,
+            status: ImportJobStatus::Running,
+            parsed: 0,
+            inserted: 0,
+            failed: 0,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+fn default_profile() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+fn always() -> bool {
+    true
+}
+
+pub struct ImportJobManager {
+    dir: PathBuf,
+    jobs: RwLock<HashMap<String, ImportJob>>,
+}
+
+impl ImportJobManager {
+    /// Open the import directory and reload saved jobs. Jobs that were running when the
+    /// server stopped are marked failed so they can be resumed. Uploads no job can use
+    /// any more are removed.
+    pub async fn new(dir: &str) -> Result<Self, RdfEditorError> {
+        tokio::fs::create_dir_all(dir).await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to create import directory: {}", e)))?;
+        
+        let mut jobs = HashMap::new();
+        let mut entries = tokio::fs::read_dir(dir).await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to read import directory: {}", e)))?;
+        while let Some(entry) = entries.next_entry().await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to read import directory: {}", e)))? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            
+            let loaded = tokio::fs::read_to_string(&path).await
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str::<ImportJob>(&text).map_err(|e| e.to_string()));
+            match loaded {
+                Ok(mut job) => {
+                    if job.status == ImportJobStatus::Running {
+                        job.status = ImportJobStatus::Failed;
+                        job.error = Some("Interrupted by server restart".to_string());
+                    }
+                    jobs.insert(job.id.clone(), job);
+                },
+                Err(e) => log::warn!("Skipping unreadable import job {}: {}", path.display(), e),
+            }
+        }
+        
+        let manager = ImportJobManager {
+            dir: PathBuf::from(dir),
+            jobs: RwLock::new(jobs),
+        };
+        manager.remove_unused_uploads().await;
+        Ok(manager)
+    }
+    
+    /// Remove the uploads without a job, and those of failed jobs left unresumed
+    /// for longer than `FAILED_UPLOAD_RETENTION_HOURS`
+    async fn remove_unused_uploads(&self) {
+        let expired = Utc::now() - chrono::Duration::hours(FAILED_UPLOAD_RETENTION_HOURS);
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return;
+        };
+        let jobs = self.jobs.read().await;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "upload") {
+                continue;
+            }
+            let id = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let unused = match jobs.get(id) {
+                Some(job) => job.status == ImportJobStatus::Failed && job.updated_at < expired,
+                None => true,
+            };
+            if unused {
+                self.remove_upload(id).await;
+            }
+        }
+    }
+    
+    async fn remove_upload(&self, id: &str) {
+        if let Err(e) = tokio::fs::remove_file(self.upload_path(id)).await {
+            log::warn!("Failed to remove upload for import job {}: {}", id, e);
+        }
+    }
+    
+    /// Where the upload for a job is spooled
+    pub fn upload_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.upload", id))
+    }
+    
+    fn job_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+    
+    pub async fn get(&self, id: &str) -> Option<ImportJob> {
+        self.jobs.read().await.get(id).cloned()
+    }
+    
+    /// All known jobs, newest first
+    pub async fn list(&self) -> Vec<ImportJob> {
+        let mut jobs: Vec<ImportJob> = self.jobs.read().await.values().cloned().collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+    
+    /// Register a job whose upload is already at `upload_path(job.id)` and start it
+    pub async fn start(self: &Arc<Self>, profile: Arc<ProfileState>, job: ImportJob) -> Result<ImportJob, RdfEditorError> {
+        self.save(&job).await?;
+        self.spawn(profile, job.clone());
+        Ok(job)
+    }
+    
+    /// Restart a failed job after its last committed chunk
+    pub async fn resume(self: &Arc<Self>, profile: Arc<ProfileState>, id: &str) -> Result<ImportJob, RdfEditorError> {
+        if !tokio::fs::try_exists(self.upload_path(id)).await.unwrap_or(false) {
+            return Err(RdfEditorError::NotFound(format!("Upload for import job {} is no longer available", id)));
+        }
+        
+        let job = {
+            let mut jobs = self.jobs.write().await;
+            let job = jobs.get_mut(id)
+                .ok_or_else(|| RdfEditorError::NotFound(format!("Import job {} not found", id)))?;
+            match job.status {
+                ImportJobStatus::Running => {
+                    return Err(RdfEditorError::InvalidInput(format!("Import job {} is still running", id)));
+                },
+                ImportJobStatus::Completed => {
+                    return Err(RdfEditorError::InvalidInput(format!("Import job {} has already completed", id)));
+                },
+                ImportJobStatus::Failed => {},
+            }
+            
+            job.status = ImportJobStatus::Running;
+            job.parsed = 0;
+            job.failed = 0;
+            job.error = None;
+            job.updated_at = Utc::now();
+            job.clone()
+        };
+        
+        self.save(&job).await?;
+        self.spawn(profile, job.clone());
+        Ok(job)
+    }
+    
+    /// Record job progress in memory and on disk
+    async fn save(&self, job: &ImportJob) -> Result<(), RdfEditorError> {
+        self.jobs.write().await.insert(job.id.clone(), job.clone());
+        
+        // Write then rename, so a crash never leaves a truncated progress file
+        let path = self.job_path(&job.id);
+        let temp = path.with_extension("json.tmp");
+        tokio::fs::write(&temp, serde_json::to_vec(job)?).await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to save import job: {}", e)))?;
+        tokio::fs::rename(&temp, &path).await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to save import job: {}", e)))
+    }
+    
+    fn spawn(self: &Arc<Self>, profile: Arc<ProfileState>, job: ImportJob) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.run(&profile, job).await;
+        });
+    }
+    
+    async fn run(&self, profile: &ProfileState, mut job: ImportJob) {
+        // Two chunks in flight: one being inserted, one being parsed
+        let (tx, mut rx) = mpsc::channel(2);
+        let path = self.upload_path(&job.id);
+        let format = job.format;
+        let base = job.base.clone().unwrap_or_else(|| job.graph.clone());
+        let skolem_prefix = job.skolemize.then(|| format!("{}{}:", SKOLEM_PREFIX, job.id));
+        let (skip, chunk_size) = (job.inserted, job.chunk_size);
+        let reader = tokio::task::spawn_blocking(move || {
+            read_chunks(&path, format, &base, skolem_prefix.as_deref(), skip, chunk_size, &tx)
+        });
+        
+        while let Some(chunk) = rx.recv().await {
+            match chunk {
+                Ok(chunk) => {
+                    job.parsed = chunk.parsed;
+                    match self.insert_chunk(profile, &job, &chunk.triples).await {
+                        Ok(()) => job.inserted += chunk.triples.len(),
+                        Err(e) => {
+                            job.failed = chunk.triples.len();
+                            job.status = ImportJobStatus::Failed;
+                            job.error = Some(e.to_string());
+                        },
+                    }
+                },
+                Err(e) => {
+                    job.status = ImportJobStatus::Failed;
+                    job.error = Some(e.to_string());
+                },
+            }
+            
+            job.updated_at = Utc::now();
+            if job.status == ImportJobStatus::Failed {
+                break;
+            }
+            self.save_or_warn(&job).await;
+        }
+        
+        // Dropping the receiver stops the reader at its next chunk
+        drop(rx);
+        if let Err(e) = reader.await {
+            job.status = ImportJobStatus::Failed;
+            job.error = Some(format!("Import reader stopped: {}", e));
+        }
+        
+        // A job that read its whole upload has no more use for it. One that failed
+        // partway keeps it for a resume.
+        if job.status == ImportJobStatus::Running {
+            if job.inserted == 0 {
+                job.status = ImportJobStatus::Failed;
+                job.error = Some("No valid triples found".to_string());
+            } else {
+                job.status = ImportJobStatus::Completed;
+            }
+            self.remove_upload(&job.id).await;
+        }
+        
+        match &job.error {
+            Some(error) => log::error!("Import job {} failed after {} triples: {}", job.id, job.inserted, error),
+            None => log::info!("Import job {} inserted {} triples", job.id, job.inserted),
+        }
+        job.updated_at = Utc::now();
+        self.save_or_warn(&job).await;
+    }
+    
+    /// Insert one chunk and log it as an import. Only the triples the chunk added are
+    /// undone, so the log keeps those already in the store as the chunk's old data.
+    async fn insert_chunk(&self, profile: &ProfileState, job: &ImportJob, triples: &[Triple]) -> Result<(), RdfEditorError> {
+        let target = (job.graph != "default").then(|| job.graph.clone());
+        let imported: Vec<Triple> = triples.iter()
+            .map(|triple| Triple { graph: triple.graph.clone().or_else(|| target.clone()), ..triple.clone() })
+            .collect();
+        let present = profile.sparql_client.present_triples(&imported).await?;
+        
+        let graphs = profile.sparql_client.add_quads_batch(triples, &job.graph).await?;
+        profile.invalidate_writes(&imported, &job.graph).await;
+        
+        profile.transaction_logger.log_batch_operation(
+            TransactionType::ImportData,
+            target.as_deref(),
+            Some(&present),
+            Some(&imported),
+            &format!("Import job {}: imported {} triples into {} graph(s)", job.id, triples.len(), graphs),
+            None,
+        ).await?;
+        Ok(())
+    }
+    
+    async fn save_or_warn(&self, job: &ImportJob) {
+        // Progress stays correct in memory even if the disk copy cannot be written
+        if let Err(e) = self.save(job).await {
+            log::warn!("Import job {}: {}", job.id, e);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Chunk {
+    /// Triples parsed up to the end of this chunk
+    parsed: usize,
+    triples: Vec<Triple>,
+}
+
+type ChunkSender = mpsc::Sender<Result<Chunk, RdfEditorError>>;
+
+/// Parse the upload at `path` and send it in chunks of `chunk_size`, leaving out the
+/// first `skip` triples and skolemizing with `skolem_prefix` if given. Runs on a blocking
+/// thread; stops early if the job stops listening.
+fn read_chunks(
+    path: &Path,
+    format: RdfFormat,
+    base: &str,
+    skolem_prefix: Option<&str>,
+    skip: usize,
+    chunk_size: usize,
+    tx: &ChunkSender,
+) {
+    let mut chunker = Chunker {
+        tx,
+        skolem_prefix,
+        skip,
+        chunk_size,
+        parsed: 0,
+        triples: Vec::new(),
+    };
+    
+    let result = parse_upload(path, format, base, chunk_size, &mut chunker);
+    // Everything parsed before an error is still inserted
+    if chunker.flush() {
+        if let Err(e) = result {
+            let _ = tx.blocking_send(Err(e));
+        }
+    }
+}
+
+fn parse_upload(
+    path: &Path,
+    format: RdfFormat,
+    base: &str,
+    lines_per_block: usize,
+    chunker: &mut Chunker,
+) -> Result<(), RdfEditorError> {
+    let read_error = |e: std::io::Error| RdfEditorError::Configuration(format!("Failed to read import upload: {}", e));
+    
+    match format {
+        // Line-based formats are parsed a block of lines at a time, so memory stays flat
+        RdfFormat::NTriples | RdfFormat::NQuads => {
+            let syntax = if matches!(format, RdfFormat::NQuads) { Syntax::NQuads } else { Syntax::Turtle };
+            let mut reader = BufReader::new(std::fs::File::open(path).map_err(read_error)?);
+            let mut state = TurtleParser::new("", Some(base), syntax);
+            loop {
+                let mut block = String::new();
+                for _ in 0..lines_per_block {
+                    if reader.read_line(&mut block).map_err(read_error)? == 0 {
+                        break;
+                    }
+                }
+                if block.is_empty() {
+                    return Ok(());
+                }
+                
+                let mut parser = state.continue_with(&block);
+                for triple in parser.by_ref() {
+                    if !chunker.push(triple?) {
+                        return Ok(());
+                    }
+                }
+                state = parser.continue_with("");
+            }
+        },
+        RdfFormat::Turtle | RdfFormat::TriG => {
+            let syntax = if matches!(format, RdfFormat::TriG) { Syntax::TriG } else { Syntax::Turtle };
+            let text = std::fs::read_to_string(path).map_err(read_error)?;
+            for triple in TurtleParser::new(&text, Some(base), syntax) {
+                if !chunker.push(triple?) {
+                    return Ok(());
+                }
+            }
+            Ok(())
+        },
+        // Tree formats have to be parsed whole, but are still inserted in chunks
+        RdfFormat::JsonLd | RdfFormat::RdfXml => {
+            let text = std::fs::read_to_string(path).map_err(read_error)?;
+            let triples = if matches!(format, RdfFormat::JsonLd) {
+                jsonld::parse(&text, Some(base))?
+            } else {
+                rdfxml::parse(&text, Some(base))?
+            };
+            for triple in triples {
+                if !chunker.push(triple) {
+                    return Ok(());
+                }
+            }
+            Ok(())
+        },
+    }
+}
+
+struct Chunker<'a> {
+    tx: &'a ChunkSender,
+    skolem_prefix: Option<&'a str>,
+    skip: usize,
+    chunk_size: usize,
+    parsed: usize,
+    triples: Vec<Triple>,
+}
+
+impl Chunker<'_> {
+    /// Add a parsed triple; false once the job has stopped listening
+    fn push(&mut self, triple: Triple) -> bool {
+        self.parsed += 1;
+        if self.parsed <= self.skip {
+            return true;
+        }
+        
+        self.triples.push(match self.skolem_prefix {
+            Some(prefix) => skolemize(triple, prefix),
+            None => triple,
+        });
+        self.triples.len() < self.chunk_size || self.flush()
+    }
+    
+    fn flush(&mut self) -> bool {
+        if self.triples.is_empty() {
+            return true;
+        }
+        let chunk = Chunk {
+            parsed: self.parsed,
+            triples: std::mem::take(&mut self.triples),
+        };
+        self.tx.blocking_send(Ok(chunk)).is_ok()
+    }
+}
+
+/// Blank nodes cannot be shared between separate INSERT DATA requests, so a job that
+/// asks for it replaces them with IRIs unique to the job. Parser labels are assigned in
+/// document order, which keeps the IRIs stable when a job is resumed.
+fn skolemize(mut triple: Triple, prefix: &str) -> Triple {
+    if let Some(label) = triple.subject.strip_prefix("_:") {
+        triple.subject = format!("{}{}", prefix, label);
+    }
+    if let RdfNode::Blank { value } = &triple.object {
+        triple.object = RdfNode::Uri { value: format!("{}{}", prefix, value) };
+    }
+    if let Some(label) = triple.graph.as_deref().and_then(|graph| graph.strip_prefix("_:")) {
+        triple.graph = Some(format!("{}{}", prefix, label));
+    }
+    triple
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    
+    fn collect(path: &Path, format: RdfFormat, skip: usize, chunk_size: usize) -> Vec<Result<Chunk, RdfEditorError>> {
+        let (tx, mut rx) = mpsc::channel(16);
+        read_chunks(path, format, "http://example.com/", Some("urn:test:"), skip, chunk_size, &tx);
+        drop(tx);
+        let mut chunks = Vec::new();
+        while let Ok(chunk) = rx.try_recv() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+    
+    #[test]
+    fn test_line_formats_are_chunked_and_resume_after_committed_triples() {
+        let path = std::env::temp_dir().join(format!("{}.upload", Uuid::new_v4()));
+        std::fs::write(&path, concat!(
+            "_:a <http://example.com/p> \"1\" .\n",
+            "<http://example.com/s> <http://example.com/p> \"2\" .\n",
+            "<http://example.com/s> <http://example.com/p> \"3\" .\n",
+            "<http://example.com/s> <http://example.com/p> \"4\" .\n",
+            "<http://example.com/s> <http://example.com/ref> _:a .\n",
+            "<http://example.com/s> <http://example.com/p> .\n",
+        )).unwrap();
+        
+        let chunks = collect(&path, RdfFormat::NTriples, 0, 2);
+        let sizes: Vec<(usize, usize)> = chunks.iter()
+            .filter_map(|chunk| chunk.as_ref().ok())
+            .map(|chunk| (chunk.parsed, chunk.triples.len()))
+            .collect();
+        assert_eq!(sizes, vec![(2, 2), (4, 2), (5, 1)]);
+        let error = chunks.last().unwrap().as_ref().unwrap_err().to_string();
+        assert!(error.contains("syntax error at line 6"), "{}", error);
+        
+        // The blank node is shared across chunks through one skolem IRI
+        let first = &chunks[0].as_ref().unwrap().triples[0];
+        let last = &chunks[2].as_ref().unwrap().triples[0];
+        assert!(first.subject.starts_with("urn:test:"));
+        assert_eq!(last.object, RdfNode::Uri { value: first.subject.clone() });
+        
+        // Resuming after three committed triples picks up the fourth, with the same IRI
+        let resumed = collect(&path, RdfFormat::NTriples, 3, 2);
+        let resumed = resumed[0].as_ref().unwrap();
+        assert_eq!(resumed.parsed, 5);
+        assert_eq!(resumed.triples.len(), 2);
+        assert_eq!(resumed.triples[1], *last);
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_blank_nodes_are_kept_unless_the_job_skolemizes() {
+        let path = std::env::temp_dir().join(format!("{}.upload", Uuid::new_v4()));
+        std::fs::write(&path, "_:a <http://example.com/p> _:b .\n").unwrap();
+        
+        let (tx, mut rx) = mpsc::channel(16);
+        read_chunks(&path, RdfFormat::NTriples, "http://example.com/", None, 0, 10, &tx);
+        let triple = &rx.try_recv().unwrap().unwrap().triples[0];
+        assert!(triple.subject.starts_with("_:"));
+        assert!(matches!(triple.object, RdfNode::Blank { .. }));
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reload_marks_interrupted_jobs_failed() {
+        let dir = std::env::temp_dir().join(format!("imports-{}", Uuid::new_v4()));
+        let dir = dir.to_str().unwrap();
+        let mut job = ImportJob::new("job-1".to_string(), DEFAULT_PROFILE.to_string(), "http://example.com/g".to_string(), RdfFormat::Turtle, None, 10);
+        job.inserted = 20;
+        
+        let manager = ImportJobManager::new(dir).await.unwrap();
+        manager.save(&job).await.unwrap();
+        drop(manager);
+        
+        let reloaded = ImportJobManager::new(dir).await.unwrap();
+        let job = reloaded.get("job-1").await.unwrap();
+        assert_eq!(job.status, ImportJobStatus::Failed);
+        assert_eq!(job.inserted, 20);
+        assert_eq!(job.error.as_deref(), Some("Interrupted by server restart"));
+        
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reload_removes_uploads_no_job_can_resume() {
+        let dir = std::env::temp_dir().join(format!("imports-{}", Uuid::new_v4()));
+        let dir = dir.to_str().unwrap();
+        let manager = ImportJobManager::new(dir).await.unwrap();
+        let recent = ImportJob::new("recent".to_string(), DEFAULT_PROFILE.to_string(), "http://example.com/g".to_string(), RdfFormat::Turtle, None, 10);
+        let mut expired = ImportJob { id: "expired".to_string(), ..recent.clone() };
+        expired.updated_at = Utc::now() - chrono::Duration::hours(FAILED_UPLOAD_RETENTION_HOURS + 1);
+        for job in [&recent, &expired] {
+            manager.save(job).await.unwrap();
+        }
+        for id in ["recent", "expired", "orphan"] {
+            std::fs::write(manager.upload_path(id), "").unwrap();
+        }
+        drop(manager);
+        
+        let reloaded = ImportJobManager::new(dir).await.unwrap();
+        assert!(reloaded.upload_path("recent").exists());
+        assert!(!reloaded.upload_path("expired").exists());
+        assert!(!reloaded.upload_path("orphan").exists());
+        
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -6,27 +6,34 @@ mod handlers;
 mod validation;
 mod transaction_log;
 mod cache;
+mod formats;
+mod import_jobs;
+mod profiles;
+mod dialect;
+mod store;
+mod transactions;
+mod conflicts;
+mod graph_nodes;
+mod single_flight;
 
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{dev::Service, middleware, web, App, HttpServer};
 use actix_cors::Cors;
 use actix_files as fs;
 use log::info;
 use std::sync::Arc;
 
 use crate::config::Config;
-use crate::sparql::SparqlClient;
-use crate::transaction_log::TransactionLogger;
-use crate::cache::{LabelCache, TypeCache, QueryCache, GraphCacheManager};
-use crate::handlers::{health, query, update, batch, import_export, transaction};
+use crate::import_jobs::ImportJobManager;
+use crate::profiles::ProfileRegistry;
+use crate::handlers::{health, cache as cache_handlers, query, update, batch, import_export, transaction, namespaces, profiles as profile_handlers};
 
+/// Shared application state. Everything tied to one SPARQL store (client, caches,
+/// transaction log) lives in its endpoint profile; handlers reach it through the
+/// `profiles::Profile` extractor.
 pub struct AppState {
     pub config: Arc<Config>,
-    pub sparql_client: Arc<SparqlClient>,
-    pub transaction_logger: Arc<TransactionLogger>,
-    pub label_cache: Arc<LabelCache>,
-    pub type_cache: Arc<TypeCache>,
-    pub query_cache: Arc<QueryCache>,
-    pub graph_cache: Arc<GraphCacheManager>,
+    pub profiles: Arc<ProfileRegistry>,
+    pub import_jobs: Arc<ImportJobManager>,
 }
 
 #[actix_web::main]
@@ -39,48 +46,44 @@ async fn main() -> std::io::Result<()> {
     let config = Arc::new(Config::from_env().expect("Failed to load configuration"));
     info!("Starting RDF Editor on {}:{}", config.host, config.port);
 
-    let sparql_client = Arc::new(
-        SparqlClient::new(&config)
-        .expect("Failed to create SPARQL client")
+    // One SPARQL client, cache set and transaction log per endpoint profile
+    let profiles = Arc::new(
+        ProfileRegistry::new(&config)
+            .await
+            .expect("Failed to set up endpoint profiles")
     );
+    for profile in profiles.iter() {
+        info!("Endpoint profile {}: {}", profile.config.name, profile.config.sparql_endpoint);
+    }
     
-    let transaction_logger = Arc::new(
-        TransactionLogger::new("transaction_log.jsonl")
+    let import_jobs = Arc::new(
+        ImportJobManager::new(&config.import_dir)
             .await
-            .expect("Failed to create transaction logger")
+            .expect("Failed to open import directory")
     );
     
-    // Initialize caches with TTL from config
-    let label_cache = Arc::new(LabelCache::new(config.cache_ttl_seconds as i64));
-    let type_cache = Arc::new(TypeCache::new(config.cache_ttl_seconds as i64));
-    let query_cache = Arc::new(QueryCache::new(config.cache_ttl_seconds as i64));
-    let graph_cache = Arc::new(GraphCacheManager::new(config.cache_ttl_seconds as i64));
-    
     // Clone for the cleanup task before moving into AppState
-    let cache_cleanup_label = label_cache.clone();
-    let cache_cleanup_type = type_cache.clone();
-    let cache_cleanup_query = query_cache.clone();
-    let cache_cleanup_graph = graph_cache.clone();
+    let cache_cleanup_profiles = profiles.clone();
 
     let app_state = web::Data::new(AppState { 
         config,
-        sparql_client,
-        transaction_logger,
-        label_cache,
-        type_cache,
-        query_cache,
-        graph_cache,
+        profiles,
+        import_jobs,
     });
     
-    // Spawn a task to periodically clean up expired cache entries
+    // Spawn a task to periodically clean up expired cache entries and idle transactions,
+    // and rotate and prune the transaction logs
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(600)); // Every 10 minutes
         loop {
             interval.tick().await;
-            cache_cleanup_label.cleanup_expired().await;
-            cache_cleanup_type.cleanup_expired().await;
-            cache_cleanup_query.cleanup_expired().await;
-            cache_cleanup_graph.cleanup_all().await;
+            for profile in cache_cleanup_profiles.iter() {
+                profile.cleanup_caches().await;
+                profile.transactions.expire_idle().await;
+                if let Err(e) = profile.transaction_logger.maintain().await {
+                    log::error!("Transaction log maintenance failed for profile {}: {}", profile.config.name, e);
+                }
+            }
         }
     });
 
@@ -101,44 +104,830 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::PayloadConfig::new(app_state.config.max_payload_size))
             .wrap(cors)
             .wrap(middleware::Logger::default())
-            .service(
-                web::scope("/api")
-                    // Health check
-                    .route("/health", web::get().to(health::check))
-                    
-                    // Query endpoints
-                    .route("/query", web::post().to(query::execute))
-                    .route("/graph/{graph_name}/triples", web::get().to(query::get_triples))
-                    .route("/graph/{graph_name}/triples/paginated", web::get().to(query::get_triples_paginated))
-                    .route("/graph/{graph_name}/resources", web::get().to(query::get_resources_with_labels))
-                    
-                    // Update endpoints
-                    .route("/graph/{graph_name}/triple", web::post().to(update::add_triple))
-                    .route("/graph/{graph_name}/triple", web::delete().to(update::delete_triple))
-                    .route("/graph/{graph_name}/triple/replace", web::put().to(update::replace_triple))
-                    
-                    // Batch operations
-                    .route("/graph/{graph_name}/triples/batch", web::post().to(batch::add_triples_batch))
-                    .route("/graph/{graph_name}/triples/batch", web::delete().to(batch::delete_triples_batch))
-                    
-                    // Import/Export
-                    .route("/export", web::get().to(import_export::export_graph))
-                    .route("/import", web::post().to(import_export::import_data))
-                    
-                    // Transaction history
-                    .route("/transactions", web::get().to(transaction::get_recent_transactions))
-                    .route("/transaction/{id}/undo", web::post().to(transaction::undo_transaction))
-                    
-                    // Graph visualization endpoints (NEW)
-                    .route("/graph/search", web::post().to(query::search_graph_nodes))
-                    .route("/graph/expand", web::post().to(query::expand_graph_node))
-                    .route("/graph/node/{uri}", web::get().to(query::get_node_info))
-                    .route("/graph/path", web::post().to(query::find_path_between_nodes))
-            )
+            // /api/profiles/{name}/... reaches the same routes against another store
+            .wrap_fn(|mut req, srv| {
+                profiles::strip_profile_prefix(&mut req);
+                srv.call(req)
+            })
+            .configure(configure_api)
             // Serve static files from the "static" directory
             .service(fs::Files::new("/", "./static").index_file("index.html"))
     })
     .bind(bind_address)?
     .run()
     .await
-}
\ No newline at end of file
+}
+
+/// The /api routes, shared by the server and the route tests
+fn configure_api(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            // Endpoint profiles; pick one per request with the X-Endpoint-Profile
+            // header or the /api/profiles/{name}/ prefix
+            .route("/profiles", web::get().to(profile_handlers::list_profiles))
+            .route("/namespaces", web::get().to(namespaces::list_namespaces))
+            
+            // Health check
+            .route("/health", web::get().to(health::check))
+            .route("/cache/stats", web::get().to(cache_handlers::stats))
+            .route("/cache/flush", web::post().to(cache_handlers::flush))
+            
+            // Query endpoints
+            .route("/query", web::post().to(query::execute))
+            .route("/graph/{graph_name}/triples", web::get().to(query::get_triples))
+            .route("/graph/{graph_name}/triples/paginated", web::get().to(query::get_triples_paginated))
+            .route("/graph/{graph_name}/resources", web::get().to(query::get_resources_with_labels))
+            
+            // Update endpoints
+            .route("/graph/{graph_name}/triple", web::post().to(update::add_triple))
+            .route("/graph/{graph_name}/triple", web::delete().to(update::delete_triple))
+            .route("/graph/{graph_name}/triple/replace", web::put().to(update::replace_triple))
+            
+            // Batch operations
+            .route("/graph/{graph_name}/triples/batch", web::post().to(batch::add_triples_batch))
+            .route("/graph/{graph_name}/triples/batch", web::delete().to(batch::delete_triples_batch))
+            
+            // Import/Export
+            .route("/export", web::get().to(import_export::export_graph))
+            .route("/import", web::post().to(import_export::import_data))
+            .route("/import/jobs", web::post().to(import_export::start_import_job))
+            .route("/import/jobs", web::get().to(import_export::list_import_jobs))
+            .route("/import/jobs/{id}", web::get().to(import_export::get_import_job))
+            .route("/import/jobs/{id}/resume", web::post().to(import_export::resume_import_job))
+            
+            // Transaction history
+            .route("/transactions", web::get().to(transaction::get_recent_transactions))
+            .route("/transactions/compact", web::post().to(transaction::compact_transactions))
+            .route("/transactions/changeset", web::get().to(transaction::export_changeset))
+            .route("/transactions/changeset", web::post().to(transaction::import_changeset))
+            .route("/transaction/{id}/undo", web::post().to(transaction::undo_transaction))
+            .route("/transaction/{id}/redo", web::post().to(transaction::redo_transaction))
+            
+            // Explicit transactions: stage writes with the X-Transaction-Id header
+            .route("/transaction/begin", web::post().to(transaction::begin_transaction))
+            .route("/transaction/{id}", web::get().to(transaction::get_transaction))
+            .route("/transaction/{id}/commit", web::post().to(transaction::commit_transaction))
+            .route("/transaction/{id}/rollback", web::post().to(transaction::rollback_transaction))
+            
+            // Graph visualization endpoints (NEW)
+            .route("/graph/search", web::post().to(query::search_graph_nodes))
+            .route("/graph/expand", web::post().to(query::expand_graph_node))
+            .route("/graph/node/{uri}", web::get().to(query::get_node_info))
+            .route("/graph/path", web::post().to(query::find_path_between_nodes))
+            
+            // Whole-graph operations; after the fixed /graph/* routes so those still match
+            .route("/graph/{graph_name}", web::put().to(import_export::replace_graph))
+            .route("/graph/{graph_name}", web::delete().to(update::drop_graph))
+            .route("/graph/{graph_name}/restore", web::post().to(transaction::restore_graph))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+    use serde_json::{json, Value};
+    use crate::config::{EndpointProfile, DEFAULT_PROFILE};
+    use crate::dialect::StoreDialect;
+    use crate::profiles::ProfileState;
+    use crate::transactions::TRANSACTION_HEADER;
+    
+    const GRAPH: &str = "http%3A%2F%2Fexample.org%2Fg";
+    
+    /// Application state with one profile on a fresh in-memory store; the
+    /// transaction log and import jobs live in a temporary directory
+    async fn state() -> web::Data<AppState> {
+        let dir = std::env::temp_dir().join(format!("rdf-editor-routes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        
+        let profile = EndpointProfile {
+            name: DEFAULT_PROFILE.to_string(),
+            sparql_endpoint: "memory:".to_string(),
+            sparql_update_endpoint: None,
+            sparql_username: None,
+            sparql_password: None,
+            sparql_password_hashed: None,
+            verify_ssl: true,
+            ca_cert: None,
+            graph_store_endpoint: None,
+            dialect: StoreDialect::Memory,
+            full_text_search: false,
+            graph_max_nodes: 500,
+            graph_max_edges: 1000,
+            graph_expansion_limit: 50,
+            graph_search_limit: 10,
+        };
+        let config = Config {
+            sparql_endpoint: profile.sparql_endpoint.clone(),
+            sparql_update_endpoint: None,
+            sparql_username: None,
+            sparql_password: None,
+            sparql_password_hashed: None,
+            sparql_ca_cert: None,
+            graph_store_endpoint: None,
+            sparql_dialect: StoreDialect::Memory,
+            full_text_search: false,
+            profiles: vec![profile.clone()],
+            default_profile: DEFAULT_PROFILE.to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            cors_enabled: false,
+            max_payload_size: 1024 * 1024,
+            verify_ssl: true,
+            timeout_seconds: 30,
+            max_retries: 0,
+            cache_enabled: true,
+            cache_ttl_seconds: 300,
+            cache_max_entries: 1000,
+            cache_max_bytes: 0,
+            cache_dir: None,
+            enable_auth: false,
+            api_key: None,
+            allowed_origins: vec!["*".to_string()],
+            graph_max_nodes: 500,
+            graph_max_edges: 1000,
+            graph_expansion_limit: 50,
+            graph_search_limit: 10,
+            export_page_size: 100,
+            import_chunk_size: 100,
+            import_max_document_size: 1024,
+            import_dir: dir.join("imports").to_string_lossy().into_owned(),
+            transaction_log_dir: dir.to_string_lossy().into_owned(),
+            transaction_log_max_bytes: 0,
+            transaction_log_max_age_hours: 0,
+            transaction_log_retain_segments: 0,
+            transaction_log_retain_days: 0,
+        };
+        
+        let profile_state = ProfileState::new(&config, &profile).await.unwrap();
+        let import_jobs = ImportJobManager::new(&config.import_dir).await.unwrap();
+        
+        web::Data::new(AppState {
+            profiles: Arc::new(ProfileRegistry::from_profiles(vec![Arc::new(profile_state)], DEFAULT_PROFILE)),
+            import_jobs: Arc::new(import_jobs),
+            config: Arc::new(config),
+        })
+    }
+    
+    macro_rules! app {
+        () => {
+            test::init_service(App::new().app_data(state().await).configure(configure_api)).await
+        };
+    }
+    
+    fn triple(object: &str) -> Value {
+        json!({
+            "subject": "http://example.org/alice",
+            "predicate": "http://example.org/knows",
+            "object": {"type": "uri", "value": object}
+        })
+    }
+    
+    #[actix_rt::test]
+    async fn test_add_list_and_delete_triples() {
+        let app = app!();
+        
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        let added = test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/bob")).send_request(&app).await;
+        assert!(added.status().is_success());
+        
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(triples[0]["object"]["value"], "http://example.org/bob");
+        
+        let deleted = test::TestRequest::delete().uri(&uri).set_json(triple("http://example.org/bob")).send_request(&app).await;
+        assert!(deleted.status().is_success());
+        
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert!(triples.is_empty());
+    }
+    
+    #[actix_rt::test]
+    async fn test_import_query_and_export() {
+        let app = app!();
+        
+        let imported = test::TestRequest::post().uri("/api/import").set_json(json!({
+            "graph": "http://example.org/g",
+            "format": "turtle",
+            "data": "@prefix ex: <http://example.org/> .\nex:alice ex:name \"Alice\" ; ex:knows ex:bob .\n"
+        })).send_request(&app).await;
+        assert!(imported.status().is_success());
+        
+        let response: Value = test::call_and_read_body_json(&app, test::TestRequest::post().uri("/api/query").set_json(json!({
+            "query": "SELECT ?name WHERE { GRAPH <http://example.org/g> { ?s <http://example.org/name> ?name } }"
+        })).to_request()).await;
+        assert_eq!(response["results"][0]["results"]["bindings"][0]["name"]["value"], "Alice");
+        
+        let exported = test::call_and_read_body(&app,
+            test::TestRequest::get().uri(&format!("/api/export?graph={}&format=ntriples", GRAPH)).to_request()).await;
+        let exported = String::from_utf8(exported.to_vec()).unwrap();
+        assert!(exported.contains("<http://example.org/alice> <http://example.org/knows> <http://example.org/bob> ."));
+        assert!(exported.contains("\"Alice\""));
+    }
+    
+    #[actix_rt::test]
+    async fn test_undo_and_drop_graph() {
+        let app = app!();
+        
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/bob")).send_request(&app).await;
+        test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/carol")).send_request(&app).await;
+        
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let transactions = page["entries"].as_array().unwrap();
+        let latest = transactions.iter()
+            .find(|t| t["record"]["new_data"][0]["object"]["value"] == "http://example.org/carol")
+            .unwrap();
+        let undone = test::TestRequest::post()
+            .uri(&format!("/api/transaction/{}/undo", latest["record"]["id"].as_str().unwrap()))
+            .send_request(&app).await;
+        assert!(undone.status().is_success());
+        
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(triples[0]["object"]["value"], "http://example.org/bob");
+        
+        let dropped = test::TestRequest::delete().uri(&format!("/api/graph/{}", GRAPH)).send_request(&app).await;
+        assert!(dropped.status().is_success());
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert!(triples.is_empty());
+    }
+    
+    #[actix_rt::test]
+    async fn test_transaction_stages_and_commits_as_one_record() {
+        let app = app!();
+        
+        let begun: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::post().uri("/api/transaction/begin").to_request()).await;
+        let id = begun["id"].as_str().unwrap().to_string();
+        
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        for object in ["http://example.org/bob", "http://example.org/carol"] {
+            let staged = test::TestRequest::post().uri(&uri)
+                .insert_header((TRANSACTION_HEADER, id.as_str()))
+                .set_json(triple(object))
+                .send_request(&app).await;
+            assert!(staged.status().is_success());
+        }
+        test::TestRequest::delete().uri(&uri)
+            .insert_header((TRANSACTION_HEADER, id.as_str()))
+            .set_json(triple("http://example.org/carol"))
+            .send_request(&app).await;
+        
+        // Nothing is written before the commit, but the transaction sees its own changes
+        let triples_uri = format!("/api/graph/{}/triples", GRAPH);
+        let stored: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&triples_uri).to_request()).await;
+        assert!(stored.is_empty());
+        let staged: Vec<Value> = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&triples_uri)
+            .insert_header((TRANSACTION_HEADER, id.as_str())).to_request()).await;
+        assert_eq!(staged.len(), 1);
+        
+        let committed = test::TestRequest::post().uri(&format!("/api/transaction/{}/commit", id)).send_request(&app).await;
+        assert!(committed.status().is_success());
+        
+        let stored: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&triples_uri).to_request()).await;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0]["object"]["value"], "http://example.org/bob");
+        
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let transactions = page["entries"].as_array().unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0]["record"]["transaction_type"], "Commit");
+        
+        let closed = test::TestRequest::post().uri(&format!("/api/transaction/{}/commit", id)).send_request(&app).await;
+        assert_eq!(closed.status(), 404);
+    }
+    
+    #[actix_rt::test]
+    async fn test_rollback_discards_staged_changes() {
+        let app = app!();
+        
+        let begun: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::post().uri("/api/transaction/begin").to_request()).await;
+        let id = begun["id"].as_str().unwrap().to_string();
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+            .insert_header((TRANSACTION_HEADER, id.as_str()))
+            .set_json(triple("http://example.org/bob"))
+            .send_request(&app).await;
+        
+        let rolled_back = test::TestRequest::post().uri(&format!("/api/transaction/{}/rollback", id)).send_request(&app).await;
+        assert!(rolled_back.status().is_success());
+        
+        let stored: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert!(stored.is_empty());
+        let gone = test::TestRequest::get().uri(&format!("/api/transaction/{}", id)).send_request(&app).await;
+        assert_eq!(gone.status(), 404);
+    }
+    
+    #[actix_rt::test]
+    async fn test_batches_are_staged_and_other_writes_refused_in_a_transaction() {
+        let app = app!();
+        let begun: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::post().uri("/api/transaction/begin").to_request()).await;
+        let id = begun["id"].as_str().unwrap().to_string();
+        
+        let batch = test::TestRequest::post().uri(&format!("/api/graph/{}/triples/batch", GRAPH))
+            .insert_header((TRANSACTION_HEADER, id.as_str()))
+            .set_json(json!({"triples": [triple("http://example.org/bob"), triple("http://example.org/carol")]}))
+            .send_request(&app).await;
+        assert!(batch.status().is_success());
+        let stored: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert!(stored.is_empty());
+        let staged: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/transaction/{}", id)).to_request()).await;
+        assert_eq!(staged["insertions"].as_array().unwrap().len(), 2);
+        
+        let dropped = test::TestRequest::delete().uri(&format!("/api/graph/{}", GRAPH))
+            .insert_header((TRANSACTION_HEADER, id.as_str()))
+            .send_request(&app).await;
+        assert_eq!(dropped.status(), 400);
+        let imported = test::TestRequest::post().uri("/api/import")
+            .insert_header((TRANSACTION_HEADER, id.as_str()))
+            .set_json(json!({
+                "graph": "http://example.org/g",
+                "format": "ntriples",
+                "data": "<http://example.org/alice> <http://example.org/knows> <http://example.org/dave> .\n"
+            }))
+            .send_request(&app).await;
+        assert_eq!(imported.status(), 400);
+    }
+    
+    /// Id of the most recent log record of a transaction type, which must be undoable
+    macro_rules! latest {
+        ($app:expr, $transaction_type:expr) => {{
+            let page: Value = test::call_and_read_body_json(&$app,
+                test::TestRequest::get().uri("/api/transactions").to_request()).await;
+            let transactions = page["entries"].as_array().unwrap();
+            let entry = transactions.iter().find(|t| t["record"]["transaction_type"] == $transaction_type).unwrap();
+            assert_eq!(entry["can_undo"], true);
+            entry["record"]["id"].as_str().unwrap().to_string()
+        }};
+    }
+    
+    #[actix_rt::test]
+    async fn test_undo_drop_import_and_batch_delete() {
+        let app = app!();
+        let triples_uri = format!("/api/graph/{}/triples", GRAPH);
+        let count = || async {
+            let triples: Vec<Value> = test::call_and_read_body_json(&app,
+                test::TestRequest::get().uri(&triples_uri).to_request()).await;
+            triples.len()
+        };
+        
+        test::TestRequest::post().uri("/api/graph/http%3A%2F%2Fexample.org%2Fg/triple")
+            .set_json(triple("http://example.org/bob")).send_request(&app).await;
+        test::TestRequest::post().uri("/api/import").set_json(json!({
+            "graph": "http://example.org/g",
+            "format": "ntriples",
+            "data": "<http://example.org/alice> <http://example.org/knows> <http://example.org/bob> .\n\
+                     <http://example.org/alice> <http://example.org/knows> <http://example.org/carol> .\n"
+        })).send_request(&app).await;
+        assert_eq!(count().await, 2);
+        
+        test::TestRequest::delete().uri(&format!("/api/graph/{}", GRAPH)).send_request(&app).await;
+        assert_eq!(count().await, 0);
+        let undo = format!("/api/transaction/{}/undo", latest!(app, "DropGraph"));
+        assert!(test::TestRequest::post().uri(&undo).send_request(&app).await.status().is_success());
+        assert_eq!(count().await, 2);
+        
+        // The import added only carol; bob was there before it
+        let undo = format!("/api/transaction/{}/undo", latest!(app, "ImportData"));
+        assert!(test::TestRequest::post().uri(&undo).send_request(&app).await.status().is_success());
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&triples_uri).to_request()).await;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(triples[0]["object"]["value"], "http://example.org/bob");
+        
+        let deleted = test::TestRequest::delete().uri(&format!("/api/graph/{}/triples/batch", GRAPH))
+            .set_json(json!({"triples": [triple("http://example.org/bob")]}))
+            .send_request(&app).await;
+        assert!(deleted.status().is_success());
+        assert_eq!(count().await, 0);
+        let undo = format!("/api/transaction/{}/undo", latest!(app, "DeleteTriplesBatch"));
+        assert!(test::TestRequest::post().uri(&undo).send_request(&app).await.status().is_success());
+        assert_eq!(count().await, 1);
+    }
+    
+    #[actix_rt::test]
+    async fn test_import_job_chunks_are_logged_and_undone() {
+        let app = app!();
+        let triples_uri = format!("/api/graph/{}/triples", GRAPH);
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+            .set_json(triple("http://example.org/bob")).send_request(&app).await;
+        
+        let job: Value = test::call_and_read_body_json(&app, test::TestRequest::post()
+            .uri(&format!("/api/import/jobs?graph={}&format=ntriples&chunk_size=1", GRAPH))
+            .set_payload("<http://example.org/alice> <http://example.org/knows> <http://example.org/bob> .\n\
+                          <http://example.org/alice> <http://example.org/knows> <http://example.org/carol> .\n")
+            .to_request()).await;
+        assert_eq!(job["skolemize"], false);
+        let job_uri = format!("/api/import/jobs/{}", job["id"].as_str().unwrap());
+        for _ in 0..100 {
+            let job: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&job_uri).to_request()).await;
+            if job["status"] != "running" {
+                assert_eq!(job["status"], "completed");
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        
+        // One record per chunk; undoing both leaves bob, which was there before the job
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions?type=ImportData").to_request()).await;
+        let imports = page["entries"].as_array().unwrap();
+        assert_eq!(imports.len(), 2);
+        for entry in imports {
+            let undo = format!("/api/transaction/{}/undo", entry["record"]["id"].as_str().unwrap());
+            assert!(test::TestRequest::post().uri(&undo).send_request(&app).await.status().is_success());
+        }
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&triples_uri).to_request()).await;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(triples[0]["object"]["value"], "http://example.org/bob");
+    }
+    
+    #[actix_rt::test]
+    async fn test_undo_deletes_blank_node_triples_by_matching() {
+        let app = app!();
+        let triples_uri = format!("/api/graph/{}/triples", GRAPH);
+        // Match the blank node triples' patterns, but through an IRI
+        let kept = [
+            json!({"subject": "http://example.org/dan", "predicate": "http://example.org/knows", "object": {"type": "uri", "value": "http://example.org/bob"}}),
+            triple("http://example.org/dan"),
+        ];
+        for triple in &kept {
+            test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+                .set_json(triple).send_request(&app).await;
+        }
+        let added = test::TestRequest::post().uri(&format!("/api/graph/{}/triples/batch", GRAPH))
+            .set_json(json!({"triples": [
+                {"subject": "_:x", "predicate": "http://example.org/knows", "object": {"type": "uri", "value": "http://example.org/bob"}},
+                {"subject": "http://example.org/alice", "predicate": "http://example.org/knows", "object": {"type": "blank", "value": "x"}},
+                triple("http://example.org/carol")
+            ]}))
+            .send_request(&app).await;
+        assert!(added.status().is_success());
+        
+        // The store labelled the blank node itself, so the logged label matches nothing
+        let undo = format!("/api/transaction/{}/undo", latest!(app, "AddTriplesBatch"));
+        let refused: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::post().uri(&undo).to_request()).await;
+        let kinds: Vec<&Value> = refused["conflicts"].as_array().unwrap().iter().map(|c| &c["kind"]).collect();
+        assert_eq!(kinds, ["unchecked", "unchecked"]);
+        let undone = test::TestRequest::post().uri(&undo).set_json(json!({"on_conflict": "force"})).send_request(&app).await;
+        assert!(undone.status().is_success());
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&triples_uri).to_request()).await;
+        assert_eq!(triples.len(), 2, "{:?}", triples);
+        assert!(triples.iter().all(|t| t["subject"] == "http://example.org/dan" || t["object"]["value"] == "http://example.org/dan"), "{:?}", triples);
+    }
+    
+    #[actix_rt::test]
+    async fn test_import_job_rejects_oversized_documents_parsed_in_memory() {
+        let app = app!();
+        let line = "<http://example.org/alice> <http://example.org/knows> <http://example.org/bob> .\n";
+        let document = line.repeat(20);
+        
+        let rejected = test::TestRequest::post()
+            .uri(&format!("/api/import/jobs?graph={}&format=turtle", GRAPH))
+            .set_payload(document.clone())
+            .send_request(&app).await;
+        assert_eq!(rejected.status(), 400);
+        let body = String::from_utf8(test::read_body(rejected).await.to_vec()).unwrap();
+        assert!(body.contains("IMPORT_MAX_DOCUMENT_SIZE"), "{}", body);
+        
+        let accepted = test::TestRequest::post()
+            .uri(&format!("/api/import/jobs?graph={}&format=ntriples", GRAPH))
+            .set_payload(document)
+            .send_request(&app).await;
+        assert_eq!(accepted.status(), 202);
+    }
+    
+    #[actix_rt::test]
+    async fn test_import_logs_only_the_imported_triples_already_present() {
+        let app = app!();
+        for object in ["http://example.org/bob", "http://example.org/dave"] {
+            test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+                .set_json(triple(object)).send_request(&app).await;
+        }
+        let imported = test::TestRequest::post().uri("/api/import").set_json(json!({
+            "graph": "http://example.org/g",
+            "format": "ntriples",
+            "data": "<http://example.org/alice> <http://example.org/knows> <http://example.org/bob> .\n\
+                     <http://example.org/alice> <http://example.org/knows> <http://example.org/carol> .\n"
+        })).send_request(&app).await;
+        assert!(imported.status().is_success());
+        
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions?type=ImportData").to_request()).await;
+        let old_data = page["entries"][0]["record"]["old_data"].as_array().unwrap();
+        assert_eq!(old_data.len(), 1);
+        assert_eq!(old_data[0]["object"]["value"], "http://example.org/bob");
+    }
+    
+    #[actix_rt::test]
+    async fn test_drop_default_graph_snapshots_only_its_own_triples() {
+        let app = app!();
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+            .set_json(triple("http://example.org/bob")).send_request(&app).await;
+        test::TestRequest::post().uri("/api/graph/default/triple")
+            .set_json(triple("http://example.org/dave")).send_request(&app).await;
+        
+        let dropped = test::TestRequest::delete().uri("/api/graph/default").send_request(&app).await;
+        assert!(dropped.status().is_success());
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let drop = page["entries"].as_array().unwrap().iter()
+            .find(|t| t["record"]["transaction_type"] == "DropGraph")
+            .unwrap();
+        let old_data = drop["record"]["old_data"].as_array().unwrap();
+        assert_eq!(old_data.len(), 1);
+        assert_eq!(old_data[0]["object"]["value"], "http://example.org/dave");
+    }
+    
+    #[actix_rt::test]
+    async fn test_undo_redo_chain() {
+        let app = app!();
+        let triples_uri = format!("/api/graph/{}/triples", GRAPH);
+        let count = || async {
+            let triples: Vec<Value> = test::call_and_read_body_json(&app,
+                test::TestRequest::get().uri(&triples_uri).to_request()).await;
+            triples.len()
+        };
+        let app = &app;
+        let post = |uri: String| async move {
+            let response = test::TestRequest::post().uri(&uri).send_request(app).await;
+            let status = response.status();
+            let body: Value = test::read_body_json(response).await;
+            (status, body)
+        };
+        
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+            .set_json(triple("http://example.org/bob")).send_request(&app).await;
+        let added = latest!(app, "AddTriple");
+        
+        let (status, undo) = post(format!("/api/transaction/{}/undo", added)).await;
+        assert!(status.is_success());
+        assert_eq!(count().await, 0);
+        let (status, _) = post(format!("/api/transaction/{}/undo", added)).await;
+        assert_eq!(status, 400);
+        let undo_id = undo["record_id"].as_str().unwrap();
+        let (status, _) = post(format!("/api/transaction/{}/undo", undo_id)).await;
+        assert_eq!(status, 400);
+        
+        // The history pairs the undo with the transaction it reverses
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let transactions = page["entries"].as_array().unwrap();
+        assert_eq!(transactions[0]["record"]["transaction_type"], "Undo");
+        assert_eq!(transactions[0]["record"]["reverses"], added.as_str());
+        assert_eq!(transactions[1]["undone_by"], undo_id);
+        assert_eq!(transactions[1]["can_redo"], true);
+        
+        let (status, redo) = post(format!("/api/transaction/{}/redo", undo_id)).await;
+        assert!(status.is_success());
+        assert_eq!(count().await, 1);
+        let (status, _) = post(format!("/api/transaction/{}/redo", added)).await;
+        assert_eq!(status, 400);
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let transactions = page["entries"].as_array().unwrap();
+        assert_eq!(transactions[0]["record"]["reverses"], undo_id);
+        assert_eq!(transactions[0]["record"]["id"], redo["record_id"]);
+        
+        // Once redone, the original can be undone again
+        assert!(post(format!("/api/transaction/{}/undo", added)).await.0.is_success());
+        assert_eq!(count().await, 0);
+    }
+    
+    #[actix_rt::test]
+    async fn test_undo_reports_and_resolves_conflicts() {
+        let app = app!();
+        let replace = |old: &str, new: &str| test::TestRequest::put()
+            .uri(&format!("/api/graph/{}/triple/replace", GRAPH))
+            .set_json(json!({"old_triple": triple(old), "new_triple": triple(new)}));
+        
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH))
+            .set_json(triple("http://example.org/a")).send_request(&app).await;
+        replace("http://example.org/a", "http://example.org/b").send_request(&app).await;
+        let replaced = latest!(app, "ReplaceTriple");
+        // Someone else edits the value again
+        replace("http://example.org/b", "http://example.org/c").send_request(&app).await;
+        
+        let undo = format!("/api/transaction/{}/undo", replaced);
+        let response = test::TestRequest::post().uri(&undo).send_request(&app).await;
+        assert_eq!(response.status(), 409);
+        let body: Value = test::read_body_json(response).await;
+        let conflicts = body["conflicts"].as_array().unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0]["kind"], "missing");
+        assert_eq!(conflicts[0]["triple"]["object"]["value"], "http://example.org/b");
+        assert_eq!(conflicts[1]["kind"], "edited");
+        assert_eq!(conflicts[1]["triple"]["object"]["value"], "http://example.org/a");
+        
+        // Skipping the edited value leaves the later edit alone
+        let restored = conflicts[1]["triple"].clone();
+        let undone: Value = test::call_and_read_body_json(&app, test::TestRequest::post().uri(&undo).set_json(json!({
+            "on_conflict": "force",
+            "resolutions": [{"triple": restored, "resolution": "skip"}]
+        })).to_request()).await;
+        assert_eq!(undone["success"], true);
+        assert_eq!(undone["conflicts"][1]["resolution"], "skip");
+        let triples: Vec<Value> = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(triples[0]["object"]["value"], "http://example.org/c");
+    }
+    
+    #[actix_rt::test]
+    async fn test_restore_graph_to_a_transaction() {
+        let app = app!();
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        let objects = || async {
+            let triples: Vec<Value> = test::call_and_read_body_json(&app,
+                test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+            let mut objects: Vec<String> = triples.iter()
+                .map(|t| t["object"]["value"].as_str().unwrap().trim_start_matches("http://example.org/").to_string())
+                .collect();
+            objects.sort();
+            objects
+        };
+        
+        for object in ["a", "b"] {
+            test::TestRequest::post().uri(&uri)
+                .set_json(triple(&format!("http://example.org/{}", object))).send_request(&app).await;
+        }
+        let point = latest!(app, "AddTriple");
+        
+        // A bad edit after the point: drop a, add c, replace b with d
+        test::TestRequest::delete().uri(&uri).set_json(triple("http://example.org/a")).send_request(&app).await;
+        test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/c")).send_request(&app).await;
+        test::TestRequest::put().uri(&format!("/api/graph/{}/triple/replace", GRAPH))
+            .set_json(json!({"old_triple": triple("http://example.org/b"), "new_triple": triple("http://example.org/d")}))
+            .send_request(&app).await;
+        assert_eq!(objects().await, ["c", "d"]);
+        
+        let restore = format!("/api/graph/{}/restore", GRAPH);
+        let preview: Value = test::call_and_read_body_json(&app, test::TestRequest::post().uri(&restore)
+            .set_json(json!({"transaction_id": point, "preview": true})).to_request()).await;
+        assert_eq!(preview["transactions"].as_array().unwrap().len(), 3);
+        assert_eq!(preview["deletions"].as_array().unwrap().len(), 2);
+        assert_eq!(preview["insertions"].as_array().unwrap().len(), 2);
+        assert_eq!(objects().await, ["c", "d"]);
+        
+        let restored: Value = test::call_and_read_body_json(&app, test::TestRequest::post().uri(&restore)
+            .set_json(json!({"transaction_id": point})).to_request()).await;
+        assert_eq!(restored["success"], true);
+        assert_eq!(objects().await, ["a", "b"]);
+        assert_eq!(latest!(app, "Restore"), restored["record_id"].as_str().unwrap());
+        
+        let neither = test::TestRequest::post().uri(&restore).set_json(json!({})).send_request(&app).await;
+        assert_eq!(neither.status(), 400);
+    }
+    
+    #[actix_rt::test]
+    async fn test_compacted_history_restores_from_snapshots() {
+        let app = app!();
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        for object in ["a", "b"] {
+            test::TestRequest::post().uri(&uri)
+                .set_json(triple(&format!("http://example.org/{}", object))).send_request(&app).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let point = chrono::Utc::now();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        test::TestRequest::delete().uri(&uri).set_json(triple("http://example.org/a")).send_request(&app).await;
+        
+        let compacted: Value = test::call_and_read_body_json(&app, test::TestRequest::post().uri("/api/transactions/compact")
+            .set_json(json!({"before": point, "interval_hours": 1})).to_request()).await;
+        assert_eq!((compacted["snapshots"].as_u64(), compacted["removed"].as_u64()), (Some(1), Some(2)));
+        
+        let page: Value = test::call_and_read_body_json(&app,
+            test::TestRequest::get().uri("/api/transactions").to_request()).await;
+        let types: Vec<&str> = page["entries"].as_array().unwrap().iter()
+            .map(|entry| entry["record"]["transaction_type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["DeleteTriple", "Snapshot"]);
+        
+        let restored: Value = test::call_and_read_body_json(&app, test::TestRequest::post()
+            .uri(&format!("/api/graph/{}/restore", GRAPH))
+            .set_json(json!({"timestamp": point})).to_request()).await;
+        assert_eq!(restored["insertions"][0]["object"]["value"], "http://example.org/a");
+        assert!(restored["deletions"].as_array().unwrap().is_empty());
+    }
+    
+    #[actix_rt::test]
+    async fn test_changeset_export_replays_elsewhere() {
+        let source = app!();
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/skipped")).send_request(&source).await;
+        test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/a")).send_request(&source).await;
+        let from = latest!(source, "AddTriple");
+        test::TestRequest::put().uri(&format!("/api/graph/{}/triple/replace", GRAPH))
+            .set_json(json!({"old_triple": triple("http://example.org/a"), "new_triple": triple("http://example.org/b")}))
+            .send_request(&source).await;
+        
+        for format in ["rdf-patch", "sparql-update"] {
+            let exported = test::call_and_read_body(&source, test::TestRequest::get()
+                .uri(&format!("/api/transactions/changeset?format={}&from={}", format, from)).to_request()).await;
+            let data = String::from_utf8(exported.to_vec()).unwrap();
+            
+            let target = app!();
+            test::TestRequest::post().uri(&uri).set_json(triple("http://example.org/a")).send_request(&target).await;
+            let imported: Value = test::call_and_read_body_json(&target, test::TestRequest::post()
+                .uri("/api/transactions/changeset")
+                .set_json(json!({"format": format, "data": data})).to_request()).await;
+            assert_eq!(imported["record_ids"].as_array().unwrap().len(), 2, "{}", data);
+            
+            let triples: Vec<Value> = test::call_and_read_body_json(&target,
+                test::TestRequest::get().uri(&format!("/api/graph/{}/triples", GRAPH)).to_request()).await;
+            let objects: Vec<&str> = triples.iter().map(|t| t["object"]["value"].as_str().unwrap()).collect();
+            assert_eq!(objects, ["http://example.org/b"]);
+            latest!(target, "Changeset");
+        }
+    }
+    
+    #[actix_rt::test]
+    async fn test_transactions_filter_and_page() {
+        let app = app!();
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        for object in ["a", "b", "c"] {
+            test::TestRequest::post().uri(&uri)
+                .set_json(triple(&format!("http://example.org/{}", object))).send_request(&app).await;
+        }
+        test::TestRequest::delete().uri(&uri).set_json(triple("http://example.org/a")).send_request(&app).await;
+        
+        let first: Value = test::call_and_read_body_json(&app, test::TestRequest::get()
+            .uri("/api/transactions?type=AddTriple&limit=2").to_request()).await;
+        assert_eq!(first["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(first["entries"][0]["record"]["new_data"][0]["object"]["value"], "http://example.org/c");
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let second: Value = test::call_and_read_body_json(&app, test::TestRequest::get()
+            .uri(&format!("/api/transactions?type=AddTriple&limit=2&cursor={}", cursor)).to_request()).await;
+        assert_eq!(second["entries"].as_array().unwrap().len(), 1);
+        assert!(second["next_cursor"].is_null());
+        
+        let by_graph: Value = test::call_and_read_body_json(&app, test::TestRequest::get()
+            .uri(&format!("/api/transactions?graph={}&predicate=http%3A%2F%2Fexample.org%2Fknows", GRAPH)).to_request()).await;
+        assert_eq!(by_graph["entries"].as_array().unwrap().len(), 4);
+        let elsewhere: Value = test::call_and_read_body_json(&app, test::TestRequest::get()
+            .uri("/api/transactions?graph=default").to_request()).await;
+        assert!(elsewhere["entries"].as_array().unwrap().is_empty());
+    }
+    
+    #[actix_rt::test]
+    async fn test_graph_endpoints_read_through_caches() {
+        let app = app!();
+        test::TestRequest::post().uri("/api/import").set_json(json!({
+            "graph": "http://example.org/g",
+            "format": "turtle",
+            "data": "@prefix ex: <http://example.org/> .\n@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+                     ex:alice ex:knows ex:bob .\nex:bob a rdfs:Class ; rdfs:label \"Bob\" .\n"
+        })).send_request(&app).await;
+        
+        let expand = || test::TestRequest::post().uri("/api/graph/expand").set_json(json!({"uri": "http://example.org/alice"})).to_request();
+        let expanded: Value = test::call_and_read_body_json(&app, expand()).await;
+        assert_eq!(expanded["nodes"][0]["label"], "Bob");
+        assert_eq!(expanded["nodes"][0]["node_type"], "class");
+        let _: Value = test::call_and_read_body_json(&app, expand()).await;
+        let stats: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/api/cache/stats").to_request()).await;
+        assert_eq!(stats["graph"]["connections"]["hits"], 1);
+        assert_eq!(stats["graph"]["nodes"]["hits"], 1);
+        assert_eq!(stats["labels"]["entries"], 1);
+        
+        let path = || test::TestRequest::post().uri("/api/graph/path")
+            .set_json(json!({"from": "http://example.org/alice", "to": "http://example.org/bob"})).to_request();
+        let found: Value = test::call_and_read_body_json(&app, path()).await;
+        assert_eq!(found["length"], 1);
+        
+        // Writes about Bob or Alice drop what was cached about them
+        test::TestRequest::post().uri(&format!("/api/graph/{}/triple", GRAPH)).set_json(json!({
+            "subject": "http://example.org/bob",
+            "predicate": "http://www.w3.org/2000/01/rdf-schema#label",
+            "object": {"type": "literal", "value": "Robert", "language": "en"}
+        })).send_request(&app).await;
+        let node: Value = test::call_and_read_body_json(&app, test::TestRequest::get()
+            .uri("/api/graph/node/http%3A%2F%2Fexample.org%2Fbob").to_request()).await;
+        assert_eq!(node["label"], "Robert");
+        
+        let uri = format!("/api/graph/{}/triple", GRAPH);
+        test::TestRequest::delete().uri(&uri).set_json(triple("http://example.org/bob")).send_request(&app).await;
+        let found: Value = test::call_and_read_body_json(&app, path()).await;
+        assert!(found["message"].is_string());
+        
+        test::TestRequest::post().uri("/api/cache/flush").send_request(&app).await;
+        let stats: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/api/cache/stats").to_request()).await;
+        assert_eq!(stats["labels"]["entries"], 0);
+        assert_eq!(stats["graph"]["paths"]["entries"], 0);
+    }
+}
@@ -1,7 +1,10 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::dialect::StoreDialect;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Triple {
+    /// Subject IRI, or a blank node written as `_:label`
     pub subject: String,
     pub predicate: String,
     pub object: RdfNode,
@@ -9,7 +12,7 @@ pub struct Triple {
     pub graph: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum RdfNode {
     Uri { value: String },
@@ -25,6 +28,9 @@ pub enum RdfNode {
 
 // Graph visualization models
 
+/// A node label as (label, language, property)
+pub type NodeLabel = (String, Option<String>, String);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GraphNode {
     pub uri: String,
@@ -78,7 +84,7 @@ pub struct GraphPathRequest {
     pub max_depth: usize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GraphPath {
     pub nodes: Vec<String>,
     pub edges: Vec<GraphEdge>,
@@ -104,9 +110,13 @@ pub struct SparqlQuery {
     pub query: String,
     #[serde(default)]
     pub graph: Option<String>,
+    /// Switch reasoning on or off for this query; stores without a per-query toggle reject it
+    #[serde(default)]
+    pub reasoning: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 pub struct PaginatedQuery {
     pub query: String,
     #[serde(default)]
@@ -122,6 +132,7 @@ fn default_limit() -> usize {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 pub struct SparqlUpdate {
     pub update: String,
     #[serde(default)]
@@ -188,17 +199,20 @@ pub struct Namespace {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[allow(dead_code)]
 pub struct NamespaceList {
     pub namespaces: Vec<Namespace>,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum RdfFormat {
     Turtle,
     NTriples,
     JsonLd,
     RdfXml,
+    TriG,
+    NQuads,
 }
 
 impl RdfFormat {
@@ -208,6 +222,8 @@ impl RdfFormat {
             RdfFormat::NTriples => "application/n-triples",
             RdfFormat::JsonLd => "application/ld+json",
             RdfFormat::RdfXml => "application/rdf+xml",
+            RdfFormat::TriG => "application/trig",
+            RdfFormat::NQuads => "application/n-quads",
         }
     }
     
@@ -217,14 +233,68 @@ impl RdfFormat {
             RdfFormat::NTriples => "nt",
             RdfFormat::JsonLd => "jsonld",
             RdfFormat::RdfXml => "rdf",
+            RdfFormat::TriG => "trig",
+            RdfFormat::NQuads => "nq",
         }
     }
+    
+    /// Formats that carry graph names, so an export without a graph covers the whole dataset
+    pub fn is_dataset(&self) -> bool {
+        matches!(self, RdfFormat::TriG | RdfFormat::NQuads)
+    }
+    
+    /// Formats with one statement per line, which can be parsed a block of lines at a time
+    pub fn is_line_based(&self) -> bool {
+        matches!(self, RdfFormat::NTriples | RdfFormat::NQuads)
+    }
+}
+
+/// Formats for changesets: a run of logged changes replayed elsewhere
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangesetFormat {
+    /// RDF Patch: `A` and `D` rows between `TX` and `TC`
+    RdfPatch,
+    /// DELETE DATA and INSERT DATA operations, each transaction introduced by a comment
+    SparqlUpdate,
+}
+
+impl ChangesetFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ChangesetFormat::RdfPatch => "application/rdf-patch",
+            ChangesetFormat::SparqlUpdate => "application/sparql-update",
+        }
+    }
+    
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ChangesetFormat::RdfPatch => "rdfp",
+            ChangesetFormat::SparqlUpdate => "ru",
+        }
+    }
+}
+
+/// Document form used when exporting JSON-LD
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonLdForm {
+    Expanded,
+    #[default]
+    Compacted,
+    Flattened,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ExportRequest {
     pub graph: Option<String>,
     pub format: RdfFormat,
+    /// Only used for JSON-LD exports
+    #[serde(default)]
+    pub jsonld_form: JsonLdForm,
+    /// Compress the download with gzip
+    #[serde(default)]
+    pub gzip: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -232,25 +302,138 @@ pub struct ImportRequest {
     pub graph: String,
     pub format: RdfFormat,
     pub data: String,
+    /// Base IRI for relative references; defaults to the target graph
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+/// New contents for a whole graph; the graph itself comes from the path
+#[derive(Debug, Deserialize)]
+pub struct GraphReplaceRequest {
+    pub format: RdfFormat,
+    pub data: String,
+    /// Base IRI for relative references; defaults to the target graph
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+/// Query parameters for a background import; the document itself is the request body
+#[derive(Debug, Deserialize)]
+pub struct ImportJobRequest {
+    pub graph: String,
+    pub format: RdfFormat,
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Triples per insert; defaults to IMPORT_CHUNK_SIZE
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    /// Replace blank nodes with IRIs, so those referenced from several chunks stay one node
+    #[serde(default)]
+    pub skolemize: bool,
+}
+
+/// Point to roll a graph back to: a time, or a transaction id meaning just after
+/// that transaction
+#[derive(Debug, Deserialize)]
+pub struct RestoreRequest {
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    /// Only report what the restore would change
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Logged changes to export, oldest first. `from` and `to` are transaction ids and
+/// both ends are included; every bound is optional.
+#[derive(Debug, Deserialize)]
+pub struct ChangesetExportRequest {
+    pub format: ChangesetFormat,
+    /// Only changes to this graph, "default" for the default graph
+    #[serde(default)]
+    pub graph: Option<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesetImportRequest {
+    pub format: ChangesetFormat,
+    pub data: String,
+}
+
+/// History to fold into graph snapshots: everything logged up to `before`,
+/// snapshotted every `interval_hours` back from it, or only at `before`
+#[derive(Debug, Deserialize)]
+pub struct CompactRequest {
+    pub before: DateTime<Utc>,
+    #[serde(default)]
+    pub interval_hours: Option<u64>,
+}
+
+/// Endpoint profile as listed by the API; credentials are never included
+#[derive(Debug, Serialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub default: bool,
+    pub query_endpoint: String,
+    pub update_endpoint: String,
+    pub graph_store_endpoint: Option<String>,
+    pub dialect: StoreDialect,
+    pub full_text_search: bool,
+    pub authenticated: bool,
+    pub verify_ssl: bool,
+    pub graph_max_nodes: usize,
+    pub graph_max_edges: usize,
+    pub graph_expansion_limit: usize,
+    pub graph_search_limit: usize,
 }
 
 impl Triple {
+    pub fn subject_is_blank(&self) -> bool {
+        self.subject.starts_with("_:")
+    }
+    
+    /// Labels of the blank nodes in subject or object position, without the `_:`
+    pub fn blank_nodes(&self) -> Vec<&str> {
+        let object = match &self.object {
+            RdfNode::Blank { value } => Some(value.as_str()),
+            _ => None,
+        };
+        self.subject.strip_prefix("_:").into_iter().chain(object).collect()
+    }
+    
     pub fn to_sparql_pattern(&self) -> String {
+        use crate::validation::{sanitize_blank_label, sanitize_iri, sanitize_language_tag, sanitize_literal_value};
+        
+        // Terms are checked where they come in, but nothing is written unescaped
         let object_str = match &self.object {
-            RdfNode::Uri { value } => format!("<{}>", value),
+            RdfNode::Uri { value } => format!("<{}>", sanitize_iri(value)),
             RdfNode::Literal { value, datatype, language } => {
-                let escaped_value = crate::validation::sanitize_literal_value(value);
+                let escaped_value = sanitize_literal_value(value);
                 let mut lit = format!("\"{}\"", escaped_value);
                 if let Some(lang) = language {
-                    lit.push_str(&format!("@{}", lang));
+                    lit.push_str(&format!("@{}", sanitize_language_tag(lang)));
                 } else if let Some(dt) = datatype {
-                    lit.push_str(&format!("^^<{}>", dt));
+                    lit.push_str(&format!("^^<{}>", sanitize_iri(dt)));
                 }
                 lit
             },
-            RdfNode::Blank { value } => format!("_:{}", value),
+            RdfNode::Blank { value } => format!("_:{}", sanitize_blank_label(value)),
+        };
+        
+        let subject_str = match self.subject.strip_prefix("_:") {
+            Some(label) => format!("_:{}", sanitize_blank_label(label)),
+            None => format!("<{}>", sanitize_iri(&self.subject)),
         };
         
-        format!("<{}> <{}> {}", self.subject, self.predicate, object_str)
+        format!("{} <{}> {}", subject_str, sanitize_iri(&self.predicate), object_str)
     }
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+//! Named endpoint profiles, selectable per request.
+//!
+//! Each profile gets its own SPARQL client, caches, transaction log and open
+//! transactions, so results and history never cross between stores. A request
+//! picks its profile with the `/api/profiles/{name}/...` path prefix or the
+//! `X-Endpoint-Profile` header, and falls back to DEFAULT_PROFILE.
+
+use actix_web::{dev::{Payload, ServiceRequest}, http::Uri, web, FromRequest, HttpMessage, HttpRequest};
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use crate::AppState;
+use crate::cache::{self, CacheSettings, GraphCacheManager, LabelCache, QueryCache, TypeCache};
+use crate::config::{Config, EndpointProfile, DEFAULT_PROFILE};
+use crate::error::RdfEditorError;
+use crate::models::Triple;
+use crate::sparql::SparqlClient;
+use crate::transaction_log::{LogPolicy, TransactionLogger};
+use crate::transactions::TransactionManager;
+
+pub const PROFILE_HEADER: &str = "X-Endpoint-Profile";
+
+/// Everything that is tied to one store
+pub struct ProfileState {
+    pub config: EndpointProfile,
+    pub sparql_client: Arc<SparqlClient>,
+    pub transaction_logger: Arc<TransactionLogger>,
+    pub transactions: Arc<TransactionManager>,
+    pub label_cache: Arc<LabelCache>,
+    pub type_cache: Arc<TypeCache>,
+    pub query_cache: Arc<QueryCache>,
+    pub graph_cache: Arc<GraphCacheManager>,
+}
+
+impl ProfileState {
+    pub async fn new(config: &Config, profile: &EndpointProfile) -> Result<Self, RdfEditorError> {
+        // The default profile keeps the original log file name
+        let log_file = if profile.name == DEFAULT_PROFILE {
+            "transaction_log.jsonl".to_string()
+        } else {
+            format!("transaction_log.{}.jsonl", profile.name)
+        };
+        let log_path = Path::new(&config.transaction_log_dir).join(log_file);
+        let cache = CacheSettings::from_config(config);
+        let cache_file = |name: &str| cache::file_path(config, &profile.name, name);
+        
+        Ok(ProfileState {
+            config: profile.clone(),
+            sparql_client: Arc::new(SparqlClient::new(config, profile)?),
+            transaction_logger: Arc::new(TransactionLogger::new(&log_path.to_string_lossy(), LogPolicy::from_config(config)).await?),
+            transactions: Arc::new(TransactionManager::new()),
+            label_cache: Arc::new(LabelCache::open(cache, cache_file("labels"))?),
+            type_cache: Arc::new(TypeCache::open(cache, cache_file("types"))?),
+            query_cache: Arc::new(QueryCache::open(cache, cache_file("queries"))?),
+            graph_cache: Arc::new(GraphCacheManager::open(cache, cache_file)?),
+        })
+    }
+    
+    pub async fn cleanup_caches(&self) {
+        self.label_cache.cleanup_expired().await;
+        self.type_cache.cleanup_expired().await;
+        self.query_cache.cleanup_expired().await;
+        self.graph_cache.cleanup_all().await;
+    }
+    
+    /// Empty every cache, on disk too
+    pub async fn flush_caches(&self) {
+        self.label_cache.clear().await;
+        self.type_cache.clear().await;
+        self.query_cache.clear().await;
+        self.graph_cache.clear().await;
+    }
+    
+    /// Drop every cached result a write of `triples` may have changed. Triples
+    /// naming no graph were written to `graph`, "default" for the default graph.
+    pub async fn invalidate_writes(&self, triples: &[Triple], graph: &str) {
+        let tags = cache::write_tags(triples, graph);
+        self.label_cache.invalidate_tags(&tags).await;
+        self.type_cache.invalidate_tags(&tags).await;
+        self.query_cache.invalidate_tags(&tags).await;
+        self.graph_cache.invalidate_tags(&tags).await;
+    }
+}
+
+pub struct ProfileRegistry {
+    profiles: Vec<Arc<ProfileState>>,
+    default: String,
+}
+
+impl ProfileRegistry {
+    pub async fn new(config: &Config) -> Result<Self, RdfEditorError> {
+        let mut profiles = Vec::new();
+        for profile in &config.profiles {
+            profiles.push(Arc::new(ProfileState::new(config, profile).await?));
+        }
+        
+        Ok(ProfileRegistry {
+            profiles,
+            default: config.default_profile.clone(),
+        })
+    }
+    
+    /// Registry over profiles set up by the caller, such as the route tests
+    #[cfg(test)]
+    pub fn from_profiles(profiles: Vec<Arc<ProfileState>>, default: &str) -> Self {
+        ProfileRegistry {
+            profiles,
+            default: default.to_string(),
+        }
+    }
+    
+    pub fn get(&self, name: &str) -> Result<Arc<ProfileState>, RdfEditorError> {
+        self.profiles
+            .iter()
+            .find(|p| p.config.name == name)
+            .cloned()
+            .ok_or_else(|| RdfEditorError::NotFound(format!("Endpoint profile {} not found", name)))
+    }
+    
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+    
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<ProfileState>> {
+        self.profiles.iter()
+    }
+}
+
+/// Profile named by the path prefix, stored in the request extensions
+struct SelectedProfile(String);
+
+/// Serve `/api/profiles/{name}/rest` with the regular `/api/rest` route, remembering
+/// the profile, so every endpoint is available per profile without duplicate routes.
+/// Runs before routing.
+pub fn strip_profile_prefix(req: &mut ServiceRequest) {
+    let Some((name, rest)) = req.path()
+        .strip_prefix("/api/profiles/")
+        .and_then(|rest| rest.split_once('/'))
+    else {
+        return;
+    };
+    
+    let uri = match req.query_string() {
+        "" => format!("/api/{}", rest),
+        query => format!("/api/{}?{}", rest, query),
+    };
+    let Ok(uri) = uri.parse::<Uri>() else {
+        return;
+    };
+    
+    let name = name.to_string();
+    req.extensions_mut().insert(SelectedProfile(name));
+    req.match_info_mut().get_mut().update(&uri);
+    req.head_mut().uri = uri;
+}
+
+/// Extractor for the profile a request targets
+pub struct Profile(Arc<ProfileState>);
+
+impl Profile {
+    fn resolve(req: &HttpRequest) -> Result<Self, RdfEditorError> {
+        let data = req.app_data::<web::Data<AppState>>()
+            .ok_or_else(|| RdfEditorError::Configuration("Application state is not configured".to_string()))?;
+        
+        let from_path = req.extensions().get::<SelectedProfile>().map(|p| p.0.clone());
+        let from_header = || req.headers()
+            .get(PROFILE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string());
+        let name = from_path
+            .or_else(from_header)
+            .unwrap_or_else(|| data.profiles.default_name().to_string());
+        
+        data.profiles.get(&name).map(Profile)
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.0.config.name
+    }
+    
+    pub fn into_inner(self) -> Arc<ProfileState> {
+        self.0
+    }
+}
+
+impl Deref for Profile {
+    type Target = ProfileState;
+    
+    fn deref(&self) -> &ProfileState {
+        &self.0
+    }
+}
+
+impl FromRequest for Profile {
+    type Error = RdfEditorError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Profile::resolve(req))
+    }
+}
@@ -0,0 +1,139 @@
+//! Coalescing of identical calls in flight.
+//!
+//! The first caller for a key runs the call; callers arriving with the same key
+//! while it runs wait for it and get a copy of its result or error. The key is
+//! forgotten as soon as the call finishes, so later callers start a fresh one.
+//! If the running caller is dropped before finishing, a waiting caller takes
+//! over and runs the call itself.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::watch;
+use crate::error::RdfEditorError;
+
+type Outcome<T> = Option<Result<T, RdfEditorError>>;
+
+pub struct SingleFlight<T> {
+    flights: Mutex<HashMap<String, watch::Receiver<Outcome<T>>>>,
+}
+
+enum Role<T> {
+    Leader(watch::Sender<Outcome<T>>),
+    Follower(watch::Receiver<Outcome<T>>),
+}
+
+/// Forgets the leader's flight when it finishes or is dropped
+struct Landing<'a, T> {
+    flights: &'a Mutex<HashMap<String, watch::Receiver<Outcome<T>>>>,
+    key: &'a str,
+}
+
+impl<T> Drop for Landing<'_, T> {
+    fn drop(&mut self) {
+        self.flights.lock().unwrap().remove(self.key);
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        SingleFlight { flights: Mutex::new(HashMap::new()) }
+    }
+    
+    /// Result of `call`, shared with every caller passing the same `key` while it runs
+    pub async fn run<F, Fut>(&self, key: &str, call: F) -> Result<T, RdfEditorError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, RdfEditorError>>,
+    {
+        let mut call = Some(call);
+        loop {
+            let role = {
+                let mut flights = self.flights.lock().unwrap();
+                match flights.get(key) {
+                    Some(receiver) => Role::Follower(receiver.clone()),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        flights.insert(key.to_string(), receiver);
+                        Role::Leader(sender)
+                    },
+                }
+            };
+            
+            match role {
+                Role::Leader(sender) => {
+                    let _landing = Landing { flights: &self.flights, key };
+                    let result = call.take().expect("a caller leads at most once")().await;
+                    sender.send_replace(Some(match &result {
+                        Ok(value) => Ok(value.clone()),
+                        Err(e) => Err(e.duplicate()),
+                    }));
+                    return result;
+                },
+                Role::Follower(mut receiver) => {
+                    // An error here means the leader was dropped; try to lead instead
+                    if let Ok(outcome) = receiver.wait_for(Option::is_some).await {
+                        return match outcome.as_ref() {
+                            Some(Ok(value)) => Ok(value.clone()),
+                            Some(Err(e)) => Err(e.duplicate()),
+                            None => unreachable!("waited for an outcome"),
+                        };
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_result() {
+        let flights: SingleFlight<usize> = SingleFlight::new();
+        let calls = AtomicUsize::new(0);
+        let call = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(calls.fetch_add(1, Ordering::SeqCst) + 1)
+        };
+        
+        let (a, b, c) = tokio::join!(flights.run("q", call), flights.run("q", call), flights.run("other", call));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert!(c.is_ok());
+        
+        // Finished flights are forgotten
+        flights.run("q", call).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_errors_are_shared_and_dropped_leaders_replaced() {
+        let flights: SingleFlight<usize> = SingleFlight::new();
+        let failing = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err::<usize, _>(RdfEditorError::Sparql("boom".to_string()))
+        };
+        let (a, b) = tokio::join!(flights.run("q", failing), flights.run("q", || async { Ok(1) }));
+        assert!(matches!(a, Err(RdfEditorError::Sparql(_))));
+        assert!(matches!(b, Err(RdfEditorError::Sparql(_))));
+        
+        // The leader gives up; the waiting caller runs its own call
+        let leader = tokio::time::timeout(Duration::from_millis(10), flights.run("q", || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(1)
+        }));
+        let (dropped, follower) = tokio::join!(leader, flights.run("q", || async { Ok(2) }));
+        assert!(dropped.is_err());
+        assert_eq!(follower.unwrap(), 2);
+    }
+}
@@ -1,87 +1,71 @@
-use crate::{error::RdfEditorError, models::{Triple, RdfNode, GraphNode, GraphEdge, NodeType}, config::Config};
-use reqwest::{Client, header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, AUTHORIZATION}};
+use crate::{error::RdfEditorError, models::{Triple, RdfNode, RdfFormat, GraphNode, GraphEdge, Namespace, NodeLabel, NodeType}, config::{Config, EndpointProfile}, dialect::StoreDialect, store::{self, ByteStream, Store}};
 use serde_json::Value;
-use base64::{Engine as _, engine::general_purpose};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use crate::single_flight::SingleFlight;
+use crate::validation::{sanitize_blank_label, sanitize_iri};
 
+/// Reads and writes for the handlers, built on whichever `Store` backs the profile
 pub struct SparqlClient {
-    client: Client,
-    query_endpoint: String,      // For SELECT, ASK, CONSTRUCT, DESCRIBE
-    update_endpoint: String,     // For INSERT, DELETE, etc.
-    headers: HeaderMap,
+    store: Box<dyn Store>,
+    full_text_search: bool,
+    /// Identical queries in flight, shared by key
+    flights: SingleFlight<Value>,
 }
 
 impl SparqlClient {
-    pub fn new(config: &Config) -> Result<Self, RdfEditorError> {
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("application/sparql-results+json"));
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
-        
-        // Add authentication if provided
-        if let Some(username) = &config.sparql_username {
-            let password = config.get_password()
-                .ok_or_else(|| RdfEditorError::Configuration("Password required when username is provided".to_string()))?;
-            
-            let auth = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
-            headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Basic {}", auth))?);
-        }
-        
-        let client = if config.verify_ssl {
-            Client::builder().default_headers(headers.clone()).build()?
-        } else {
-            Client::builder()
-                .danger_accept_invalid_certs(true)
-                .default_headers(headers.clone())
-                .build()?
-        };
-        
-        Ok(SparqlClient {
-            client,
-            query_endpoint: config.sparql_endpoint.clone(),
-            update_endpoint: config.get_update_endpoint().to_string(),
-            headers,
-        })
+    /// Client for one endpoint profile; timeout and retries are shared by all profiles
+    pub fn new(config: &Config, profile: &EndpointProfile) -> Result<Self, RdfEditorError> {
+        Ok(Self::with_store(store::open(config, profile)?, profile.full_text_search))
+    }
+    
+    pub fn with_store(store: Box<dyn Store>, full_text_search: bool) -> Self {
+        SparqlClient { store, full_text_search, flights: SingleFlight::new() }
+    }
+    
+    pub fn dialect(&self) -> StoreDialect {
+        self.store.dialect()
     }
     
     pub async fn query(&self, query: &str) -> Result<Value, RdfEditorError> {
-        log::debug!("Executing SPARQL query on endpoint: {}", self.query_endpoint);
-        log::debug!("Query: {}", query);
-        
-        let response = self.client
-            .post(&self.query_endpoint)
-            .form(&[("query", query)])
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            log::error!("SPARQL query failed with status {}: {}", status, error_text);
-            return Err(RdfEditorError::Sparql(format!("Query failed with status {}: {}", status, error_text)));
-        }
-        
-        let result = response.json::<Value>().await?;
-        Ok(result)
+        self.store.query(query, None).await
+    }
+    
+    /// Run a query with reasoning switched on or off, or left at the store's default
+    /// for `None`. Only dialects with a per-query toggle accept `Some`.
+    pub async fn query_with_reasoning(&self, query: &str, reasoning: Option<bool>) -> Result<Value, RdfEditorError> {
+        self.store.query(query, reasoning).await
+    }
+    
+    /// Like `query_with_reasoning`, but callers passing the same `key` while the
+    /// query runs share its one call to the store, result or error
+    pub async fn query_coalesced(&self, key: &str, query: &str, reasoning: Option<bool>) -> Result<Value, RdfEditorError> {
+        self.flights.run(key, || self.store.query(query, reasoning)).await
     }
     
     pub async fn update(&self, update: &str) -> Result<(), RdfEditorError> {
-        log::debug!("Executing SPARQL update on endpoint: {}", self.update_endpoint);
-        log::debug!("Update: {}", update);
-        
-        let response = self.client
-            .post(&self.update_endpoint)
-            .form(&[("update", update)])
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            log::error!("SPARQL update failed with status {}: {}", status, error_text);
-            return Err(RdfEditorError::Sparql(format!("Update failed with status {}: {}", status, error_text)));
-        }
-        
-        Ok(())
+        self.store.update(update).await
+    }
+    
+    /// Apply `updates` all or nothing: inside a store transaction where the dialect has
+    /// a transaction API, otherwise joined into a single update request
+    pub async fn update_atomic(&self, updates: &[String]) -> Result<(), RdfEditorError> {
+        self.store.update_atomic(updates).await
+    }
+    
+    /// Prefixes stored with the database, for dialects with a namespace API
+    pub async fn namespaces(&self) -> Result<Option<Vec<Namespace>>, RdfEditorError> {
+        self.store.namespaces().await
+    }
+    
+    /// Whether whole-graph operations can use the Graph Store Protocol
+    pub fn has_graph_store(&self) -> bool {
+        self.store.has_graph_store()
+    }
+    
+    /// Fetch a whole graph in `format` with a graph store GET, streamed as it arrives;
+    /// `None` means the graph does not exist
+    pub async fn graph_store_get(&self, graph: &str, format: RdfFormat) -> Result<Option<ByteStream>, RdfEditorError> {
+        self.store.graph_store_get(graph, format).await
     }
     
     pub async fn get_triples(&self, graph: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
@@ -98,20 +82,52 @@ impl SparqlClient {
         self.parse_triples_from_results(result, graph)
     }
     
+    /// One page of triples, ordered by subject so pages are stable and group by subject
     pub async fn get_triples_paginated(&self, graph: Option<&str>, limit: usize, offset: usize) -> Result<Vec<Triple>, RdfEditorError> {
         let query = if let Some(g) = graph {
             format!(
-                "SELECT ?s ?p ?o WHERE {{ GRAPH <{}> {{ ?s ?p ?o }} }} LIMIT {} OFFSET {}",
+                "SELECT ?s ?p ?o WHERE {{ GRAPH <{}> {{ ?s ?p ?o }} }} ORDER BY ?s ?p ?o LIMIT {} OFFSET {}",
                 g, limit, offset
             )
         } else {
-            format!("SELECT ?s ?p ?o WHERE {{ ?s ?p ?o }} LIMIT {} OFFSET {}", limit, offset)
+            format!("SELECT ?s ?p ?o WHERE {{ ?s ?p ?o }} ORDER BY ?s ?p ?o LIMIT {} OFFSET {}", limit, offset)
         };
         
         let result = self.query(&query).await?;
         self.parse_triples_from_results(result, graph)
     }
     
+    /// One page of the default graph's own triples, leaving out those a union default
+    /// graph takes from the named graphs, ordered like `get_triples_paginated`
+    async fn get_default_triples_paginated(&self, limit: usize, offset: usize) -> Result<Vec<Triple>, RdfEditorError> {
+        let query = format!(r#"
+            SELECT ?s ?p ?o WHERE {{
+                ?s ?p ?o FILTER NOT EXISTS {{ GRAPH ?any {{ ?s ?p ?o }} }}
+            }}
+            ORDER BY ?s ?p ?o LIMIT {} OFFSET {}
+        "#, limit, offset);
+        
+        let result = self.query(&query).await?;
+        self.parse_triples_from_results(result, None)
+    }
+    
+    /// One page of every quad in the store, ordered by graph then subject. Default graph
+    /// triples that are not also in a named graph come back with `graph: None`, so stores
+    /// with a union default graph are not duplicated.
+    pub async fn get_quads_paginated(&self, limit: usize, offset: usize) -> Result<Vec<Triple>, RdfEditorError> {
+        let query = format!(r#"
+            SELECT ?g ?s ?p ?o WHERE {{
+                {{ GRAPH ?g {{ ?s ?p ?o }} }}
+                UNION
+                {{ ?s ?p ?o FILTER NOT EXISTS {{ GRAPH ?any {{ ?s ?p ?o }} }} }}
+            }}
+            ORDER BY ?g ?s ?p ?o LIMIT {} OFFSET {}
+        "#, limit, offset);
+        
+        let result = self.query(&query).await?;
+        self.parse_triples_from_results(result, None)
+    }
+    
     pub async fn count_triples(&self, graph: Option<&str>) -> Result<usize, RdfEditorError> {
         let query = if let Some(g) = graph {
             format!(
@@ -137,57 +153,130 @@ impl SparqlClient {
         Ok(0)
     }
     
+    /// Whether `triple` is in the graph it names, `None` being the default graph
+    pub async fn contains_triple(&self, triple: &Triple) -> Result<bool, RdfEditorError> {
+        let query = match &triple.graph {
+            Some(g) => format!("ASK {{ GRAPH <{}> {{ {} }} }}", g, triple.to_sparql_pattern()),
+            // Stores with a union default graph would also find it in named graphs
+            None => format!(
+                "ASK {{ {0} FILTER NOT EXISTS {{ GRAPH ?g {{ {0} }} }} }}",
+                triple.to_sparql_pattern()
+            ),
+        };
+        
+        let result = self.query(&query).await?;
+        result["boolean"].as_bool()
+            .ok_or_else(|| RdfEditorError::Sparql("Missing ASK result".to_string()))
+    }
+    
+    /// Those of `triples` already in the graph each one names, read with one VALUES
+    /// query per graph. Triples holding blank nodes cannot be matched and are left out.
+    pub async fn present_triples(&self, triples: &[Triple]) -> Result<Vec<Triple>, RdfEditorError> {
+        let ground: Vec<Triple> = triples.iter()
+            .filter(|triple| triple.blank_nodes().is_empty())
+            .cloned()
+            .collect();
+        
+        let mut present = Vec::new();
+        for (graph, group) in by_graph(&ground) {
+            // Rows are numbered so matches map back to the triples as given
+            let rows: Vec<String> = group.iter().enumerate()
+                .map(|(i, triple)| format!("({} {})", i, triple.to_sparql_pattern()))
+                .collect();
+            let pattern = if graph == "default" {
+                "?s ?p ?o FILTER NOT EXISTS { GRAPH ?any { ?s ?p ?o } }".to_string()
+            } else {
+                format!("GRAPH <{}> {{ ?s ?p ?o }}", graph)
+            };
+            let query = format!(
+                "SELECT ?i WHERE {{ VALUES (?i ?s ?p ?o) {{ {} }} {} }}",
+                rows.join(" "), pattern
+            );
+            
+            let result = self.query(&query).await?;
+            let bindings = result["results"]["bindings"].as_array()
+                .ok_or_else(|| RdfEditorError::Sparql("Invalid query response".to_string()))?;
+            for binding in bindings {
+                let row = binding["i"]["value"].as_str().and_then(|i| i.parse::<usize>().ok());
+                if let Some(triple) = row.and_then(|i| group.get(i)) {
+                    present.push(triple.clone());
+                }
+            }
+        }
+        Ok(present)
+    }
+    
     // New graph-specific methods
     
     pub async fn get_node_info(&self, uri: &str) -> Result<GraphNode, RdfEditorError> {
+        let mut found = self.get_labels_and_types(&[uri.to_string()]).await?;
+        let (labels, types) = found.remove(uri).unwrap_or_default();
+        Ok(self.graph_node(uri, &labels, types))
+    }
+    
+    /// Labels, as (label, language, property), and types of each of `uris`, read
+    /// with one VALUES query. Every URI gets an entry, empty when nothing is known.
+    pub async fn get_labels_and_types(
+        &self,
+        uris: &[String],
+    ) -> Result<HashMap<String, (Vec<NodeLabel>, Vec<String>)>, RdfEditorError> {
+        let mut found: HashMap<String, (Vec<NodeLabel>, Vec<String>)> = uris.iter()
+            .map(|uri| (uri.clone(), Default::default()))
+            .collect();
+        if uris.is_empty() {
+            return Ok(found);
+        }
+        
+        let values: Vec<String> = uris.iter().map(|uri| format!("<{}>", uri)).collect();
         let query = format!(r#"
             PREFIX rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#>
             PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
             PREFIX skos: <http://www.w3.org/2004/02/skos/core#>
             
-            SELECT ?type ?label ?labelProp ?lang WHERE {{
-                OPTIONAL {{ <{}> rdf:type ?type }}
-                OPTIONAL {{
-                    <{}> ?labelProp ?label .
+            SELECT ?resource ?type ?label ?labelProp WHERE {{
+                VALUES ?resource {{ {} }}
+                {{ ?resource rdf:type ?type }}
+                UNION
+                {{
+                    ?resource ?labelProp ?label .
                     FILTER(?labelProp IN (rdfs:label, skos:prefLabel, skos:altLabel))
-                    BIND(LANG(?label) as ?lang)
                 }}
             }}
-        "#, uri, uri);
+        "#, values.join(" "));
         
         let result = self.query(&query).await?;
         let bindings = result["results"]["bindings"].as_array()
             .ok_or_else(|| RdfEditorError::Sparql("Invalid query response".to_string()))?;
         
-        // Extract types and labels
-        let mut types = Vec::new();
-        let mut labels = HashMap::new();
-        
         for binding in bindings {
+            let Some((labels, types)) = binding["resource"]["value"].as_str().and_then(|uri| found.get_mut(uri)) else {
+                continue;
+            };
             if let Some(type_val) = binding["type"]["value"].as_str() {
-                if !types.contains(&type_val.to_string()) {
+                if !types.iter().any(|t| t == type_val) {
                     types.push(type_val.to_string());
                 }
             }
-            
-            if let Some(label_val) = binding["label"]["value"].as_str() {
-                let lang = binding["lang"]["value"].as_str().unwrap_or("");
-                labels.insert(lang.to_string(), label_val.to_string());
+            if let (Some(label), Some(property)) = (binding["label"]["value"].as_str(), binding["labelProp"]["value"].as_str()) {
+                let lang = binding["label"]["xml:lang"].as_str().map(|s| s.to_string());
+                labels.push((label.to_string(), lang, property.to_string()));
             }
         }
         
-        // Determine node type
-        let node_type = self.determine_node_type(&types);
-        
-        // Get best label
-        let label = self.get_best_label(&labels);
-        
-        Ok(GraphNode {
+        Ok(found)
+    }
+    
+    /// Node for `uri` from its labels, as (label, language, property), and types
+    pub fn graph_node(&self, uri: &str, labels: &[NodeLabel], types: Vec<String>) -> GraphNode {
+        let labels: HashMap<String, String> = labels.iter()
+            .map(|(label, lang, _)| (lang.clone().unwrap_or_default(), label.clone()))
+            .collect();
+        GraphNode {
             uri: uri.to_string(),
-            label,
+            label: self.get_best_label(&labels),
+            node_type: self.determine_node_type(&types),
             types,
-            node_type,
-        })
+        }
     }
     
     pub async fn get_node_connections(&self, uri: &str, limit: usize) -> Result<(Vec<GraphNode>, Vec<GraphEdge>), RdfEditorError> {
@@ -305,18 +394,30 @@ impl SparqlClient {
     }
     
     pub async fn search_nodes_by_label(&self, search_term: &str, limit: usize) -> Result<Vec<GraphNode>, RdfEditorError> {
+        // Use the store's full-text index when one is set up, a substring filter otherwise
+        let text_match = self.full_text_search
+            .then(|| self.dialect().text_search("?resource", "?label", search_term))
+            .flatten();
+        let (index_pattern, filter) = match text_match {
+            Some(pattern) => (pattern, String::new()),
+            None => (String::new(), format!(
+                "FILTER(CONTAINS(LCASE(STR(?label)), LCASE(\"{}\")))", crate::formats::escape_literal(search_term)
+            )),
+        };
+        
         let query = format!(r#"
             PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
             PREFIX skos: <http://www.w3.org/2004/02/skos/core#>
             PREFIX rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#>
             
             SELECT DISTINCT ?resource ?label ?type WHERE {{
+                {}
                 ?resource ?labelProp ?label .
                 FILTER(?labelProp IN (rdfs:label, skos:prefLabel, skos:altLabel))
-                FILTER(CONTAINS(LCASE(STR(?label)), LCASE("{}")))
+                {}
                 OPTIONAL {{ ?resource rdf:type ?type }}
             }} LIMIT {}
-        "#, search_term, limit);
+        "#, index_pattern, filter, limit);
         
         let result = self.query(&query).await?;
         let mut nodes = Vec::new();
@@ -462,32 +563,111 @@ impl SparqlClient {
         self.update(&update).await
     }
     
+    /// Insert `triples` into `graph` ("default" meaning the default graph) in one update
     pub async fn add_triples_batch(&self, triples: &[Triple], graph: &str) -> Result<(), RdfEditorError> {
         if triples.is_empty() {
             return Ok(());
         }
         
-        let mut update = format!("INSERT DATA {{ GRAPH <{}> {{", graph);
+        self.update(&insert_data(graph, triples)).await
+    }
+    
+    /// Insert quads into the graph each one names, and plain triples into `graph`
+    /// ("default" meaning the default graph). Each graph is written in a single request,
+    /// a graph store POST when available, so blank node labels stay consistent. Returns
+    /// the number of graphs written.
+    pub async fn add_quads_batch(&self, triples: &[Triple], graph: &str) -> Result<usize, RdfEditorError> {
+        let mut by_graph: Vec<(&str, Vec<Triple>)> = Vec::new();
         for triple in triples {
-            update.push_str(&format!(" {}", triple.to_sparql_pattern()));
+            let target = triple.graph.as_deref().unwrap_or(graph);
+            if target.starts_with("_:") {
+                return Err(RdfEditorError::InvalidInput(
+                    "Blank node graph names are not supported".to_string()
+                ));
+            }
+            match by_graph.iter_mut().find(|(g, _)| *g == target) {
+                Some((_, graph_triples)) => graph_triples.push(triple.clone()),
+                None => by_graph.push((target, vec![triple.clone()])),
+            }
         }
-        update.push_str(" } }");
         
-        self.update(&update).await
+        if self.has_graph_store() {
+            for (target, graph_triples) in &by_graph {
+                self.store.graph_store_write(target, graph_triples, false).await?;
+            }
+            return Ok(by_graph.len());
+        }
+        
+        // One update per graph, applied together so a failure leaves no graph half imported
+        let updates: Vec<String> = by_graph.iter()
+            .map(|(target, graph_triples)| insert_data(target, graph_triples))
+            .collect();
+        self.update_atomic(&updates).await?;
+        
+        Ok(by_graph.len())
+    }
+    
+    /// Replace the contents of `graph` ("default" meaning the default graph) with `triples`:
+    /// a graph store PUT when available, otherwise DROP and INSERT DATA in one update
+    pub async fn replace_graph(&self, graph: &str, triples: &[Triple]) -> Result<(), RdfEditorError> {
+        if self.has_graph_store() {
+            return self.store.graph_store_write(graph, triples, true).await;
+        }
+        
+        let drop = if graph == "default" {
+            "DROP SILENT DEFAULT".to_string()
+        } else {
+            format!("DROP SILENT GRAPH <{}>", graph)
+        };
+        
+        self.update_atomic(&[drop, insert_data(graph, triples)]).await
+    }
+    
+    /// Remove `graph` ("default" meaning the default graph). Dropping a graph that does
+    /// not exist is not an error.
+    pub async fn drop_graph(&self, graph: &str) -> Result<(), RdfEditorError> {
+        if self.has_graph_store() {
+            return self.store.graph_store_delete(graph).await;
+        }
+        
+        if graph == "default" {
+            self.update("DROP SILENT DEFAULT").await
+        } else {
+            self.update(&format!("DROP SILENT GRAPH <{}>", graph)).await
+        }
     }
     
+    /// Delete `deletions` then insert `insertions` all or nothing, each triple going to
+    /// the graph it names
+    pub async fn apply_changes(&self, deletions: &[Triple], insertions: &[Triple]) -> Result<(), RdfEditorError> {
+        self.update_atomic(&change_updates(deletions, insertions)).await
+    }
+    
+    /// Delete `triples` from `graph` ("default" meaning the default graph) in one update
     pub async fn delete_triples_batch(&self, triples: &[Triple], graph: &str) -> Result<(), RdfEditorError> {
         if triples.is_empty() {
             return Ok(());
         }
         
-        let mut update = format!("DELETE DATA {{ GRAPH <{}> {{", graph);
-        for triple in triples {
-            update.push_str(&format!(" {}", triple.to_sparql_pattern()));
+        self.update(&delete_data(graph, triples)).await
+    }
+    
+    /// Every triple of `graph`, read page by page. For "default", only the default
+    /// graph's own triples, not those a union default graph shows from named graphs.
+    pub async fn snapshot_graph(&self, graph: &str, page_size: usize) -> Result<Vec<Triple>, RdfEditorError> {
+        let mut triples = Vec::new();
+        loop {
+            let page = if graph == "default" {
+                self.get_default_triples_paginated(page_size, triples.len()).await?
+            } else {
+                self.get_triples_paginated(Some(graph), page_size, triples.len()).await?
+            };
+            let done = page.len() < page_size;
+            triples.extend(page);
+            if done {
+                return Ok(triples);
+            }
         }
-        update.push_str(" } }");
-        
-        self.update(&update).await
     }
     
     fn parse_triples_from_results(&self, result: Value, graph: Option<&str>) -> Result<Vec<Triple>, RdfEditorError> {
@@ -496,8 +676,12 @@ impl SparqlClient {
         if let Some(bindings) = result["results"]["bindings"].as_array() {
             for binding in bindings {
                 let subject = binding["s"]["value"].as_str()
-                    .ok_or_else(|| RdfEditorError::Sparql("Missing subject".to_string()))?
-                    .to_string();
+                    .ok_or_else(|| RdfEditorError::Sparql("Missing subject".to_string()))?;
+                let subject = if binding["s"]["type"].as_str() == Some("bnode") {
+                    format!("_:{}", subject)
+                } else {
+                    subject.to_string()
+                };
                 
                 let predicate = binding["p"]["value"].as_str()
                     .ok_or_else(|| RdfEditorError::Sparql("Missing predicate".to_string()))?
@@ -505,6 +689,9 @@ impl SparqlClient {
                 
                 let object = self.parse_rdf_node(&binding["o"])?;
                 
+                // Dataset queries bind the graph per row
+                let graph = binding["g"]["value"].as_str().or(graph);
+                
                 triples.push(Triple {
                     subject,
                     predicate,
@@ -536,4 +723,107 @@ impl SparqlClient {
             _ => Err(RdfEditorError::Sparql(format!("Unknown node type: {}", node_type))),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Triples grouped by the graph they name, "default" for the default graph, in first-seen order
+pub fn by_graph(triples: &[Triple]) -> Vec<(String, Vec<Triple>)> {
+    let mut groups: Vec<(String, Vec<Triple>)> = Vec::new();
+    for triple in triples {
+        let graph = triple.graph.as_deref().unwrap_or("default");
+        match groups.iter_mut().find(|(g, _)| g == graph) {
+            Some((_, group)) => group.push(triple.clone()),
+            None => groups.push((graph.to_string(), vec![triple.clone()])),
+        }
+    }
+    groups
+}
+
+/// A deletion (see `delete_data`) for each graph of `deletions`, then INSERT DATA for
+/// each graph of `insertions`
+pub fn change_updates(deletions: &[Triple], insertions: &[Triple]) -> Vec<String> {
+    let mut updates: Vec<String> = by_graph(deletions).iter()
+        .map(|(graph, triples)| delete_data(graph, triples))
+        .collect();
+    updates.extend(by_graph(insertions).iter().map(|(graph, triples)| insert_data(graph, triples)));
+    updates
+}
+
+/// Update deleting `triples` from `graph`, "default" meaning the default graph. DELETE DATA
+/// takes no blank nodes, so triples holding them are deleted by matching instead: each
+/// group linked through shared blank nodes is matched as a whole, its blank nodes turned
+/// into variables that may only bind blank nodes.
+fn delete_data(graph: &str, triples: &[Triple]) -> String {
+    let (blank, ground): (Vec<Triple>, Vec<Triple>) = triples.iter().cloned()
+        .partition(|triple| !triple.blank_nodes().is_empty());
+    
+    let mut operations = Vec::new();
+    if !ground.is_empty() {
+        operations.push(insert_data(graph, &ground).replacen("INSERT DATA", "DELETE DATA", 1));
+    }
+    for (pattern, variables) in blank_node_patterns(&blank) {
+        let filter: Vec<String> = variables.iter().map(|variable| format!("isBlank({})", variable)).collect();
+        let matched = format!("{} FILTER({})", pattern, filter.join(" && "));
+        operations.push(if graph == "default" {
+            format!("DELETE {{{} }} WHERE {{{} }}", pattern, matched)
+        } else {
+            let graph = sanitize_iri(graph);
+            format!("DELETE {{ GRAPH <{}> {{{} }} }} WHERE {{ GRAPH <{}> {{{} }} }}", graph, pattern, graph, matched)
+        });
+    }
+    operations.join(" ;\n")
+}
+
+/// Patterns for `triples` with their blank nodes as variables, one per group of
+/// triples linked through shared blank nodes, with the variables each uses
+fn blank_node_patterns(triples: &[Triple]) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(HashSet<&str>, Vec<&Triple>)> = Vec::new();
+    for triple in triples {
+        let mut group: (HashSet<&str>, Vec<&Triple>) = (triple.blank_nodes().into_iter().collect(), vec![triple]);
+        let (linked, unlinked): (Vec<_>, Vec<_>) = groups.into_iter()
+            .partition(|(labels, _)| !labels.is_disjoint(&group.0));
+        for (labels, members) in linked {
+            group.0.extend(labels);
+            group.1.extend(members);
+        }
+        groups = unlinked;
+        groups.push(group);
+    }
+    
+    groups.into_iter().map(|(_, members)| {
+        let mut variables: Vec<&str> = Vec::new();
+        let mut variable = |label| {
+            let index = variables.iter().position(|&known| known == label).unwrap_or_else(|| {
+                variables.push(label);
+                variables.len() - 1
+            });
+            format!("?b{}", index)
+        };
+        let mut pattern = String::new();
+        for triple in members {
+            let mut text = triple.to_sparql_pattern();
+            if let Some(label) = triple.subject.strip_prefix("_:") {
+                text = format!("{}{}", variable(label), &text[sanitize_blank_label(label).len() + 2..]);
+            }
+            if let RdfNode::Blank { value } = &triple.object {
+                text = format!("{}{}", &text[..text.len() - sanitize_blank_label(value).len() - 2], variable(value));
+            }
+            pattern.push_str(&format!(" {} .", text));
+        }
+        let names = (0..variables.len()).map(|index| format!("?b{}", index)).collect();
+        (pattern, names)
+    }).collect()
+}
+
+/// INSERT DATA for `triples` into `graph`, "default" meaning the default graph
+fn insert_data(graph: &str, triples: &[Triple]) -> String {
+    let mut data = String::new();
+    for triple in triples {
+        data.push_str(&format!(" {} .", triple.to_sparql_pattern()));
+    }
+    
+    if graph == "default" {
+        format!("INSERT DATA {{{} }}", data)
+    } else {
+        format!("INSERT DATA {{ GRAPH <{}> {{{} }} }}", sanitize_iri(graph), data)
+    }
+}
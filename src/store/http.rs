@@ -0,0 +1,624 @@
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose};
+use futures_util::StreamExt;
+use rand::Rng;
+use reqwest::{Certificate, Client, RequestBuilder, Response, StatusCode, header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, AUTHORIZATION, LOCATION, RETRY_AFTER}};
+use serde_json::Value;
+use std::time::Duration;
+use url::Url;
+use crate::{config::{Config, EndpointProfile}, dialect::{StoreDialect, TransactionApi}, error::RdfEditorError, formats::ntriples, models::{Namespace, RdfFormat, Triple}};
+use super::{ByteStream, Store};
+
+/// Delay before the first retry; doubles with every further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+/// Upper bound for the backoff, and the longest `Retry-After` worth waiting for
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Remote SPARQL endpoint, using the store's native APIs where its dialect has them
+pub struct HttpStore {
+    client: Client,
+    query_endpoint: String,      // For SELECT, ASK, CONSTRUCT, DESCRIBE
+    update_endpoint: String,     // For INSERT, DELETE, etc.
+    graph_store_endpoint: Option<String>, // Graph Store Protocol, for whole-graph reads and writes
+    dialect: StoreDialect,
+    timeout: Duration,
+    max_retries: u32,
+}
+
+/// The kinds of request sent to the store, which differ in what is safe to retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Query,
+    Update,
+    GraphGet,
+    GraphPut,
+    GraphPost,
+    GraphDelete,
+    Transaction,
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Query => "Query",
+            Operation::Update => "Update",
+            Operation::GraphGet => "Graph store GET",
+            Operation::GraphPut => "Graph store PUT",
+            Operation::GraphPost => "Graph store POST",
+            Operation::GraphDelete => "Graph store DELETE",
+            Operation::Transaction => "Transaction",
+        }
+    }
+    
+    /// Reads and the idempotent graph store writes (PUT, DELETE) retry any overload or
+    /// gateway status. An update or POST behind a 502/504 may already have been applied,
+    /// so those only retry 429 (rejected before processing).
+    fn retries_status(self, status: StatusCode) -> bool {
+        match self {
+            Operation::Query | Operation::GraphGet | Operation::GraphPut | Operation::GraphDelete => {
+                matches!(status.as_u16(), 429 | 502 | 503 | 504)
+            },
+            Operation::Update | Operation::GraphPost | Operation::Transaction => status == StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+    
+    /// A graph store GET or DELETE answers 404 for a graph that does not exist
+    fn accepts_not_found(self) -> bool {
+        matches!(self, Operation::GraphGet | Operation::GraphDelete)
+    }
+    
+    /// Whole graphs streamed to or from the graph store take as long as they take;
+    /// everything else must finish within the configured timeout
+    fn has_deadline(self) -> bool {
+        !matches!(self, Operation::GraphGet | Operation::GraphPut | Operation::GraphPost)
+    }
+}
+
+impl HttpStore {
+    /// Client for one endpoint profile; timeout and retries are shared by all profiles.
+    /// The timeout bounds connecting and every request but graph store transfers.
+    pub fn new(config: &Config, profile: &EndpointProfile) -> Result<Self, RdfEditorError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/sparql-results+json"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+        
+        // Add authentication if provided
+        if let Some(username) = &profile.sparql_username {
+            let password = profile.get_password()
+                .ok_or_else(|| RdfEditorError::Configuration("Password required when username is provided".to_string()))?;
+            
+            let auth = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Basic {}", auth))?);
+        }
+        
+        let timeout = Duration::from_secs(config.timeout_seconds);
+        let mut builder = Client::builder()
+            .default_headers(headers)
+            .connect_timeout(timeout)
+            .danger_accept_invalid_certs(!profile.verify_ssl);
+        
+        if let Some(path) = &profile.ca_cert {
+            let pem = std::fs::read(path).map_err(|e| RdfEditorError::Configuration(
+                format!("Failed to read CA certificate {} for profile {}: {}", path, profile.name, e)
+            ))?;
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        
+        Ok(HttpStore {
+            client: builder.build()?,
+            query_endpoint: profile.sparql_endpoint.clone(),
+            update_endpoint: profile.get_update_endpoint().to_string(),
+            graph_store_endpoint: profile.graph_store_endpoint.clone(),
+            dialect: profile.dialect,
+            timeout,
+            max_retries: config.max_retries,
+        })
+    }
+    
+    /// Open a store transaction; returns its id (Stardog) or URL (RDF4J)
+    async fn begin_transaction(&self, api: TransactionApi, base: &str) -> Result<String, RdfEditorError> {
+        let response = match api {
+            TransactionApi::Stardog => {
+                let url = format!("{}/transaction/begin", base);
+                self.send(Operation::Transaction, || self.client.post(&url)).await?
+            },
+            TransactionApi::Rdf4j => {
+                let url = format!("{}/transactions", base);
+                self.send(Operation::Transaction, || self.client.post(&url)).await?
+            },
+        };
+        
+        match api {
+            TransactionApi::Stardog => Ok(response.text().await.map_err(|e| self.request_error(e))?.trim().to_string()),
+            TransactionApi::Rdf4j => response.headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(String::from)
+                .ok_or_else(|| RdfEditorError::Sparql("Transaction started without a Location header".to_string())),
+        }
+    }
+    
+    async fn transaction_update(&self, api: TransactionApi, base: &str, transaction: &str, update: &str) -> Result<(), RdfEditorError> {
+        log::debug!("Update in transaction {}: {}", transaction, update);
+        match api {
+            TransactionApi::Stardog => {
+                let url = format!("{}/{}/update", base, transaction);
+                self.send(Operation::Transaction, || self.client.post(&url).form(&[("update", update)])).await?;
+            },
+            TransactionApi::Rdf4j => {
+                self.send(Operation::Transaction, || {
+                    self.client.put(transaction)
+                        .query(&[("action", "UPDATE")])
+                        .header(CONTENT_TYPE, "application/sparql-update")
+                        .body(update.to_string())
+                }).await?;
+            },
+        }
+        Ok(())
+    }
+    
+    /// Commit, or roll back when `commit` is false
+    async fn end_transaction(&self, api: TransactionApi, base: &str, transaction: &str, commit: bool) -> Result<(), RdfEditorError> {
+        match api {
+            TransactionApi::Stardog => {
+                let action = if commit { "commit" } else { "rollback" };
+                let url = format!("{}/transaction/{}/{}", base, action, transaction);
+                self.send(Operation::Transaction, || self.client.post(&url)).await?;
+            },
+            TransactionApi::Rdf4j if commit => {
+                self.send(Operation::Transaction, || self.client.put(transaction).query(&[("action", "COMMIT")])).await?;
+            },
+            TransactionApi::Rdf4j => {
+                self.send(Operation::Transaction, || self.client.delete(transaction)).await?;
+            },
+        }
+        Ok(())
+    }
+    
+    /// Send the request `build` makes, retrying with exponential backoff what is safe to
+    /// retry: connection failures (the request never reached the store) and the statuses
+    /// allowed by `Operation::retries_status`.
+    async fn send(&self, operation: Operation, build: impl Fn() -> RequestBuilder) -> Result<Response, RdfEditorError> {
+        let mut attempt = 0;
+        loop {
+            let request = if operation.has_deadline() { build().timeout(self.timeout) } else { build() };
+            let (error, retry_after) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if response.status() == StatusCode::NOT_FOUND && operation.accepts_not_found() => {
+                    return Ok(response);
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = retry_after(response.headers());
+                    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                    log::error!("{} failed with status {}: {}", operation.name(), status, error_text);
+                    let error = RdfEditorError::Sparql(format!("{} failed with status {}: {}", operation.name(), status, error_text));
+                    if !operation.retries_status(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                },
+                Err(e) if e.is_connect() => (RdfEditorError::Http(e), None),
+                Err(e) => return Err(self.request_error(e)),
+            };
+            
+            let delay = retry_after.unwrap_or_else(|| backoff(attempt));
+            if attempt >= self.max_retries || delay > RETRY_MAX_DELAY {
+                return Err(error);
+            }
+            
+            attempt += 1;
+            log::warn!("Retrying {} in {:?} (attempt {} of {}): {}", operation.name(), delay, attempt, self.max_retries, error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    fn request_error(&self, e: reqwest::Error) -> RdfEditorError {
+        if e.is_timeout() {
+            RdfEditorError::Timeout(format!("SPARQL endpoint did not respond within {} seconds", self.timeout.as_secs_f64()))
+        } else {
+            RdfEditorError::Http(e)
+        }
+    }
+    
+    /// Graph store URL for `graph`, "default" meaning the default graph
+    fn graph_store_url(&self, graph: &str) -> Result<Url, RdfEditorError> {
+        let endpoint = self.graph_store_endpoint.as_deref()
+            .ok_or_else(|| RdfEditorError::Configuration("No graph store endpoint configured".to_string()))?;
+        let mut url = Url::parse(endpoint)
+            .map_err(|e| RdfEditorError::Configuration(format!("Invalid graph store endpoint {}: {}", endpoint, e)))?;
+        
+        if graph == "default" {
+            url.query_pairs_mut().append_key_only("default");
+        } else {
+            url.query_pairs_mut().append_pair("graph", graph);
+        }
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Store for HttpStore {
+    fn dialect(&self) -> StoreDialect {
+        self.dialect
+    }
+    
+    /// Only dialects with a per-query reasoning toggle accept `Some(_)`
+    async fn query(&self, query: &str, reasoning: Option<bool>) -> Result<Value, RdfEditorError> {
+        log::debug!("Executing SPARQL query on endpoint: {}", self.query_endpoint);
+        log::debug!("Query: {}", query);
+        
+        let reasoning = match reasoning {
+            Some(enabled) => {
+                let parameter = self.dialect.reasoning_parameter().ok_or_else(|| RdfEditorError::InvalidInput(
+                    format!("Reasoning cannot be toggled per query on {}", self.dialect)
+                ))?;
+                vec![(parameter, enabled.to_string())]
+            },
+            None => Vec::new(),
+        };
+        
+        let response = self.send(Operation::Query, || {
+            self.client.post(&self.query_endpoint).query(&reasoning).form(&[("query", query)])
+        }).await?;
+        
+        let result = response.json::<Value>().await
+            .map_err(|e| self.request_error(e))?;
+        Ok(result)
+    }
+    
+    async fn update(&self, update: &str) -> Result<(), RdfEditorError> {
+        log::debug!("Executing SPARQL update on endpoint: {}", self.update_endpoint);
+        log::debug!("Update: {}", update);
+        
+        self.send(Operation::Update, || {
+            self.client.post(&self.update_endpoint).form(&[("update", update)])
+        }).await?;
+        
+        Ok(())
+    }
+    
+    /// Inside a store transaction where the dialect has a transaction API, otherwise
+    /// joined into a single update request
+    async fn update_atomic(&self, updates: &[String]) -> Result<(), RdfEditorError> {
+        match updates {
+            [] => return Ok(()),
+            [update] => return self.update(update).await,
+            _ => {},
+        }
+        
+        let Some((api, base)) = self.dialect.transaction_api(&self.query_endpoint) else {
+            return self.update(&updates.join(" ;\n")).await;
+        };
+        
+        let transaction = self.begin_transaction(api, &base).await?;
+        for update in updates {
+            if let Err(e) = self.transaction_update(api, &base, &transaction, update).await {
+                if let Err(rollback) = self.end_transaction(api, &base, &transaction, false).await {
+                    log::error!("Failed to roll back transaction {}: {}", transaction, rollback);
+                }
+                return Err(e);
+            }
+        }
+        self.end_transaction(api, &base, &transaction, true).await
+    }
+    
+    /// Read from the namespace API, for dialects that have one
+    async fn namespaces(&self) -> Result<Option<Vec<Namespace>>, RdfEditorError> {
+        let Some(url) = self.dialect.namespaces_endpoint(&self.query_endpoint) else {
+            return Ok(None);
+        };
+        
+        let response = self.send(Operation::Query, || self.client.get(&url).header(ACCEPT, "application/json")).await?;
+        let body = response.json::<Value>().await.map_err(|e| self.request_error(e))?;
+        
+        let namespace = |prefix: &Value, uri: &Value| Some(Namespace {
+            prefix: prefix.as_str()?.to_string(),
+            uri: uri.as_str()?.to_string(),
+        });
+        let namespaces = match self.dialect {
+            // {"namespaces": [{"prefix": "owl", "name": "http://..."}]}
+            StoreDialect::Stardog => body["namespaces"].as_array()
+                .map(|list| list.iter().filter_map(|ns| namespace(&ns["prefix"], &ns["name"])).collect()),
+            // SPARQL results with ?prefix and ?namespace
+            _ => body["results"]["bindings"].as_array()
+                .map(|list| list.iter().filter_map(|b| namespace(&b["prefix"]["value"], &b["namespace"]["value"])).collect()),
+        };
+        
+        Ok(Some(namespaces.unwrap_or_default()))
+    }
+    
+    fn has_graph_store(&self) -> bool {
+        self.graph_store_endpoint.is_some()
+    }
+    
+    /// The response body is streamed on as it arrives
+    async fn graph_store_get(&self, graph: &str, format: RdfFormat) -> Result<Option<ByteStream>, RdfEditorError> {
+        let url = self.graph_store_url(graph)?;
+        log::debug!("Graph store GET {}", url);
+        
+        let response = self.send(Operation::GraphGet, || {
+            self.client.get(url.clone()).header(ACCEPT, format.content_type())
+        }).await?;
+        
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.bytes_stream().map(|bytes| bytes.map_err(RdfEditorError::Http)).boxed()))
+    }
+    
+    /// One N-Triples document, so blank node labels are shared across it: PUT replaces
+    /// the graph, POST adds to it
+    async fn graph_store_write(&self, graph: &str, triples: &[Triple], replace: bool) -> Result<(), RdfEditorError> {
+        let operation = if replace { Operation::GraphPut } else { Operation::GraphPost };
+        let url = self.graph_store_url(graph)?;
+        let body = ntriples::serialize(triples);
+        log::debug!("{} {} ({} triples)", operation.name(), url, triples.len());
+        
+        self.send(operation, || {
+            let request = match operation {
+                Operation::GraphPut => self.client.put(url.clone()),
+                _ => self.client.post(url.clone()),
+            };
+            request.header(CONTENT_TYPE, RdfFormat::NTriples.content_type()).body(body.clone())
+        }).await?;
+        
+        Ok(())
+    }
+    
+    async fn graph_store_delete(&self, graph: &str) -> Result<(), RdfEditorError> {
+        let url = self.graph_store_url(graph)?;
+        log::debug!("Graph store DELETE {}", url);
+        self.send(Operation::GraphDelete, || self.client.delete(url.clone())).await?;
+        Ok(())
+    }
+}
+
+/// Backoff before retry number `attempt + 1`: doubling from `RETRY_BASE_DELAY`, capped at
+/// `RETRY_MAX_DELAY`, with the upper half jittered so clients do not retry in lockstep
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RETRY_MAX_DELAY);
+    let half = ceiling / 2;
+    half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+/// Delay requested by a `Retry-After` header, given in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RdfNode;
+    use crate::sparql::SparqlClient;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/sparql-results+json\r\nContent-Length: 2\r\n\r\n{}";
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n";
+    const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+    const TOO_MANY: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n";
+    
+    /// Serve the canned responses in order, repeating the last one; returns the URL and a request counter
+    async fn serve(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        let (url, requests, _) = record(responses).await;
+        (url, requests)
+    }
+    
+    /// Like `serve`, also keeping the start of every request received
+    async fn record(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sparql", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let counter = requests.clone();
+        let log = received.clone();
+        
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let response = responses[n.min(responses.len() - 1)];
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let request = read_request(&mut socket).await;
+                    log.lock().unwrap().push(request);
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        
+        (url, requests, received)
+    }
+    
+    /// Read one request: the head, then as much body as Content-Length announces
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let text = String::from_utf8_lossy(&request).into_owned();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head.lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().to_string()))
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return text;
+                }
+            }
+            match socket.read(&mut buffer).await {
+                Ok(0) | Err(_) => return String::from_utf8_lossy(&request).into_owned(),
+                Ok(read) => request.extend_from_slice(&buffer[..read]),
+            }
+        }
+    }
+    
+    fn client(url: &str, timeout: Duration) -> HttpStore {
+        HttpStore {
+            client: Client::builder().connect_timeout(timeout).build().unwrap(),
+            query_endpoint: url.to_string(),
+            update_endpoint: url.to_string(),
+            graph_store_endpoint: None,
+            dialect: StoreDialect::Generic,
+            timeout,
+            max_retries: 2,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_queries_retry_overload_statuses() {
+        let (url, requests) = serve(vec![UNAVAILABLE, TOO_MANY, OK]).await;
+        
+        let result = client(&url, Duration::from_secs(5)).query("ASK {}", None).await;
+        
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_retries_stop_at_max_retries() {
+        let (url, requests) = serve(vec![UNAVAILABLE]).await;
+        
+        let result = client(&url, Duration::from_secs(5)).query("ASK {}", None).await;
+        
+        assert!(matches!(result, Err(RdfEditorError::Sparql(_))), "{:?}", result);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_updates_only_retry_rate_limits() {
+        let (url, requests) = serve(vec![UNAVAILABLE, OK]).await;
+        let result = client(&url, Duration::from_secs(5)).update("CLEAR ALL").await;
+        assert!(result.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        
+        let (url, requests) = serve(vec![TOO_MANY, OK]).await;
+        let result = client(&url, Duration::from_secs(5)).update("CLEAR ALL").await;
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_slow_endpoint_surfaces_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sparql", listener.local_addr().unwrap());
+        // Accept connections but never answer
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        
+        let result = client(&url, Duration::from_millis(200)).query("ASK {}", None).await;
+        
+        assert!(matches!(result, Err(RdfEditorError::Timeout(_))), "{:?}", result);
+    }
+    
+    #[tokio::test]
+    async fn test_graph_store_downloads_outlast_the_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sparql", listener.local_addr().unwrap());
+        // Send the body in two halves, the second after the timeout has passed
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_request(&mut socket).await;
+            let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nfirst").await;
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            let _ = socket.write_all(b"-half").await;
+        });
+        
+        let mut store = client(&url, Duration::from_millis(200));
+        store.graph_store_endpoint = Some(url.clone());
+        let mut stream = store.graph_store_get("default", RdfFormat::NTriples).await.unwrap().unwrap();
+        let mut body = Vec::new();
+        while let Some(bytes) = stream.next().await {
+            body.extend_from_slice(&bytes.unwrap());
+        }
+        
+        assert_eq!(body, b"first-half");
+    }
+    
+    #[tokio::test]
+    async fn test_graph_store_requests() {
+        let (url, _, received) = record(vec![OK, OK, NOT_FOUND]).await;
+        let mut store = client(&url, Duration::from_secs(5));
+        store.graph_store_endpoint = Some(url.clone());
+        let client = SparqlClient::with_store(Box::new(store), false);
+        let triple = Triple {
+            subject: "http://example.com/s".to_string(),
+            predicate: "http://example.com/p".to_string(),
+            object: RdfNode::Uri { value: "http://example.com/o".to_string() },
+            graph: None,
+        };
+        
+        client.replace_graph("http://example.com/g", std::slice::from_ref(&triple)).await.unwrap();
+        client.add_quads_batch(&[triple], "default").await.unwrap();
+        // A missing graph is already dropped
+        client.drop_graph("http://example.com/g").await.unwrap();
+        
+        let received = received.lock().unwrap();
+        assert!(received[0].starts_with("PUT /sparql?graph=http%3A%2F%2Fexample.com%2Fg "), "{}", received[0]);
+        assert!(received[0].contains("content-type: application/n-triples"));
+        assert!(received[0].ends_with("<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n"));
+        assert!(received[1].starts_with("POST /sparql?default "), "{}", received[1]);
+        assert!(received[2].starts_with("DELETE /sparql?graph="), "{}", received[2]);
+    }
+    
+    #[tokio::test]
+    async fn test_stardog_transaction_rolls_back_on_failure() {
+        const BEGIN: &str = "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntx1";
+        const FAILED: &str = "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n";
+        let (url, _, received) = record(vec![BEGIN, OK, FAILED, OK]).await;
+        let mut client = client(&url.replace("/sparql", "/db/query"), Duration::from_secs(5));
+        client.dialect = StoreDialect::Stardog;
+        
+        let updates = vec!["CLEAR GRAPH <urn:a>".to_string(), "CLEAR GRAPH <urn:b>".to_string()];
+        let result = client.update_atomic(&updates).await;
+        
+        assert!(matches!(result, Err(RdfEditorError::Sparql(_))), "{:?}", result);
+        let received = received.lock().unwrap();
+        let lines: Vec<&str> = received.iter().map(|r| r.lines().next().unwrap()).collect();
+        assert_eq!(lines, vec![
+            "POST /db/transaction/begin HTTP/1.1",
+            "POST /db/tx1/update HTTP/1.1",
+            "POST /db/tx1/update HTTP/1.1",
+            "POST /db/transaction/rollback/tx1 HTTP/1.1",
+        ]);
+    }
+    
+    #[test]
+    fn test_backoff_doubles_with_jitter_and_caps() {
+        for attempt in 0..4 {
+            let ceiling = RETRY_BASE_DELAY * 2u32.pow(attempt);
+            let delay = backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+        }
+        assert!(backoff(40) <= RETRY_MAX_DELAY);
+    }
+    
+    #[test]
+    fn test_retry_after_accepts_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
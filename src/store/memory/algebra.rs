@@ -0,0 +1,261 @@
+//! Parsed form of queries and updates, as evaluated by `eval`.
+
+use super::term::Term;
+
+/// Prefix of the hidden variables standing for blank nodes and aggregates;
+/// `:` cannot appear in a SPARQL variable name, so these never clash
+pub const HIDDEN_PREFIX: &str = "_:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermPattern {
+    Term(Term),
+    Variable(String),
+    /// Blank node in a template, fresh for every solution
+    BlankNode(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Path {
+    Link(Term),
+    Inverse(Box<Path>),
+    Sequence(Vec<Path>),
+    Alternative(Vec<Path>),
+    ZeroOrMore(Box<Path>),
+    OneOrMore(Box<Path>),
+    ZeroOrOne(Box<Path>),
+    /// `!(p1|^p2)`: forward IRIs excluded, inverse IRIs excluded
+    Negated(Vec<Term>, Vec<Term>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verb {
+    Term(TermPattern),
+    Path(Path),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriplePattern {
+    pub subject: TermPattern,
+    pub verb: Verb,
+    pub object: TermPattern,
+}
+
+/// Triple template with the graph it is written to or deleted from
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuadPattern {
+    pub graph: Option<TermPattern>,
+    pub triple: TriplePattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Bgp(Vec<TriplePattern>),
+    Join(Box<Pattern>, Box<Pattern>),
+    LeftJoin(Box<Pattern>, Box<Pattern>),
+    Union(Box<Pattern>, Box<Pattern>),
+    Minus(Box<Pattern>, Box<Pattern>),
+    Filter(Expr, Box<Pattern>),
+    Graph(TermPattern, Box<Pattern>),
+    Extend(Box<Pattern>, String, Expr),
+    Values(Vec<String>, Vec<Vec<Option<Term>>>),
+    SubSelect(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Constant(Term),
+    Variable(String),
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    Arithmetic(ArithmeticOp, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    In(Box<Expr>, Vec<Expr>, bool),
+    Exists(Box<Pattern>, bool),
+    /// Built-in function, name upper-cased
+    Call(String, Vec<Expr>),
+    /// Function named by IRI: the XSD casts
+    Function(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Sample,
+    GroupConcat,
+}
+
+/// An aggregate, computed per group into the hidden variable `variable`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub distinct: bool,
+    /// `None` for `COUNT(*)`
+    pub expr: Option<Expr>,
+    pub separator: String,
+    pub variable: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    All,
+    Variables(Vec<(String, Option<Expr>)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryForm {
+    Select { projection: Projection, distinct: bool },
+    Ask,
+    Construct(Vec<TriplePattern>),
+    Describe(Vec<TermPattern>),
+}
+
+/// FROM and FROM NAMED graphs
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DatasetClause {
+    pub default: Vec<Term>,
+    pub named: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub form: QueryForm,
+    pub dataset: Option<DatasetClause>,
+    pub pattern: Pattern,
+    pub group_by: Vec<(Expr, Option<String>)>,
+    pub aggregates: Vec<Aggregate>,
+    pub having: Vec<Expr>,
+    /// Expressions with `true` for descending
+    pub order_by: Vec<(Expr, bool)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+/// Target of CLEAR and DROP
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphTarget {
+    Default,
+    Named(Term),
+    AllNamed,
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphTransfer {
+    Add,
+    Move,
+    Copy,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateOperation {
+    InsertData(Vec<QuadPattern>),
+    DeleteData(Vec<QuadPattern>),
+    Modify {
+        with: Option<Term>,
+        delete: Vec<QuadPattern>,
+        insert: Vec<QuadPattern>,
+        using: Option<DatasetClause>,
+        pattern: Pattern,
+    },
+    Clear { target: GraphTarget, silent: bool },
+    Drop { target: GraphTarget, silent: bool },
+    Create { graph: Term, silent: bool },
+    /// Source and destination, `None` being the default graph
+    Transfer { kind: GraphTransfer, from: Option<Term>, to: Option<Term>, silent: bool },
+}
+
+impl Pattern {
+    pub fn empty() -> Self {
+        Pattern::Bgp(Vec::new())
+    }
+
+    /// Variables the pattern can bind, in order of first appearance
+    pub fn variables(&self, out: &mut Vec<String>) {
+        fn add(out: &mut Vec<String>, name: &str) {
+            if !name.starts_with(HIDDEN_PREFIX) && !out.iter().any(|v| v == name) {
+                out.push(name.to_string());
+            }
+        }
+        fn term(out: &mut Vec<String>, pattern: &TermPattern) {
+            if let TermPattern::Variable(name) = pattern {
+                add(out, name);
+            }
+        }
+
+        match self {
+            Pattern::Bgp(triples) => {
+                for triple in triples {
+                    term(out, &triple.subject);
+                    if let Verb::Term(verb) = &triple.verb {
+                        term(out, verb);
+                    }
+                    term(out, &triple.object);
+                }
+            },
+            Pattern::Join(left, right) | Pattern::LeftJoin(left, right)
+            | Pattern::Union(left, right) => {
+                left.variables(out);
+                right.variables(out);
+            },
+            Pattern::Minus(left, _) | Pattern::Filter(_, left) => left.variables(out),
+            Pattern::Graph(name, inner) => {
+                term(out, name);
+                inner.variables(out);
+            },
+            Pattern::Extend(inner, name, _) => {
+                inner.variables(out);
+                add(out, name);
+            },
+            Pattern::Values(names, _) => {
+                for name in names {
+                    add(out, name);
+                }
+            },
+            Pattern::SubSelect(query) => {
+                for name in query.projected_variables() {
+                    add(out, &name);
+                }
+            },
+        }
+    }
+}
+
+impl Query {
+    /// Variables in the results, in order
+    pub fn projected_variables(&self) -> Vec<String> {
+        match &self.form {
+            QueryForm::Select { projection: Projection::Variables(variables), .. } => {
+                variables.iter().map(|(name, _)| name.clone()).collect()
+            },
+            _ => {
+                let mut variables = Vec::new();
+                self.pattern.variables(&mut variables);
+                variables
+            },
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, BTreeSet};
+use super::term::{Statement, Term};
+
+type Index = BTreeMap<Term, BTreeMap<Term, BTreeSet<Term>>>;
+
+/// One graph, indexed three ways so any pattern with a bound position is a lookup
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    spo: Index,
+    pos: Index,
+    osp: Index,
+}
+
+fn index_insert(index: &mut Index, a: &Term, b: &Term, c: &Term) {
+    index.entry(a.clone()).or_default().entry(b.clone()).or_default().insert(c.clone());
+}
+
+fn index_remove(index: &mut Index, a: &Term, b: &Term, c: &Term) {
+    if let Some(second) = index.get_mut(a) {
+        if let Some(third) = second.get_mut(b) {
+            third.remove(c);
+            if third.is_empty() {
+                second.remove(b);
+            }
+        }
+        if second.is_empty() {
+            index.remove(a);
+        }
+    }
+}
+
+impl Graph {
+    pub fn contains(&self, s: &Term, p: &Term, o: &Term) -> bool {
+        self.spo.get(s).and_then(|ps| ps.get(p)).is_some_and(|os| os.contains(o))
+    }
+
+    /// Add a statement; false when it was already present
+    pub fn insert(&mut self, (s, p, o): &Statement) -> bool {
+        if self.contains(s, p, o) {
+            return false;
+        }
+        index_insert(&mut self.spo, s, p, o);
+        index_insert(&mut self.pos, p, o, s);
+        index_insert(&mut self.osp, o, s, p);
+        true
+    }
+
+    /// Remove a statement; false when it was not present
+    pub fn remove(&mut self, (s, p, o): &Statement) -> bool {
+        if !self.contains(s, p, o) {
+            return false;
+        }
+        index_remove(&mut self.spo, s, p, o);
+        index_remove(&mut self.pos, p, o, s);
+        index_remove(&mut self.osp, o, s, p);
+        true
+    }
+
+    /// Statements matching a pattern, `None` standing for any term
+    pub fn matching(&self, s: Option<&Term>, p: Option<&Term>, o: Option<&Term>) -> Vec<Statement> {
+        let mut out = Vec::new();
+        match (s, p, o) {
+            (Some(s), Some(p), Some(o)) => {
+                if self.contains(s, p, o) {
+                    out.push((s.clone(), p.clone(), o.clone()));
+                }
+            },
+            (Some(s), Some(p), None) => {
+                for o in self.spo.get(s).and_then(|ps| ps.get(p)).into_iter().flatten() {
+                    out.push((s.clone(), p.clone(), o.clone()));
+                }
+            },
+            (Some(s), None, Some(o)) => {
+                for p in self.osp.get(o).and_then(|ss| ss.get(s)).into_iter().flatten() {
+                    out.push((s.clone(), p.clone(), o.clone()));
+                }
+            },
+            (Some(s), None, None) => {
+                for (p, os) in self.spo.get(s).into_iter().flatten() {
+                    out.extend(os.iter().map(|o| (s.clone(), p.clone(), o.clone())));
+                }
+            },
+            (None, Some(p), Some(o)) => {
+                for s in self.pos.get(p).and_then(|os| os.get(o)).into_iter().flatten() {
+                    out.push((s.clone(), p.clone(), o.clone()));
+                }
+            },
+            (None, Some(p), None) => {
+                for (o, ss) in self.pos.get(p).into_iter().flatten() {
+                    out.extend(ss.iter().map(|s| (s.clone(), p.clone(), o.clone())));
+                }
+            },
+            (None, None, Some(o)) => {
+                for (s, ps) in self.osp.get(o).into_iter().flatten() {
+                    out.extend(ps.iter().map(|p| (s.clone(), p.clone(), o.clone())));
+                }
+            },
+            (None, None, None) => {
+                for (s, ps) in &self.spo {
+                    for (p, os) in ps {
+                        out.extend(os.iter().map(|o| (s.clone(), p.clone(), o.clone())));
+                    }
+                }
+            },
+        }
+        out
+    }
+
+    /// Every term used as a subject or object
+    pub fn nodes(&self) -> BTreeSet<Term> {
+        self.spo.keys().chain(self.osp.keys()).cloned().collect()
+    }
+}
+
+/// The default graph and the named graphs, keyed by their IRI
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    pub default: Graph,
+    pub named: BTreeMap<Term, Graph>,
+}
+
+impl Dataset {
+    /// Graph for a name, `None` being the default graph
+    pub fn graph(&self, name: Option<&Term>) -> Option<&Graph> {
+        match name {
+            None => Some(&self.default),
+            Some(name) => self.named.get(name),
+        }
+    }
+
+    /// Graph for a name, created if it does not exist yet
+    pub fn graph_mut(&mut self, name: Option<&Term>) -> &mut Graph {
+        match name {
+            None => &mut self.default,
+            Some(name) => self.named.entry(name.clone()).or_default(),
+        }
+    }
+
+    /// Replace a graph's contents; `None` removes a named graph. Returns what was there.
+    pub fn set_graph(&mut self, name: Option<&Term>, graph: Option<Graph>) -> Option<Graph> {
+        match name {
+            None => Some(std::mem::replace(&mut self.default, graph.unwrap_or_default())),
+            Some(name) => match graph {
+                Some(graph) => self.named.insert(name.clone(), graph),
+                None => self.named.remove(name),
+            },
+        }
+    }
+}
@@ -0,0 +1,1107 @@
+//! Query evaluation over a `Dataset`.
+//!
+//! Patterns are evaluated left to right, each one extending the solutions
+//! produced so far, so a FILTER or OPTIONAL always sees the bindings made
+//! before it. Without FROM the default graph is the union of all graphs, as
+//! the graph views and the quad export expect; updates still write to the
+//! real default graph.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Timelike};
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use uuid::Uuid;
+use super::algebra::*;
+use super::dataset::{Dataset, Graph};
+use super::term::*;
+
+pub type Solution = BTreeMap<String, Term>;
+
+pub struct Evaluator<'a> {
+    /// Graphs whose union is the default graph
+    default: Vec<&'a Graph>,
+    /// Graphs reachable with GRAPH
+    named: Vec<(&'a Term, &'a Graph)>,
+    now: Term,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Evaluator over `dataset`, narrowed by FROM / USING clauses or an update's WITH graph
+    pub fn new(dataset: &'a Dataset, clause: Option<&DatasetClause>, with: Option<&Term>) -> Self {
+        let all_named: Vec<(&Term, &Graph)> = dataset.named.iter().collect();
+        let (default, named) = match (clause, with) {
+            (Some(clause), _) => (
+                clause.default.iter().filter_map(|name| dataset.named.get(name)).collect(),
+                all_named.into_iter().filter(|(name, _)| clause.named.contains(name)).collect(),
+            ),
+            (None, Some(with)) => (dataset.named.get(with).into_iter().collect(), all_named),
+            (None, None) => {
+                let mut default = vec![&dataset.default];
+                default.extend(dataset.named.values());
+                (default, all_named)
+            },
+        };
+
+        Evaluator {
+            default,
+            named,
+            now: Term::typed(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true), XSD_DATE_TIME),
+        }
+    }
+
+    /// Results of a query in the SPARQL 1.1 JSON format. CONSTRUCT and DESCRIBE
+    /// come back as `?s ?p ?o` bindings, one per statement.
+    pub fn query(&self, query: &Query) -> Value {
+        match &query.form {
+            QueryForm::Ask => {
+                let found = !self.pattern(&query.pattern, &self.default, vec![Solution::new()]).is_empty();
+                json!({"head": {}, "boolean": found})
+            },
+            QueryForm::Select { .. } => {
+                let variables = query.projected_variables();
+                let bindings: Vec<Value> = self.select(query, &self.default).iter()
+                    .map(|solution| {
+                        let row: serde_json::Map<String, Value> = variables.iter()
+                            .filter_map(|name| solution.get(name).map(|term| (name.clone(), term.to_json())))
+                            .collect();
+                        Value::Object(row)
+                    })
+                    .collect();
+                json!({"head": {"vars": variables}, "results": {"bindings": bindings}})
+            },
+            QueryForm::Construct(template) => {
+                let mut statements = BTreeSet::new();
+                for solution in self.select(query, &self.default) {
+                    let mut blanks = HashMap::new();
+                    for triple in template {
+                        if let Some(statement) = instantiate(triple, &solution, &mut blanks) {
+                            statements.insert(statement);
+                        }
+                    }
+                }
+                statements_json(statements)
+            },
+            QueryForm::Describe(resources) => {
+                let mut described = BTreeSet::new();
+                let solutions = self.select(query, &self.default);
+                for resource in resources {
+                    match resource {
+                        TermPattern::Term(term) => {
+                            described.insert(term.clone());
+                        },
+                        TermPattern::Variable(name) => {
+                            described.extend(solutions.iter().filter_map(|s| s.get(name).cloned()));
+                        },
+                        TermPattern::BlankNode(_) => {},
+                    }
+                }
+                if resources.is_empty() {
+                    for solution in &solutions {
+                        described.extend(solution.values().filter(|term| !term.is_literal()).cloned());
+                    }
+                }
+
+                let mut statements = BTreeSet::new();
+                for resource in &described {
+                    statements.extend(self.statements(&self.default, Some(resource), None, None));
+                }
+                statements_json(statements)
+            },
+        }
+    }
+
+    /// Solutions of the WHERE clause of an update
+    pub fn solutions(&self, pattern: &Pattern) -> Vec<Solution> {
+        self.pattern(pattern, &self.default, vec![Solution::new()])
+    }
+
+    /// Solutions of a SELECT (or the WHERE clause of another form) after grouping,
+    /// projection, ordering, DISTINCT, OFFSET and LIMIT
+    fn select(&self, query: &Query, view: &[&Graph]) -> Vec<Solution> {
+        let mut solutions = self.pattern(&query.pattern, view, vec![Solution::new()]);
+
+        if !query.group_by.is_empty() || !query.aggregates.is_empty() {
+            solutions = self.group(query, view, solutions);
+        }
+        for condition in &query.having {
+            solutions.retain(|solution| self.expr(condition, solution, view).and_then(|t| ebv(&t)) == Some(true));
+        }
+
+        let (projection, distinct) = match &query.form {
+            QueryForm::Select { projection, distinct } => (Some(projection), *distinct),
+            _ => (None, false),
+        };
+        if let Some(Projection::Variables(variables)) = projection {
+            for solution in &mut solutions {
+                for (name, expr) in variables {
+                    if let Some(expr) = expr {
+                        if let Some(value) = self.expr(expr, solution, view) {
+                            solution.entry(name.clone()).or_insert(value);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !query.order_by.is_empty() {
+            let keys: Vec<Vec<Option<Term>>> = solutions.iter()
+                .map(|solution| query.order_by.iter().map(|(expr, _)| self.expr(expr, solution, view)).collect())
+                .collect();
+            let mut order: Vec<usize> = (0..solutions.len()).collect();
+            order.sort_by(|&a, &b| {
+                for (i, (_, descending)) in query.order_by.iter().enumerate() {
+                    let ordering = order_terms(keys[a][i].as_ref(), keys[b][i].as_ref());
+                    let ordering = if *descending { ordering.reverse() } else { ordering };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                Ordering::Equal
+            });
+            let mut slots: Vec<Option<Solution>> = solutions.into_iter().map(Some).collect();
+            solutions = order.into_iter().filter_map(|i| slots[i].take()).collect();
+        }
+
+        if projection.is_some() {
+            let variables = query.projected_variables();
+            for solution in &mut solutions {
+                solution.retain(|name, _| variables.contains(name));
+            }
+        }
+        if distinct {
+            let mut seen = BTreeSet::new();
+            solutions.retain(|solution| seen.insert(solution.clone()));
+        }
+
+        let solutions = solutions.into_iter().skip(query.offset);
+        match query.limit {
+            Some(limit) => solutions.take(limit).collect(),
+            None => solutions.collect(),
+        }
+    }
+
+    /// One solution per group, binding the group keys and the aggregate values
+    fn group(&self, query: &Query, view: &[&Graph], solutions: Vec<Solution>) -> Vec<Solution> {
+        let mut groups: Vec<(Vec<Option<Term>>, Vec<Solution>)> = Vec::new();
+        let mut index: BTreeMap<Vec<Option<Term>>, usize> = BTreeMap::new();
+        for solution in solutions {
+            let key: Vec<Option<Term>> = query.group_by.iter()
+                .map(|(expr, _)| self.expr(expr, &solution, view))
+                .collect();
+            match index.get(&key) {
+                Some(&i) => groups[i].1.push(solution),
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, vec![solution]));
+                },
+            }
+        }
+        // Aggregating without GROUP BY gives one row even over no solutions
+        if groups.is_empty() && query.group_by.is_empty() {
+            groups.push((Vec::new(), Vec::new()));
+        }
+
+        groups.into_iter().map(|(key, members)| {
+            let mut solution = Solution::new();
+            for ((_, alias), value) in query.group_by.iter().zip(key) {
+                if let (Some(alias), Some(value)) = (alias, value) {
+                    solution.insert(alias.clone(), value);
+                }
+            }
+            for aggregate in &query.aggregates {
+                if let Some(value) = self.aggregate(aggregate, &members, view) {
+                    solution.insert(aggregate.variable.clone(), value);
+                }
+            }
+            solution
+        }).collect()
+    }
+
+    fn aggregate(&self, aggregate: &Aggregate, members: &[Solution], view: &[&Graph]) -> Option<Term> {
+        let Some(expr) = &aggregate.expr else {
+            // COUNT(*)
+            let count = if aggregate.distinct {
+                members.iter().collect::<BTreeSet<_>>().len()
+            } else {
+                members.len()
+            };
+            return Some(Term::integer(count as i64));
+        };
+
+        let mut values: Vec<Term> = members.iter().filter_map(|solution| self.expr(expr, solution, view)).collect();
+        if aggregate.distinct {
+            let mut seen = BTreeSet::new();
+            values.retain(|value| seen.insert(value.clone()));
+        }
+
+        match aggregate.function {
+            AggregateFunction::Count => Some(Term::integer(values.len() as i64)),
+            AggregateFunction::Sum => values.iter()
+                .try_fold(Number::Integer(0), |sum, value| arithmetic(ArithmeticOp::Add, sum, number(value)?))
+                .map(Number::to_term),
+            AggregateFunction::Avg => {
+                if values.is_empty() {
+                    return Some(Term::integer(0));
+                }
+                let sum = values.iter()
+                    .try_fold(Number::Integer(0), |sum, value| arithmetic(ArithmeticOp::Add, sum, number(value)?))?;
+                let sum = match sum {
+                    Number::Integer(i) => Number::Decimal(i as f64),
+                    other => other,
+                };
+                arithmetic(ArithmeticOp::Divide, sum, Number::Integer(values.len() as i64)).map(Number::to_term)
+            },
+            AggregateFunction::Min => values.into_iter().min_by(|a, b| order_terms(Some(a), Some(b))),
+            AggregateFunction::Max => values.into_iter().max_by(|a, b| order_terms(Some(a), Some(b))),
+            AggregateFunction::Sample => values.into_iter().next(),
+            AggregateFunction::GroupConcat => {
+                let joined: Vec<&str> = values.iter().map(Term::lexical).collect();
+                Some(Term::string(joined.join(&aggregate.separator)))
+            },
+        }
+    }
+
+    // Graph patterns
+
+    fn pattern(&self, pattern: &Pattern, view: &[&'a Graph], input: Vec<Solution>) -> Vec<Solution> {
+        if input.is_empty() {
+            return input;
+        }
+
+        match pattern {
+            Pattern::Bgp(triples) => {
+                let mut out = Vec::new();
+                let triples: Vec<&TriplePattern> = triples.iter().collect();
+                for solution in input {
+                    self.bgp(triples.clone(), view, solution, &mut out);
+                }
+                out
+            },
+            Pattern::Join(left, right) => {
+                let left = self.pattern(left, view, input);
+                self.pattern(right, view, left)
+            },
+            Pattern::LeftJoin(left, right) => {
+                let mut out = Vec::new();
+                for solution in self.pattern(left, view, input) {
+                    let extended = self.pattern(right, view, vec![solution.clone()]);
+                    if extended.is_empty() {
+                        out.push(solution);
+                    } else {
+                        out.extend(extended);
+                    }
+                }
+                out
+            },
+            Pattern::Union(left, right) => {
+                let mut out = self.pattern(left, view, input.clone());
+                out.extend(self.pattern(right, view, input));
+                out
+            },
+            Pattern::Minus(left, right) => {
+                let removed = self.pattern(right, view, vec![Solution::new()]);
+                let mut out = self.pattern(left, view, input);
+                out.retain(|solution| !removed.iter().any(|other| {
+                    other.keys().any(|name| solution.contains_key(name)) && compatible(solution, other)
+                }));
+                out
+            },
+            Pattern::Filter(condition, inner) => {
+                let mut out = self.pattern(inner, view, input);
+                out.retain(|solution| self.expr(condition, solution, view).and_then(|t| ebv(&t)) == Some(true));
+                out
+            },
+            Pattern::Graph(name, inner) => {
+                let mut out = Vec::new();
+                for solution in input {
+                    let bound = match name {
+                        TermPattern::Term(term) => Some(term.clone()),
+                        TermPattern::Variable(variable) => solution.get(variable).cloned(),
+                        TermPattern::BlankNode(_) => None,
+                    };
+                    match bound {
+                        Some(term) => {
+                            if let Some((_, graph)) = self.named.iter().find(|(n, _)| **n == term) {
+                                out.extend(self.pattern(inner, &[graph], vec![solution]));
+                            }
+                        },
+                        None => {
+                            let TermPattern::Variable(variable) = name else { continue };
+                            for (graph_name, graph) in &self.named {
+                                let mut extended = solution.clone();
+                                extended.insert(variable.clone(), (*graph_name).clone());
+                                out.extend(self.pattern(inner, &[graph], vec![extended]));
+                            }
+                        },
+                    }
+                }
+                out
+            },
+            Pattern::Extend(inner, name, expr) => {
+                let mut out = self.pattern(inner, view, input);
+                for solution in &mut out {
+                    if !solution.contains_key(name) {
+                        if let Some(value) = self.expr(expr, solution, view) {
+                            solution.insert(name.clone(), value);
+                        }
+                    }
+                }
+                out
+            },
+            Pattern::Values(names, rows) => {
+                let rows: Vec<Solution> = rows.iter().map(|row| {
+                    names.iter().zip(row)
+                        .filter_map(|(name, value)| value.clone().map(|v| (name.clone(), v)))
+                        .collect()
+                }).collect();
+                join_solutions(input, &rows)
+            },
+            Pattern::SubSelect(query) => {
+                let results = self.select(query, view);
+                join_solutions(input, &results)
+            },
+        }
+    }
+
+    /// Match the remaining triples, most constrained first
+    fn bgp(&self, mut remaining: Vec<&TriplePattern>, view: &[&Graph], solution: Solution, out: &mut Vec<Solution>) {
+        if remaining.is_empty() {
+            out.push(solution);
+            return;
+        }
+
+        let mut best = 0;
+        let mut best_bound = 0;
+        for (i, triple) in remaining.iter().enumerate() {
+            let bound = [&triple.subject, &triple.object].iter().filter(|t| resolve(t, &solution).is_some()).count()
+                + match &triple.verb {
+                    Verb::Term(verb) => resolve(verb, &solution).is_some() as usize,
+                    Verb::Path(_) => 0,
+                };
+            if i == 0 || bound > best_bound {
+                best = i;
+                best_bound = bound;
+            }
+        }
+        let triple = remaining.remove(best);
+
+        for extended in self.triple(triple, view, &solution) {
+            self.bgp(remaining.clone(), view, extended, out);
+        }
+    }
+
+    fn triple(&self, triple: &TriplePattern, view: &[&Graph], solution: &Solution) -> Vec<Solution> {
+        let subject = resolve(&triple.subject, solution);
+        let object = resolve(&triple.object, solution);
+        let mut out = Vec::new();
+
+        match &triple.verb {
+            Verb::Term(verb) => {
+                let predicate = resolve(verb, solution);
+                for (s, p, o) in self.statements(view, subject.as_ref(), predicate.as_ref(), object.as_ref()) {
+                    let mut extended = solution.clone();
+                    if bind(&mut extended, &triple.subject, &s) && bind(&mut extended, verb, &p) && bind(&mut extended, &triple.object, &o) {
+                        out.push(extended);
+                    }
+                }
+            },
+            Verb::Path(path) => {
+                for (s, o) in self.path_pairs(path, view, subject.as_ref(), object.as_ref()) {
+                    let mut extended = solution.clone();
+                    if bind(&mut extended, &triple.subject, &s) && bind(&mut extended, &triple.object, &o) {
+                        out.push(extended);
+                    }
+                }
+            },
+        }
+        out
+    }
+
+    /// Statements in the view matching a pattern; the union of several graphs has no duplicates
+    fn statements(&self, view: &[&Graph], s: Option<&Term>, p: Option<&Term>, o: Option<&Term>) -> Vec<Statement> {
+        match view {
+            [graph] => graph.matching(s, p, o),
+            graphs => graphs.iter()
+                .flat_map(|graph| graph.matching(s, p, o))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    // Property paths
+
+    fn path_pairs(&self, path: &Path, view: &[&Graph], start: Option<&Term>, end: Option<&Term>) -> Vec<(Term, Term)> {
+        match (start, end) {
+            (Some(start), _) => self.path_from(path, view, start).into_iter()
+                .filter(|o| end.is_none_or(|end| end == o))
+                .map(|o| (start.clone(), o))
+                .collect(),
+            (None, Some(end)) => self.path_from(&invert(path), view, end).into_iter()
+                .map(|s| (s, end.clone()))
+                .collect(),
+            (None, None) => {
+                let nodes: BTreeSet<Term> = view.iter().flat_map(|graph| graph.nodes()).collect();
+                nodes.into_iter()
+                    .flat_map(|node| {
+                        self.path_from(path, view, &node).into_iter().map(move |o| (node.clone(), o))
+                    })
+                    .collect()
+            },
+        }
+    }
+
+    /// Every node reachable from `start` along `path`
+    fn path_from(&self, path: &Path, view: &[&Graph], start: &Term) -> BTreeSet<Term> {
+        match path {
+            Path::Link(predicate) => self.statements(view, Some(start), Some(predicate), None)
+                .into_iter().map(|(_, _, o)| o).collect(),
+            Path::Inverse(inner) => match inner.as_ref() {
+                Path::Link(predicate) => self.statements(view, None, Some(predicate), Some(start))
+                    .into_iter().map(|(s, _, _)| s).collect(),
+                inner => self.path_from(&invert(inner), view, start),
+            },
+            Path::Sequence(steps) => {
+                let mut current = BTreeSet::from([start.clone()]);
+                for step in steps {
+                    current = current.iter().flat_map(|node| self.path_from(step, view, node)).collect();
+                }
+                current
+            },
+            Path::Alternative(alternatives) => alternatives.iter()
+                .flat_map(|alternative| self.path_from(alternative, view, start))
+                .collect(),
+            Path::ZeroOrMore(inner) => {
+                let mut reached = self.closure(inner, view, start);
+                reached.insert(start.clone());
+                reached
+            },
+            Path::OneOrMore(inner) => self.closure(inner, view, start),
+            Path::ZeroOrOne(inner) => {
+                let mut reached = self.path_from(inner, view, start);
+                reached.insert(start.clone());
+                reached
+            },
+            Path::Negated(forward, backward) => {
+                let mut reached = BTreeSet::new();
+                if !forward.is_empty() || backward.is_empty() {
+                    reached.extend(self.statements(view, Some(start), None, None).into_iter()
+                        .filter(|(_, p, _)| !forward.contains(p))
+                        .map(|(_, _, o)| o));
+                }
+                if !backward.is_empty() {
+                    reached.extend(self.statements(view, None, None, Some(start)).into_iter()
+                        .filter(|(_, p, _)| !backward.contains(p))
+                        .map(|(s, _, _)| s));
+                }
+                reached
+            },
+        }
+    }
+
+    /// Nodes reachable in one or more steps of `path`
+    fn closure(&self, path: &Path, view: &[&Graph], start: &Term) -> BTreeSet<Term> {
+        let mut reached = BTreeSet::new();
+        let mut frontier = vec![start.clone()];
+        while let Some(node) = frontier.pop() {
+            for next in self.path_from(path, view, &node) {
+                if reached.insert(next.clone()) {
+                    frontier.push(next);
+                }
+            }
+        }
+        reached
+    }
+
+    // Expressions
+
+    fn expr(&self, expr: &Expr, solution: &Solution, view: &[&Graph]) -> Option<Term> {
+        match expr {
+            Expr::Constant(term) => Some(term.clone()),
+            Expr::Variable(name) => solution.get(name).cloned(),
+            Expr::Or(left, right) => {
+                let left = self.expr(left, solution, view).and_then(|t| ebv(&t));
+                let right = self.expr(right, solution, view).and_then(|t| ebv(&t));
+                match (left, right) {
+                    (Some(true), _) | (_, Some(true)) => Some(Term::boolean(true)),
+                    (Some(false), Some(false)) => Some(Term::boolean(false)),
+                    _ => None,
+                }
+            },
+            Expr::And(left, right) => {
+                let left = self.expr(left, solution, view).and_then(|t| ebv(&t));
+                let right = self.expr(right, solution, view).and_then(|t| ebv(&t));
+                match (left, right) {
+                    (Some(false), _) | (_, Some(false)) => Some(Term::boolean(false)),
+                    (Some(true), Some(true)) => Some(Term::boolean(true)),
+                    _ => None,
+                }
+            },
+            Expr::Not(inner) => self.expr(inner, solution, view).and_then(|t| ebv(&t)).map(|b| Term::boolean(!b)),
+            Expr::Compare(op, left, right) => {
+                let left = self.expr(left, solution, view)?;
+                let right = self.expr(right, solution, view)?;
+                let result = match op {
+                    CompareOp::Eq => equal(&left, &right)?,
+                    CompareOp::Ne => !equal(&left, &right)?,
+                    CompareOp::Lt => compare(&left, &right)? == Ordering::Less,
+                    CompareOp::Le => compare(&left, &right)? != Ordering::Greater,
+                    CompareOp::Gt => compare(&left, &right)? == Ordering::Greater,
+                    CompareOp::Ge => compare(&left, &right)? != Ordering::Less,
+                };
+                Some(Term::boolean(result))
+            },
+            Expr::Arithmetic(op, left, right) => {
+                let left = number(&self.expr(left, solution, view)?)?;
+                let right = number(&self.expr(right, solution, view)?)?;
+                arithmetic(*op, left, right).map(Number::to_term)
+            },
+            Expr::Negate(inner) => {
+                let value = number(&self.expr(inner, solution, view)?)?;
+                arithmetic(ArithmeticOp::Subtract, Number::Integer(0), value).map(Number::to_term)
+            },
+            Expr::In(needle, list, negated) => {
+                let needle = self.expr(needle, solution, view)?;
+                let found = list.iter().any(|item| {
+                    self.expr(item, solution, view).and_then(|value| equal(&needle, &value)) == Some(true)
+                });
+                Some(Term::boolean(found != *negated))
+            },
+            Expr::Exists(pattern, negated) => {
+                let found = !self.pattern(pattern, view, vec![solution.clone()]).is_empty();
+                Some(Term::boolean(found != *negated))
+            },
+            Expr::Call(name, args) => self.call(name, args, solution, view),
+            Expr::Function(iri, args) => {
+                let [arg] = args.as_slice() else { return None };
+                cast(iri, &self.expr(arg, solution, view)?)
+            },
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], solution: &Solution, view: &[&Graph]) -> Option<Term> {
+        // Functions that do not evaluate all their arguments
+        match name {
+            "BOUND" => {
+                let [Expr::Variable(variable)] = args else { return None };
+                return Some(Term::boolean(solution.contains_key(variable)));
+            },
+            "IF" => {
+                let [condition, then, otherwise] = args else { return None };
+                let condition = ebv(&self.expr(condition, solution, view)?)?;
+                return self.expr(if condition { then } else { otherwise }, solution, view);
+            },
+            "COALESCE" => return args.iter().find_map(|arg| self.expr(arg, solution, view)),
+            _ => {},
+        }
+
+        let values: Vec<Term> = args.iter()
+            .map(|arg| self.expr(arg, solution, view))
+            .collect::<Option<_>>()?;
+        let string = |i: usize| values.get(i).filter(|t| t.is_string_literal()).map(Term::lexical);
+        let text = |i: usize| values.get(i).filter(|t| t.is_literal()).map(Term::lexical);
+
+        match (name, values.as_slice()) {
+            ("STR", [term]) => match term {
+                Term::Blank(_) => None,
+                term => Some(Term::string(term.lexical())),
+            },
+            ("LANG", [Term::Literal { language, .. }]) => Some(Term::string(language.clone().unwrap_or_default())),
+            ("LANGMATCHES", [_, _]) => {
+                let tag = text(0)?.to_lowercase();
+                let range = text(1)?.to_lowercase();
+                let matches = if range == "*" {
+                    !tag.is_empty()
+                } else {
+                    tag == range || tag.starts_with(&format!("{}-", range))
+                };
+                Some(Term::boolean(matches))
+            },
+            ("DATATYPE", [term]) => term.datatype().map(Term::iri),
+            ("IRI" | "URI", [term]) => match term {
+                Term::Iri(_) => Some(term.clone()),
+                term if term.is_string_literal() => Some(Term::iri(term.lexical())),
+                _ => None,
+            },
+            ("BNODE", _) => Some(Term::Blank(format!("b{}", Uuid::new_v4().simple()))),
+            ("RAND", []) => Some(Number::Double(rand::random::<f64>()).to_term()),
+            ("ABS" | "CEIL" | "FLOOR" | "ROUND", [term]) => {
+                let rounded = match number(term)? {
+                    Number::Integer(i) if name == "ABS" => Number::Integer(i.checked_abs()?),
+                    Number::Integer(i) => Number::Integer(i),
+                    Number::Decimal(f) => Number::Decimal(round(name, f)),
+                    Number::Double(f) => Number::Double(round(name, f)),
+                };
+                Some(rounded.to_term())
+            },
+            ("CONCAT", terms) => {
+                let mut joined = String::new();
+                for term in terms {
+                    joined.push_str(term.is_string_literal().then(|| term.lexical())?);
+                }
+                let languages: BTreeSet<Option<&String>> = terms.iter().map(|term| match term {
+                    Term::Literal { language, .. } => language.as_ref(),
+                    _ => None,
+                }).collect();
+                Some(match languages.into_iter().collect::<Vec<_>>().as_slice() {
+                    [Some(language)] => Term::lang_string(joined, language),
+                    _ => Term::string(joined),
+                })
+            },
+            ("STRLEN", [_]) => Some(Term::integer(string(0)?.chars().count() as i64)),
+            ("UCASE", [term]) => Some(with_language(term, string(0)?.to_uppercase())),
+            ("LCASE", [term]) => Some(with_language(term, string(0)?.to_lowercase())),
+            ("ENCODE_FOR_URI", [_]) => Some(Term::string(urlencoding::encode(string(0)?).into_owned())),
+            ("CONTAINS", [_, _]) => Some(Term::boolean(string(0)?.contains(string(1)?))),
+            ("STRSTARTS", [_, _]) => Some(Term::boolean(string(0)?.starts_with(string(1)?))),
+            ("STRENDS", [_, _]) => Some(Term::boolean(string(0)?.ends_with(string(1)?))),
+            ("STRBEFORE", [term, _]) => Some(match string(0)?.find(string(1)?) {
+                Some(i) => with_language(term, string(0)?[..i].to_string()),
+                None => Term::string(""),
+            }),
+            ("STRAFTER", [term, _]) => {
+                let needle = string(1)?;
+                Some(match string(0)?.find(needle) {
+                    Some(i) => with_language(term, string(0)?[i + needle.len()..].to_string()),
+                    None => Term::string(""),
+                })
+            },
+            ("YEAR", [term]) => date_time(term).map(|dt| Term::integer(dt.year() as i64)),
+            ("MONTH", [term]) => date_time(term).map(|dt| Term::integer(dt.month() as i64)),
+            ("DAY", [term]) => date_time(term).map(|dt| Term::integer(dt.day() as i64)),
+            ("HOURS", [term]) => date_time(term).map(|dt| Term::integer(dt.hour() as i64)),
+            ("MINUTES", [term]) => date_time(term).map(|dt| Term::integer(dt.minute() as i64)),
+            ("SECONDS", [term]) => date_time(term).map(|dt| {
+                let seconds = dt.second() as f64 + dt.nanosecond() as f64 / 1e9;
+                Number::Decimal(seconds).to_term()
+            }),
+            ("TIMEZONE", [term]) => {
+                let value = term.lexical();
+                if !has_timezone(value) {
+                    return None;
+                }
+                let offset = date_time(term)?.offset().local_minus_utc();
+                let sign = if offset < 0 { "-" } else { "" };
+                let (hours, minutes) = (offset.abs() / 3600, offset.abs() % 3600 / 60);
+                let duration = match (hours, minutes) {
+                    (0, 0) => "PT0S".to_string(),
+                    (h, 0) => format!("{}PT{}H", sign, h),
+                    (0, m) => format!("{}PT{}M", sign, m),
+                    (h, m) => format!("{}PT{}H{}M", sign, h, m),
+                };
+                Some(Term::typed(duration, "http://www.w3.org/2001/XMLSchema#dayTimeDuration"))
+            },
+            ("TZ", [term]) => {
+                date_time(term)?;
+                let value = term.lexical();
+                let zone = if value.ends_with('Z') {
+                    "Z".to_string()
+                } else if has_timezone(value) {
+                    value[value.len() - 6..].to_string()
+                } else {
+                    String::new()
+                };
+                Some(Term::string(zone))
+            },
+            ("NOW", []) => Some(self.now.clone()),
+            ("UUID", []) => Some(Term::iri(format!("urn:uuid:{}", Uuid::new_v4()))),
+            ("STRUUID", []) => Some(Term::string(Uuid::new_v4().to_string())),
+            ("SHA256", [_]) => Some(Term::string(hex(&Sha256::digest(string(0)?.as_bytes())))),
+            ("SHA384", [_]) => Some(Term::string(hex(&Sha384::digest(string(0)?.as_bytes())))),
+            ("SHA512", [_]) => Some(Term::string(hex(&Sha512::digest(string(0)?.as_bytes())))),
+            ("STRLANG", [term, _]) if term.is_string_literal() && term.datatype() == Some(XSD_STRING) => {
+                Some(Term::lang_string(term.lexical(), text(1)?))
+            },
+            ("STRDT", [term, Term::Iri(datatype)]) if term.datatype() == Some(XSD_STRING) => {
+                Some(Term::typed(term.lexical(), datatype))
+            },
+            ("SAMETERM", [a, b]) => Some(Term::boolean(a == b)),
+            ("ISIRI" | "ISURI", [term]) => Some(Term::boolean(matches!(term, Term::Iri(_)))),
+            ("ISBLANK", [term]) => Some(Term::boolean(matches!(term, Term::Blank(_)))),
+            ("ISLITERAL", [term]) => Some(Term::boolean(term.is_literal())),
+            ("ISNUMERIC", [term]) => Some(Term::boolean(number(term).is_some())),
+            ("REGEX", [_, _] | [_, _, _]) => {
+                let regex = regex(string(1)?, values.get(2).map(Term::lexical))?;
+                Some(Term::boolean(regex.is_match(string(0)?)))
+            },
+            ("SUBSTR", [term, start] | [term, start, _]) => {
+                let value = string(0)?;
+                let start = number(start)?.as_f64().round();
+                let end = match values.get(2) {
+                    Some(length) => start + number(length)?.as_f64().round(),
+                    None => f64::INFINITY,
+                };
+                let substring: String = value.chars().enumerate()
+                    .filter(|(i, _)| {
+                        let position = (*i + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, c)| c)
+                    .collect();
+                Some(with_language(term, substring))
+            },
+            ("REPLACE", [term, _, _] | [term, _, _, _]) => {
+                let regex = regex(string(1)?, values.get(3).map(Term::lexical))?;
+                let replaced = regex.replace_all(string(0)?, text(2)?).into_owned();
+                Some(with_language(term, replaced))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Term bound to a pattern position, if any
+fn resolve(pattern: &TermPattern, solution: &Solution) -> Option<Term> {
+    match pattern {
+        TermPattern::Term(term) => Some(term.clone()),
+        TermPattern::Variable(name) | TermPattern::BlankNode(name) => solution.get(name).cloned(),
+    }
+}
+
+/// Bind a pattern position to `term`; false if it is already bound to something else
+fn bind(solution: &mut Solution, pattern: &TermPattern, term: &Term) -> bool {
+    match pattern {
+        TermPattern::Term(expected) => expected == term,
+        TermPattern::Variable(name) | TermPattern::BlankNode(name) => match solution.get(name) {
+            Some(bound) => bound == term,
+            None => {
+                solution.insert(name.clone(), term.clone());
+                true
+            },
+        },
+    }
+}
+
+fn compatible(a: &Solution, b: &Solution) -> bool {
+    b.iter().all(|(name, term)| a.get(name).is_none_or(|other| other == term))
+}
+
+fn join_solutions(left: Vec<Solution>, right: &[Solution]) -> Vec<Solution> {
+    let mut out = Vec::new();
+    for solution in left {
+        for other in right {
+            if compatible(&solution, other) {
+                let mut merged = solution.clone();
+                merged.extend(other.iter().map(|(k, v)| (k.clone(), v.clone())));
+                out.push(merged);
+            }
+        }
+    }
+    out
+}
+
+/// Path walked backwards
+fn invert(path: &Path) -> Path {
+    match path {
+        Path::Link(_) => Path::Inverse(Box::new(path.clone())),
+        Path::Inverse(inner) => inner.as_ref().clone(),
+        Path::Sequence(steps) => Path::Sequence(steps.iter().rev().map(invert).collect()),
+        Path::Alternative(alternatives) => Path::Alternative(alternatives.iter().map(invert).collect()),
+        Path::ZeroOrMore(inner) => Path::ZeroOrMore(Box::new(invert(inner))),
+        Path::OneOrMore(inner) => Path::OneOrMore(Box::new(invert(inner))),
+        Path::ZeroOrOne(inner) => Path::ZeroOrOne(Box::new(invert(inner))),
+        Path::Negated(forward, backward) => Path::Negated(backward.clone(), forward.clone()),
+    }
+}
+
+/// Statement for a template triple, or `None` if a variable is unbound or the result
+/// is not valid RDF. Template blank nodes get one fresh label per solution.
+pub fn instantiate(triple: &TriplePattern, solution: &Solution, blanks: &mut HashMap<String, Term>) -> Option<Statement> {
+    let Verb::Term(verb) = &triple.verb else { return None };
+    let subject = instantiate_term(&triple.subject, solution, blanks)?;
+    let predicate = instantiate_term(verb, solution, blanks)?;
+    let object = instantiate_term(&triple.object, solution, blanks)?;
+    if subject.is_literal() || !matches!(predicate, Term::Iri(_)) {
+        return None;
+    }
+    Some((subject, predicate, object))
+}
+
+pub fn instantiate_term(pattern: &TermPattern, solution: &Solution, blanks: &mut HashMap<String, Term>) -> Option<Term> {
+    match pattern {
+        TermPattern::Term(term) => Some(term.clone()),
+        TermPattern::Variable(name) => solution.get(name).cloned(),
+        TermPattern::BlankNode(label) => Some(blanks.entry(label.clone())
+            .or_insert_with(|| Term::Blank(format!("b{}", Uuid::new_v4().simple())))
+            .clone()),
+    }
+}
+
+fn statements_json(statements: BTreeSet<Statement>) -> Value {
+    let bindings: Vec<Value> = statements.into_iter()
+        .map(|(s, p, o)| json!({"s": s.to_json(), "p": p.to_json(), "o": o.to_json()}))
+        .collect();
+    json!({"head": {"vars": ["s", "p", "o"]}, "results": {"bindings": bindings}})
+}
+
+// Values
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Integer(i64),
+    Decimal(f64),
+    Double(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Decimal(f) | Number::Double(f) => f,
+        }
+    }
+
+    fn to_term(self) -> Term {
+        match self {
+            Number::Integer(i) => Term::integer(i),
+            Number::Decimal(f) if f.fract() == 0.0 && f.is_finite() => Term::typed(format!("{:.1}", f), XSD_DECIMAL),
+            Number::Decimal(f) => Term::typed(f.to_string(), XSD_DECIMAL),
+            Number::Double(f) if f.is_nan() => Term::typed("NaN", XSD_DOUBLE),
+            Number::Double(f) if f.is_infinite() => Term::typed(if f > 0.0 { "INF" } else { "-INF" }, XSD_DOUBLE),
+            Number::Double(f) => Term::typed(format!("{:E}", f), XSD_DOUBLE),
+        }
+    }
+}
+
+const INTEGER_TYPES: [&str; 13] = [
+    "integer", "int", "long", "short", "byte", "nonNegativeInteger", "positiveInteger", "negativeInteger",
+    "nonPositiveInteger", "unsignedLong", "unsignedInt", "unsignedShort", "unsignedByte",
+];
+
+fn number(term: &Term) -> Option<Number> {
+    let Term::Literal { value, datatype: Some(datatype), .. } = term else { return None };
+    let local = datatype.strip_prefix(XSD)?;
+    let value = value.trim();
+    if INTEGER_TYPES.contains(&local) {
+        return value.parse().ok().map(Number::Integer);
+    }
+    match local {
+        "decimal" => value.parse().ok().map(Number::Decimal),
+        "double" | "float" => match value {
+            "INF" | "+INF" => Some(Number::Double(f64::INFINITY)),
+            "-INF" => Some(Number::Double(f64::NEG_INFINITY)),
+            "NaN" => Some(Number::Double(f64::NAN)),
+            _ => value.parse().ok().map(Number::Double),
+        },
+        _ => None,
+    }
+}
+
+fn arithmetic(op: ArithmeticOp, left: Number, right: Number) -> Option<Number> {
+    match (left, right) {
+        (Number::Integer(a), Number::Integer(b)) if op != ArithmeticOp::Divide => match op {
+            ArithmeticOp::Add => a.checked_add(b),
+            ArithmeticOp::Subtract => a.checked_sub(b),
+            _ => a.checked_mul(b),
+        }.map(Number::Integer),
+        (Number::Double(_), _) | (_, Number::Double(_)) => {
+            let (a, b) = (left.as_f64(), right.as_f64());
+            Some(Number::Double(match op {
+                ArithmeticOp::Add => a + b,
+                ArithmeticOp::Subtract => a - b,
+                ArithmeticOp::Multiply => a * b,
+                ArithmeticOp::Divide => a / b,
+            }))
+        },
+        _ => {
+            let (a, b) = (left.as_f64(), right.as_f64());
+            if op == ArithmeticOp::Divide && b == 0.0 {
+                return None;
+            }
+            Some(Number::Decimal(match op {
+                ArithmeticOp::Add => a + b,
+                ArithmeticOp::Subtract => a - b,
+                ArithmeticOp::Multiply => a * b,
+                ArithmeticOp::Divide => a / b,
+            }))
+        },
+    }
+}
+
+fn round(function: &str, value: f64) -> f64 {
+    match function {
+        "ABS" => value.abs(),
+        "CEIL" => value.ceil(),
+        "FLOOR" => value.floor(),
+        // SPARQL rounds halves up, also for negative numbers
+        _ => (value + 0.5).floor(),
+    }
+}
+
+/// Effective boolean value; `None` is a type error
+fn ebv(term: &Term) -> Option<bool> {
+    if let Some(n) = number(term) {
+        let f = n.as_f64();
+        return Some(f != 0.0 && !f.is_nan());
+    }
+    match term {
+        Term::Literal { value, .. } if term.datatype() == Some(XSD_BOOLEAN) => Some(value == "true" || value == "1"),
+        Term::Literal { value, .. } if term.is_string_literal() => Some(!value.is_empty()),
+        _ => None,
+    }
+}
+
+fn boolean(term: &Term) -> Option<bool> {
+    (term.datatype() == Some(XSD_BOOLEAN)).then(|| matches!(term.lexical(), "true" | "1"))
+}
+
+fn date_time(term: &Term) -> Option<DateTime<FixedOffset>> {
+    let datatype = term.datatype()?;
+    if datatype != XSD_DATE_TIME && datatype != "http://www.w3.org/2001/XMLSchema#date" {
+        return None;
+    }
+    let value = term.lexical();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed);
+    }
+    let utc = FixedOffset::east_opt(0)?;
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f") {
+        return naive.and_local_timezone(utc).single();
+    }
+    let date = NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()?;
+    date.and_hms_opt(0, 0, 0)?.and_local_timezone(utc).single()
+}
+
+fn has_timezone(value: &str) -> bool {
+    value.ends_with('Z') || value.len() > 6 && matches!(&value[value.len() - 6..value.len() - 5], "+" | "-")
+        && value.as_bytes()[value.len() - 3] == b':'
+}
+
+/// `=` on RDF terms: numbers, booleans and dates by value, everything else by identity
+fn equal(left: &Term, right: &Term) -> Option<bool> {
+    if let (Some(a), Some(b)) = (number(left), number(right)) {
+        return Some(a.as_f64() == b.as_f64());
+    }
+    if let (Some(a), Some(b)) = (boolean(left), boolean(right)) {
+        return Some(a == b);
+    }
+    if let (Some(a), Some(b)) = (date_time(left), date_time(right)) {
+        return Some(a == b);
+    }
+    Some(left == right)
+}
+
+/// `<` and friends; `None` for terms that cannot be ordered against each other
+fn compare(left: &Term, right: &Term) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (number(left), number(right)) {
+        return match (a, b) {
+            (Number::Integer(a), Number::Integer(b)) => Some(a.cmp(&b)),
+            _ => a.as_f64().partial_cmp(&b.as_f64()),
+        };
+    }
+    if let (Some(a), Some(b)) = (boolean(left), boolean(right)) {
+        return Some(a.cmp(&b));
+    }
+    if let (Some(a), Some(b)) = (date_time(left), date_time(right)) {
+        return Some(a.cmp(&b));
+    }
+    match (left, right) {
+        (Term::Literal { value: a, datatype: None, language: la }, Term::Literal { value: b, datatype: None, language: lb }) if la == lb => {
+            Some(a.cmp(b))
+        },
+        _ => None,
+    }
+}
+
+/// ORDER BY: unbound, then blank nodes, IRIs and literals
+fn order_terms(left: Option<&Term>, right: Option<&Term>) -> Ordering {
+    fn rank(term: Option<&Term>) -> u8 {
+        match term {
+            None => 0,
+            Some(Term::Blank(_)) => 1,
+            Some(Term::Iri(_)) => 2,
+            Some(Term::Literal { .. }) => 3,
+        }
+    }
+
+    match (left, right) {
+        (Some(a @ Term::Literal { .. }), Some(b @ Term::Literal { .. })) => compare(a, b)
+            .unwrap_or_else(|| a.lexical().cmp(b.lexical()).then_with(|| a.cmp(b))),
+        (Some(a), Some(b)) if rank(left) == rank(right) => a.lexical().cmp(b.lexical()),
+        _ => rank(left).cmp(&rank(right)),
+    }
+}
+
+fn cast(datatype: &str, term: &Term) -> Option<Term> {
+    if matches!(term, Term::Blank(_)) {
+        return None;
+    }
+    let value = term.lexical();
+    let local = datatype.strip_prefix(XSD)?;
+    match local {
+        "string" => Some(Term::string(value)),
+        "boolean" => {
+            let result = match number(term) {
+                Some(n) => n.as_f64() != 0.0 && !n.as_f64().is_nan(),
+                None => match value.trim() {
+                    "true" | "1" => true,
+                    "false" | "0" => false,
+                    _ => return None,
+                },
+            };
+            Some(Term::boolean(result))
+        },
+        "integer" => {
+            let result = match number(term) {
+                Some(Number::Integer(i)) => i,
+                Some(n) if n.as_f64().is_finite() => n.as_f64().trunc() as i64,
+                Some(_) => return None,
+                None => match boolean(term) {
+                    Some(b) => b as i64,
+                    None => value.trim().parse().ok()?,
+                },
+            };
+            Some(Term::integer(result))
+        },
+        "decimal" | "double" | "float" => {
+            let result = match number(term) {
+                Some(n) => n.as_f64(),
+                None => match boolean(term) {
+                    Some(b) => b as i64 as f64,
+                    None => value.trim().parse().ok()?,
+                },
+            };
+            Some(if local == "decimal" {
+                if !result.is_finite() {
+                    return None;
+                }
+                Number::Decimal(result).to_term()
+            } else {
+                Number::Double(result).to_term()
+            })
+        },
+        "dateTime" => {
+            let typed = Term::typed(value.trim(), XSD_DATE_TIME);
+            date_time(&typed).map(|_| typed)
+        },
+        _ => None,
+    }
+}
+
+/// String result carrying the language tag of `like`
+fn with_language(like: &Term, value: String) -> Term {
+    match like {
+        Term::Literal { language: Some(language), .. } => Term::lang_string(value, language),
+        _ => Term::string(value),
+    }
+}
+
+fn regex(pattern: &str, flags: Option<&str>) -> Option<Regex> {
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.unwrap_or_default().chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            's' => builder.dot_matches_new_line(true),
+            'm' => builder.multi_line(true),
+            'x' => builder.ignore_whitespace(true),
+            _ => return None,
+        };
+    }
+    builder.build().ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,522 @@
+//! Embedded SPARQL 1.1 quad store.
+//!
+//! Keeps the whole dataset in memory and evaluates queries and updates
+//! itself, so the editor runs without an external triple store and the
+//! routes can be tested end to end. It covers the SPARQL the editor and its
+//! users typically send: basic and optional patterns, UNION, MINUS, FILTER
+//! with the built-in functions, property paths, subqueries, aggregates and
+//! the update operations. SERVICE and LOAD are not supported.
+
+mod algebra;
+mod dataset;
+mod eval;
+mod parser;
+mod term;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+use crate::dialect::StoreDialect;
+use crate::error::RdfEditorError;
+use crate::formats;
+use crate::models::RdfFormat;
+use super::Store;
+use algebra::{GraphTarget, GraphTransfer, QuadPattern, TermPattern, UpdateOperation};
+use dataset::{Dataset, Graph};
+use eval::Evaluator;
+use term::{Statement, Term};
+
+pub struct MemoryStore {
+    dataset: RwLock<Dataset>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore { dataset: RwLock::new(Dataset::default()) }
+    }
+
+    /// Store seeded from an RDF file, the format picked by its extension. The file is
+    /// only read; edits stay in memory.
+    pub fn load(path: &str) -> Result<Self, RdfEditorError> {
+        let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let format = match extension.to_ascii_lowercase().as_str() {
+            "ttl" => RdfFormat::Turtle,
+            "nt" => RdfFormat::NTriples,
+            "nq" => RdfFormat::NQuads,
+            "trig" => RdfFormat::TriG,
+            "jsonld" | "json" => RdfFormat::JsonLd,
+            "rdf" | "owl" | "xml" => RdfFormat::RdfXml,
+            _ => return Err(RdfEditorError::Configuration(format!(
+                "Cannot tell the RDF format of {} from its extension", path
+            ))),
+        };
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to read {}: {}", path, e)))?;
+
+        let mut dataset = Dataset::default();
+        for triple in formats::parse(format, &data, None)? {
+            let (statement, graph) = term::from_triple(&triple);
+            dataset.graph_mut(graph.as_ref()).insert(&statement);
+        }
+
+        Ok(MemoryStore { dataset: RwLock::new(dataset) })
+    }
+
+    fn apply(&self, operations: &[UpdateOperation]) -> Result<(), RdfEditorError> {
+        let mut dataset = self.dataset.write().unwrap_or_else(|e| e.into_inner());
+        let mut transaction = Transaction { dataset: &mut dataset, journal: Vec::new() };
+
+        for operation in operations {
+            if let Err(e) = transaction.execute(operation) {
+                transaction.rollback();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    fn dialect(&self) -> StoreDialect {
+        StoreDialect::Memory
+    }
+
+    async fn query(&self, query: &str, reasoning: Option<bool>) -> Result<Value, RdfEditorError> {
+        if reasoning.is_some() {
+            return Err(RdfEditorError::InvalidInput(
+                "Reasoning cannot be toggled per query on the memory store".to_string()
+            ));
+        }
+
+        let query = parser::parse_query(query)?;
+        let dataset = self.dataset.read().unwrap_or_else(|e| e.into_inner());
+        Ok(Evaluator::new(&dataset, query.dataset.as_ref(), None).query(&query))
+    }
+
+    async fn update(&self, update: &str) -> Result<(), RdfEditorError> {
+        self.apply(&parser::parse_update(update)?)
+    }
+
+    async fn update_atomic(&self, updates: &[String]) -> Result<(), RdfEditorError> {
+        // Parse everything first so a syntax error leaves the data untouched
+        let mut operations = Vec::new();
+        for update in updates {
+            operations.extend(parser::parse_update(update)?);
+        }
+        self.apply(&operations)
+    }
+}
+
+/// Change made by an update, kept until the request succeeds
+enum Change {
+    Inserted(Option<Term>, Statement),
+    Removed(Option<Term>, Statement),
+    /// A graph as it was before being replaced, created or dropped
+    Replaced(Option<Term>, Option<Graph>),
+}
+
+/// Updates applied to the dataset with a journal to undo them, so a failing
+/// operation leaves the whole request without effect
+struct Transaction<'a> {
+    dataset: &'a mut Dataset,
+    journal: Vec<Change>,
+}
+
+impl Transaction<'_> {
+    fn rollback(&mut self) {
+        while let Some(change) = self.journal.pop() {
+            match change {
+                Change::Inserted(graph, statement) => {
+                    self.dataset.graph_mut(graph.as_ref()).remove(&statement);
+                },
+                Change::Removed(graph, statement) => {
+                    self.dataset.graph_mut(graph.as_ref()).insert(&statement);
+                },
+                Change::Replaced(graph, old) => {
+                    self.dataset.set_graph(graph.as_ref(), old);
+                },
+            }
+        }
+    }
+
+    fn exists(&self, graph: Option<&Term>) -> bool {
+        self.dataset.graph(graph).is_some()
+    }
+
+    fn insert(&mut self, graph: Option<Term>, statement: Statement) {
+        if !self.exists(graph.as_ref()) {
+            self.journal.push(Change::Replaced(graph.clone(), None));
+        }
+        if self.dataset.graph_mut(graph.as_ref()).insert(&statement) {
+            self.journal.push(Change::Inserted(graph, statement));
+        }
+    }
+
+    fn remove(&mut self, graph: Option<Term>, statement: Statement) {
+        let Some(named) = self.dataset.graph(graph.as_ref()) else { return };
+        if named.contains(&statement.0, &statement.1, &statement.2) {
+            self.dataset.graph_mut(graph.as_ref()).remove(&statement);
+            self.journal.push(Change::Removed(graph, statement));
+        }
+    }
+
+    fn set_graph(&mut self, graph: Option<Term>, contents: Option<Graph>) {
+        let old = self.dataset.set_graph(graph.as_ref(), contents);
+        self.journal.push(Change::Replaced(graph, old));
+    }
+
+    fn execute(&mut self, operation: &UpdateOperation) -> Result<(), RdfEditorError> {
+        match operation {
+            UpdateOperation::InsertData(quads) => {
+                for (graph, statement) in data_statements(quads, true)? {
+                    self.insert(graph, statement);
+                }
+            },
+            UpdateOperation::DeleteData(quads) => {
+                for (graph, statement) in data_statements(quads, false)? {
+                    self.remove(graph, statement);
+                }
+            },
+            UpdateOperation::Modify { with, delete, insert, using, pattern } => {
+                let (deletions, insertions) = {
+                    let evaluator = Evaluator::new(self.dataset, using.as_ref(), with.as_ref());
+                    let solutions = evaluator.solutions(pattern);
+                    let mut deletions = Vec::new();
+                    let mut insertions = Vec::new();
+                    for solution in &solutions {
+                        let mut blanks = HashMap::new();
+                        for quad in delete {
+                            // Blank nodes in a DELETE template never match anything
+                            if has_blank_node(quad) {
+                                continue;
+                            }
+                            if let Some(quad) = instantiate_quad(quad, with, solution, &mut blanks) {
+                                deletions.push(quad);
+                            }
+                        }
+                        let mut blanks = HashMap::new();
+                        for quad in insert {
+                            if let Some(quad) = instantiate_quad(quad, with, solution, &mut blanks) {
+                                insertions.push(quad);
+                            }
+                        }
+                    }
+                    (deletions, insertions)
+                };
+
+                for (graph, statement) in deletions {
+                    self.remove(graph, statement);
+                }
+                for (graph, statement) in insertions {
+                    self.insert(graph, statement);
+                }
+            },
+            UpdateOperation::Clear { target, silent } => {
+                for graph in self.targets(target, *silent)? {
+                    let contents = graph.is_some().then(Graph::default);
+                    self.set_graph(graph, contents);
+                }
+            },
+            UpdateOperation::Drop { target, silent } => {
+                for graph in self.targets(target, *silent)? {
+                    self.set_graph(graph, None);
+                }
+            },
+            UpdateOperation::Create { graph, silent } => {
+                if self.exists(Some(graph)) {
+                    if !silent {
+                        return Err(RdfEditorError::Sparql(format!("Graph <{}> already exists", graph.lexical())));
+                    }
+                } else {
+                    self.set_graph(Some(graph.clone()), Some(Graph::default()));
+                }
+            },
+            UpdateOperation::Transfer { kind, from, to, silent } => {
+                if from == to {
+                    return Ok(());
+                }
+                let Some(source) = self.dataset.graph(from.as_ref()).cloned() else {
+                    if *silent {
+                        return Ok(());
+                    }
+                    return Err(missing_graph(from.as_ref()));
+                };
+
+                match kind {
+                    GraphTransfer::Add => {
+                        for statement in source.matching(None, None, None) {
+                            self.insert(to.clone(), statement);
+                        }
+                    },
+                    GraphTransfer::Copy => self.set_graph(to.clone(), Some(source)),
+                    GraphTransfer::Move => {
+                        self.set_graph(to.clone(), Some(source));
+                        self.set_graph(from.clone(), None);
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Graphs a CLEAR or DROP applies to, `None` being the default graph
+    fn targets(&self, target: &GraphTarget, silent: bool) -> Result<Vec<Option<Term>>, RdfEditorError> {
+        let named = || self.dataset.named.keys().cloned().map(Some);
+        Ok(match target {
+            GraphTarget::Default => vec![None],
+            GraphTarget::Named(graph) if self.exists(Some(graph)) => vec![Some(graph.clone())],
+            GraphTarget::Named(_) if silent => Vec::new(),
+            GraphTarget::Named(graph) => return Err(missing_graph(Some(graph))),
+            GraphTarget::AllNamed => named().collect(),
+            GraphTarget::All => std::iter::once(None).chain(named()).collect(),
+        })
+    }
+}
+
+fn missing_graph(graph: Option<&Term>) -> RdfEditorError {
+    match graph {
+        Some(graph) => RdfEditorError::Sparql(format!("Graph <{}> does not exist", graph.lexical())),
+        None => RdfEditorError::Sparql("The default graph does not exist".to_string()),
+    }
+}
+
+fn has_blank_node(quad: &QuadPattern) -> bool {
+    [&quad.triple.subject, &quad.triple.object].iter().any(|t| matches!(t, TermPattern::BlankNode(_)))
+}
+
+/// Statements of INSERT DATA, with `insert`, or DELETE DATA. Blank node labels
+/// are scoped to the request, so INSERT DATA mints a fresh node for each label
+/// and DELETE DATA, which could never match one, rejects them.
+fn data_statements(quads: &[QuadPattern], insert: bool) -> Result<Vec<(Option<Term>, Statement)>, RdfEditorError> {
+    let mut blanks: HashMap<String, Term> = HashMap::new();
+    let mut term = |pattern: &TermPattern| match pattern {
+        TermPattern::Term(term) => Ok(term.clone()),
+        TermPattern::BlankNode(label) if insert => Ok(blanks.entry(label.clone())
+            .or_insert_with(|| Term::Blank(format!("b{}", Uuid::new_v4().simple())))
+            .clone()),
+        TermPattern::BlankNode(label) => Err(RdfEditorError::Sparql(format!(
+            "Blank node _:{} is not allowed in DELETE DATA", label
+        ))),
+        TermPattern::Variable(name) => Err(RdfEditorError::Sparql(format!(
+            "Variable ?{} is not allowed in INSERT DATA or DELETE DATA", name
+        ))),
+    };
+
+    quads.iter().map(|quad| {
+        let algebra::Verb::Term(verb) = &quad.triple.verb else {
+            return Err(RdfEditorError::Sparql("Property paths are not allowed in data".to_string()));
+        };
+        let graph = quad.graph.as_ref().map(&mut term).transpose()?;
+        Ok((graph, (term(&quad.triple.subject)?, term(verb)?, term(&quad.triple.object)?)))
+    }).collect()
+}
+
+/// Quad of a DELETE or INSERT template for one solution; triples without a graph go to WITH
+fn instantiate_quad(
+    quad: &QuadPattern,
+    with: &Option<Term>,
+    solution: &eval::Solution,
+    blanks: &mut HashMap<String, Term>,
+) -> Option<(Option<Term>, Statement)> {
+    let graph = match &quad.graph {
+        Some(graph) => match eval::instantiate_term(graph, solution, blanks)? {
+            graph @ Term::Iri(_) => Some(graph),
+            _ => return None,
+        },
+        None => with.clone(),
+    };
+    Some((graph, eval::instantiate(&quad.triple, solution, blanks)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn store(data: &str) -> MemoryStore {
+        let store = MemoryStore::new();
+        store.update(&format!("PREFIX ex: <http://example.org/> INSERT DATA {{ {} }}", data)).await.unwrap();
+        store
+    }
+
+    /// Values of one variable across the result rows, in order
+    async fn column(store: &MemoryStore, query: &str, variable: &str) -> Vec<String> {
+        let results = store.query(&format!("PREFIX ex: <http://example.org/> {}", query), None).await.unwrap();
+        results["results"]["bindings"].as_array().unwrap().iter()
+            .map(|row| row[variable]["value"].as_str().unwrap_or("UNBOUND").to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_selects_with_optional_filter_and_order() {
+        let store = store(r#"
+            ex:a ex:name "Alice" ; ex:age 30 .
+            ex:b ex:name "Bob" ; ex:age 25 ; ex:email "bob@example.org" .
+            ex:c ex:name "Carol"@en ; ex:age 41 .
+        "#).await;
+
+        let names = column(&store, "SELECT ?name WHERE { ?s ex:name ?name ; ex:age ?age FILTER(?age > 26) } ORDER BY DESC(?age)", "name").await;
+        assert_eq!(names, vec!["Carol", "Alice"]);
+
+        let emails = column(&store, "SELECT ?s ?email WHERE { ?s ex:name ?n OPTIONAL { ?s ex:email ?email } } ORDER BY ?s", "email").await;
+        assert_eq!(emails, vec!["UNBOUND", "bob@example.org", "UNBOUND"]);
+
+        let english = column(&store, "SELECT ?n WHERE { ?s ex:name ?n FILTER(langMatches(lang(?n), 'en')) }", "n").await;
+        assert_eq!(english, vec!["Carol"]);
+
+        let page = column(&store, "SELECT ?s WHERE { ?s ex:age ?age } ORDER BY ?age LIMIT 1 OFFSET 1", "s").await;
+        assert_eq!(page, vec!["http://example.org/a"]);
+    }
+
+    #[tokio::test]
+    async fn test_default_graph_is_the_union_of_all_graphs() {
+        let store = store("ex:a ex:p 1 . GRAPH ex:g1 { ex:b ex:p 2 } GRAPH ex:g2 { ex:c ex:p 3 . ex:a ex:p 1 }").await;
+
+        let all = column(&store, "SELECT ?s WHERE { ?s ex:p ?o } ORDER BY ?s", "s").await;
+        assert_eq!(all.len(), 3);
+
+        let graphs = column(&store, "SELECT DISTINCT ?g WHERE { GRAPH ?g { ?s ?p ?o } } ORDER BY ?g", "g").await;
+        assert_eq!(graphs, vec!["http://example.org/g1", "http://example.org/g2"]);
+
+        let from = column(&store, "SELECT ?s FROM ex:g1 WHERE { ?s ex:p ?o }", "s").await;
+        assert_eq!(from, vec!["http://example.org/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_group_and_filter() {
+        let store = store("ex:a a ex:Person . ex:b a ex:Person . ex:c a ex:Place . ex:a ex:score 2 . ex:b ex:score 3").await;
+
+        let results = store.query(
+            "SELECT ?type (COUNT(?s) AS ?n) WHERE { ?s a ?type } GROUP BY ?type HAVING (COUNT(?s) > 1)",
+            None,
+        ).await.unwrap();
+        let rows = results["results"]["bindings"].as_array().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["type"]["value"], "http://example.org/Person");
+        assert_eq!(rows[0]["n"], json!({"type": "literal", "value": "2", "datatype": term::XSD_INTEGER}));
+
+        let total = column(&store, "SELECT (SUM(?v) AS ?total) WHERE { ?s ex:score ?v }", "total").await;
+        assert_eq!(total, vec!["5"]);
+
+        let none = column(&store, "SELECT (COUNT(*) AS ?n) WHERE { ?s ex:missing ?o }", "n").await;
+        assert_eq!(none, vec!["0"]);
+    }
+
+    #[tokio::test]
+    async fn test_follows_property_paths() {
+        let store = store("ex:a ex:next ex:b . ex:b ex:next ex:c . ex:c ex:next ex:d . ex:d ex:label 'end'").await;
+
+        let reachable = column(&store, "SELECT ?x WHERE { ex:a ex:next+ ?x } ORDER BY ?x", "x").await;
+        assert_eq!(reachable.len(), 3);
+
+        let start = column(&store, "SELECT ?x WHERE { ?x ex:next/ex:next/ex:next/ex:label 'end' }", "x").await;
+        assert_eq!(start, vec!["http://example.org/a"]);
+
+        let back = column(&store, "SELECT ?x WHERE { ex:c ^ex:next* ?x } ORDER BY ?x", "x").await;
+        assert_eq!(back.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ask_and_construct() {
+        let store = store("ex:a ex:knows ex:b").await;
+
+        let found = store.query("ASK { <http://example.org/a> ?p ?o }", None).await.unwrap();
+        assert_eq!(found["boolean"], true);
+
+        let results = store.query(
+            "PREFIX ex: <http://example.org/> CONSTRUCT { ?o ex:knownBy ?s } WHERE { ?s ex:knows ?o }",
+            None,
+        ).await.unwrap();
+        let rows = results["results"]["bindings"].as_array().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["s"]["value"], "http://example.org/b");
+        assert_eq!(rows[0]["p"]["value"], "http://example.org/knownBy");
+    }
+
+    #[tokio::test]
+    async fn test_modifies_with_delete_insert_where() {
+        let store = store("GRAPH ex:g { ex:a ex:status 'draft' . ex:b ex:status 'draft' }").await;
+        store.update(r#"
+            PREFIX ex: <http://example.org/>
+            WITH ex:g
+            DELETE { ?s ex:status 'draft' } INSERT { ?s ex:status 'published' }
+            WHERE { ?s ex:status 'draft' }
+        "#).await.unwrap();
+
+        let status = column(&store, "SELECT DISTINCT ?v WHERE { GRAPH ex:g { ?s ex:status ?v } }", "v").await;
+        assert_eq!(status, vec!["published"]);
+
+        store.update("PREFIX ex: <http://example.org/> DELETE WHERE { GRAPH ex:g { ex:a ?p ?o } }").await.unwrap();
+        let left = column(&store, "SELECT ?s WHERE { ?s ?p ?o }", "s").await;
+        assert_eq!(left, vec!["http://example.org/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_insert_data_mints_blank_nodes_per_request() {
+        let store = store("ex:alice ex:address _:b0 . _:b0 ex:city 'Paris'").await;
+        store.update("PREFIX ex: <http://example.org/> INSERT DATA { ex:bob ex:address _:b0 . _:b0 ex:city 'Oslo' }").await.unwrap();
+
+        let cities = column(&store, "SELECT ?c WHERE { ex:alice ex:address ?a . ?a ex:city ?c }", "c").await;
+        assert_eq!(cities, vec!["Paris"]);
+        assert!(store.update("PREFIX ex: <http://example.org/> DELETE DATA { _:b0 ex:city 'Paris' }").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_graph_management() {
+        let store = store("GRAPH ex:g { ex:a ex:p 1 }").await;
+
+        assert!(store.update("CREATE GRAPH <http://example.org/g>").await.is_err());
+        store.update("CREATE SILENT GRAPH <http://example.org/g>").await.unwrap();
+
+        store.update("COPY <http://example.org/g> TO <http://example.org/h>").await.unwrap();
+        store.update("DROP GRAPH <http://example.org/g>").await.unwrap();
+        assert!(store.update("DROP GRAPH <http://example.org/g>").await.is_err());
+        store.update("DROP SILENT GRAPH <http://example.org/g>").await.unwrap();
+
+        let graphs = column(&store, "SELECT DISTINCT ?g WHERE { GRAPH ?g { ?s ?p ?o } }", "g").await;
+        assert_eq!(graphs, vec!["http://example.org/h"]);
+    }
+
+    #[tokio::test]
+    async fn test_atomic_updates_apply_all_or_nothing() {
+        let store = store("ex:a ex:p 1").await;
+
+        let result = store.update_atomic(&[
+            "INSERT DATA { <http://example.org/b> <http://example.org/p> 2 }".to_string(),
+            "CREATE GRAPH <http://example.org/new>".to_string(),
+            "DROP GRAPH <http://example.org/missing>".to_string(),
+        ]).await;
+        assert!(result.is_err());
+
+        let subjects = column(&store, "SELECT ?s WHERE { ?s ?p ?o }", "s").await;
+        assert_eq!(subjects, vec!["http://example.org/a"]);
+        let created = store.query("ASK { GRAPH <http://example.org/new> { } }", None).await.unwrap();
+        assert_eq!(created["boolean"], false);
+
+        let error = store.update("INSERT DATA { <http://example.org/a> }").await.unwrap_err();
+        assert!(matches!(error, RdfEditorError::Sparql(message) if message.contains("syntax error")));
+    }
+
+    #[tokio::test]
+    async fn test_loads_a_seed_file() {
+        let path = std::env::temp_dir().join(format!("memory-store-{}.ttl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "@prefix ex: <http://example.org/> .\nex:a ex:p ex:b .\n").unwrap();
+
+        let store = MemoryStore::load(path.to_str().unwrap()).unwrap();
+        let objects = column(&store, "SELECT ?o WHERE { ex:a ex:p ?o }", "o").await;
+        assert_eq!(objects, vec!["http://example.org/b"]);
+
+        std::fs::remove_file(&path).ok();
+        assert!(matches!(MemoryStore::load("seed.unknown"), Err(RdfEditorError::Configuration(_))));
+    }
+}
@@ -0,0 +1,1411 @@
+//! SPARQL 1.1 query and update parser.
+//!
+//! Covers the query forms, property paths, the full expression grammar with
+//! aggregates and subqueries, and every update operation except LOAD.
+//! SERVICE is rejected: the embedded store has nothing to federate with.
+
+use std::collections::HashMap;
+use url::Url;
+use uuid::Uuid;
+use crate::error::RdfEditorError;
+use super::algebra::*;
+use super::term::*;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    PrefixedName(String, String),
+    Variable(String),
+    BlankLabel(String),
+    String(String),
+    LangTag(String),
+    Integer(String),
+    Decimal(String),
+    Double(String),
+    /// Keywords, `a`, `true`/`false` and function names
+    Word(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&str; 26] = [
+    "^^", "!=", "<=", ">=", "&&", "||",
+    "{", "}", "(", ")", "[", "]", ".", ";", ",", "=", "<", ">", "!", "+", "-", "*", "/", "^", "|", "?",
+];
+
+const BUILTINS: &[&str] = &[
+    "STR", "LANG", "LANGMATCHES", "DATATYPE", "BOUND", "IRI", "URI", "BNODE", "RAND", "ABS", "CEIL",
+    "FLOOR", "ROUND", "CONCAT", "STRLEN", "UCASE", "LCASE", "ENCODE_FOR_URI", "CONTAINS", "STRSTARTS",
+    "STRENDS", "STRBEFORE", "STRAFTER", "YEAR", "MONTH", "DAY", "HOURS", "MINUTES", "SECONDS",
+    "TIMEZONE", "TZ", "NOW", "UUID", "STRUUID", "SHA256", "SHA384", "SHA512",
+    "COALESCE", "IF", "STRLANG", "STRDT", "SAMETERM", "ISIRI", "ISURI", "ISBLANK", "ISLITERAL",
+    "ISNUMERIC", "REGEX", "SUBSTR", "REPLACE",
+];
+
+fn syntax_error(source: &str, offset: usize, message: &str) -> RdfEditorError {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    RdfEditorError::Sparql(format!("SPARQL syntax error at line {}, column {}: {}", line, column, message))
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, RdfEditorError> {
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let at = |i: usize| chars.get(i).map(|&(_, c)| c);
+    let offset = |i: usize| chars.get(i).map_or(source.len(), |&(o, _)| o);
+
+    while i < chars.len() {
+        let c = chars[i].1;
+        let start = offset(i);
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '#' {
+            while at(i).is_some_and(|c| c != '\n') {
+                i += 1;
+            }
+            continue;
+        }
+
+        // IRI reference, or a comparison operator when the text cannot be an IRI
+        if c == '<' {
+            let mut j = i + 1;
+            while let Some(c) = at(j) {
+                if c == '>' || c.is_whitespace() || "<\"{}|^`\\".contains(c) {
+                    break;
+                }
+                j += 1;
+            }
+            if at(j) == Some('>') {
+                let iri: String = chars[i + 1..j].iter().map(|&(_, c)| c).collect();
+                tokens.push((Token::Iri(iri), start));
+                i = j + 1;
+                continue;
+            }
+        }
+
+        if c == '?' || c == '$' {
+            let mut j = i + 1;
+            while at(j).is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                j += 1;
+            }
+            if j > i + 1 {
+                let name: String = chars[i + 1..j].iter().map(|&(_, c)| c).collect();
+                tokens.push((Token::Variable(name), start));
+                i = j;
+                continue;
+            }
+        }
+
+        if c == '"' || c == '\'' {
+            let long = at(i + 1) == Some(c) && at(i + 2) == Some(c);
+            let mut j = if long { i + 3 } else { i + 1 };
+            let mut value = String::new();
+            loop {
+                let Some(ch) = at(j) else {
+                    return Err(syntax_error(source, start, "unterminated string"));
+                };
+                if long {
+                    if ch == c && at(j + 1) == Some(c) && at(j + 2) == Some(c) && at(j + 3) != Some(c) {
+                        j += 3;
+                        break;
+                    }
+                } else if ch == c {
+                    j += 1;
+                    break;
+                } else if ch == '\n' || ch == '\r' {
+                    return Err(syntax_error(source, start, "line break in string"));
+                }
+
+                if ch == '\\' {
+                    let escaped = at(j + 1).ok_or_else(|| syntax_error(source, start, "unterminated string"))?;
+                    j += 2;
+                    match escaped {
+                        't' => value.push('\t'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        '"' | '\'' | '\\' => value.push(escaped),
+                        'u' | 'U' => {
+                            let len = if escaped == 'u' { 4 } else { 8 };
+                            let hex: String = chars.get(j..j + len).unwrap_or_default().iter().map(|&(_, c)| c).collect();
+                            let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                                .ok_or_else(|| syntax_error(source, offset(j), "invalid unicode escape"))?;
+                            value.push(code);
+                            j += len;
+                        },
+                        _ => return Err(syntax_error(source, offset(j - 1), "invalid escape")),
+                    }
+                    continue;
+                }
+                value.push(ch);
+                j += 1;
+            }
+            tokens.push((Token::String(value), start));
+            i = j;
+            continue;
+        }
+
+        if c == '@' {
+            let mut j = i + 1;
+            while at(j).is_some_and(|c| c.is_ascii_alphanumeric() || c == '-') {
+                j += 1;
+            }
+            let tag: String = chars[i + 1..j].iter().map(|&(_, c)| c).collect();
+            if tag.is_empty() {
+                return Err(syntax_error(source, start, "empty language tag"));
+            }
+            tokens.push((Token::LangTag(tag), start));
+            i = j;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && at(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let mut j = i;
+            while at(j).is_some_and(|c| c.is_ascii_digit()) {
+                j += 1;
+            }
+            let mut decimal = false;
+            if at(j) == Some('.') && at(j + 1).is_some_and(|c| c.is_ascii_digit()) {
+                decimal = true;
+                j += 1;
+                while at(j).is_some_and(|c| c.is_ascii_digit()) {
+                    j += 1;
+                }
+            }
+            let mut double = false;
+            if matches!(at(j), Some('e' | 'E')) {
+                let mut k = j + 1;
+                if matches!(at(k), Some('+' | '-')) {
+                    k += 1;
+                }
+                if at(k).is_some_and(|c| c.is_ascii_digit()) {
+                    double = true;
+                    j = k;
+                    while at(j).is_some_and(|c| c.is_ascii_digit()) {
+                        j += 1;
+                    }
+                }
+            }
+            let text: String = chars[i..j].iter().map(|&(_, c)| c).collect();
+            let token = if double {
+                Token::Double(text)
+            } else if decimal {
+                Token::Decimal(text)
+            } else {
+                Token::Integer(text)
+            };
+            tokens.push((token, start));
+            i = j;
+            continue;
+        }
+
+        if c == '_' && at(i + 1) == Some(':') {
+            let mut j = i + 2;
+            while at(j).is_some_and(|c| is_name_char(c) || c == '.') {
+                j += 1;
+            }
+            while j > i + 2 && at(j - 1) == Some('.') {
+                j -= 1;
+            }
+            let label: String = chars[i + 2..j].iter().map(|&(_, c)| c).collect();
+            tokens.push((Token::BlankLabel(label), start));
+            i = j;
+            continue;
+        }
+
+        // Keywords and prefixed names; a trailing '.' ends the triple instead
+        if c.is_alphabetic() || c == '_' || c == ':' {
+            let mut j = i;
+            let mut text = String::new();
+            let mut prefix_end = None;
+            while let Some(ch) = at(j) {
+                if ch == ':' && prefix_end.is_none() {
+                    prefix_end = Some(text.len());
+                    text.push(ch);
+                } else if is_name_char(ch) || ch == '.' || (prefix_end.is_some() && (ch == ':' || ch == '%')) {
+                    text.push(ch);
+                } else if ch == '\\' && prefix_end.is_some() {
+                    if let Some(escaped) = at(j + 1) {
+                        text.push(escaped);
+                        j += 1;
+                    }
+                } else {
+                    break;
+                }
+                j += 1;
+            }
+            while text.ends_with('.') {
+                text.pop();
+                j -= 1;
+            }
+            let token = match prefix_end {
+                Some(end) => Token::PrefixedName(text[..end].to_string(), text[end + 1..].to_string()),
+                None => Token::Word(text),
+            };
+            tokens.push((token, start));
+            i = j;
+            continue;
+        }
+
+        let rest = &source[start..];
+        match PUNCTUATION.iter().find(|p| rest.starts_with(**p)) {
+            Some(p) => {
+                tokens.push((Token::Punct(p), start));
+                i += p.chars().count();
+            },
+            None => return Err(syntax_error(source, start, &format!("unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a SPARQL query
+pub fn parse_query(source: &str) -> Result<Query, RdfEditorError> {
+    let mut parser = Parser::new(source)?;
+    parser.prologue()?;
+    let query = parser.query()?;
+    parser.expect_end()?;
+    Ok(query)
+}
+
+/// Parse a SPARQL update request: operations separated by `;`
+pub fn parse_update(source: &str) -> Result<Vec<UpdateOperation>, RdfEditorError> {
+    let mut parser = Parser::new(source)?;
+    let mut operations = Vec::new();
+    loop {
+        parser.prologue()?;
+        if parser.at_end() {
+            break;
+        }
+        operations.push(parser.update_operation()?);
+        if !parser.eat_punct(";") {
+            break;
+        }
+    }
+    parser.expect_end()?;
+    Ok(operations)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    prefixes: HashMap<String, String>,
+    base: Option<Url>,
+    /// Aggregates of the queries being parsed, innermost last
+    aggregates: Vec<Vec<Aggregate>>,
+    aggregate_count: usize,
+    allow_aggregates: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, RdfEditorError> {
+        Ok(Parser {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+            prefixes: HashMap::new(),
+            base: None,
+            aggregates: Vec::new(),
+            aggregate_count: 0,
+            allow_aggregates: false,
+        })
+    }
+
+    fn error(&self, message: &str) -> RdfEditorError {
+        let offset = self.tokens.get(self.pos).map_or(self.source.len(), |(_, o)| *o);
+        syntax_error(self.source, offset, message)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + ahead).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn expect_end(&self) -> Result<(), RdfEditorError> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected trailing input"))
+        }
+    }
+
+    fn peek_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.peek_punct(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), RdfEditorError> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", punct)))
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.keyword_at(0, keyword)
+    }
+
+    fn keyword_at(&self, ahead: usize, keyword: &str) -> bool {
+        matches!(self.peek_at(ahead), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), RdfEditorError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", keyword)))
+        }
+    }
+
+    fn resolve(&self, iri: String) -> String {
+        match &self.base {
+            Some(base) if Url::parse(&iri).is_err() => base.join(&iri).map(String::from).unwrap_or(iri),
+            _ => iri,
+        }
+    }
+
+    fn expand(&self, prefix: &str, local: &str) -> Result<String, RdfEditorError> {
+        self.prefixes.get(prefix)
+            .map(|namespace| format!("{}{}", namespace, local))
+            .ok_or_else(|| self.error(&format!("undefined prefix '{}:'", prefix)))
+    }
+
+    fn prologue(&mut self) -> Result<(), RdfEditorError> {
+        loop {
+            if self.eat_keyword("PREFIX") {
+                let Some(Token::PrefixedName(prefix, local)) = self.next() else {
+                    return Err(self.error("expected a prefix"));
+                };
+                if !local.is_empty() {
+                    return Err(self.error("expected a prefix"));
+                }
+                let iri = self.iri()?;
+                self.prefixes.insert(prefix, iri);
+            } else if self.eat_keyword("BASE") {
+                let iri = self.iri()?;
+                self.base = Some(Url::parse(&iri).map_err(|_| self.error("BASE must be an absolute IRI"))?);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn iri(&mut self) -> Result<String, RdfEditorError> {
+        match self.next() {
+            Some(Token::Iri(iri)) => Ok(self.resolve(iri)),
+            Some(Token::PrefixedName(prefix, local)) => self.expand(&prefix, &local),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected an IRI"))
+            },
+        }
+    }
+
+    fn peek_iri(&self) -> bool {
+        matches!(self.peek(), Some(Token::Iri(_) | Token::PrefixedName(..)))
+    }
+
+    fn variable(&mut self) -> Result<String, RdfEditorError> {
+        match self.next() {
+            Some(Token::Variable(name)) => Ok(name),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a variable"))
+            },
+        }
+    }
+
+    fn integer(&mut self) -> Result<usize, RdfEditorError> {
+        match self.next() {
+            Some(Token::Integer(text)) => text.parse().map_err(|_| self.error("integer out of range")),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected an integer"))
+            },
+        }
+    }
+
+    // Queries
+
+    fn query(&mut self) -> Result<Query, RdfEditorError> {
+        self.aggregates.push(Vec::new());
+        let mut short_construct = false;
+        let form = if self.eat_keyword("SELECT") {
+            self.select_clause()?
+        } else if self.eat_keyword("ASK") {
+            QueryForm::Ask
+        } else if self.eat_keyword("CONSTRUCT") {
+            if self.peek_punct("{") {
+                QueryForm::Construct(self.template()?)
+            } else {
+                // CONSTRUCT WHERE { ... }: the pattern is also the template
+                short_construct = true;
+                QueryForm::Construct(Vec::new())
+            }
+        } else if self.eat_keyword("DESCRIBE") {
+            let mut resources = Vec::new();
+            if !self.eat_punct("*") {
+                while matches!(self.peek(), Some(Token::Variable(_))) || self.peek_iri() {
+                    resources.push(self.var_or_term()?);
+                }
+                if resources.is_empty() {
+                    return Err(self.error("expected resources to describe"));
+                }
+            }
+            QueryForm::Describe(resources)
+        } else {
+            return Err(self.error("expected SELECT, ASK, CONSTRUCT or DESCRIBE"));
+        };
+
+        let dataset = self.dataset_clause("FROM")?;
+
+        let form_is_describe = matches!(form, QueryForm::Describe(_));
+        let mut construct_template = None;
+        let pattern = if form_is_describe && !self.peek_keyword("WHERE") && !self.peek_punct("{") {
+            Pattern::empty()
+        } else {
+            self.eat_keyword("WHERE");
+            if short_construct {
+                self.expect_punct("{")?;
+                let mut triples = Vec::new();
+                self.triples_block(&mut triples, "}")?;
+                self.expect_punct("}")?;
+                let pattern = Pattern::Bgp(blank_nodes_to_variables(&triples));
+                construct_template = Some(triples);
+                pattern
+            } else {
+                self.group_graph_pattern()?
+            }
+        };
+
+        let mut query = self.solution_modifiers(form, dataset, pattern)?;
+        if let Some(template) = construct_template {
+            query.form = QueryForm::Construct(template);
+        }
+
+        if self.eat_keyword("VALUES") {
+            let values = self.data_block()?;
+            query.pattern = join(query.pattern, values);
+        }
+        Ok(query)
+    }
+
+    fn select_clause(&mut self) -> Result<QueryForm, RdfEditorError> {
+        let distinct = self.eat_keyword("DISTINCT");
+        if !distinct {
+            self.eat_keyword("REDUCED");
+        }
+
+        if self.eat_punct("*") {
+            return Ok(QueryForm::Select { projection: Projection::All, distinct });
+        }
+
+        let mut variables = Vec::new();
+        loop {
+            if let Some(Token::Variable(name)) = self.peek().cloned() {
+                self.pos += 1;
+                variables.push((name, None));
+            } else if self.eat_punct("(") {
+                let expr = self.aggregate_scope(|parser| parser.expression())?;
+                self.expect_keyword("AS")?;
+                let name = self.variable()?;
+                self.expect_punct(")")?;
+                variables.push((name, Some(expr)));
+            } else {
+                break;
+            }
+        }
+        if variables.is_empty() {
+            return Err(self.error("expected variables to select"));
+        }
+        Ok(QueryForm::Select { projection: Projection::Variables(variables), distinct })
+    }
+
+    /// Run `parse` with aggregates allowed, restoring the previous setting
+    fn aggregate_scope<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, RdfEditorError>) -> Result<T, RdfEditorError> {
+        let allowed = std::mem::replace(&mut self.allow_aggregates, true);
+        let result = parse(self);
+        self.allow_aggregates = allowed;
+        result
+    }
+
+    fn dataset_clause(&mut self, keyword: &str) -> Result<Option<DatasetClause>, RdfEditorError> {
+        let mut clause = DatasetClause::default();
+        let mut any = false;
+        while self.eat_keyword(keyword) {
+            any = true;
+            if self.eat_keyword("NAMED") {
+                clause.named.push(Term::Iri(self.iri()?));
+            } else {
+                clause.default.push(Term::Iri(self.iri()?));
+            }
+        }
+        Ok(any.then_some(clause))
+    }
+
+    fn solution_modifiers(&mut self, form: QueryForm, dataset: Option<DatasetClause>, pattern: Pattern) -> Result<Query, RdfEditorError> {
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            loop {
+                if let Some(Token::Variable(name)) = self.peek().cloned() {
+                    self.pos += 1;
+                    group_by.push((Expr::Variable(name.clone()), Some(name)));
+                } else if self.eat_punct("(") {
+                    let expr = self.expression()?;
+                    let alias = if self.eat_keyword("AS") { Some(self.variable()?) } else { None };
+                    self.expect_punct(")")?;
+                    group_by.push((expr, alias));
+                } else if self.peek_call() {
+                    group_by.push((self.primary()?, None));
+                } else {
+                    break;
+                }
+            }
+            if group_by.is_empty() {
+                return Err(self.error("expected a group condition"));
+            }
+        }
+
+        let mut having = Vec::new();
+        if self.eat_keyword("HAVING") {
+            while self.peek_punct("(") || self.peek_call() {
+                having.push(self.aggregate_scope(|parser| parser.constraint())?);
+            }
+            if having.is_empty() {
+                return Err(self.error("expected a HAVING condition"));
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let descending = self.peek_keyword("DESC");
+                if self.eat_keyword("ASC") || self.eat_keyword("DESC") {
+                    self.expect_punct("(")?;
+                    let expr = self.aggregate_scope(|parser| parser.expression())?;
+                    self.expect_punct(")")?;
+                    order_by.push((expr, descending));
+                } else if let Some(Token::Variable(name)) = self.peek().cloned() {
+                    self.pos += 1;
+                    order_by.push((Expr::Variable(name), false));
+                } else if self.peek_punct("(") || self.peek_call() {
+                    order_by.push((self.aggregate_scope(|parser| parser.constraint())?, false));
+                } else {
+                    break;
+                }
+            }
+            if order_by.is_empty() {
+                return Err(self.error("expected an order condition"));
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = 0;
+        for _ in 0..2 {
+            if self.eat_keyword("LIMIT") {
+                limit = Some(self.integer()?);
+            } else if self.eat_keyword("OFFSET") {
+                offset = self.integer()?;
+            }
+        }
+
+        Ok(Query {
+            form,
+            dataset,
+            pattern,
+            group_by,
+            aggregates: self.aggregates.pop().unwrap_or_default(),
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    // Graph patterns
+
+    fn group_graph_pattern(&mut self) -> Result<Pattern, RdfEditorError> {
+        self.expect_punct("{")?;
+
+        if self.peek_keyword("SELECT") {
+            let allowed = std::mem::replace(&mut self.allow_aggregates, false);
+            let query = self.query();
+            self.allow_aggregates = allowed;
+            let query = query?;
+            self.expect_punct("}")?;
+            return Ok(Pattern::SubSelect(Box::new(query)));
+        }
+
+        let mut pattern = Pattern::empty();
+        let mut filters = Vec::new();
+        loop {
+            if self.eat_punct("}") {
+                break;
+            }
+
+            if self.eat_keyword("FILTER") {
+                filters.push(self.constraint()?);
+            } else if self.eat_keyword("OPTIONAL") {
+                let optional = self.group_graph_pattern()?;
+                pattern = Pattern::LeftJoin(Box::new(pattern), Box::new(optional));
+            } else if self.eat_keyword("MINUS") {
+                let minus = self.group_graph_pattern()?;
+                pattern = Pattern::Minus(Box::new(pattern), Box::new(minus));
+            } else if self.eat_keyword("GRAPH") {
+                let name = self.var_or_iri()?;
+                let inner = self.group_graph_pattern()?;
+                pattern = join(pattern, Pattern::Graph(name, Box::new(inner)));
+            } else if self.eat_keyword("BIND") {
+                self.expect_punct("(")?;
+                let expr = self.expression()?;
+                self.expect_keyword("AS")?;
+                let name = self.variable()?;
+                self.expect_punct(")")?;
+                pattern = Pattern::Extend(Box::new(pattern), name, expr);
+            } else if self.eat_keyword("VALUES") {
+                let values = self.data_block()?;
+                pattern = join(pattern, values);
+            } else if self.peek_keyword("SERVICE") {
+                return Err(self.error("SERVICE is not supported by the in-memory store"));
+            } else if self.peek_punct("{") {
+                let mut union = self.group_graph_pattern()?;
+                while self.eat_keyword("UNION") {
+                    let right = self.group_graph_pattern()?;
+                    union = Pattern::Union(Box::new(union), Box::new(right));
+                }
+                pattern = join(pattern, union);
+            } else {
+                let mut triples = Vec::new();
+                self.triples_same_subject(&mut triples)?;
+                pattern = join(pattern, Pattern::Bgp(blank_nodes_to_variables(&triples)));
+            }
+
+            self.eat_punct(".");
+        }
+
+        Ok(match filters.into_iter().reduce(|a, b| Expr::And(Box::new(a), Box::new(b))) {
+            Some(filter) => Pattern::Filter(filter, Box::new(pattern)),
+            None => pattern,
+        })
+    }
+
+    /// `VALUES ?x { ... }` or `VALUES (?x ?y) { (...) ... }`
+    fn data_block(&mut self) -> Result<Pattern, RdfEditorError> {
+        let mut rows = Vec::new();
+        if let Some(Token::Variable(name)) = self.peek().cloned() {
+            self.pos += 1;
+            self.expect_punct("{")?;
+            while !self.eat_punct("}") {
+                rows.push(vec![self.data_value()?]);
+            }
+            return Ok(Pattern::Values(vec![name], rows));
+        }
+
+        self.expect_punct("(")?;
+        let mut names = Vec::new();
+        while !self.eat_punct(")") {
+            names.push(self.variable()?);
+        }
+        self.expect_punct("{")?;
+        while !self.eat_punct("}") {
+            self.expect_punct("(")?;
+            let mut row = Vec::new();
+            while !self.eat_punct(")") {
+                row.push(self.data_value()?);
+            }
+            if row.len() != names.len() {
+                return Err(self.error("VALUES row has the wrong number of values"));
+            }
+            rows.push(row);
+        }
+        Ok(Pattern::Values(names, rows))
+    }
+
+    fn data_value(&mut self) -> Result<Option<Term>, RdfEditorError> {
+        if self.eat_keyword("UNDEF") {
+            return Ok(None);
+        }
+        match self.var_or_term()? {
+            TermPattern::Term(term) => Ok(Some(term)),
+            _ => Err(self.error("expected a constant")),
+        }
+    }
+
+    /// Triples up to `end` (not consumed), separated by '.'
+    fn triples_block(&mut self, out: &mut Vec<TriplePattern>, end: &str) -> Result<(), RdfEditorError> {
+        while !self.peek_punct(end) && !self.peek_keyword("GRAPH") && !self.at_end() {
+            self.triples_same_subject(out)?;
+            if !self.eat_punct(".") {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn triples_same_subject(&mut self, out: &mut Vec<TriplePattern>) -> Result<(), RdfEditorError> {
+        if self.peek_punct("[") || self.peek_punct("(") {
+            let subject = self.graph_node(out)?;
+            if self.peek_verb() {
+                self.property_list(subject, out)?;
+            }
+            return Ok(());
+        }
+        let subject = self.var_or_term()?;
+        self.property_list(subject, out)
+    }
+
+    fn peek_verb(&self) -> bool {
+        match self.peek() {
+            Some(Token::Variable(_) | Token::Iri(_) | Token::PrefixedName(..)) => true,
+            Some(Token::Word(w)) => w == "a",
+            Some(Token::Punct(p)) => matches!(*p, "^" | "!" | "("),
+            _ => false,
+        }
+    }
+
+    fn property_list(&mut self, subject: TermPattern, out: &mut Vec<TriplePattern>) -> Result<(), RdfEditorError> {
+        loop {
+            let verb = self.verb()?;
+            loop {
+                let object = self.graph_node(out)?;
+                out.push(TriplePattern { subject: subject.clone(), verb: verb.clone(), object });
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            if !self.eat_punct(";") {
+                return Ok(());
+            }
+            while self.eat_punct(";") {}
+            if !self.peek_verb() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn verb(&mut self) -> Result<Verb, RdfEditorError> {
+        if let Some(Token::Variable(name)) = self.peek().cloned() {
+            self.pos += 1;
+            return Ok(Verb::Term(TermPattern::Variable(name)));
+        }
+        match self.path()? {
+            Path::Link(iri) => Ok(Verb::Term(TermPattern::Term(iri))),
+            path => Ok(Verb::Path(path)),
+        }
+    }
+
+    fn path(&mut self) -> Result<Path, RdfEditorError> {
+        let mut alternatives = vec![self.path_sequence()?];
+        while self.eat_punct("|") {
+            alternatives.push(self.path_sequence()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Path::Alternative(alternatives) })
+    }
+
+    fn path_sequence(&mut self) -> Result<Path, RdfEditorError> {
+        let mut steps = vec![self.path_step()?];
+        while self.eat_punct("/") {
+            steps.push(self.path_step()?);
+        }
+        Ok(if steps.len() == 1 { steps.remove(0) } else { Path::Sequence(steps) })
+    }
+
+    fn path_step(&mut self) -> Result<Path, RdfEditorError> {
+        let inverse = self.eat_punct("^");
+        let primary = if self.eat_punct("(") {
+            let path = self.path()?;
+            self.expect_punct(")")?;
+            path
+        } else if self.eat_punct("!") {
+            let mut forward = Vec::new();
+            let mut backward = Vec::new();
+            let grouped = self.eat_punct("(");
+            loop {
+                let inverse = self.eat_punct("^");
+                let iri = self.path_iri()?;
+                if inverse { backward.push(iri) } else { forward.push(iri) }
+                if !grouped || !self.eat_punct("|") {
+                    break;
+                }
+            }
+            if grouped {
+                self.expect_punct(")")?;
+            }
+            Path::Negated(forward, backward)
+        } else {
+            Path::Link(self.path_iri()?)
+        };
+
+        let path = if self.eat_punct("*") {
+            Path::ZeroOrMore(Box::new(primary))
+        } else if self.eat_punct("+") {
+            Path::OneOrMore(Box::new(primary))
+        } else if self.eat_punct("?") {
+            Path::ZeroOrOne(Box::new(primary))
+        } else {
+            primary
+        };
+        Ok(if inverse { Path::Inverse(Box::new(path)) } else { path })
+    }
+
+    fn path_iri(&mut self) -> Result<Term, RdfEditorError> {
+        if matches!(self.peek(), Some(Token::Word(w)) if w == "a") {
+            self.pos += 1;
+            return Ok(Term::iri(RDF_TYPE));
+        }
+        Ok(Term::Iri(self.iri()?))
+    }
+
+    /// Subject or object, including `[ ... ]` and `( ... )` which add their own triples
+    fn graph_node(&mut self, out: &mut Vec<TriplePattern>) -> Result<TermPattern, RdfEditorError> {
+        if self.eat_punct("[") {
+            let node = fresh_blank();
+            if !self.eat_punct("]") {
+                self.property_list(node.clone(), out)?;
+                self.expect_punct("]")?;
+            }
+            return Ok(node);
+        }
+
+        if self.eat_punct("(") {
+            let mut items = Vec::new();
+            while !self.eat_punct(")") {
+                items.push(self.graph_node(out)?);
+            }
+            let mut list = TermPattern::Term(Term::iri(RDF_NIL));
+            for item in items.into_iter().rev() {
+                let node = fresh_blank();
+                out.push(TriplePattern { subject: node.clone(), verb: Verb::Term(TermPattern::Term(Term::iri(RDF_FIRST))), object: item });
+                out.push(TriplePattern { subject: node.clone(), verb: Verb::Term(TermPattern::Term(Term::iri(RDF_REST))), object: list });
+                list = node;
+            }
+            return Ok(list);
+        }
+
+        self.var_or_term()
+    }
+
+    fn var_or_iri(&mut self) -> Result<TermPattern, RdfEditorError> {
+        if let Some(Token::Variable(name)) = self.peek().cloned() {
+            self.pos += 1;
+            return Ok(TermPattern::Variable(name));
+        }
+        Ok(TermPattern::Term(Term::Iri(self.iri()?)))
+    }
+
+    fn var_or_term(&mut self) -> Result<TermPattern, RdfEditorError> {
+        match self.peek().cloned() {
+            Some(Token::Variable(name)) => {
+                self.pos += 1;
+                Ok(TermPattern::Variable(name))
+            },
+            Some(Token::BlankLabel(label)) => {
+                self.pos += 1;
+                Ok(TermPattern::BlankNode(label))
+            },
+            Some(Token::Punct("[")) if self.peek_at(1) == Some(&Token::Punct("]")) => {
+                self.pos += 2;
+                Ok(fresh_blank())
+            },
+            _ => Ok(TermPattern::Term(self.term()?)),
+        }
+    }
+
+    /// IRI or literal
+    fn term(&mut self) -> Result<Term, RdfEditorError> {
+        if self.peek_iri() {
+            return Ok(Term::Iri(self.iri()?));
+        }
+
+        let negative = self.peek_punct("-");
+        if (negative || self.peek_punct("+")) && matches!(self.peek_at(1), Some(Token::Integer(_) | Token::Decimal(_) | Token::Double(_))) {
+            self.pos += 1;
+        }
+
+        match self.next() {
+            Some(Token::String(value)) => {
+                if let Some(Token::LangTag(tag)) = self.peek().cloned() {
+                    self.pos += 1;
+                    return Ok(Term::lang_string(value, &tag));
+                }
+                if self.eat_punct("^^") {
+                    let datatype = self.iri()?;
+                    return Ok(Term::typed(value, &datatype));
+                }
+                Ok(Term::string(value))
+            },
+            Some(Token::Integer(text)) => Ok(Term::typed(signed(negative, text), XSD_INTEGER)),
+            Some(Token::Decimal(text)) => Ok(Term::typed(signed(negative, text), XSD_DECIMAL)),
+            Some(Token::Double(text)) => Ok(Term::typed(signed(negative, text), XSD_DOUBLE)),
+            Some(Token::Word(w)) if w == "true" || w == "false" => Ok(Term::boolean(w == "true")),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected an RDF term"))
+            },
+        }
+    }
+
+    /// Triples inside braces for CONSTRUCT
+    fn template(&mut self) -> Result<Vec<TriplePattern>, RdfEditorError> {
+        self.expect_punct("{")?;
+        let mut triples = Vec::new();
+        self.triples_block(&mut triples, "}")?;
+        self.expect_punct("}")?;
+        for triple in &triples {
+            if matches!(triple.verb, Verb::Path(_)) {
+                return Err(self.error("property paths are not allowed in templates"));
+            }
+        }
+        Ok(triples)
+    }
+
+    // Expressions
+
+    fn constraint(&mut self) -> Result<Expr, RdfEditorError> {
+        if self.eat_punct("(") {
+            let expr = self.expression()?;
+            self.expect_punct(")")?;
+            return Ok(expr);
+        }
+        if self.peek_call() {
+            return self.primary();
+        }
+        Err(self.error("expected a constraint"))
+    }
+
+    /// Built-in or IRI function call ahead
+    fn peek_call(&self) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) => {
+                let upper = w.to_uppercase();
+                BUILTINS.contains(&upper.as_str()) || matches!(upper.as_str(), "NOT" | "EXISTS")
+                    || aggregate_function(&upper).is_some()
+            },
+            Some(Token::Iri(_) | Token::PrefixedName(..)) => self.peek_at(1) == Some(&Token::Punct("(")),
+            _ => false,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, RdfEditorError> {
+        let mut expr = self.and_expression()?;
+        while self.eat_punct("||") {
+            let right = self.and_expression()?;
+            expr = Expr::Or(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn and_expression(&mut self) -> Result<Expr, RdfEditorError> {
+        let mut expr = self.relational_expression()?;
+        while self.eat_punct("&&") {
+            let right = self.relational_expression()?;
+            expr = Expr::And(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn relational_expression(&mut self) -> Result<Expr, RdfEditorError> {
+        let left = self.additive_expression()?;
+        let op = match self.peek() {
+            Some(Token::Punct("=")) => Some(CompareOp::Eq),
+            Some(Token::Punct("!=")) => Some(CompareOp::Ne),
+            Some(Token::Punct("<")) => Some(CompareOp::Lt),
+            Some(Token::Punct("<=")) => Some(CompareOp::Le),
+            Some(Token::Punct(">")) => Some(CompareOp::Gt),
+            Some(Token::Punct(">=")) => Some(CompareOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let right = self.additive_expression()?;
+            return Ok(Expr::Compare(op, Box::new(left), Box::new(right)));
+        }
+
+        let negated = self.peek_keyword("NOT") && self.keyword_at(1, "IN");
+        if negated {
+            self.pos += 1;
+        }
+        if self.eat_keyword("IN") {
+            let list = self.argument_list()?;
+            return Ok(Expr::In(Box::new(left), list, negated));
+        }
+        Ok(left)
+    }
+
+    fn additive_expression(&mut self) -> Result<Expr, RdfEditorError> {
+        let mut expr = self.multiplicative_expression()?;
+        loop {
+            let op = if self.eat_punct("+") {
+                ArithmeticOp::Add
+            } else if self.eat_punct("-") {
+                ArithmeticOp::Subtract
+            } else {
+                return Ok(expr);
+            };
+            let right = self.multiplicative_expression()?;
+            expr = Expr::Arithmetic(op, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn multiplicative_expression(&mut self) -> Result<Expr, RdfEditorError> {
+        let mut expr = self.unary_expression()?;
+        loop {
+            let op = if self.eat_punct("*") {
+                ArithmeticOp::Multiply
+            } else if self.eat_punct("/") {
+                ArithmeticOp::Divide
+            } else {
+                return Ok(expr);
+            };
+            let right = self.unary_expression()?;
+            expr = Expr::Arithmetic(op, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn unary_expression(&mut self) -> Result<Expr, RdfEditorError> {
+        if self.eat_punct("!") {
+            return Ok(Expr::Not(Box::new(self.primary()?)));
+        }
+        if self.eat_punct("-") {
+            return Ok(Expr::Negate(Box::new(self.primary()?)));
+        }
+        self.eat_punct("+");
+        self.primary()
+    }
+
+    fn argument_list(&mut self) -> Result<Vec<Expr>, RdfEditorError> {
+        self.expect_punct("(")?;
+        let mut args = Vec::new();
+        if self.eat_punct(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expression()?);
+            if self.eat_punct(")") {
+                return Ok(args);
+            }
+            self.expect_punct(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, RdfEditorError> {
+        if self.eat_punct("(") {
+            let expr = self.expression()?;
+            self.expect_punct(")")?;
+            return Ok(expr);
+        }
+
+        match self.peek().cloned() {
+            Some(Token::Variable(name)) => {
+                self.pos += 1;
+                Ok(Expr::Variable(name))
+            },
+            Some(Token::Iri(_) | Token::PrefixedName(..)) => {
+                let iri = self.iri()?;
+                if self.peek_punct("(") {
+                    let args = self.argument_list()?;
+                    return Ok(Expr::Function(iri, args));
+                }
+                Ok(Expr::Constant(Term::Iri(iri)))
+            },
+            Some(Token::Word(word)) if word != "true" && word != "false" => {
+                let upper = word.to_uppercase();
+                self.pos += 1;
+
+                if upper == "NOT" {
+                    self.expect_keyword("EXISTS")?;
+                    return Ok(Expr::Exists(Box::new(self.group_graph_pattern()?), true));
+                }
+                if upper == "EXISTS" {
+                    return Ok(Expr::Exists(Box::new(self.group_graph_pattern()?), false));
+                }
+                if let Some(function) = aggregate_function(&upper) {
+                    return self.aggregate(function);
+                }
+                if !BUILTINS.contains(&upper.as_str()) {
+                    self.pos -= 1;
+                    return Err(self.error(&format!("unknown function {}", word)));
+                }
+
+                let args = self.argument_list()?;
+                Ok(Expr::Call(upper, args))
+            },
+            _ => Ok(Expr::Constant(self.term()?)),
+        }
+    }
+
+    fn aggregate(&mut self, function: AggregateFunction) -> Result<Expr, RdfEditorError> {
+        if !self.allow_aggregates {
+            return Err(self.error("aggregates are only allowed in SELECT, HAVING and ORDER BY"));
+        }
+        self.expect_punct("(")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        let expr = if function == AggregateFunction::Count && self.eat_punct("*") {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        let mut separator = " ".to_string();
+        if function == AggregateFunction::GroupConcat && self.eat_punct(";") {
+            self.expect_keyword("SEPARATOR")?;
+            self.expect_punct("=")?;
+            match self.next() {
+                Some(Token::String(value)) => separator = value,
+                _ => return Err(self.error("expected a separator string")),
+            }
+        }
+        self.expect_punct(")")?;
+
+        let variable = format!("{}agg{}", HIDDEN_PREFIX, self.aggregate_count);
+        self.aggregate_count += 1;
+        let aggregates = self.aggregates.last_mut()
+            .ok_or_else(|| syntax_error(self.source, 0, "aggregate outside a query"))?;
+        aggregates.push(Aggregate { function, distinct, expr, separator, variable: variable.clone() });
+        Ok(Expr::Variable(variable))
+    }
+
+    // Updates
+
+    fn update_operation(&mut self) -> Result<UpdateOperation, RdfEditorError> {
+        if self.peek_keyword("LOAD") {
+            return Err(self.error("LOAD is not supported by the in-memory store"));
+        }
+
+        for keyword in ["CLEAR", "DROP"] {
+            if self.eat_keyword(keyword) {
+                let silent = self.eat_keyword("SILENT");
+                let target = if self.eat_keyword("DEFAULT") {
+                    GraphTarget::Default
+                } else if self.eat_keyword("NAMED") {
+                    GraphTarget::AllNamed
+                } else if self.eat_keyword("ALL") {
+                    GraphTarget::All
+                } else {
+                    self.expect_keyword("GRAPH")?;
+                    GraphTarget::Named(Term::Iri(self.iri()?))
+                };
+                return Ok(if keyword == "CLEAR" {
+                    UpdateOperation::Clear { target, silent }
+                } else {
+                    UpdateOperation::Drop { target, silent }
+                });
+            }
+        }
+
+        if self.eat_keyword("CREATE") {
+            let silent = self.eat_keyword("SILENT");
+            self.expect_keyword("GRAPH")?;
+            return Ok(UpdateOperation::Create { graph: Term::Iri(self.iri()?), silent });
+        }
+
+        for (keyword, kind) in [("ADD", GraphTransfer::Add), ("MOVE", GraphTransfer::Move), ("COPY", GraphTransfer::Copy)] {
+            if self.eat_keyword(keyword) {
+                let silent = self.eat_keyword("SILENT");
+                let from = self.graph_or_default()?;
+                self.expect_keyword("TO")?;
+                let to = self.graph_or_default()?;
+                return Ok(UpdateOperation::Transfer { kind, from, to, silent });
+            }
+        }
+
+        if self.peek_keyword("INSERT") && self.keyword_at(1, "DATA") {
+            self.pos += 2;
+            return Ok(UpdateOperation::InsertData(self.quad_data()?));
+        }
+        if self.peek_keyword("DELETE") && self.keyword_at(1, "DATA") {
+            self.pos += 2;
+            return Ok(UpdateOperation::DeleteData(self.quad_data()?));
+        }
+        if self.peek_keyword("DELETE") && self.keyword_at(1, "WHERE") {
+            self.pos += 2;
+            let quads = quads_to_patterns(self.quad_pattern()?);
+            let pattern = quads_where(&quads);
+            return Ok(UpdateOperation::Modify { with: None, delete: quads, insert: Vec::new(), using: None, pattern });
+        }
+
+        let with = if self.eat_keyword("WITH") { Some(Term::Iri(self.iri()?)) } else { None };
+        let mut delete = Vec::new();
+        let mut insert = Vec::new();
+        if self.eat_keyword("DELETE") {
+            delete = quads_to_patterns(self.quad_pattern()?);
+            if self.eat_keyword("INSERT") {
+                insert = self.quad_pattern()?;
+            }
+        } else if self.eat_keyword("INSERT") {
+            insert = self.quad_pattern()?;
+        } else {
+            return Err(self.error("expected an update operation"));
+        }
+        let using = self.dataset_clause("USING")?;
+        self.expect_keyword("WHERE")?;
+        let pattern = self.group_graph_pattern()?;
+        Ok(UpdateOperation::Modify { with, delete, insert, using, pattern })
+    }
+
+    fn graph_or_default(&mut self) -> Result<Option<Term>, RdfEditorError> {
+        if self.eat_keyword("DEFAULT") {
+            return Ok(None);
+        }
+        self.eat_keyword("GRAPH");
+        Ok(Some(Term::Iri(self.iri()?)))
+    }
+
+    /// `{ triples GRAPH <g> { triples } ... }`
+    fn quad_pattern(&mut self) -> Result<Vec<QuadPattern>, RdfEditorError> {
+        self.expect_punct("{")?;
+        let mut quads = Vec::new();
+        loop {
+            if self.eat_punct("}") {
+                return Ok(quads);
+            }
+            let (graph, triples) = if self.eat_keyword("GRAPH") {
+                let graph = self.var_or_iri()?;
+                self.expect_punct("{")?;
+                let mut triples = Vec::new();
+                self.triples_block(&mut triples, "}")?;
+                self.expect_punct("}")?;
+                (Some(graph), triples)
+            } else {
+                let mut triples = Vec::new();
+                self.triples_block(&mut triples, "}")?;
+                if triples.is_empty() {
+                    return Err(self.error("expected triples"));
+                }
+                (None, triples)
+            };
+            for triple in triples {
+                if matches!(triple.verb, Verb::Path(_)) {
+                    return Err(self.error("property paths are not allowed in templates"));
+                }
+                quads.push(QuadPattern { graph: graph.clone(), triple });
+            }
+            self.eat_punct(".");
+        }
+    }
+
+    /// Quads for INSERT DATA and DELETE DATA, which take no variables
+    fn quad_data(&mut self) -> Result<Vec<QuadPattern>, RdfEditorError> {
+        let quads = self.quad_pattern()?;
+        for quad in &quads {
+            let terms = [quad.graph.as_ref(), Some(&quad.triple.subject), Some(&quad.triple.object)];
+            let verb_is_variable = matches!(quad.triple.verb, Verb::Term(TermPattern::Variable(_)));
+            if verb_is_variable || terms.into_iter().flatten().any(|t| matches!(t, TermPattern::Variable(_))) {
+                return Err(self.error("variables are not allowed in data"));
+            }
+        }
+        Ok(quads)
+    }
+}
+
+fn signed(negative: bool, text: String) -> String {
+    if negative { format!("-{}", text) } else { text }
+}
+
+fn aggregate_function(name: &str) -> Option<AggregateFunction> {
+    match name {
+        "COUNT" => Some(AggregateFunction::Count),
+        "SUM" => Some(AggregateFunction::Sum),
+        "AVG" => Some(AggregateFunction::Avg),
+        "MIN" => Some(AggregateFunction::Min),
+        "MAX" => Some(AggregateFunction::Max),
+        "SAMPLE" => Some(AggregateFunction::Sample),
+        "GROUP_CONCAT" => Some(AggregateFunction::GroupConcat),
+        _ => None,
+    }
+}
+
+/// Anonymous blank node, `[]` in the text
+fn fresh_blank() -> TermPattern {
+    TermPattern::BlankNode(format!("b{}", Uuid::new_v4().simple()))
+}
+
+/// Blank nodes in a graph pattern match like variables that are never returned
+fn blank_nodes_to_variables(triples: &[TriplePattern]) -> Vec<TriplePattern> {
+    let convert = |term: &TermPattern| match term {
+        TermPattern::BlankNode(label) => TermPattern::Variable(format!("{}{}", HIDDEN_PREFIX, label)),
+        other => other.clone(),
+    };
+    triples.iter().map(|triple| TriplePattern {
+        subject: convert(&triple.subject),
+        verb: triple.verb.clone(),
+        object: convert(&triple.object),
+    }).collect()
+}
+
+fn quads_to_patterns(quads: Vec<QuadPattern>) -> Vec<QuadPattern> {
+    quads.into_iter().map(|quad| QuadPattern {
+        graph: quad.graph,
+        triple: blank_nodes_to_variables(std::slice::from_ref(&quad.triple)).remove(0),
+    }).collect()
+}
+
+/// The WHERE pattern of `DELETE WHERE`: its quads read as a graph pattern
+fn quads_where(quads: &[QuadPattern]) -> Pattern {
+    let mut pattern = Pattern::empty();
+    for quad in quads {
+        let bgp = Pattern::Bgp(vec![quad.triple.clone()]);
+        pattern = join(pattern, match &quad.graph {
+            Some(graph) => Pattern::Graph(graph.clone(), Box::new(bgp)),
+            None => bgp,
+        });
+    }
+    pattern
+}
+
+/// Join two patterns, merging adjacent basic graph patterns
+fn join(left: Pattern, right: Pattern) -> Pattern {
+    match (left, right) {
+        (Pattern::Bgp(left), right) if left.is_empty() => right,
+        (left, Pattern::Bgp(right)) if right.is_empty() => left,
+        (Pattern::Bgp(mut left), Pattern::Bgp(right)) => {
+            left.extend(right);
+            Pattern::Bgp(left)
+        },
+        (left, right) => Pattern::Join(Box::new(left), Box::new(right)),
+    }
+}
@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use crate::models::{RdfNode, Triple};
+
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+pub const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+pub const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+pub const XSD_DECIMAL: &str = "http://www.w3.org/2001/XMLSchema#decimal";
+pub const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+pub const XSD_DATE_TIME: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+pub const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+pub const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+pub const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+
+/// An RDF term. Literals without a language keep `datatype: None` for
+/// xsd:string, so a plain and an explicitly typed string are the same term.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Term {
+    Iri(String),
+    /// Blank node label, without the `_:` prefix
+    Blank(String),
+    Literal {
+        value: String,
+        datatype: Option<String>,
+        language: Option<String>,
+    },
+}
+
+/// Subject, predicate and object of one statement
+pub type Statement = (Term, Term, Term);
+
+impl Term {
+    pub fn iri(value: impl Into<String>) -> Self {
+        Term::Iri(value.into())
+    }
+
+    pub fn string(value: impl Into<String>) -> Self {
+        Term::Literal { value: value.into(), datatype: None, language: None }
+    }
+
+    pub fn typed(value: impl Into<String>, datatype: &str) -> Self {
+        let datatype = (datatype != XSD_STRING).then(|| datatype.to_string());
+        Term::Literal { value: value.into(), datatype, language: None }
+    }
+
+    pub fn lang_string(value: impl Into<String>, language: &str) -> Self {
+        Term::Literal { value: value.into(), datatype: None, language: Some(language.to_lowercase()) }
+    }
+
+    pub fn boolean(value: bool) -> Self {
+        Term::typed(value.to_string(), XSD_BOOLEAN)
+    }
+
+    pub fn integer(value: i64) -> Self {
+        Term::typed(value.to_string(), XSD_INTEGER)
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(self, Term::Literal { .. })
+    }
+
+    /// Datatype IRI of a literal, with the implicit ones spelled out
+    pub fn datatype(&self) -> Option<&str> {
+        match self {
+            Term::Literal { language: Some(_), .. } => Some(RDF_LANG_STRING),
+            Term::Literal { datatype: Some(datatype), .. } => Some(datatype),
+            Term::Literal { .. } => Some(XSD_STRING),
+            _ => None,
+        }
+    }
+
+    /// Lexical form of a literal, or the IRI or label of a resource
+    pub fn lexical(&self) -> &str {
+        match self {
+            Term::Iri(value) | Term::Blank(value) => value,
+            Term::Literal { value, .. } => value,
+        }
+    }
+
+    /// Plain, xsd:string or language-tagged literal: what the string functions accept
+    pub fn is_string_literal(&self) -> bool {
+        matches!(self, Term::Literal { datatype: None, .. })
+    }
+
+    /// Term for a triple's subject, where blank nodes are written `_:label`
+    pub fn from_subject(subject: &str) -> Self {
+        match subject.strip_prefix("_:") {
+            Some(label) => Term::Blank(label.to_string()),
+            None => Term::Iri(subject.to_string()),
+        }
+    }
+
+    pub fn from_node(node: &RdfNode) -> Self {
+        match node {
+            RdfNode::Uri { value } => Term::Iri(value.clone()),
+            RdfNode::Blank { value } => Term::Blank(value.trim_start_matches("_:").to_string()),
+            RdfNode::Literal { value, language: Some(language), .. } => Term::lang_string(value.clone(), language),
+            RdfNode::Literal { value, datatype, .. } => Term::typed(value.clone(), datatype.as_deref().unwrap_or(XSD_STRING)),
+        }
+    }
+
+    /// Binding in the SPARQL 1.1 JSON results format
+    pub fn to_json(&self) -> Value {
+        match self {
+            Term::Iri(value) => json!({"type": "uri", "value": value}),
+            Term::Blank(label) => json!({"type": "bnode", "value": label}),
+            Term::Literal { value, language: Some(language), .. } => {
+                json!({"type": "literal", "value": value, "xml:lang": language})
+            },
+            Term::Literal { value, datatype: Some(datatype), .. } => {
+                json!({"type": "literal", "value": value, "datatype": datatype})
+            },
+            Term::Literal { value, .. } => json!({"type": "literal", "value": value}),
+        }
+    }
+}
+
+/// Statement and graph name for a triple; `None` means the triple names no graph
+pub fn from_triple(triple: &Triple) -> (Statement, Option<Term>) {
+    let statement = (
+        Term::from_subject(&triple.subject),
+        Term::Iri(triple.predicate.clone()),
+        Term::from_node(&triple.object),
+    );
+    (statement, triple.graph.as_deref().map(Term::from_subject))
+}
@@ -0,0 +1,90 @@
+//! Storage backends behind `SparqlClient`.
+//!
+//! A `Store` executes SPARQL queries and updates and, where it can, whole-graph
+//! Graph Store Protocol operations. `SparqlClient` builds every read and write
+//! on top of this surface, so any backend serves all routes. The endpoint URL
+//! picks the backend: `memory:` (optionally followed by a file to load) selects
+//! the embedded store, anything else a remote SPARQL endpoint over HTTP.
+
+pub mod http;
+pub mod memory;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use serde_json::Value;
+use crate::config::{Config, EndpointProfile};
+use crate::dialect::StoreDialect;
+use crate::error::RdfEditorError;
+use crate::models::{Namespace, RdfFormat, Triple};
+
+/// URL scheme that selects the embedded in-memory store
+pub const MEMORY_SCHEME: &str = "memory:";
+
+/// Serialized graph body, passed through to the client as it arrives
+pub type ByteStream = BoxStream<'static, Result<Bytes, RdfEditorError>>;
+
+#[async_trait]
+pub trait Store: Send + Sync {
+    fn dialect(&self) -> StoreDialect;
+    
+    /// Evaluate a query and return SPARQL JSON results. `reasoning` switches
+    /// inference on or off where the store supports a per-query toggle.
+    async fn query(&self, query: &str, reasoning: Option<bool>) -> Result<Value, RdfEditorError>;
+    
+    async fn update(&self, update: &str) -> Result<(), RdfEditorError>;
+    
+    /// Apply several updates all or nothing
+    async fn update_atomic(&self, updates: &[String]) -> Result<(), RdfEditorError>;
+    
+    /// Prefixes stored with the data, or `None` when the store keeps none
+    async fn namespaces(&self) -> Result<Option<Vec<Namespace>>, RdfEditorError> {
+        Ok(None)
+    }
+    
+    /// Whether the `graph_store_*` operations are available
+    fn has_graph_store(&self) -> bool {
+        false
+    }
+    
+    /// A whole graph serialized by the store; `None` when the graph does not exist
+    async fn graph_store_get(&self, _graph: &str, _format: RdfFormat) -> Result<Option<ByteStream>, RdfEditorError> {
+        Err(no_graph_store())
+    }
+    
+    /// Write `triples` to `graph` as one document, replacing its contents if `replace`
+    async fn graph_store_write(&self, _graph: &str, _triples: &[Triple], _replace: bool) -> Result<(), RdfEditorError> {
+        Err(no_graph_store())
+    }
+    
+    /// Remove `graph`; a graph that does not exist is not an error
+    async fn graph_store_delete(&self, _graph: &str) -> Result<(), RdfEditorError> {
+        Err(no_graph_store())
+    }
+}
+
+fn no_graph_store() -> RdfEditorError {
+    RdfEditorError::Configuration("No graph store endpoint configured".to_string())
+}
+
+/// Backend for an endpoint profile
+pub fn open(config: &Config, profile: &EndpointProfile) -> Result<Box<dyn Store>, RdfEditorError> {
+    match profile.sparql_endpoint.strip_prefix(MEMORY_SCHEME) {
+        Some(source) => {
+            let source = source.trim();
+            // Nothing is ever saved, so say so where an operator will see it
+            let store = if source.is_empty() {
+                log::warn!("Profile {} uses the embedded store; its data is lost when the server stops", profile.name);
+                memory::MemoryStore::new()
+            } else {
+                log::warn!(
+                    "Profile {} uses the embedded store seeded from {}; edits are not written back and are lost when the server stops",
+                    profile.name, source
+                );
+                memory::MemoryStore::load(source)?
+            };
+            Ok(Box::new(store))
+        },
+        None => Ok(Box::new(http::HttpStore::new(config, profile)?)),
+    }
+}
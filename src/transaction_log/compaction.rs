@@ -0,0 +1,161 @@
+//! Compaction of old history.
+//!
+//! Everything logged before a cutoff is replaced by snapshots of each graph it
+//! touched: one at the cutoff and, given an interval, one per interval before
+//! it back to the oldest record, leaving out any that match the one before.
+//! Restoring to a time inside the compacted part lands on the last snapshot at
+//! or before it. Records from the cutoff on are kept as they are. The result is
+//! written as a single archive that replaces all the older segments.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use uuid::Uuid;
+use crate::error::RdfEditorError;
+use crate::models::Triple;
+use crate::sparql::SparqlClient;
+use tokio::fs::File;
+use super::{prior_state, scan, segments, LogIndex, TransactionLogger, TransactionRecord, TransactionType};
+
+#[derive(Debug, Serialize)]
+pub struct Compaction {
+    /// Snapshot records written
+    pub snapshots: usize,
+    /// Records folded into them
+    pub removed: usize,
+    /// Graphs left out because a record touching them lacks the data to rebuild
+    /// their earlier states; their history before the cutoff is gone all the same
+    pub skipped: Vec<String>,
+}
+
+/// Times the graphs are read again when the log changes while they are read
+const ATTEMPTS: usize = 3;
+
+impl TransactionLogger {
+    /// Fold the records logged up to `before` into graph snapshots taken every
+    /// `interval` back from it, or only at `before` without one. The graphs are read
+    /// from the store without holding the log, so writes carry on meanwhile; should
+    /// any land in the log before it is locked, the graphs are read again.
+    pub async fn compact(
+        &self,
+        client: &SparqlClient,
+        before: DateTime<Utc>,
+        interval: Option<Duration>,
+        page_size: usize,
+    ) -> Result<Compaction, RdfEditorError> {
+        for _ in 0..ATTEMPTS {
+            let (cutoff, first, len, graphs) = {
+                let index = self.index.read().await;
+                let cutoff = index.position_after(before);
+                (cutoff, index.first(), index.len(), index.graphs(index.first()..cutoff))
+            };
+            if cutoff == first {
+                return Ok(Compaction { snapshots: 0, removed: 0, skipped: Vec::new() });
+            }
+            let mut current = Vec::new();
+            for graph in graphs {
+                let triples = client.snapshot_graph(&graph, page_size).await?;
+                current.push((graph, triples));
+            }
+            
+            let mut file = self.log_file.lock().await;
+            let mut index = self.index.write().await;
+            if index.position_after(before) == cutoff && index.first() == first && index.len() == len {
+                return self.replace_compacted(&mut file, &mut index, before, interval, current).await;
+            }
+        }
+        
+        Err(RdfEditorError::InvalidInput(
+            "The log kept changing while compacting; try again".to_string()
+        ))
+    }
+    
+    /// Write the snapshots of the graphs, `current` holding their content now, and
+    /// swap them in for the records up to `before`. Runs with the log locked.
+    async fn replace_compacted(
+        &self,
+        file: &mut File,
+        index: &mut LogIndex,
+        before: DateTime<Utc>,
+        interval: Option<Duration>,
+        current: Vec<(String, Vec<Triple>)>,
+    ) -> Result<Compaction, RdfEditorError> {
+        let cutoff = index.position_after(before);
+        let mut compaction = Compaction {
+            snapshots: 0,
+            removed: cutoff - index.first(),
+            skipped: Vec::new(),
+        };
+        let oldest = index.get(index.first()).timestamp;
+        let mut boundaries = vec![before];
+        if let Some(interval) = interval.filter(|interval| *interval > Duration::zero()) {
+            while let Some(boundary) = boundaries.last().map(|last| *last - interval).filter(|b| *b >= oldest) {
+                boundaries.push(boundary);
+            }
+        }
+        boundaries.reverse();
+        
+        let mut snapshots: Vec<TransactionRecord> = Vec::new();
+        'graphs: for (graph, current) in current {
+            let mut graph_snapshots: Vec<TransactionRecord> = Vec::new();
+            for &boundary in &boundaries {
+                let records = self.touching(index, index.position_after(boundary), &graph).await?;
+                let Ok(prior) = prior_state(&records, Some(&graph)) else {
+                    compaction.skipped.push(graph);
+                    continue 'graphs;
+                };
+                let (deletions, insertions) = prior.diff(&current);
+                let deletions: HashSet<&Triple> = deletions.iter().collect();
+                let content: Vec<Triple> = current.iter()
+                    .filter(|t| !deletions.contains(t))
+                    .chain(&insertions)
+                    .cloned()
+                    .collect();
+                
+                if graph_snapshots.last().is_some_and(|last| same_triples(last.new_data.as_deref(), &content)) {
+                    continue;
+                }
+                graph_snapshots.push(TransactionRecord {
+                    id: Uuid::new_v4().to_string(),
+                    timestamp: boundary,
+                    transaction_type: TransactionType::Snapshot,
+                    graph: Some(graph.clone()),
+                    old_data: None,
+                    description: format!("Snapshot of graph {}: {} triples", graph, content.len()),
+                    new_data: Some(content),
+                    reverses: None,
+                    user_id: None,
+                    user_name: None,
+                });
+            }
+            snapshots.extend(graph_snapshots);
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        
+        // Archive the active file so the log is all archives, then replace
+        // them with one holding the snapshots and the records kept
+        self.rotate(file, index).await?;
+        let kept: Vec<usize> = (cutoff.max(index.first())..index.len()).collect();
+        let mut content = Vec::new();
+        for record in snapshots.iter().chain(self.read_entries(index, &kept).await?.iter().map(|entry| &entry.record)) {
+            serde_json::to_writer(&mut content, record)?;
+            content.push(b'\n');
+        }
+        
+        let seq = index.active();
+        segments::write_archive(segments::archive_path(&self.log_path, seq), content).await?;
+        for old in segments::archives(&self.log_path).await?.into_iter().filter(|old| *old < seq) {
+            segments::remove(segments::archive_path(&self.log_path, old)).await?;
+        }
+        *self.archive_cache.lock().await = None;
+        *index = scan(&self.log_path).await?.0;
+        
+        compaction.snapshots = snapshots.len();
+        Ok(compaction)
+    }
+}
+
+fn same_triples(a: Option<&[Triple]>, b: &[Triple]) -> bool {
+    let a: HashSet<&Triple> = a.unwrap_or_default().iter().collect();
+    a.len() == b.len() && b.iter().all(|t| a.contains(t))
+}
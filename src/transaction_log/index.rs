@@ -0,0 +1,317 @@
+//! Offset index over the JSONL log.
+//!
+//! Built by one scan when the log is opened and kept current as records are
+//! appended. Each entry holds the segment and offset of its line plus the
+//! fields queries filter on, so a query reads only the lines it returns.
+//! Subjects and predicates get posting lists, and the Undo and Redo links are
+//! followed as records arrive so any record's undo state is known without a
+//! scan. Positions count records from the start of the log and stay put when
+//! retention drops the oldest segments.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::ops::Range;
+use super::{LogQuery, TransactionRecord, TransactionType};
+
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub id: String,
+    /// Segment holding the line, its byte offset there and its length with the newline
+    pub segment: u64,
+    pub offset: u64,
+    pub len: usize,
+    pub timestamp: DateTime<Utc>,
+    pub transaction_type: TransactionType,
+    pub user_id: Option<String>,
+    /// Graphs the record touches, "default" for the default graph
+    pub graphs: Vec<String>,
+}
+
+impl IndexEntry {
+    fn matches(&self, query: &LogQuery) -> bool {
+        query.graph.as_ref().is_none_or(|g| self.graphs.contains(g))
+            && query.user.as_ref().is_none_or(|u| self.user_id.as_ref() == Some(u))
+            && query.transaction_type.as_ref().is_none_or(|t| &self.transaction_type == t)
+            && query.since.is_none_or(|since| self.timestamp >= since)
+            && query.until.is_none_or(|until| self.timestamp <= until)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LogIndex {
+    entries: Vec<IndexEntry>,
+    /// Position of `entries[0]`: records dropped by retention
+    first: usize,
+    positions: HashMap<String, usize>,
+    /// Positions of the records mentioning each subject and predicate, ascending
+    subjects: HashMap<String, Vec<usize>>,
+    predicates: HashMap<String, Vec<usize>>,
+    /// Transaction id -> the Undo currently reversing it
+    undone_by: HashMap<String, String>,
+    /// Undo id -> the transaction it reverses
+    undo_targets: HashMap<String, String>,
+    /// Segment new records go to, its length so far and when its first record was logged
+    active: u64,
+    end: u64,
+    active_since: Option<DateTime<Utc>>,
+}
+
+impl LogIndex {
+    /// Position after the last record
+    pub fn len(&self) -> usize {
+        self.first + self.entries.len()
+    }
+    
+    pub fn first(&self) -> usize {
+        self.first
+    }
+    
+    pub fn active(&self) -> u64 {
+        self.active
+    }
+    
+    /// Bytes in the active segment
+    pub fn active_len(&self) -> u64 {
+        self.end
+    }
+    
+    pub fn active_since(&self) -> Option<DateTime<Utc>> {
+        self.active_since
+    }
+    
+    /// Continue in segment `seq`, which starts empty
+    pub fn start_segment(&mut self, seq: u64) {
+        self.active = seq;
+        self.end = 0;
+        self.active_since = None;
+    }
+    
+    /// Forget the records of segments up to `seq`, which must be the oldest
+    pub fn drop_segments_through(&mut self, seq: u64) {
+        let dropped = self.entries.iter().take_while(|entry| entry.segment <= seq).count();
+        for entry in self.entries.drain(..dropped) {
+            self.positions.remove(&entry.id);
+        }
+        self.first += dropped;
+    }
+    
+    /// When the newest record of segment `seq` was logged
+    pub fn last_timestamp(&self, seq: u64) -> Option<DateTime<Utc>> {
+        self.entries.iter().rev()
+            .skip_while(|entry| entry.segment > seq)
+            .take_while(|entry| entry.segment == seq)
+            .map(|entry| entry.timestamp)
+            .next()
+    }
+    
+    /// Index `record`, written as a line of `len` bytes at the end of the log
+    pub fn push(&mut self, record: &TransactionRecord, len: usize) {
+        let position = self.len();
+        let record_graph = record.graph.clone().unwrap_or_else(|| "default".to_string());
+        
+        let mut graphs: Vec<String> = record.graph.iter().cloned().collect();
+        for triple in record.old_data.iter().chain(&record.new_data).flatten() {
+            let graph = triple.graph.as_ref().unwrap_or(&record_graph);
+            if !graphs.contains(graph) {
+                graphs.push(graph.clone());
+            }
+            for (key, postings) in [(&triple.subject, &mut self.subjects), (&triple.predicate, &mut self.predicates)] {
+                let postings = postings.entry(key.clone()).or_default();
+                if postings.last() != Some(&position) {
+                    postings.push(position);
+                }
+            }
+        }
+        
+        if let Some(reverses) = &record.reverses {
+            match record.transaction_type {
+                TransactionType::Undo => {
+                    self.undone_by.insert(reverses.clone(), record.id.clone());
+                    self.undo_targets.insert(record.id.clone(), reverses.clone());
+                },
+                TransactionType::Redo => {
+                    if let Some(target) = self.undo_targets.get(reverses) {
+                        self.undone_by.remove(target);
+                    }
+                },
+                _ => {},
+            }
+        }
+        
+        self.positions.insert(record.id.clone(), position);
+        self.active_since.get_or_insert(record.timestamp);
+        self.entries.push(IndexEntry {
+            id: record.id.clone(),
+            segment: self.active,
+            offset: self.end,
+            len,
+            timestamp: record.timestamp,
+            transaction_type: record.transaction_type.clone(),
+            user_id: record.user_id.clone(),
+            graphs,
+        });
+        self.end += len as u64;
+    }
+    
+    /// Step over a line that is not a record
+    pub fn skip(&mut self, len: usize) {
+        self.end += len as u64;
+    }
+    
+    pub fn get(&self, position: usize) -> &IndexEntry {
+        &self.entries[position - self.first]
+    }
+    
+    pub fn contains(&self, id: &str) -> bool {
+        self.positions.contains_key(id)
+    }
+    
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.positions.get(id).copied()
+    }
+    
+    /// Position of the first record logged after `timestamp`
+    pub fn position_after(&self, timestamp: DateTime<Utc>) -> usize {
+        self.first + self.entries.iter()
+            .position(|entry| entry.timestamp > timestamp)
+            .unwrap_or(self.entries.len())
+    }
+    
+    /// Graphs the records in `range` touch, in order of first appearance
+    pub fn graphs(&self, range: Range<usize>) -> Vec<String> {
+        let mut graphs: Vec<String> = Vec::new();
+        for position in range.start.max(self.first)..range.end.min(self.len()) {
+            for graph in &self.get(position).graphs {
+                if !graphs.contains(graph) {
+                    graphs.push(graph.clone());
+                }
+            }
+        }
+        graphs
+    }
+    
+    pub fn undone_by(&self, id: &str) -> Option<&String> {
+        self.undone_by.get(id)
+    }
+    
+    /// Positions in `range` of up to `limit` records matching `query`, newest first
+    pub fn find(&self, query: &LogQuery, range: Range<usize>, limit: usize) -> Vec<usize> {
+        let mut postings: Vec<&Vec<usize>> = Vec::new();
+        for (key, lists) in [(&query.subject, &self.subjects), (&query.predicate, &self.predicates)] {
+            if let Some(key) = key {
+                match lists.get(key) {
+                    Some(list) => postings.push(list),
+                    None => return Vec::new(),
+                }
+            }
+        }
+        
+        // Walk the shortest posting list when there is one, else every record
+        let start = range.start.max(self.first);
+        let end = range.end.min(self.len());
+        let candidates: Box<dyn Iterator<Item = usize>> = match postings.iter().min_by_key(|list| list.len()) {
+            Some(shortest) => Box::new(shortest.iter().rev().copied()
+                .skip_while(move |&i| i >= end)
+                .take_while(move |&i| i >= start)),
+            None => Box::new((start..end).rev()),
+        };
+        candidates
+            .filter(|&i| postings.iter().all(|list| list.binary_search(&i).is_ok()))
+            .filter(|&i| self.get(i).matches(query))
+            .take(limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RdfNode, Triple};
+    use uuid::Uuid;
+    
+    fn record(transaction_type: TransactionType, subject: &str, reverses: Option<&TransactionRecord>) -> TransactionRecord {
+        TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type,
+            graph: Some("http://example.org/g".to_string()),
+            old_data: None,
+            new_data: Some(vec![Triple {
+                subject: format!("http://example.org/{}", subject),
+                predicate: "http://example.org/p".to_string(),
+                object: RdfNode::Uri { value: "http://example.org/o".to_string() },
+                graph: None,
+            }]),
+            description: String::new(),
+            reverses: reverses.map(|r| r.id.clone()),
+            user_id: None,
+            user_name: None,
+        }
+    }
+    
+    #[test]
+    fn test_index_follows_undo_and_redo_links() {
+        let added = record(TransactionType::AddTriple, "a", None);
+        let undo = record(TransactionType::Undo, "a", Some(&added));
+        let redo = record(TransactionType::Redo, "a", Some(&undo));
+        let undo_again = record(TransactionType::Undo, "a", Some(&added));
+        
+        let mut index = LogIndex::default();
+        index.push(&added, 10);
+        assert_eq!(index.undone_by(&added.id), None);
+        index.push(&undo, 10);
+        assert_eq!(index.undone_by(&added.id), Some(&undo.id));
+        index.push(&redo, 10);
+        assert_eq!(index.undone_by(&added.id), None);
+        index.push(&undo_again, 10);
+        assert_eq!(index.undone_by(&added.id), Some(&undo_again.id));
+        assert_eq!(index.get(3).offset, 30);
+    }
+    
+    #[test]
+    fn test_dropped_segments_keep_positions() {
+        let records: Vec<TransactionRecord> = ["a", "b", "a"].iter()
+            .map(|subject| record(TransactionType::AddTriple, subject, None))
+            .collect();
+        let mut index = LogIndex::default();
+        index.start_segment(1);
+        index.push(&records[0], 10);
+        index.start_segment(2);
+        index.push(&records[1], 10);
+        index.push(&records[2], 10);
+        assert_eq!(index.last_timestamp(1), Some(records[0].timestamp));
+        
+        index.drop_segments_through(1);
+        assert_eq!((index.first(), index.len()), (1, 3));
+        assert_eq!(index.position(&records[0].id), None);
+        assert_eq!(index.position(&records[2].id), Some(2));
+        assert_eq!(index.get(2).offset, 10);
+        let query = LogQuery { subject: Some("http://example.org/a".to_string()), ..LogQuery::default() };
+        assert_eq!(index.find(&query, 0..3, 10), vec![2]);
+    }
+    
+    #[test]
+    fn test_find_filters_newest_first() {
+        let mut index = LogIndex::default();
+        let records = [
+            record(TransactionType::AddTriple, "a", None),
+            record(TransactionType::DeleteTriple, "b", None),
+            record(TransactionType::AddTriple, "a", None),
+            record(TransactionType::AddTriple, "b", None),
+        ];
+        for record in &records {
+            index.push(record, 10);
+        }
+        
+        let query = LogQuery { subject: Some("http://example.org/a".to_string()), ..LogQuery::default() };
+        assert_eq!(index.find(&query, 0..4, 10), vec![2, 0]);
+        assert_eq!(index.find(&query, 0..2, 10), vec![0]);
+        assert_eq!(index.find(&query, 1..4, 10), vec![2]);
+        let query = LogQuery { transaction_type: Some(TransactionType::AddTriple), ..LogQuery::default() };
+        assert_eq!(index.find(&query, 0..4, 2), vec![3, 2]);
+        let query = LogQuery { graph: Some("default".to_string()), ..LogQuery::default() };
+        assert!(index.find(&query, 0..4, 10).is_empty());
+        let query = LogQuery { subject: Some("http://example.org/none".to_string()), ..LogQuery::default() };
+        assert!(index.find(&query, 0..4, 10).is_empty());
+    }
+}
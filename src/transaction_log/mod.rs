@@ -0,0 +1,982 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use std::collections::{HashMap, HashSet};
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use crate::config::Config;
+use crate::error::RdfEditorError;
+use crate::formats::changeset::Change;
+use crate::models::Triple;
+use uuid::Uuid;
+
+mod compaction;
+mod index;
+mod segments;
+
+use index::LogIndex;
+
+/// Records per page of a log query when the request does not say
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum TransactionType {
+    AddTriple,
+    DeleteTriple,
+    ReplaceTriple,
+    AddTriplesBatch,
+    DeleteTriplesBatch,
+    DropGraph,
+    ReplaceGraph,
+    ImportData,
+    /// Staged edits committed together; old_data holds the deletions, new_data the insertions
+    Commit,
+    /// Reversal of the transaction named by `reverses`
+    Undo,
+    /// Reversal of the Undo named by `reverses`, applying the original transaction again
+    Redo,
+    /// Graph rolled back to an earlier point; old_data holds the deletions, new_data the insertions
+    Restore,
+    /// One change of an imported changeset; old_data holds the deletions, new_data the insertions
+    Changeset,
+    /// Everything the graph held at this point, written by compaction in place
+    /// of the history before it; new_data holds the triples
+    Snapshot,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionRecord {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub transaction_type: TransactionType,
+    pub graph: Option<String>,
+    pub old_data: Option<Vec<Triple>>,
+    pub new_data: Option<Vec<Triple>>,
+    pub description: String,
+    /// For Undo and Redo, the record this one reverses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverses: Option<String>,
+    // For future use when we add user authentication
+    pub user_id: Option<String>,
+    pub user_name: Option<String>,
+}
+
+impl TransactionRecord {
+    /// Whether the record holds the data its inverse needs. Graph drops, graph
+    /// replaces and imports logged without a snapshot of the graph cannot be undone.
+    pub fn can_undo(&self) -> bool {
+        match self.transaction_type {
+            TransactionType::AddTriple | TransactionType::AddTriplesBatch => self.new_data.is_some(),
+            TransactionType::DeleteTriple | TransactionType::DeleteTriplesBatch
+            | TransactionType::DropGraph => self.old_data.is_some(),
+            TransactionType::ReplaceTriple | TransactionType::ReplaceGraph
+            | TransactionType::ImportData | TransactionType::Commit | TransactionType::Restore
+            | TransactionType::Changeset => {
+                self.old_data.is_some() && self.new_data.is_some()
+            },
+            // Reversed by redoing or undoing the transaction they apply to
+            TransactionType::Undo | TransactionType::Redo => false,
+            // A state rather than a change
+            TransactionType::Snapshot => false,
+        }
+    }
+    
+    /// Whether the record holds the triples it changed. Undo and Redo records
+    /// always do, though they are reversed through the records they apply to.
+    pub fn has_changes(&self) -> bool {
+        self.can_undo() || matches!(self.transaction_type, TransactionType::Undo | TransactionType::Redo)
+    }
+    
+    /// What reverses the record: triples to delete, then triples to insert back.
+    /// Every triple names its graph, `None` being the default graph.
+    pub fn inverse(&self) -> (Vec<Triple>, Vec<Triple>) {
+        let with_graph = |triples: &Option<Vec<Triple>>| -> Vec<Triple> {
+            triples.iter().flatten().map(|triple| Triple {
+                graph: triple.graph.clone().or_else(|| self.graph.clone()).filter(|g| g != "default"),
+                ..triple.clone()
+            }).collect()
+        };
+        let old = with_graph(&self.old_data);
+        let new = with_graph(&self.new_data);
+        let old_set: HashSet<&Triple> = old.iter().collect();
+        let new_set: HashSet<&Triple> = new.iter().collect();
+        
+        let removals = new.iter().filter(|t| !old_set.contains(t)).cloned().collect();
+        // An import only adds, so the snapshot taken before it is still in the store
+        let restores = match self.transaction_type {
+            TransactionType::ImportData => Vec::new(),
+            _ => old.iter().filter(|t| !new_set.contains(t)).cloned().collect(),
+        };
+        (removals, restores)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionLogEntry {
+    pub record: TransactionRecord,
+    pub can_undo: bool,
+    /// Undone and not redone since
+    #[serde(default)]
+    pub can_redo: bool,
+    /// The Undo record currently reversing this transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undone_by: Option<String>,
+}
+
+/// Filters for reading the log, all optional and combined with AND
+#[derive(Debug, Deserialize, Default)]
+pub struct LogQuery {
+    /// Graph the record touches, "default" for the default graph
+    pub graph: Option<String>,
+    pub user: Option<String>,
+    #[serde(rename = "type")]
+    pub transaction_type: Option<TransactionType>,
+    /// IRIs appearing as subject or predicate in the record's data
+    pub subject: Option<String>,
+    pub predicate: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Page size, DEFAULT_PAGE_SIZE when absent, at most MAX_PAGE_SIZE
+    pub limit: Option<usize>,
+}
+
+/// One page of log entries, newest first
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    pub entries: Vec<TransactionLogEntry>,
+    /// Cursor for the next, older page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// When to rotate the active log file and which archives to keep; a zero
+/// disables that limit
+#[derive(Debug, Clone, Default)]
+pub struct LogPolicy {
+    /// Rotate once the active file reaches this many bytes
+    pub max_bytes: u64,
+    /// Rotate once the oldest record in the active file is this old
+    pub max_age_hours: u64,
+    /// Archives to keep, the oldest deleted first
+    pub retain_segments: usize,
+    /// Delete archives whose newest record is older than this
+    pub retain_days: u64,
+}
+
+impl LogPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        LogPolicy {
+            max_bytes: config.transaction_log_max_bytes,
+            max_age_hours: config.transaction_log_max_age_hours,
+            retain_segments: config.transaction_log_retain_segments,
+            retain_days: config.transaction_log_retain_days,
+        }
+    }
+}
+
+/// Append-only JSONL log, rotated into gzipped archives (see `segments`) and
+/// indexed in memory (see `index`). Reads span the archives transparently.
+pub struct TransactionLogger {
+    log_path: String,
+    policy: LogPolicy,
+    log_file: Arc<Mutex<File>>,
+    index: Arc<RwLock<LogIndex>>,
+    /// Last archive decompressed, as pages often read several lines from one
+    archive_cache: Mutex<Option<(u64, Arc<Vec<u8>>)>>,
+    batch_mode: Arc<Mutex<bool>>,
+    batch_buffer: Arc<Mutex<Vec<TransactionRecord>>>,
+    /// Transactions with an undo or redo running, see `claim_reversal`
+    reversals: std::sync::Mutex<HashSet<String>>,
+}
+
+/// An undo or redo of a transaction in progress; dropping it lets the next one start
+pub struct ReversalClaim<'a> {
+    reversals: &'a std::sync::Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl Drop for ReversalClaim<'_> {
+    fn drop(&mut self) {
+        self.reversals.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl TransactionLogger {
+    /// Open the log at `log_path`, creating it and its directory if needed, and
+    /// index it along with its archives
+    pub async fn new(log_path: &str, policy: LogPolicy) -> Result<Self, RdfEditorError> {
+        if let Some(dir) = Path::new(log_path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await
+                .map_err(|e| RdfEditorError::Configuration(format!("Failed to create log directory: {}", e)))?;
+        }
+        
+        // Finish a rotation that was cut short
+        let rotating = segments::rotating_path(log_path);
+        if tokio::fs::try_exists(&rotating).await.unwrap_or(false) {
+            let seq = segments::archives(log_path).await?.last().map_or(1, |last| last + 1);
+            segments::archive(rotating, segments::archive_path(log_path, seq)).await?;
+        }
+        
+        let mut file = open_active(log_path).await?;
+        let (mut index, torn) = scan(log_path).await?;
+        if torn {
+            // A write cut short left a partial line; end it so the next record starts clean
+            file.write_all(b"\n").await
+                .map_err(|e| RdfEditorError::Configuration(format!("Failed to write log: {}", e)))?;
+            index.skip(1);
+        }
+            
+        Ok(TransactionLogger {
+            log_path: log_path.to_string(),
+            policy,
+            log_file: Arc::new(Mutex::new(file)),
+            index: Arc::new(RwLock::new(index)),
+            archive_cache: Mutex::new(None),
+            batch_mode: Arc::new(Mutex::new(false)),
+            batch_buffer: Arc::new(Mutex::new(Vec::new())),
+            reversals: std::sync::Mutex::new(HashSet::new()),
+        })
+    }
+    
+    /// Claim the undo or redo of transaction `id` until the claim is dropped. Fails while
+    /// another undo or redo of it runs, as both would pass their checks against the log
+    /// before either had written. Undo and redo both claim the original transaction.
+    pub fn claim_reversal(&self, id: &str) -> Result<ReversalClaim<'_>, RdfEditorError> {
+        if !self.reversals.lock().unwrap().insert(id.to_string()) {
+            return Err(RdfEditorError::InvalidInput(format!(
+                "Transaction {} is already being undone or redone",
+                id
+            )));
+        }
+        Ok(ReversalClaim { reversals: &self.reversals, id: id.to_string() })
+    }
+    
+    pub async fn start_batch(&self) {
+        let mut batch_mode = self.batch_mode.lock().await;
+        *batch_mode = true;
+    }
+    
+    pub async fn end_batch(&self) -> Result<(), RdfEditorError> {
+        let mut batch_mode = self.batch_mode.lock().await;
+        *batch_mode = false;
+        
+        // Flush the batch buffer
+        let mut buffer = self.batch_buffer.lock().await;
+        let records: Vec<TransactionRecord> = buffer.drain(..).collect();
+        self.append(&records).await
+    }
+    
+    pub async fn log_add_triple(
+        &self,
+        graph: Option<&str>,
+        triple: &Triple,
+        user_id: Option<&str>,
+    ) -> Result<String, RdfEditorError> {
+        let record = TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type: TransactionType::AddTriple,
+            graph: graph.map(String::from),
+            old_data: None,
+            new_data: Some(vec![triple.clone()]),
+            description: format!("Added triple: {}", triple.to_sparql_pattern()),
+            reverses: None,
+            user_id: user_id.map(String::from),
+            user_name: None,
+        };
+        
+        self.write_record(record).await
+    }
+    
+    pub async fn log_delete_triple(
+        &self,
+        graph: Option<&str>,
+        triple: &Triple,
+        user_id: Option<&str>,
+    ) -> Result<String, RdfEditorError> {
+        let record = TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type: TransactionType::DeleteTriple,
+            graph: graph.map(String::from),
+            old_data: Some(vec![triple.clone()]),
+            new_data: None,
+            description: format!("Deleted triple: {}", triple.to_sparql_pattern()),
+            reverses: None,
+            user_id: user_id.map(String::from),
+            user_name: None,
+        };
+        
+        self.write_record(record).await
+    }
+    
+    pub async fn log_replace_triple(
+        &self,
+        graph: Option<&str>,
+        old_triple: &Triple,
+        new_triple: &Triple,
+        user_id: Option<&str>,
+    ) -> Result<String, RdfEditorError> {
+        let record = TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type: TransactionType::ReplaceTriple,
+            graph: graph.map(String::from),
+            old_data: Some(vec![old_triple.clone()]),
+            new_data: Some(vec![new_triple.clone()]),
+            description: format!(
+                "Replaced triple: {} -> {}",
+                old_triple.to_sparql_pattern(),
+                new_triple.to_sparql_pattern()
+            ),
+            reverses: None,
+            user_id: user_id.map(String::from),
+            user_name: None,
+        };
+        
+        self.write_record(record).await
+    }
+    
+    pub async fn log_batch_operation(
+        &self,
+        transaction_type: TransactionType,
+        graph: Option<&str>,
+        old_triples: Option<&[Triple]>,
+        new_triples: Option<&[Triple]>,
+        description: &str,
+        user_id: Option<&str>,
+    ) -> Result<String, RdfEditorError> {
+        let record = TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type,
+            graph: graph.map(String::from),
+            old_data: old_triples.map(|t| t.to_vec()),
+            new_data: new_triples.map(|t| t.to_vec()),
+            description: description.to_string(),
+            reverses: None,
+            user_id: user_id.map(String::from),
+            user_name: None,
+        };
+        
+        self.write_record(record).await
+    }
+    
+    /// Log an Undo or Redo of record `reverses`, with the triples it removed and added
+    pub async fn log_reversal(
+        &self,
+        transaction_type: TransactionType,
+        reverses: &str,
+        graph: Option<&str>,
+        removed: &[Triple],
+        added: &[Triple],
+        description: &str,
+    ) -> Result<String, RdfEditorError> {
+        let record = TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type,
+            graph: graph.map(String::from),
+            old_data: Some(removed.to_vec()),
+            new_data: Some(added.to_vec()),
+            description: description.to_string(),
+            reverses: Some(reverses.to_string()),
+            user_id: None,
+            user_name: None,
+        };
+        
+        self.write_record(record).await
+    }
+    
+    async fn write_record(&self, record: TransactionRecord) -> Result<String, RdfEditorError> {
+        let id = record.id.clone();
+        
+        let batch_mode = self.batch_mode.lock().await;
+        if *batch_mode {
+            // In batch mode, buffer the records
+            let mut buffer = self.batch_buffer.lock().await;
+            buffer.push(record);
+        } else {
+            // Write immediately
+            self.append(&[record]).await?;
+        }
+        
+        Ok(id)
+    }
+    
+    /// Write `records` at the end of the log and index them, rotating when the
+    /// policy says so. The index stays locked until the lines are flushed, so
+    /// readers never see an entry before its line.
+    async fn append(&self, records: &[TransactionRecord]) -> Result<(), RdfEditorError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        
+        let mut file = self.log_file.lock().await;
+        let mut index = self.index.write().await;
+        for record in records {
+            let line = format!("{}\n", serde_json::to_string(record)?);
+            file.write_all(line.as_bytes()).await
+                .map_err(|e| RdfEditorError::Configuration(format!("Failed to write log: {}", e)))?;
+            index.push(record, line.len());
+        }
+        
+        file.flush().await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to flush log: {}", e)))?;
+        
+        if self.rotation_due(&index) {
+            self.rotate(&mut file, &mut index).await?;
+        }
+        Ok(())
+    }
+    
+    fn rotation_due(&self, index: &LogIndex) -> bool {
+        let too_big = self.policy.max_bytes > 0 && index.active_len() >= self.policy.max_bytes;
+        let too_old = self.policy.max_age_hours > 0 && index.active_since().is_some_and(|since| {
+            since < Utc::now() - Duration::hours(self.policy.max_age_hours as i64)
+        });
+        too_big || too_old
+    }
+    
+    /// Archive the active file and start a new one, then apply retention.
+    /// Callers hold the file and index locks.
+    async fn rotate(&self, file: &mut File, index: &mut LogIndex) -> Result<(), RdfEditorError> {
+        if index.active_len() > 0 {
+            let rotating = segments::rotating_path(&self.log_path);
+            tokio::fs::rename(&self.log_path, &rotating).await
+                .map_err(|e| RdfEditorError::Configuration(format!("Failed to rotate log: {}", e)))?;
+            *file = open_active(&self.log_path).await?;
+            segments::archive(rotating, segments::archive_path(&self.log_path, index.active())).await?;
+            index.start_segment(index.active() + 1);
+        }
+        
+        self.apply_retention(index).await
+    }
+    
+    /// Delete the oldest archives past the retention limits
+    async fn apply_retention(&self, index: &mut LogIndex) -> Result<(), RdfEditorError> {
+        let archives = segments::archives(&self.log_path).await?;
+        let cutoff = Utc::now() - Duration::days(self.policy.retain_days as i64);
+        for (i, &seq) in archives.iter().enumerate() {
+            let over_count = self.policy.retain_segments > 0 && archives.len() - i > self.policy.retain_segments;
+            let expired = self.policy.retain_days > 0 && index.last_timestamp(seq).is_none_or(|last| last < cutoff);
+            if !over_count && !expired {
+                break;
+            }
+            segments::remove(segments::archive_path(&self.log_path, seq)).await?;
+            index.drop_segments_through(seq);
+        }
+        Ok(())
+    }
+    
+    /// Rotate by age and apply retention; run periodically, since both can come
+    /// due without any writes
+    pub async fn maintain(&self) -> Result<(), RdfEditorError> {
+        let mut file = self.log_file.lock().await;
+        let mut index = self.index.write().await;
+        if self.rotation_due(&index) {
+            self.rotate(&mut file, &mut index).await
+        } else {
+            self.apply_retention(&mut index).await
+        }
+    }
+    
+    /// Records matching `query`, newest first, a page at a time
+    pub async fn query(&self, query: &LogQuery) -> Result<LogPage, RdfEditorError> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        
+        let index = self.index.read().await;
+        let before = match &query.cursor {
+            Some(cursor) => index.position(cursor)
+                .ok_or_else(|| RdfEditorError::InvalidInput(format!("Unknown cursor {}", cursor)))?,
+            None => index.len(),
+        };
+        let mut positions = index.find(query, 0..before, limit + 1);
+        let next_cursor = if positions.len() > limit {
+            positions.truncate(limit);
+            positions.last().map(|&p| index.get(p).id.clone())
+        } else {
+            None
+        };
+        
+        Ok(LogPage {
+            entries: self.read_entries(&index, &positions).await?,
+            next_cursor,
+        })
+    }
+    
+    /// The record with `id` and its undo state
+    pub async fn entry(&self, id: &str) -> Result<TransactionLogEntry, RdfEditorError> {
+        let index = self.index.read().await;
+        let position = index.position(id)
+            .ok_or_else(|| RdfEditorError::NotFound(format!("Transaction {} not found", id)))?;
+        
+        let mut entries = self.read_entries(&index, &[position]).await?;
+        Ok(entries.remove(0))
+    }
+    
+    /// Position in the log of record `id`
+    pub async fn position_of_record(&self, id: &str) -> Result<usize, RdfEditorError> {
+        self.index.read().await.position(id)
+            .ok_or_else(|| RdfEditorError::NotFound(format!("Transaction {} not found", id)))
+    }
+    
+    /// Position in the log just after record `id`
+    pub async fn position_after_record(&self, id: &str) -> Result<usize, RdfEditorError> {
+        Ok(self.position_of_record(id).await? + 1)
+    }
+    
+    /// Position in the log of the first record after `timestamp`
+    pub async fn position_after_time(&self, timestamp: DateTime<Utc>) -> usize {
+        self.index.read().await.position_after(timestamp)
+    }
+    
+    /// Where to replay `graph`'s history from to get back to position `start`.
+    /// Compacted history only has snapshots, so a position inside it moves back
+    /// to the graph's last snapshot at or before it.
+    pub async fn restore_start(&self, start: usize, graph: &str) -> usize {
+        let index = self.index.read().await;
+        let query = LogQuery {
+            graph: Some(graph.to_string()),
+            transaction_type: Some(TransactionType::Snapshot),
+            ..LogQuery::default()
+        };
+        if index.find(&query, start..index.len(), 1).is_empty() {
+            return start;
+        }
+        index.find(&query, 0..start, 1).first().copied().unwrap_or(start)
+    }
+    
+    /// Records from position `start` on that touch `graph`, oldest first
+    pub async fn records_touching(&self, start: usize, graph: &str) -> Result<Vec<TransactionRecord>, RdfEditorError> {
+        let index = self.index.read().await;
+        self.touching(&index, start, graph).await
+    }
+    
+    async fn touching(&self, index: &LogIndex, start: usize, graph: &str) -> Result<Vec<TransactionRecord>, RdfEditorError> {
+        let query = LogQuery { graph: Some(graph.to_string()), ..LogQuery::default() };
+        self.records_in(index, &query, start..index.len()).await
+    }
+    
+    /// Records at positions in `range` matching `query`, oldest first; the query's
+    /// cursor and limit are ignored
+    pub async fn records(&self, query: &LogQuery, range: Range<usize>) -> Result<Vec<TransactionRecord>, RdfEditorError> {
+        let index = self.index.read().await;
+        self.records_in(&index, query, range).await
+    }
+    
+    async fn records_in(&self, index: &LogIndex, query: &LogQuery, range: Range<usize>) -> Result<Vec<TransactionRecord>, RdfEditorError> {
+        let mut positions = index.find(query, range, usize::MAX);
+        positions.reverse();
+        Ok(self.read_entries(index, &positions).await?.into_iter().map(|entry| entry.record).collect())
+    }
+    
+    /// Read the records at `positions` into log entries. Callers hold the index
+    /// lock, so no rotation moves the lines meanwhile.
+    async fn read_entries(&self, index: &LogIndex, positions: &[usize]) -> Result<Vec<TransactionLogEntry>, RdfEditorError> {
+        let mut active: Option<File> = None;
+        let mut entries = Vec::with_capacity(positions.len());
+        for &position in positions {
+            let located = index.get(position);
+            let line = if located.segment == index.active() {
+                if active.is_none() {
+                    active = Some(File::open(&self.log_path).await
+                        .map_err(|e| RdfEditorError::Configuration(format!("Failed to open log for reading: {}", e)))?);
+                }
+                let file = active.as_mut().unwrap();
+                let mut line = vec![0; located.len];
+                file.seek(SeekFrom::Start(located.offset)).await
+                    .map_err(|e| RdfEditorError::Configuration(format!("Failed to read log: {}", e)))?;
+                file.read_exact(&mut line).await
+                    .map_err(|e| RdfEditorError::Configuration(format!("Failed to read log: {}", e)))?;
+                line
+            } else {
+                let archive = self.archive(located.segment).await?;
+                let start = located.offset as usize;
+                archive.get(start..start + located.len)
+                    .ok_or_else(|| RdfEditorError::Configuration(format!("Log archive {} is truncated", located.segment)))?
+                    .to_vec()
+            };
+            let record: TransactionRecord = serde_json::from_slice(&line)?;
+            let undone_by = index.undone_by(&record.id).cloned();
+            
+            entries.push(TransactionLogEntry {
+                can_undo: record.can_undo() && undone_by.is_none(),
+                can_redo: undone_by.is_some(),
+                undone_by,
+                record,
+            });
+        }
+        Ok(entries)
+    }
+    
+    /// Decompressed archive `seq`, from the cache when it was the last one read
+    async fn archive(&self, seq: u64) -> Result<Arc<Vec<u8>>, RdfEditorError> {
+        let mut cache = self.archive_cache.lock().await;
+        if let Some((cached, content)) = cache.as_ref() {
+            if *cached == seq {
+                return Ok(content.clone());
+            }
+        }
+        
+        let content = Arc::new(segments::read_archive(segments::archive_path(&self.log_path, seq)).await?);
+        *cache = Some((seq, content.clone()));
+        Ok(content)
+    }
+}
+
+async fn open_active(log_path: &str) -> Result<File, RdfEditorError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .await
+        .map_err(|e| RdfEditorError::Configuration(format!("Failed to open log file: {}", e)))
+}
+
+/// Index the archives of `log_path` and then its active file, line by line.
+/// Lines that are not records, or repeat a record already seen, are stepped
+/// over; the flag tells whether the active file's last line is missing its newline.
+async fn scan(log_path: &str) -> Result<(LogIndex, bool), RdfEditorError> {
+    let mut index = LogIndex::default();
+    let archives = segments::archives(log_path).await?;
+    for &seq in &archives {
+        index.start_segment(seq);
+        let content = segments::read_archive(segments::archive_path(log_path, seq)).await?;
+        scan_lines(&mut index, content.as_slice()).await?;
+    }
+    
+    index.start_segment(archives.last().map_or(1, |last| last + 1));
+    let file = File::open(log_path).await
+        .map_err(|e| RdfEditorError::Configuration(format!("Failed to open log for reading: {}", e)))?;
+    let torn = scan_lines(&mut index, BufReader::new(file)).await?;
+    Ok((index, torn))
+}
+
+/// Index the lines of one segment; true when the last is missing its newline
+async fn scan_lines(index: &mut LogIndex, mut reader: impl AsyncBufRead + Unpin) -> Result<bool, RdfEditorError> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let len = reader.read_until(b'\n', &mut line).await
+            .map_err(|e| RdfEditorError::Configuration(format!("Failed to read log line: {}", e)))?;
+        if len == 0 {
+            return Ok(false);
+        }
+        if line.last() != Some(&b'\n') {
+            index.skip(len);
+            return Ok(true);
+        }
+        
+        match serde_json::from_slice::<TransactionRecord>(&line) {
+            Ok(record) if !index.contains(&record.id) => index.push(&record, len),
+            _ => index.skip(len),
+        }
+    }
+}
+
+/// What a graph held before a run of logged changes, as far as they touched it
+#[derive(Debug, Default, PartialEq)]
+pub struct PriorState {
+    /// Triples the changes added that were not there before them
+    pub absent: Vec<Triple>,
+    /// Triples the changes removed that were there before them
+    pub present: Vec<Triple>,
+    /// Ids of the records that touched the graph, oldest first
+    pub transactions: Vec<String>,
+    /// Whole content of the graph from the oldest snapshot among the records;
+    /// `absent` and `present` then apply to it rather than to the graph as it is now
+    pub base: Option<Vec<Triple>>,
+}
+
+impl PriorState {
+    /// Deletions and insertions taking the graph from `current` back to its
+    /// state before the changes
+    pub fn diff(&self, current: &[Triple]) -> (Vec<Triple>, Vec<Triple>) {
+        let current_set: HashSet<&Triple> = current.iter().collect();
+        let changed: HashSet<&Triple> = self.absent.iter().chain(&self.present).collect();
+        let desired: Vec<&Triple> = match &self.base {
+            Some(base) => base.iter().filter(|t| !changed.contains(t)).chain(&self.present).collect(),
+            None => self.present.iter().collect(),
+        };
+        
+        let deletions = match &self.base {
+            Some(_) => {
+                let desired: HashSet<&Triple> = desired.iter().copied().collect();
+                current.iter().filter(|t| !desired.contains(t)).cloned().collect()
+            },
+            None => self.absent.iter().filter(|t| current_set.contains(t)).cloned().collect(),
+        };
+        let insertions = desired.into_iter().filter(|t| !current_set.contains(t)).cloned().collect();
+        (deletions, insertions)
+    }
+}
+
+/// Net inverse of `records` (oldest first) restricted to `graph`, `None` being the
+/// default graph. A snapshot among them stands for all the history before it.
+/// Fails when a record touching the graph lacks the data to reverse it.
+pub fn prior_state(records: &[TransactionRecord], graph: Option<&str>) -> Result<PriorState, RdfEditorError> {
+    let graph = graph.filter(|g| *g != "default");
+    let mut state = PriorState::default();
+    // Triple -> whether it was there before the records, set by the oldest record touching it
+    let mut before: HashMap<Triple, bool> = HashMap::new();
+    let mut order: Vec<Triple> = Vec::new();
+    let mut unrecoverable: Vec<&str> = Vec::new();
+    
+    for record in records.iter().rev() {
+        let (removals, restores) = record.inverse();
+        let in_graph = |triple: &Triple| triple.graph.as_deref() == graph;
+        let record_graph = record.graph.as_deref().map(|g| (g != "default").then_some(g));
+        if record_graph != Some(graph) && !removals.iter().chain(&restores).any(in_graph) {
+            continue;
+        }
+        if record.transaction_type == TransactionType::Snapshot {
+            // The graph's whole content here; the newer changes are measured from it
+            state.base = Some(removals.into_iter().filter(in_graph).collect());
+            before.clear();
+            order.clear();
+            unrecoverable.clear();
+            continue;
+        }
+        if !record.has_changes() {
+            unrecoverable.push(&record.id);
+            continue;
+        }
+        
+        state.transactions.push(record.id.clone());
+        for (triples, was_there) in [(removals, false), (restores, true)] {
+            for triple in triples.into_iter().filter(in_graph) {
+                if before.insert(triple.clone(), was_there).is_none() {
+                    order.push(triple);
+                }
+            }
+        }
+    }
+    
+    if !unrecoverable.is_empty() {
+        unrecoverable.reverse();
+        return Err(RdfEditorError::InvalidInput(format!(
+            "Transactions logged without their data cannot be reversed: {}",
+            unrecoverable.join(", ")
+        )));
+    }
+    
+    state.transactions.reverse();
+    for triple in order {
+        if before[&triple] {
+            state.present.push(triple);
+        } else {
+            state.absent.push(triple);
+        }
+    }
+    Ok(state)
+}
+
+/// What `records` (oldest first) changed, one change per record, restricted to
+/// `graph` when given, "default" being the default graph. Snapshots left by
+/// compaction are states rather than changes and are left out. Fails when a
+/// record lacks the triples it changed.
+pub fn changes(records: &[TransactionRecord], graph: Option<&str>) -> Result<Vec<Change>, RdfEditorError> {
+    let graph = graph.map(|g| (g != "default").then_some(g));
+    let mut changes = Vec::new();
+    let mut unrecoverable: Vec<&str> = Vec::new();
+    for record in records {
+        if record.transaction_type == TransactionType::Snapshot {
+            continue;
+        }
+        if !record.has_changes() {
+            unrecoverable.push(&record.id);
+            continue;
+        }
+        
+        // The inverse removes what the record inserted and restores what it deleted
+        let (insertions, deletions) = record.inverse();
+        let in_graph = |triple: &Triple| graph.is_none_or(|graph| triple.graph.as_deref() == graph);
+        let change = Change {
+            label: Some(format!("{} {}", record.id, record.description)),
+            deletions: deletions.into_iter().filter(in_graph).collect(),
+            insertions: insertions.into_iter().filter(in_graph).collect(),
+        };
+        if !change.is_empty() {
+            changes.push(change);
+        }
+    }
+    
+    if !unrecoverable.is_empty() {
+        return Err(RdfEditorError::InvalidInput(format!(
+            "Transactions logged without their data cannot be exported: {}",
+            unrecoverable.join(", ")
+        )));
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RdfNode;
+    
+    fn triple(object: &str, graph: Option<&str>) -> Triple {
+        Triple {
+            subject: "http://example.org/s".to_string(),
+            predicate: "http://example.org/p".to_string(),
+            object: RdfNode::Literal { value: object.to_string(), datatype: None, language: None },
+            graph: graph.map(String::from),
+        }
+    }
+    
+    fn record(transaction_type: TransactionType, old: Option<Vec<Triple>>, new: Option<Vec<Triple>>) -> TransactionRecord {
+        TransactionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            transaction_type,
+            graph: Some("http://example.org/g".to_string()),
+            old_data: old,
+            new_data: new,
+            description: String::new(),
+            reverses: None,
+            user_id: None,
+            user_name: None,
+        }
+    }
+    
+    #[test]
+    fn test_inverse_of_graph_replace_is_the_difference() {
+        let replaced = record(
+            TransactionType::ReplaceGraph,
+            Some(vec![triple("kept", None), triple("old", None)]),
+            Some(vec![triple("kept", None), triple("new", None)]),
+        );
+        
+        let (removals, restores) = replaced.inverse();
+        assert_eq!(removals, vec![triple("new", Some("http://example.org/g"))]);
+        assert_eq!(restores, vec![triple("old", Some("http://example.org/g"))]);
+    }
+    
+    #[test]
+    fn test_inverse_of_import_only_removes_new_triples() {
+        let imported = record(
+            TransactionType::ImportData,
+            Some(vec![triple("before", None), triple("both", None)]),
+            Some(vec![triple("both", None), triple("quad", Some("http://example.org/other"))]),
+        );
+        
+        let (removals, restores) = imported.inverse();
+        assert_eq!(removals, vec![triple("quad", Some("http://example.org/other"))]);
+        assert!(restores.is_empty());
+    }
+    
+    #[test]
+    fn test_prior_state_keeps_the_oldest_change_per_triple() {
+        let g = Some("http://example.org/g");
+        let records = vec![
+            record(TransactionType::DeleteTriple, Some(vec![triple("a", None)]), None),
+            record(TransactionType::AddTriple, None, Some(vec![triple("c", None)])),
+            record(TransactionType::ReplaceTriple, Some(vec![triple("b", None)]), Some(vec![triple("a", None)])),
+            TransactionRecord {
+                graph: Some("http://example.org/other".to_string()),
+                ..record(TransactionType::AddTriple, None, Some(vec![triple("other", None)]))
+            },
+        ];
+        
+        let state = prior_state(&records, g).unwrap();
+        assert_eq!(state.present, vec![triple("a", g), triple("b", g)]);
+        assert_eq!(state.absent, vec![triple("c", g)]);
+        assert_eq!(state.transactions, vec![records[0].id.clone(), records[1].id.clone(), records[2].id.clone()]);
+        
+        let unlogged = record(TransactionType::DropGraph, None, None);
+        assert!(matches!(prior_state(&[unlogged], g), Err(RdfEditorError::InvalidInput(_))));
+    }
+    
+    #[test]
+    fn test_records_without_snapshots_cannot_be_undone() {
+        assert!(!record(TransactionType::DropGraph, None, None).can_undo());
+        assert!(record(TransactionType::DropGraph, Some(Vec::new()), None).can_undo());
+        assert!(!record(TransactionType::ImportData, None, Some(vec![triple("a", None)])).can_undo());
+    }
+    
+    #[tokio::test]
+    async fn test_reopened_log_is_indexed_and_paged() {
+        let path = std::env::temp_dir().join(format!("log-{}", Uuid::new_v4())).join("transaction_log.jsonl");
+        let path = path.to_str().unwrap();
+        
+        let logger = TransactionLogger::new(path, LogPolicy::default()).await.unwrap();
+        let first = logger.log_add_triple(Some("http://example.org/g"), &triple("0", None), None).await.unwrap();
+        for i in 1..150 {
+            logger.log_add_triple(Some("http://example.org/g"), &triple(&i.to_string(), None), None).await.unwrap();
+        }
+        let undo = logger.log_reversal(TransactionType::Undo, &first, Some("http://example.org/g"), &[], &[], "").await.unwrap();
+        drop(logger);
+        // A record cut short by a crash
+        let mut file = OpenOptions::new().append(true).open(path).await.unwrap();
+        file.write_all(b"{\"id\": \"torn").await.unwrap();
+        drop(file);
+        
+        let logger = TransactionLogger::new(path, LogPolicy::default()).await.unwrap();
+        let entry = logger.entry(&first).await.unwrap();
+        assert_eq!(entry.undone_by, Some(undo.clone()));
+        assert!(!entry.can_undo && entry.can_redo);
+        let last = logger.log_delete_triple(None, &triple("x", None), Some("alice")).await.unwrap();
+        
+        let query = LogQuery { transaction_type: Some(TransactionType::AddTriple), limit: Some(100), ..LogQuery::default() };
+        let page = logger.query(&query).await.unwrap();
+        assert_eq!(page.entries.len(), 100);
+        let next = LogQuery { cursor: page.next_cursor, ..query };
+        let page = logger.query(&next).await.unwrap();
+        assert_eq!(page.entries.len(), 50);
+        assert_eq!(page.entries.last().unwrap().record.id, first);
+        assert!(page.next_cursor.is_none());
+        
+        let query = LogQuery { user: Some("alice".to_string()), graph: Some("default".to_string()), ..LogQuery::default() };
+        let page = logger.query(&query).await.unwrap();
+        assert_eq!(page.entries.iter().map(|e| &e.record.id).collect::<Vec<_>>(), vec![&last]);
+    }
+    
+    #[tokio::test]
+    async fn test_reversals_are_claimed_one_at_a_time() {
+        let path = std::env::temp_dir().join(format!("log-{}", Uuid::new_v4())).join("transaction_log.jsonl");
+        let logger = TransactionLogger::new(path.to_str().unwrap(), LogPolicy::default()).await.unwrap();
+        
+        let claim = logger.claim_reversal("t1").unwrap();
+        assert!(matches!(logger.claim_reversal("t1"), Err(RdfEditorError::InvalidInput(_))));
+        assert!(logger.claim_reversal("t2").is_ok());
+        drop(claim);
+        assert!(logger.claim_reversal("t1").is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_rotated_log_reads_across_archives() {
+        let dir = std::env::temp_dir().join(format!("rdf-editor-log-{}", Uuid::new_v4()));
+        let path = dir.join("log.jsonl");
+        let path = path.to_str().unwrap();
+        let rotate_every_record = LogPolicy { max_bytes: 1, ..LogPolicy::default() };
+        
+        let logger = TransactionLogger::new(path, rotate_every_record.clone()).await.unwrap();
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            ids.push(logger.log_add_triple(Some("http://example.org/g"), &triple(&i.to_string(), None), None).await.unwrap());
+        }
+        assert_eq!(segments::archives(path).await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(logger.entry(&ids[0]).await.unwrap().record.id, ids[0]);
+        
+        let logger = TransactionLogger::new(path, LogPolicy { retain_segments: 2, ..rotate_every_record }).await.unwrap();
+        let page = logger.query(&LogQuery { limit: Some(2), ..LogQuery::default() }).await.unwrap();
+        assert_eq!(page.entries[1].record.id, ids[1]);
+        assert_eq!(page.next_cursor.as_deref(), Some(ids[1].as_str()));
+        
+        ids.push(logger.log_add_triple(Some("http://example.org/g"), &triple("3", None), None).await.unwrap());
+        assert_eq!(segments::archives(path).await.unwrap(), vec![3, 4]);
+        assert!(matches!(logger.entry(&ids[1]).await, Err(RdfEditorError::NotFound(_))));
+        let page = logger.query(&LogQuery::default()).await.unwrap();
+        let found: Vec<&str> = page.entries.iter().map(|entry| entry.record.id.as_str()).collect();
+        assert_eq!(found, [ids[3].as_str(), ids[2].as_str()]);
+        
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+//! Log segments on disk.
+//!
+//! The active segment is the configured log path. Rotation gzips it into
+//! `{log path}.{seq}.gz` and starts a fresh file; sequence numbers count up
+//! from 1, so archives sort oldest first and the active segment is always the
+//! newest. An archive is decompressed whole when read.
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::error::RdfEditorError;
+
+/// Path of archived segment `seq`
+pub fn archive_path(log_path: &str, seq: u64) -> PathBuf {
+    PathBuf::from(format!("{}.{:06}.gz", log_path, seq))
+}
+
+/// Where the active segment is moved while it is being archived
+pub fn rotating_path(log_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.rotating", log_path))
+}
+
+/// Sequence numbers of the archived segments of `log_path`, oldest first
+pub async fn archives(log_path: &str) -> Result<Vec<u64>, RdfEditorError> {
+    let path = Path::new(log_path);
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let prefix = format!("{}.", path.file_name().unwrap_or_default().to_string_lossy());
+    
+    let mut seqs = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(io_error)?;
+    while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let seq = name.strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".gz"))
+            .and_then(|seq| seq.parse::<u64>().ok());
+        if let Some(seq) = seq {
+            seqs.push(seq);
+        }
+    }
+    seqs.sort_unstable();
+    Ok(seqs)
+}
+
+/// Gzip the plain file `from` into `to`, then remove `from`. The archive is
+/// written under a temporary name first, so `to` is never partial.
+pub async fn archive(from: PathBuf, to: PathBuf) -> Result<(), RdfEditorError> {
+    blocking(move || {
+        let mut input = std::fs::File::open(&from)?;
+        write_gzip(&to, &mut input)?;
+        std::fs::remove_file(&from)
+    }).await
+}
+
+/// Write `content` as archive `to`
+pub async fn write_archive(to: PathBuf, content: Vec<u8>) -> Result<(), RdfEditorError> {
+    blocking(move || write_gzip(&to, &mut content.as_slice())).await
+}
+
+/// Decompressed content of the archive at `path`
+pub async fn read_archive(path: PathBuf) -> Result<Vec<u8>, RdfEditorError> {
+    blocking(move || {
+        let mut content = Vec::new();
+        GzDecoder::new(std::fs::File::open(&path)?).read_to_end(&mut content)?;
+        Ok(content)
+    }).await
+}
+
+pub async fn remove(path: PathBuf) -> Result<(), RdfEditorError> {
+    tokio::fs::remove_file(path).await.map_err(io_error)
+}
+
+fn write_gzip(to: &Path, input: &mut impl Read) -> std::io::Result<()> {
+    let partial = to.with_extension("gz.partial");
+    let mut encoder = GzEncoder::new(std::fs::File::create(&partial)?, Compression::default());
+    std::io::copy(input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::rename(&partial, to)
+}
+
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<T, RdfEditorError> {
+    tokio::task::spawn_blocking(work).await
+        .map_err(|e| RdfEditorError::Configuration(format!("Log segment task failed: {}", e)))?
+        .map_err(io_error)
+}
+
+fn io_error(e: std::io::Error) -> RdfEditorError {
+    RdfEditorError::Configuration(format!("Log segment error: {}", e))
+}
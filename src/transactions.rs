@@ -0,0 +1,248 @@
+//! Explicit transactions spanning several edits.
+//!
+//! `POST /api/transaction/begin` opens a transaction; triple adds, deletes and
+//! replaces, single or in batches, sent with its id in the `X-Transaction-Id`
+//! header are staged instead of written. Other writes refuse the header. Commit applies the net changes as one atomic update and logs them
+//! as a single record; rollback discards them. Transactions are per profile and
+//! live in memory, so a restart rolls back whatever was open.
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::error::RdfEditorError;
+use crate::models::Triple;
+
+pub const TRANSACTION_HEADER: &str = "X-Transaction-Id";
+
+/// Open transactions untouched for this long are rolled back
+pub const IDLE_TIMEOUT_MINUTES: i64 = 60;
+
+/// Changes staged in an open transaction. Each triple names its graph, `None`
+/// being the default graph. The two lists never share a triple, so applying
+/// the deletions before the insertions gives the net effect of the edits.
+#[derive(Debug, Serialize, Clone)]
+pub struct StagedTransaction {
+    pub id: String,
+    pub started: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub deletions: Vec<Triple>,
+    pub insertions: Vec<Triple>,
+}
+
+impl StagedTransaction {
+    fn new() -> Self {
+        let now = Utc::now();
+        StagedTransaction {
+            id: Uuid::new_v4().to_string(),
+            started: now,
+            last_activity: now,
+            deletions: Vec::new(),
+            insertions: Vec::new(),
+        }
+    }
+    
+    pub fn add(&mut self, triple: Triple) {
+        self.deletions.retain(|t| t != &triple);
+        if !self.insertions.contains(&triple) {
+            self.insertions.push(triple);
+        }
+    }
+    
+    pub fn delete(&mut self, triple: Triple) {
+        self.insertions.retain(|t| t != &triple);
+        if !self.deletions.contains(&triple) {
+            self.deletions.push(triple);
+        }
+    }
+    
+    pub fn replace(&mut self, old_triple: Triple, new_triple: Triple) {
+        self.delete(old_triple);
+        self.add(new_triple);
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.deletions.is_empty() && self.insertions.is_empty()
+    }
+    
+    /// Graphs the staged changes touch, "default" for the default graph
+    pub fn graphs(&self) -> Vec<String> {
+        let mut graphs: Vec<String> = Vec::new();
+        for triple in self.deletions.iter().chain(&self.insertions) {
+            let graph = triple.graph.clone().unwrap_or_else(|| "default".to_string());
+            if !graphs.contains(&graph) {
+                graphs.push(graph);
+            }
+        }
+        graphs
+    }
+    
+    /// The triples of `graph` as the transaction sees them: `stored` with the
+    /// staged deletions removed and the staged insertions added
+    pub fn overlay(&self, graph: Option<&str>, stored: Vec<Triple>) -> Vec<Triple> {
+        let in_graph = |triple: &Triple| triple.graph.as_deref() == graph;
+        let same = |a: &Triple, b: &Triple| {
+            a.subject == b.subject && a.predicate == b.predicate && a.object == b.object
+        };
+        
+        let mut triples: Vec<Triple> = stored.into_iter()
+            .filter(|triple| !self.deletions.iter().any(|d| in_graph(d) && same(d, triple)))
+            .collect();
+        for inserted in self.insertions.iter().filter(|t| in_graph(t)) {
+            if !triples.iter().any(|t| same(t, inserted)) {
+                triples.push(inserted.clone());
+            }
+        }
+        triples
+    }
+}
+
+/// The open transactions of one profile
+pub struct TransactionManager {
+    open: Mutex<HashMap<String, StagedTransaction>>,
+}
+
+impl TransactionManager {
+    pub fn new() -> Self {
+        TransactionManager {
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    pub async fn begin(&self) -> StagedTransaction {
+        let transaction = StagedTransaction::new();
+        self.open.lock().await.insert(transaction.id.clone(), transaction.clone());
+        transaction
+    }
+    
+    pub async fn get(&self, id: &str) -> Result<StagedTransaction, RdfEditorError> {
+        self.open.lock().await.get(id).cloned().ok_or_else(|| not_open(id))
+    }
+    
+    /// Stage an edit in transaction `id`
+    pub async fn stage(&self, id: &str, edit: impl FnOnce(&mut StagedTransaction)) -> Result<(), RdfEditorError> {
+        let mut open = self.open.lock().await;
+        let transaction = open.get_mut(id).ok_or_else(|| not_open(id))?;
+        edit(transaction);
+        transaction.last_activity = Utc::now();
+        Ok(())
+    }
+    
+    /// Close transaction `id`, handing back its staged changes
+    pub async fn take(&self, id: &str) -> Result<StagedTransaction, RdfEditorError> {
+        self.open.lock().await.remove(id).ok_or_else(|| not_open(id))
+    }
+    
+    /// Reopen a transaction whose commit failed, so it can be retried or rolled back
+    pub async fn restore(&self, transaction: StagedTransaction) {
+        self.open.lock().await.insert(transaction.id.clone(), transaction);
+    }
+    
+    /// Roll back transactions idle for longer than IDLE_TIMEOUT_MINUTES
+    pub async fn expire_idle(&self) {
+        let cutoff = Utc::now() - Duration::minutes(IDLE_TIMEOUT_MINUTES);
+        self.open.lock().await.retain(|_, transaction| transaction.last_activity > cutoff);
+    }
+}
+
+impl Default for TransactionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn not_open(id: &str) -> RdfEditorError {
+    RdfEditorError::NotFound(format!("Transaction {} is not open", id))
+}
+
+/// Extractor for the transaction a write is staged in, from the `X-Transaction-Id` header
+pub struct TransactionId(Option<String>);
+
+impl TransactionId {
+    pub fn get(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+    
+    /// For writes that cannot be staged, which must not slip past an open transaction
+    pub fn refuse(&self, operation: &str) -> Result<(), RdfEditorError> {
+        match self.get() {
+            Some(id) => Err(RdfEditorError::InvalidInput(format!(
+                "{} cannot be staged in transaction {}; send it without the {} header", operation, id, TRANSACTION_HEADER
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromRequest for TransactionId {
+    type Error = RdfEditorError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req.headers()
+            .get(TRANSACTION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        ready(Ok(TransactionId(id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RdfNode;
+    
+    fn triple(object: &str, graph: Option<&str>) -> Triple {
+        Triple {
+            subject: "http://example.org/s".to_string(),
+            predicate: "http://example.org/p".to_string(),
+            object: RdfNode::Uri { value: format!("http://example.org/{}", object) },
+            graph: graph.map(String::from),
+        }
+    }
+    
+    #[test]
+    fn test_staging_keeps_the_net_changes() {
+        let mut staged = StagedTransaction::new();
+        staged.add(triple("a", None));
+        staged.delete(triple("a", None));
+        staged.delete(triple("b", Some("http://example.org/g")));
+        staged.add(triple("b", Some("http://example.org/g")));
+        staged.replace(triple("c", None), triple("d", None));
+        staged.add(triple("d", None));
+        
+        assert_eq!(staged.deletions, vec![triple("a", None), triple("c", None)]);
+        assert_eq!(staged.insertions, vec![triple("b", Some("http://example.org/g")), triple("d", None)]);
+        assert_eq!(staged.graphs(), vec!["default", "http://example.org/g"]);
+    }
+    
+    #[test]
+    fn test_overlay_applies_changes_to_one_graph() {
+        let mut staged = StagedTransaction::new();
+        staged.delete(triple("a", Some("http://example.org/g")));
+        staged.add(triple("b", Some("http://example.org/g")));
+        staged.add(triple("c", None));
+        
+        let stored = vec![triple("a", Some("http://example.org/g")), triple("x", Some("http://example.org/g"))];
+        let view = staged.overlay(Some("http://example.org/g"), stored);
+        assert_eq!(view, vec![triple("x", Some("http://example.org/g")), triple("b", Some("http://example.org/g"))]);
+    }
+    
+    #[tokio::test]
+    async fn test_take_closes_and_restore_reopens() {
+        let manager = TransactionManager::new();
+        let id = manager.begin().await.id;
+        
+        manager.stage(&id, |staged| staged.add(triple("a", None))).await.unwrap();
+        let staged = manager.take(&id).await.unwrap();
+        assert_eq!(staged.insertions.len(), 1);
+        assert!(matches!(manager.get(&id).await, Err(RdfEditorError::NotFound(_))));
+        
+        manager.restore(staged).await;
+        assert_eq!(manager.get(&id).await.unwrap().insertions.len(), 1);
+    }
+}
@@ -56,6 +56,13 @@ pub fn validate_language_tag(lang: &str) -> Result<(), RdfEditorError> {
         ));
     }
     
+    // Tags are written into queries unquoted, so subtags may hold nothing else
+    if parts[1..].iter().any(|part| part.is_empty() || part.len() > 8 || !part.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return Err(RdfEditorError::InvalidInput(
+            format!("Invalid language tag: {}. Subtags should be 1-8 letters or digits", lang)
+        ));
+    }
+    
     Ok(())
 }
 
@@ -89,4 +96,33 @@ pub fn sanitize_literal_value(value: &str) -> String {
         .replace('\n', "\\n")
         .replace('\r', "\\r")
         .replace('\t', "\\t")
-}
\ No newline at end of file
+}
+/// Percent-encode the characters an `<IRIREF>` cannot hold, so an IRI can
+/// never close its brackets inside a query
+pub fn sanitize_iri(iri: &str) -> String {
+    let mut sanitized = String::with_capacity(iri.len());
+    for c in iri.chars() {
+        if c <= ' ' || "<>\"{}|^`\\".contains(c) {
+            sanitized.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            sanitized.push(c);
+        }
+    }
+    sanitized
+}
+
+pub fn sanitize_language_tag(lang: &str) -> String {
+    lang.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect()
+}
+
+/// Blank node labels that SPARQL accepts are kept; others are rewritten into
+/// one it does
+pub fn sanitize_blank_label(label: &str) -> String {
+    let safe = label.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if safe {
+        label.to_string()
+    } else {
+        crate::formats::portable_blank_label(label)
+    }
+}
@@ -2002,9 +2002,9 @@ async function loadTransactionHistory() {
     
     try {
         const response = await fetch(`${API_BASE}/transactions`);
-        const transactions = await response.json();
+        const page = await response.json();
         
-        displayTransactionHistory(transactions);
+        displayTransactionHistory(page.entries);
     } catch (error) {
         listDiv.innerHTML = `<div class="message error">Error loading history: ${error.message}</div>`;
     }